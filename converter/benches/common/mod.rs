@@ -0,0 +1,12 @@
+// benches/common/mod.rs
+// ============================================================================
+// Datos compartidos por los benchmarks
+// ============================================================================
+
+use rand::Rng;
+
+/// Tensor f32 uniforme en [-2, 2)
+pub fn generate_random_tensor(size: usize) -> Vec<f32> {
+    let mut rng = rand::thread_rng();
+    (0..size).map(|_| rng.gen_range(-2.0..2.0)).collect()
+}
@@ -0,0 +1,52 @@
+// benches/dictionary_bench.rs
+// ============================================================================
+// Dictionary Benchmark - RegexSet vs recorrido lineal
+// ============================================================================
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
+use helios_convert::dictionary::{validate_tensor_name, validate_tensor_name_linear};
+
+/// Nombres realistas de un MoE grande: mezcla de válidos e inválidos
+fn generate_names(count: usize) -> Vec<String> {
+    let suffixes = [
+        "attn.q_proj.weight",
+        "attn.o_proj.weight",
+        "mlp.down.weight",
+        "ln_attn_in.weight",
+        "attn.qproj.weight",  // inválido: recorre todos los patrones
+    ];
+    let prefixes = ["", "code.", "vision.", "cortex."];
+    (0..count)
+        .map(|i| format!(
+            "{}layer{}.{}",
+            prefixes[i % prefixes.len()],
+            i % 96,
+            suffixes[i % suffixes.len()]
+        ))
+        .collect()
+}
+
+fn bench_dictionary(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Dictionary");
+    
+    for count in [1_000, 10_000, 100_000].iter() {
+        let names = generate_names(*count);
+        
+        group.bench_with_input(
+            BenchmarkId::new("RegexSet", count),
+            &names,
+            |b, n| b.iter(|| n.iter().filter(|name| validate_tensor_name(black_box(name))).count()),
+        );
+        
+        group.bench_with_input(
+            BenchmarkId::new("Linear", count),
+            &names,
+            |b, n| b.iter(|| n.iter().filter(|name| validate_tensor_name_linear(black_box(name))).count()),
+        );
+    }
+    
+    group.finish();
+}
+
+criterion_group!(benches, bench_dictionary);
+criterion_main!(benches);
@@ -3,14 +3,11 @@
 // HQS Benchmark
 // ============================================================================
 
+mod common;
+
+use common::generate_random_tensor;
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
 use helios_convert::hqs::{quantize_hq5k, quantize_hq5k_fast, quantize_hq4k, quantize_hq4k_fast};
-use rand::Rng;
-
-fn generate_random_tensor(size: usize) -> Vec<f32> {
-    let mut rng = rand::thread_rng();
-    (0..size).map(|_| rng.gen_range(-2.0..2.0)).collect()
-}
 
 fn bench_hq5k(c: &mut Criterion) {
     let mut group = c.benchmark_group("HQ5K");
@@ -0,0 +1,85 @@
+// benches/quant.rs
+// ============================================================================
+// Quant Throughput Benchmark - elementos/s sobre shapes reales de LLM
+// ============================================================================
+//
+// Línea base para medir regresiones (SIMD, rayon) en la cuantización:
+//   cargo bench --bench quant
+//
+// El embedding 256k×4096 (1G elementos, 4 GB en f32) solo se incluye con
+// HQS_BENCH_FULL=1; el resto cabe en cualquier máquina de CI.
+//
+// ============================================================================
+
+mod common;
+
+use common::generate_random_tensor;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use helios_convert::hqs::grid_search::optimize_superblock;
+use helios_convert::hqs::{quantize_hq4k, quantize_hq4k_fast, quantize_hq5k, GridConfig, SUPER_BLOCK_SIZE};
+
+/// (etiqueta, elementos): un super-block suelto para overhead por llamada + shapes reales
+fn shapes() -> Vec<(&'static str, usize)> {
+    let mut shapes = vec![
+        ("256", SUPER_BLOCK_SIZE),
+        ("4096x4096", 4096 * 4096),
+        ("11008x4096", 11008 * 4096),
+    ];
+    if std::env::var("HQS_BENCH_FULL").is_ok_and(|v| v == "1") {
+        shapes.push(("256000x4096", 256_000 * 4096));
+    }
+    shapes
+}
+
+fn bench_quantize(c: &mut Criterion) {
+    type QuantFn = fn(&[f32]) -> Vec<u8>;
+    let variants: [(&str, QuantFn); 3] = [
+        ("hq4k", quantize_hq4k),
+        ("hq4k_fast", quantize_hq4k_fast),
+        ("hq5k", quantize_hq5k),
+    ];
+    
+    let mut group = c.benchmark_group("Quantize");
+    group.sample_size(10);
+    
+    for (label, numel) in shapes() {
+        let tensor = generate_random_tensor(numel);
+        group.throughput(Throughput::Elements(numel as u64));
+        
+        for (name, quantize) in variants {
+            group.bench_with_input(
+                BenchmarkId::new(name, label),
+                &tensor,
+                |b, t| b.iter(|| black_box(quantize(t))),
+            );
+        }
+    }
+    
+    group.finish();
+}
+
+fn bench_grid_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("GridSearch");
+    
+    for blocks in [1usize, 64, 4096] {
+        let data = generate_random_tensor(blocks * SUPER_BLOCK_SIZE);
+        group.throughput(Throughput::Elements(data.len() as u64));
+        
+        for (name, config) in [("hq4k", GridConfig::hq4k()), ("hq5k", GridConfig::hq5k())] {
+            group.bench_with_input(
+                BenchmarkId::new(name, blocks * SUPER_BLOCK_SIZE),
+                &data,
+                |b, d| b.iter(|| {
+                    for chunk in d.chunks_exact(SUPER_BLOCK_SIZE) {
+                        black_box(optimize_superblock(chunk, &config));
+                    }
+                }),
+            );
+        }
+    }
+    
+    group.finish();
+}
+
+criterion_group!(benches, bench_quantize, bench_grid_search);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+// fuzz/fuzz_targets/parse_hnf.rs
+// ============================================================================
+// FUZZ - HnfReader y validate_htf con bytes arbitrarios
+// ============================================================================
+//
+// Uso (nightly):
+//   cargo install cargo-fuzz
+//   cd converter && cargo +nightly fuzz run parse_hnf fuzz/corpus/parse_hnf
+//
+// El corpus semilla (corpus/parse_hnf/minimal.hnf) es un HNFv9 válido con
+// un tensor, execution hints y tokenizer HTF3. Cualquier pánico es un bug:
+// las entradas malformadas tienen que acabar en Err / errores de validación.
+//
+// ============================================================================
+
+#![no_main]
+
+use helios_convert::hnf::{HnfReader, BLOCK_NAMES};
+use helios_convert::htf::validate::validate_htf;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Los bytes crudos también como HTF suelto
+    let _ = validate_htf(data);
+    
+    let Ok(reader) = HnfReader::from_bytes(data.to_vec()) else {
+        return;
+    };
+    for block_id in 0..BLOCK_NAMES.len() {
+        let _ = reader.block_bytes(block_id);
+        let _ = reader.block_tensors(block_id);
+    }
+    let _ = reader.execution_hints_json();
+    let _ = reader.model_card();
+    if let Some(htf) = reader.tokenizer_bytes() {
+        let _ = validate_htf(htf);
+    }
+});
@@ -3,16 +3,17 @@
 // HNF INSPECTOR - Inspecciona estructura de archivos HNFv9
 // ============================================================================
 //
-// Uso: helios-inspect archivo.hnf
+// Uso: helios-inspect archivo.hnf [--manifest] [--hints] [--card] [--capabilities] [--dump-hints-bin out.bin]
+//        helios-inspect archivo.hnf --list-tensors [--block text]
 //
 // ============================================================================
 
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
 use std::path::PathBuf;
 
-use anyhow::{Result, Context};
+use anyhow::Result;
 use clap::Parser;
+use helios_convert::hnf::{HeaderFlags, HnfReader, TensorManifest, BLOCK_EXEC_HINTS_BIN, BLOCK_NAMES, MAGIC};
+use helios_convert::{term, outln};
 
 #[derive(Parser)]
 #[command(name = "helios-inspect")]
@@ -28,66 +29,49 @@ struct Args {
     /// Show execution hints JSON
     #[arg(long)]
     hints: bool,
+    
+    /// Print only the bundled model card (--model-card) and exit
+    #[arg(long)]
+    card: bool,
+    
+    /// Print the manifest capabilities (quant formats, arch, features) as JSON and exit
+    #[arg(long)]
+    capabilities: bool,
+    
+    /// Write the binary execution hints (block 0xB) to this file and exit
+    #[arg(long, value_name = "OUT")]
+    dump_hints_bin: Option<PathBuf>,
+    
+    /// Print a sorted table of tensors (name, shape, dtype, size) and exit
+    #[arg(long)]
+    list_tensors: bool,
+    
+    /// With --list-tensors: only tensors of this block (e.g. text, vision)
+    #[arg(long, value_name = "BLOCK", requires = "list_tensors")]
+    block: Option<String>,
+    
+    /// Plain ASCII output ([OK]/[FAIL], | borders); automatic when stdout is not a TTY
+    #[arg(long, visible_alias = "ascii")]
+    no_color: bool,
 }
 
-const BLOCK_NAMES: [&str; 16] = [
-    "text_model",       // 0x0
-    "vision",           // 0x1
-    "audio",            // 0x2
-    "video",            // 0x3
-    "spatial_3d",       // 0x4
-    "personality",      // 0x5
-    "memory",           // 0x6
-    "cortex",           // 0x7
-    "code_exec",        // 0x8
-    "tokenizer",        // 0x9 - HTF tokenizer
-    "execution_hints",  // 0xA
-    "expert_router",    // 0xB
-    "tools",            // 0xC
-    "reserved_1",       // 0xD
-    "reserved_2",       // 0xE
-    "reserved_3",       // 0xF
-];
-
-const FLAG_NAMES: [(u32, &str); 12] = [
-    (0, "HAS_VISION"),
-    (1, "HAS_AUDIO"),
-    (2, "HAS_VIDEO"),
-    (3, "HAS_SPATIAL"),
-    (4, "HAS_PERSONALITY"),
-    (5, "HAS_MEMORY"),
-    (6, "HAS_CORTEX"),
-    (7, "HAS_CODE_EXEC"),
-    (8, "HAS_TOOLS"),
-    (9, "HAS_EXPERT_ROUTER"),
-    (10, "IS_MOE"),
-    (11, "IS_MULTIMODAL"),
+const FLAG_NAMES: [(u32, &str); 14] = [
+    (HeaderFlags::HAS_VISION, "HAS_VISION"),
+    (HeaderFlags::HAS_AUDIO, "HAS_AUDIO"),
+    (HeaderFlags::HAS_VIDEO, "HAS_VIDEO"),
+    (HeaderFlags::HAS_SPATIAL, "HAS_SPATIAL"),
+    (HeaderFlags::HAS_PERSONALITY, "HAS_PERSONALITY"),
+    (HeaderFlags::HAS_MEMORY, "HAS_MEMORY"),
+    (HeaderFlags::HAS_CORTEX, "HAS_CORTEX"),
+    (HeaderFlags::HAS_CODE_EXEC, "HAS_CODE_EXEC"),
+    (HeaderFlags::HAS_TOKENIZER, "HAS_TOKENIZER"),
+    (HeaderFlags::HAS_EXEC_HINTS_BIN, "HAS_EXEC_HINTS_BIN"),
+    (HeaderFlags::HAS_TOOLS, "HAS_TOOLS"),
+    (HeaderFlags::HAS_EXPERT_ROUTER, "HAS_EXPERT_ROUTER"),
+    (HeaderFlags::IS_MOE, "IS_MOE"),
+    (HeaderFlags::IS_MULTIMODAL, "IS_MULTIMODAL"),
 ];
 
-#[derive(Debug)]
-struct HnfHeader {
-    magic: [u8; 8],
-    version_major: u16,
-    version_minor: u16,
-    flags: u32,
-    block_count: u32,
-    header_size: u32,
-    block_table_offset: u64,
-    manifest_offset: u64,
-    manifest_size: u64,
-    file_size: u64,
-    checksum: u32,
-}
-
-#[derive(Debug)]
-struct BlockEntry {
-    id: u32,
-    block_type: u32,
-    offset: u64,
-    size: u64,
-    checksum: u64,
-}
-
 fn format_size(size: u64) -> String {
     if size == 0 {
         "vacío".to_string()
@@ -129,142 +113,179 @@ fn make_bar_log(size: u64, width: usize) -> String {
     "█".repeat(filled.max(1)) + &"░".repeat(width.saturating_sub(filled.max(1)))
 }
 
-fn read_header(f: &mut File) -> Result<HnfHeader> {
-    let mut buf = [0u8; 64];
-    f.read_exact(&mut buf)?;
-    
-    Ok(HnfHeader {
-        magic: buf[0..8].try_into().unwrap(),
-        version_major: u16::from_le_bytes(buf[8..10].try_into().unwrap()),
-        version_minor: u16::from_le_bytes(buf[10..12].try_into().unwrap()),
-        flags: u32::from_le_bytes(buf[12..16].try_into().unwrap()),
-        block_count: u32::from_le_bytes(buf[16..20].try_into().unwrap()),
-        header_size: u32::from_le_bytes(buf[20..24].try_into().unwrap()),
-        block_table_offset: u64::from_le_bytes(buf[24..32].try_into().unwrap()),
-        manifest_offset: u64::from_le_bytes(buf[32..40].try_into().unwrap()),
-        manifest_size: u64::from_le_bytes(buf[40..48].try_into().unwrap()),
-        file_size: u64::from_le_bytes(buf[48..56].try_into().unwrap()),
-        checksum: u32::from_le_bytes(buf[56..60].try_into().unwrap()),
-    })
+/// Bloque por nombre: exacto o prefijo único ("text" -> text_model)
+fn resolve_block(name: &str) -> Result<&'static str> {
+    if let Some(exact) = BLOCK_NAMES.iter().find(|b| **b == name) {
+        return Ok(exact);
+    }
+    let matches: Vec<&'static str> = BLOCK_NAMES.iter().copied().filter(|b| b.starts_with(name)).collect();
+    match matches.as_slice() {
+        [single] => Ok(single),
+        [] => anyhow::bail!("Unknown block '{}' (expected one of: {})", name, BLOCK_NAMES.join(", ")),
+        _ => anyhow::bail!("Ambiguous block '{}': {}", name, matches.join(", ")),
+    }
 }
 
-fn read_block_table(f: &mut File, offset: u64) -> Result<Vec<BlockEntry>> {
-    f.seek(SeekFrom::Start(offset))?;
-    
-    let mut blocks = Vec::with_capacity(16);
+/// Tabla de tensores del manifest ordenada por nombre, con total
+fn list_tensors(reader: &HnfReader, block: Option<&str>) -> Result<()> {
+    let block = block.map(resolve_block).transpose()?;
+    let mut rows: Vec<(String, String, String, u64)> = reader.manifest().get("tensors")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|t| block.is_none() || t.get("block").and_then(|v| v.as_str()) == block)
+        .filter_map(|t| {
+            let tensor: TensorManifest = serde_json::from_value(t.clone()).ok()?;
+            let shape = format!("{:?}", tensor.shape);
+            Some((tensor.name, shape, tensor.dtype, tensor.size))
+        })
+        .collect();
+    rows.sort_by(|a, b| a.0.cmp(&b.0));
     
-    for _ in 0..16 {
-        let mut buf = [0u8; 32];
-        f.read_exact(&mut buf)?;
-        
-        blocks.push(BlockEntry {
-            id: u32::from_le_bytes(buf[0..4].try_into().unwrap()),
-            block_type: u32::from_le_bytes(buf[4..8].try_into().unwrap()),
-            offset: u64::from_le_bytes(buf[8..16].try_into().unwrap()),
-            size: u64::from_le_bytes(buf[16..24].try_into().unwrap()),
-            checksum: u64::from_le_bytes(buf[24..32].try_into().unwrap()),
-        });
+    let name_width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0).max(4);
+    let shape_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0).max(5);
+    println!("{:name_width$}  {:shape_width$}  {:8}  {:>10}", "NAME", "SHAPE", "DTYPE", "SIZE");
+    for (name, shape, dtype, size) in &rows {
+        println!("{:name_width$}  {:shape_width$}  {:8}  {:>10}", name, shape, dtype, format_size(*size));
     }
-    
-    Ok(blocks)
+    let total: u64 = rows.iter().map(|r| r.3).sum();
+    println!("Total: {} tensors, {}", rows.len(), format_size(total));
+    Ok(())
+}
+
+/// Caja con las primeras 30 líneas del JSON indentado
+fn print_json(title: &str, json: &serde_json::Value) {
+    outln!("┌──────────────────────────────────────────────────────────────────────────────┐");
+    outln!("│ {:76} │", title);
+    outln!("├──────────────────────────────────────────────────────────────────────────────┤");
+    let pretty = serde_json::to_string_pretty(json).unwrap_or_default();
+    for line in pretty.lines().take(30) {
+        outln!("│  {}  │", format!("{:74}", line));
+    }
+    if pretty.lines().count() > 30 {
+        outln!("│  ... (truncado)                                                              │");
+    }
+    outln!("└──────────────────────────────────────────────────────────────────────────────┘");
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    term::init(args.no_color);
     
     let file_size = std::fs::metadata(&args.file)?.len();
-    let mut f = File::open(&args.file)
-        .with_context(|| format!("Cannot open {}", args.file.display()))?;
+    let reader = HnfReader::open(&args.file)?;
+    let header = reader.header();
+    
+    // Texto tal cual, sin cajas: pensado para redirigir a un .md
+    if args.card {
+        let card = reader.model_card()
+            .ok_or_else(|| anyhow::anyhow!("{} has no model card", args.file.display()))?;
+        print!("{}", card);
+        return Ok(());
+    }
     
-    // Leer header
-    let header = read_header(&mut f)?;
+    // JSON tal cual: lo consume un engine o un script de compatibilidad
+    if args.capabilities {
+        let capabilities = reader.manifest().get("capabilities")
+            .ok_or_else(|| anyhow::anyhow!("{} has no capabilities in its manifest (older converter)", args.file.display()))?;
+        println!("{}", serde_json::to_string_pretty(capabilities)?);
+        return Ok(());
+    }
+    
+    // Vista rápida de qué hay dentro, sin volcar el manifest entero
+    if args.list_tensors {
+        return list_tensors(&reader, args.block.as_deref());
+    }
+    
+    // Blob tal cual para probar el parser binario del engine
+    if let Some(out) = &args.dump_hints_bin {
+        let blob = reader.block_bytes(BLOCK_EXEC_HINTS_BIN);
+        if blob.is_empty() {
+            anyhow::bail!("{} has no binary execution hints (block 0xB)", args.file.display());
+        }
+        std::fs::write(out, blob)?;
+        println!("{} bytes -> {}", blob.len(), out.display());
+        return Ok(());
+    }
     
     // Validar magic
-    let expected_magic = b"HNFv9\x00\x00\x00";
-    let magic_ok = &header.magic == expected_magic;
+    let magic_ok = &header.magic == MAGIC;
     
-    println!();
-    println!("════════════════════════════════════════════════════════════════════════════════");
-    println!("  HNFv9 INSPECTOR");
-    println!("════════════════════════════════════════════════════════════════════════════════");
-    println!("  Archivo:      {}", args.file.display());
-    println!("  Tamaño real:  {}", format_size(file_size));
-    println!();
+    outln!();
+    outln!("════════════════════════════════════════════════════════════════════════════════");
+    outln!("  HNFv9 INSPECTOR");
+    outln!("════════════════════════════════════════════════════════════════════════════════");
+    outln!("  Archivo:      {}", args.file.display());
+    outln!("  Tamaño real:  {}", format_size(file_size));
+    outln!();
     
     // ═══════════════════════════════════════════════════════════════
     // HEADER
     // ═══════════════════════════════════════════════════════════════
-    println!("┌──────────────────────────────────────────────────────────────────────────────┐");
-    println!("│ HEADER (64 bytes)                                                            │");
-    println!("├──────────────────────────────────────────────────────────────────────────────┤");
+    outln!("┌──────────────────────────────────────────────────────────────────────────────┐");
+    outln!("│ HEADER (64 bytes)                                                            │");
+    outln!("├──────────────────────────────────────────────────────────────────────────────┤");
     
     let magic_str: String = header.magic.iter()
         .map(|&b| if b == 0 { '.' } else { b as char })
         .collect();
     let status = if magic_ok { "✓" } else { "✗ INVÁLIDO" };
     
-    println!("│  Magic:          {:20} {}                          │", format!("{:?}", magic_str), status);
-    println!("│  Versión:        {}.{}                                                        │", header.version_major, header.version_minor);
-    println!("│  Block Count:    {:4}                                                        │", header.block_count);
-    println!("│  Header Size:    {:4}                                                        │", header.header_size);
-    println!("│  File Size:      {:12}                                              │", format_size(header.file_size));
-    println!("│  Checksum:       0x{:08X}                                                  │", header.checksum);
-    println!("└──────────────────────────────────────────────────────────────────────────────┘");
-    println!();
+    outln!("│  Magic:          {:20} {}                          │", format!("{:?}", magic_str), status);
+    outln!("│  Versión:        {}.{}                                                        │", header.version_major, header.version_minor);
+    outln!("│  Block Count:    {:4}                                                        │", header.block_count);
+    outln!("│  Header Size:    {:4}                                                        │", header.header_size);
+    outln!("│  File Size:      {:12}                                              │", format_size(header.file_size));
+    outln!("│  Checksum:       0x{:08X}                                                  │", header.checksum);
+    outln!("└──────────────────────────────────────────────────────────────────────────────┘");
+    outln!();
     
     // ═══════════════════════════════════════════════════════════════
     // FLAGS
     // ═══════════════════════════════════════════════════════════════
-    println!("┌──────────────────────────────────────────────────────────────────────────────┐");
-    println!("│ FLAGS                                                                        │");
-    println!("├──────────────────────────────────────────────────────────────────────────────┤");
+    outln!("┌──────────────────────────────────────────────────────────────────────────────┐");
+    outln!("│ FLAGS                                                                        │");
+    outln!("├──────────────────────────────────────────────────────────────────────────────┤");
     
     let mut active_flags = Vec::new();
-    for (bit, name) in FLAG_NAMES.iter() {
-        if header.flags & (1 << bit) != 0 {
+    for (flag, name) in FLAG_NAMES.iter() {
+        if header.flags.has(*flag) {
             active_flags.push(*name);
         }
     }
     
     if active_flags.is_empty() {
-        println!("│  (ningún flag activo)                                                        │");
+        outln!("│  (ningún flag activo)                                                        │");
     } else {
         for flag in &active_flags {
-            println!("│  ✓ {:72} │", flag);
+            outln!("│  ✓ {:72} │", flag);
         }
     }
     
-    println!("└──────────────────────────────────────────────────────────────────────────────┘");
-    println!();
+    outln!("└──────────────────────────────────────────────────────────────────────────────┘");
+    outln!();
     
     // ═══════════════════════════════════════════════════════════════
     // BLOCK TABLE
     // ═══════════════════════════════════════════════════════════════
-    let blocks = read_block_table(&mut f, header.block_table_offset)?;
+    let blocks = reader.blocks();
     
-    // Encontrar max_size solo de bloques de datos (no hints/tokenizer)
-    let max_data_size = blocks.iter()
-        .filter(|b| b.id < 10) // Solo bloques de datos
-        .map(|b| b.size)
-        .max()
-        .unwrap_or(1);
-    
-    println!("┌──────────────────────────────────────────────────────────────────────────────┐");
-    println!("│ BLOQUES                                                                      │");
-    println!("├──────────────────────────────────────────────────────────────────────────────┤");
+    outln!("┌──────────────────────────────────────────────────────────────────────────────┐");
+    outln!("│ BLOQUES                                                                      │");
+    outln!("├──────────────────────────────────────────────────────────────────────────────┤");
     
     // Categorías
     let categories = [
         ("MODALIDADES", vec![0, 1, 2, 3, 4]),
         ("IDENTIDAD", vec![5, 6]),
-        ("CAPACIDADES", vec![7, 8, 9]),
-        ("RUNTIME", vec![10]),
-        ("EXPERTS", vec![11]),
+        ("CAPACIDADES", vec![7, 8, 9, 12]),
+        ("RUNTIME", vec![10, 11]),
+        ("EXPERTS", vec![13]),
     ];
     
     for (cat_name, indices) in &categories {
-        println!("│  {}                                                                      │", cat_name);
-        println!("│  ────────────────────────────────────────────────────────────────────────  │");
+        outln!("│  {}                                                                      │", cat_name);
+        outln!("│  ────────────────────────────────────────────────────────────────────────  │");
         
         for &idx in indices {
             let b = &blocks[idx];
@@ -276,52 +297,42 @@ fn main() -> Result<()> {
             
             let status = if b.size > 0 { "█" } else { "░" };
             
-            println!("│  [{}] {:18} {} {:>12}  {}               │", 
+            outln!("│  [{}] {:18} {} {:>12}  {}               │", 
                 format!("{:X}", idx), name, bar, size_str, status);
         }
-        println!("│                                                                              │");
+        outln!("│                                                                              │");
     }
     
-    println!("└──────────────────────────────────────────────────────────────────────────────┘");
-    println!();
+    outln!("└──────────────────────────────────────────────────────────────────────────────┘");
+    outln!();
     
     // ═══════════════════════════════════════════════════════════════
     // TOKENIZER & MANIFEST
     // ═══════════════════════════════════════════════════════════════
     
-    // Calcular offset del tokenizer
-    let last_block_end = blocks.iter()
-        .filter(|b| b.size > 0)
-        .map(|b| b.offset + b.size)
-        .max()
-        .unwrap_or(0);
-    
-    let tok_offset = ((last_block_end + 31) / 32) * 32;
-    let tok_size = if header.manifest_offset > tok_offset {
-        header.manifest_offset - tok_offset
-    } else {
-        0
-    };
-    
-    println!("┌──────────────────────────────────────────────────────────────────────────────┐");
-    println!("│ TOKENIZER & MANIFEST                                                         │");
-    println!("├──────────────────────────────────────────────────────────────────────────────┤");
-    println!("│  Tokenizer:                                                                  │");
-    println!("│    Offset:       0x{:X}                                                      │", tok_offset);
-    println!("│    Size:         {:12}                                              │", format_size(tok_size));
-    println!("│                                                                              │");
-    println!("│  Manifest:                                                                   │");
-    println!("│    Offset:       0x{:X}                                                   │", header.manifest_offset);
-    println!("│    Size:         {:12}                                              │", format_size(header.manifest_size));
-    println!("└──────────────────────────────────────────────────────────────────────────────┘");
-    println!();
+    // Tokenizer HTF: bloque 0x9
+    let tokenizer = &blocks[helios_convert::hnf::BLOCK_TOKENIZER];
+    let (tok_offset, tok_size) = (tokenizer.offset, tokenizer.size);
+    
+    outln!("┌──────────────────────────────────────────────────────────────────────────────┐");
+    outln!("│ TOKENIZER & MANIFEST                                                         │");
+    outln!("├──────────────────────────────────────────────────────────────────────────────┤");
+    outln!("│  Tokenizer:                                                                  │");
+    outln!("│    Offset:       0x{:X}                                                      │", tok_offset);
+    outln!("│    Size:         {:12}                                              │", format_size(tok_size));
+    outln!("│                                                                              │");
+    outln!("│  Manifest:                                                                   │");
+    outln!("│    Offset:       0x{:X}                                                   │", header.manifest_offset);
+    outln!("│    Size:         {:12}                                              │", format_size(header.manifest_size));
+    outln!("└──────────────────────────────────────────────────────────────────────────────┘");
+    outln!();
     
     // ═══════════════════════════════════════════════════════════════
     // MAPA DE ARCHIVO
     // ═══════════════════════════════════════════════════════════════
-    println!("┌──────────────────────────────────────────────────────────────────────────────┐");
-    println!("│ MAPA DE ARCHIVO                                                              │");
-    println!("├──────────────────────────────────────────────────────────────────────────────┤");
+    outln!("┌──────────────────────────────────────────────────────────────────────────────┐");
+    outln!("│ MAPA DE ARCHIVO                                                              │");
+    outln!("├──────────────────────────────────────────────────────────────────────────────┤");
     
     let mut sections: Vec<(&str, u64, u64)> = vec![
         ("Header", 0, 64),
@@ -334,10 +345,6 @@ fn main() -> Result<()> {
         }
     }
     
-    if tok_size > 0 {
-        sections.push(("Tokenizer", tok_offset, tok_size));
-    }
-    
     sections.push(("Manifest", header.manifest_offset, header.manifest_size));
     
     // Ordenar por offset
@@ -347,34 +354,33 @@ fn main() -> Result<()> {
         let pct = *size as f64 / file_size as f64 * 100.0;
         let bar = make_bar(*size, file_size, 35);
         
-        println!("│  {:20} [{}] {:>6.2}%               │", name, bar, pct);
+        outln!("│  {:20} [{}] {:>6.2}%               │", name, bar, pct);
     }
     
-    println!("└──────────────────────────────────────────────────────────────────────────────┘");
-    println!();
+    // Lo que no es header, tabla, bloque ni manifest: alineación a 32 bytes
+    let padding = reader.padding_bytes();
+    let bound = reader.padding_bound();
+    let status = if padding <= bound { "✓" } else { "⚠ excede la alineación" };
+    outln!("│                                                                              │");
+    outln!("│  Padding:        {:>10} bytes (cota {} bytes) {:24} │", padding, bound, status);
+    
+    outln!("└──────────────────────────────────────────────────────────────────────────────┘");
+    outln!();
     
     // ═══════════════════════════════════════════════════════════════
     // MANIFEST (opcional)
     // ═══════════════════════════════════════════════════════════════
-    if args.manifest && header.manifest_size > 0 {
-        f.seek(SeekFrom::Start(header.manifest_offset))?;
-        let mut manifest_data = vec![0u8; header.manifest_size as usize];
-        f.read_exact(&mut manifest_data)?;
-        
-        if let Ok(json_str) = String::from_utf8(manifest_data) {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&json_str) {
-                println!("┌──────────────────────────────────────────────────────────────────────────────┐");
-                println!("│ MANIFEST JSON                                                                │");
-                println!("├──────────────────────────────────────────────────────────────────────────────┤");
-                let pretty = serde_json::to_string_pretty(&json).unwrap_or_default();
-                for line in pretty.lines().take(30) {
-                    println!("│  {}  │", format!("{:74}", line));
-                }
-                if pretty.lines().count() > 30 {
-                    println!("│  ... (truncado)                                                              │");
-                }
-                println!("└──────────────────────────────────────────────────────────────────────────────┘");
-            }
+    if args.manifest {
+        print_json("MANIFEST JSON", reader.manifest());
+    }
+    
+    // ═══════════════════════════════════════════════════════════════
+    // EXECUTION HINTS (opcional)
+    // ═══════════════════════════════════════════════════════════════
+    if args.hints {
+        match reader.execution_hints_json()? {
+            Some(hints) => print_json("EXECUTION HINTS JSON", &hints),
+            None => outln!("  (sin execution_hints)"),
         }
     }
     
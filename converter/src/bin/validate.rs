@@ -8,24 +8,36 @@
 // Valida:
 //   - HNFv9 (.hnf) - Modelo principal
 //   - HTF v1.2.1 (.htf embebido) - Tokenizer
+//   - HTF2/HTF3 sueltos (.htf de build_htf) - htf::validate::validate_htf
 //
 // Uso:
-//   helios-validate archivo.hnf [-v]
+//   helios-validate archivo.hnf [-v] [--checksum xxh3|blake3] [--ascii]
+//   helios-validate archivo.hnf --spot-check 16   (dequantiza 16 tensores al azar)
+//   helios-validate tokenizer.htf
 //
 // ============================================================================
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
 
 use clap::Parser;
+use helios_convert::{term, eoutln, outln};
+use helios_convert::hnf::{
+    span, BlockEntry, ChecksumAlgo, HeaderFlags, HnfHeader, HnfReader, TensorManifest, BLOCK_NAMES, MAGIC,
+    MINOR_BLAKE3, MINOR_CHECKSUM_SEGMENTS, MINOR_EXEC_HINTS_BIN, VERSION_MAJOR, VERSION_MINOR,
+};
+use helios_convert::hints::binary::{build_execution_hints_binary, ExecutionHintsBin, TextModelConfigBin};
+use helios_convert::hqs::{dequantize_with, QuantFormat};
+use helios_convert::htf::validate::{inner_checksum, validate_htf, print_validation_result};
+use helios_convert::htf::{HTF_MAGIC, HTF_MAGIC_V13};
 
 // ============================================================================
 // CONSTANTES HNFv9 (HNFv9_MASTER_SPEC.txt)
 // ============================================================================
 
-const HNF_MAGIC: &[u8; 8] = b"HNFv9\x00\x00\x00";
-const HNF_VERSION_MAJOR: u16 = 9;
+// Magic, versiones, nombres de bloque y flags: los de helios_convert::hnf
 const HNF_BLOCK_COUNT: usize = 16;
 const HNF_HEADER_SIZE: usize = 64;
 const HNF_BLOCK_ENTRY_SIZE: usize = 32;
@@ -33,26 +45,6 @@ const HNF_BLOCK_TABLE_SIZE: usize = HNF_BLOCK_COUNT * HNF_BLOCK_ENTRY_SIZE; // 5
 const HNF_BLOCK_TABLE_OFFSET: usize = HNF_HEADER_SIZE; // 64
 const HNF_ALIGNMENT: usize = 32; // CUDA alignment
 
-// Nombres de bloques según spec (0x7 = cortex, 0x9 = tokenizer)
-const HNF_BLOCK_NAMES: [&str; 16] = [
-    "text_model",       // 0x0 - OBLIGATORIO
-    "vision",           // 0x1
-    "audio",            // 0x2
-    "video",            // 0x3
-    "spatial_3d",       // 0x4
-    "personality",      // 0x5 - MAX 20MB
-    "memory",           // 0x6 - MAX 50MB
-    "cortex",           // 0x7
-    "code_exec",        // 0x8
-    "tokenizer",        // 0x9 - HTF tokenizer
-    "execution_hints",  // 0xA - OBLIGATORIO
-    "expert_router",    // 0xB
-    "tools",            // 0xC
-    "reserved_1",       // 0xD
-    "reserved_2",       // 0xE
-    "reserved_3",       // 0xF
-];
-
 // Límites
 const PERSONALITY_MAX_SIZE: usize = 20 * 1024 * 1024; // 20 MB
 const MEMORY_MAX_SIZE: usize = 50 * 1024 * 1024;      // 50 MB
@@ -109,13 +101,14 @@ const VALID_ARCHS: &[&str] = &[
     "phi", "phi3", "phi4",
     "mistral", "mixtral",
     "falcon", "mpt", "gpt2",
+    "persimmon", "fuyu",
     "clip", "siglip", "vit",
 ];
 
 const VALID_DTYPES: &[&str] = &["fp16", "bf16", "fp32"];
 const VALID_ATTENTION_TYPES: &[&str] = &["mha", "gqa", "mqa"];
 const VALID_MLP_TYPES: &[&str] = &["swiglu", "geglu", "gated", "standard"];
-const VALID_MLP_ACTIVATIONS: &[&str] = &["silu", "gelu", "gelu_new", "gelu_fast", "relu", "quick_gelu"];
+const VALID_MLP_ACTIVATIONS: &[&str] = &["silu", "gelu", "gelu_new", "gelu_fast", "relu", "relu2", "quick_gelu"];
 const VALID_NORM_TYPES: &[&str] = &["rmsnorm", "layernorm"];
 
 // ============================================================================
@@ -168,31 +161,6 @@ impl ValidationResult {
     }
 }
 
-#[derive(Debug, Clone)]
-struct HnfHeader {
-    magic: [u8; 8],
-    version_major: u16,
-    version_minor: u16,
-    flags: u32,
-    block_count: u32,
-    header_size: u32,
-    block_table_offset: u64,
-    manifest_offset: u64,
-    manifest_size: u64,
-    file_size: u64,
-    checksum: u32,
-}
-
-#[derive(Debug, Clone)]
-struct BlockEntry {
-    id: u32,
-    block_type: u32,
-    offset: u64,
-    size: u64,
-    checksum: u64,
-    name: String,
-}
-
 #[derive(Debug, Clone)]
 struct HtfInfo {
     offset: usize,
@@ -227,28 +195,73 @@ fn format_size(size: usize) -> String {
     }
 }
 
+/// N bytes en `offset`, o ceros si no caben: los llamadores comprueban
+/// límites antes, esto solo evita el pánico con entradas malformadas
+fn read_le<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    offset.checked_add(N)
+        .and_then(|end| data.get(offset..end))
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or([0; N])
+}
+
 fn read_u16_le(data: &[u8], offset: usize) -> u16 {
-    u16::from_le_bytes([data[offset], data[offset + 1]])
+    u16::from_le_bytes(read_le(data, offset))
 }
 
 fn read_u32_le(data: &[u8], offset: usize) -> u32 {
-    u32::from_le_bytes([
-        data[offset], data[offset + 1], 
-        data[offset + 2], data[offset + 3]
-    ])
+    u32::from_le_bytes(read_le(data, offset))
 }
 
 fn read_u64_le(data: &[u8], offset: usize) -> u64 {
-    u64::from_le_bytes([
-        data[offset], data[offset + 1], data[offset + 2], data[offset + 3],
-        data[offset + 4], data[offset + 5], data[offset + 6], data[offset + 7],
-    ])
+    u64::from_le_bytes(read_le(data, offset))
+}
+
+/// "text.layer17.attn.q_proj.weight" → ("text", 17, "attn.q_proj.weight")
+fn split_layer_name(name: &str) -> Option<(String, u64, String)> {
+    let parts: Vec<&str> = name.split('.').collect();
+    parts.iter().enumerate().find_map(|(i, part)| {
+        let index = part.strip_prefix("layer")?;
+        if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some((parts[..i].join("."), index.parse().ok()?, parts[i + 1..].join(".")))
+    })
+}
+
+/// Grupo funcional de un sufijo de capa: "attn.k_proj.weight" → "attn",
+/// "mlp.up.weight" → "mlp". Biases, norms y expertos MoE no entran
+fn projection_group(suffix: &str) -> Option<&'static str> {
+    let projection = suffix.strip_suffix(".weight")?;
+    match projection.split_once('.')? {
+        ("attn", proj) if proj.ends_with("_proj") => Some("attn"),
+        ("mlp", proj) if !proj.contains('.') => Some("mlp"),
+        _ => None,
+    }
+}
+
+/// [3, 4, 5, 9] → "3-5, 9"
+fn format_layer_ranges(layers: &[u64]) -> String {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for &layer in layers {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == layer => *end = layer,
+            _ => ranges.push((layer, layer)),
+        }
+    }
+    ranges.iter()
+        .map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 fn xxh3_64(data: &[u8]) -> u64 {
     xxhash_rust::xxh3::xxh3_64(data)
 }
 
+fn parse_checksum_algo(s: &str) -> Result<ChecksumAlgo, String> {
+    ChecksumAlgo::parse(s).ok_or_else(|| format!("algoritmo desconocido '{}' (xxh3, blake3)", s.to_ascii_lowercase()))
+}
+
 fn domain_type_name(t: u8) -> &'static str {
     match t {
         HTF_DOMAIN_TEXT => "TEXT",
@@ -277,6 +290,8 @@ struct HnfValidator {
     data: Vec<u8>,
     verbose: bool,
     result: ValidationResult,
+    /// --checksum: algoritmo exigido (None = el que declare el header)
+    expected_checksum: Option<ChecksumAlgo>,
 }
 
 impl HnfValidator {
@@ -285,40 +300,55 @@ impl HnfValidator {
             data,
             verbose,
             result: ValidationResult::default(),
+            expected_checksum: None,
         }
     }
     
+    fn with_expected_checksum(mut self, algo: Option<ChecksumAlgo>) -> Self {
+        self.expected_checksum = algo;
+        self
+    }
+    
+    /// Algoritmo de checksum declarado en el header
+    fn checksum_algo(&self) -> ChecksumAlgo {
+        self.result.header.as_ref()
+            .map(|h| ChecksumAlgo::from_flags(h.flags))
+            .unwrap_or(ChecksumAlgo::Xxh3)
+    }
+    
     fn log(&self, msg: &str) {
         if self.verbose {
-            println!("    {}", msg);
+            outln!("    {}", msg);
         }
     }
     
     fn validate(mut self) -> ValidationResult {
-        println!("\n{}", "=".repeat(72));
-        println!("HNFv9 STRICT VALIDATOR");
-        println!("{}", "=".repeat(72));
-        println!("  Tamaño: {}", format_size(self.data.len()));
+        outln!("\n{}", "=".repeat(72));
+        outln!("HNFv9 STRICT VALIDATOR");
+        outln!("{}", "=".repeat(72));
+        outln!("  Tamaño: {}", format_size(self.data.len()));
         
         // Lista de validaciones
         let checks: Vec<(&str, fn(&mut Self))> = vec![
-            ("[1/12] HEADER", Self::validate_header),
-            ("[2/12] BLOCK TABLE", Self::validate_block_table),
-            ("[3/12] BLOQUES OBLIGATORIOS", Self::validate_required_blocks),
-            ("[4/12] LÍMITES DE TAMAÑO", Self::validate_block_limits),
-            ("[5/12] FLAGS COHERENTES", Self::validate_flags),
-            ("[6/12] ORDEN FÍSICO", Self::validate_physical_order),
-            ("[7/12] ALINEACIÓN", Self::validate_alignment),
-            ("[8/12] EXECUTION_HINTS", Self::validate_execution_hints),
-            ("[9/12] TOKENIZER HTF", Self::validate_tokenizer),
-            ("[10/12] MANIFEST", Self::validate_manifest),
-            ("[11/12] CHECKSUMS", Self::validate_checksums),
-            ("[12/12] TENSORES", Self::validate_tensors),
+            ("[1/14] HEADER", Self::validate_header),
+            ("[2/14] BLOCK TABLE", Self::validate_block_table),
+            ("[3/14] BLOQUES OBLIGATORIOS", Self::validate_required_blocks),
+            ("[4/14] LÍMITES DE TAMAÑO", Self::validate_block_limits),
+            ("[5/14] FLAGS COHERENTES", Self::validate_flags),
+            ("[6/14] ORDEN FÍSICO", Self::validate_physical_order),
+            ("[7/14] ALINEACIÓN", Self::validate_alignment),
+            ("[8/14] EXECUTION_HINTS", Self::validate_execution_hints),
+            ("[9/14] TOKENIZER HTF", Self::validate_tokenizer),
+            ("[10/14] MANIFEST", Self::validate_manifest),
+            ("[11/14] FEATURES POR VERSIÓN", Self::validate_version_features),
+            ("[12/14] CHECKSUMS", Self::validate_checksums),
+            ("[13/14] TENSORES", Self::validate_tensors),
+            ("[14/14] FLAGS DE MODELO", Self::validate_model_flags),
         ];
         
         for (name, check_fn) in checks {
-            println!("\n{}", "─".repeat(72));
-            println!("{}", name);
+            outln!("\n{}", "─".repeat(72));
+            outln!("{}", name);
             check_fn(&mut self);
         }
         
@@ -333,34 +363,22 @@ impl HnfValidator {
             return;
         }
         
-        let mut magic = [0u8; 8];
-        magic.copy_from_slice(&self.data[0..8]);
-        
-        let header = HnfHeader {
-            magic,
-            version_major: read_u16_le(&self.data, 8),
-            version_minor: read_u16_le(&self.data, 10),
-            flags: read_u32_le(&self.data, 12),
-            block_count: read_u32_le(&self.data, 16),
-            header_size: read_u32_le(&self.data, 20),
-            block_table_offset: read_u64_le(&self.data, 24),
-            manifest_offset: read_u64_le(&self.data, 32),
-            manifest_size: read_u64_le(&self.data, 40),
-            file_size: read_u64_le(&self.data, 48),
-            checksum: read_u32_le(&self.data, 56),
+        // Sin validar: el validador informa de cada campo por separado
+        let Ok(header) = HnfHeader::from_bytes(&self.data[..HNF_HEADER_SIZE]) else {
+            return;
         };
         
         // Validaciones estrictas
-        if &header.magic != HNF_MAGIC {
+        if &header.magic != MAGIC {
             self.result.add_error("HEADER", 
-                &format!("Magic inválido: {:?} (esperado: {:?})", header.magic, HNF_MAGIC), true);
+                &format!("Magic inválido: {:?} (esperado: {:?})", header.magic, MAGIC), true);
         } else {
             self.log(&format!("✓ Magic: {:?}", header.magic));
         }
         
-        if header.version_major != HNF_VERSION_MAJOR {
+        if header.version_major != VERSION_MAJOR {
             self.result.add_error("HEADER",
-                &format!("version_major: {} (esperado: {})", header.version_major, HNF_VERSION_MAJOR), true);
+                &format!("version_major: {} (esperado: {})", header.version_major, VERSION_MAJOR), true);
         } else {
             self.log(&format!("✓ Versión: {}.{}", header.version_major, header.version_minor));
         }
@@ -389,7 +407,7 @@ impl HnfValidator {
             self.log(&format!("✓ file_size: {}", header.file_size));
         }
         
-        if header.manifest_offset + header.manifest_size != header.file_size {
+        if header.manifest_offset.checked_add(header.manifest_size) != Some(header.file_size) {
             self.result.add_error("HEADER",
                 &format!("Manifest no está al EOF: {}+{} != {}", 
                     header.manifest_offset, header.manifest_size, header.file_size), true);
@@ -412,19 +430,14 @@ impl HnfValidator {
         for i in 0..HNF_BLOCK_COUNT {
             let offset = HNF_BLOCK_TABLE_OFFSET + i * HNF_BLOCK_ENTRY_SIZE;
             
-            let block = BlockEntry {
-                id: read_u32_le(&self.data, offset),
-                block_type: read_u32_le(&self.data, offset + 4),
-                offset: read_u64_le(&self.data, offset + 8),
-                size: read_u64_le(&self.data, offset + 16),
-                checksum: read_u64_le(&self.data, offset + 24),
-                name: HNF_BLOCK_NAMES[i].to_string(),
+            let Ok(block) = BlockEntry::from_bytes(&self.data[offset..offset + HNF_BLOCK_ENTRY_SIZE]) else {
+                return;
             };
             
             // Validar id y type
-            if block.id != i as u32 {
+            if block.block_id != i as u32 {
                 self.result.add_error("BLOCK_TABLE",
-                    &format!("Bloque {}: block_id={} (esperado: {})", i, block.id, i), true);
+                    &format!("Bloque {}: block_id={} (esperado: {})", i, block.block_id, i), true);
             }
             
             if block.block_type != i as u32 {
@@ -440,7 +453,7 @@ impl HnfValidator {
             
             if block.size > 0 {
                 self.log(&format!("✓ [{:2}] {:20}: {:>12} @ {}", 
-                    i, HNF_BLOCK_NAMES[i], format_size(block.size as usize), block.offset));
+                    i, BLOCK_NAMES[i], format_size(block.size as usize), block.offset));
             }
             
             blocks.push(block);
@@ -503,22 +516,23 @@ impl HnfValidator {
         
         let flags = header.flags;
         
-        // Mapeo flag -> índice de bloque
-        let flag_block_map: [(u32, usize, &str); 10] = [
-            (1 << 0, 1, "vision"),
-            (1 << 1, 2, "audio"),
-            (1 << 2, 3, "video"),
-            (1 << 3, 4, "spatial_3d"),
-            (1 << 4, 5, "personality"),
-            (1 << 5, 6, "memory"),
-            (1 << 6, 7, "cortex"),  // ← CORREGIDO
-            (1 << 7, 8, "code_exec"),
-            (1 << 8, 9, "tools"),
-            (1 << 9, 11, "expert_router"),
+        // Mapeo flag -> índice de bloque (HeaderFlags del writer)
+        let flag_block_map: [(u32, usize, &str); 11] = [
+            (HeaderFlags::HAS_VISION, 1, "vision"),
+            (HeaderFlags::HAS_AUDIO, 2, "audio"),
+            (HeaderFlags::HAS_VIDEO, 3, "video"),
+            (HeaderFlags::HAS_SPATIAL, 4, "spatial_3d"),
+            (HeaderFlags::HAS_PERSONALITY, 5, "personality"),
+            (HeaderFlags::HAS_MEMORY, 6, "memory"),
+            (HeaderFlags::HAS_CORTEX, 7, "cortex"),
+            (HeaderFlags::HAS_CODE_EXEC, 8, "code_exec"),
+            (HeaderFlags::HAS_EXEC_HINTS_BIN, 11, "exec_hints_bin"),
+            (HeaderFlags::HAS_TOOLS, 12, "tools"),
+            (HeaderFlags::HAS_EXPERT_ROUTER, 13, "expert_router"),
         ];
         
         for (flag, idx, name) in flag_block_map.iter() {
-            let has_flag = (flags & flag) != 0;
+            let has_flag = flags.has(*flag);
             let has_data = self.result.blocks[*idx].size > 0;
             
             if has_flag && !has_data {
@@ -532,14 +546,47 @@ impl HnfValidator {
             }
         }
         
-        // IS_MULTIMODAL
-        let is_multimodal = (flags & (1 << 11)) != 0;
-        let has_multimodal = self.result.blocks[1].size > 0 
-            || self.result.blocks[2].size > 0 
-            || self.result.blocks[3].size > 0;
+        // IS_MULTIMODAL: vision/audio/video/spatial_3d
+        let is_multimodal = flags.has(HeaderFlags::IS_MULTIMODAL);
+        let has_multimodal = self.result.blocks[1..=4].iter().any(|b| b.size > 0);
         
         if is_multimodal && !has_multimodal {
             self.result.add_error("FLAGS", "IS_MULTIMODAL activo pero no hay datos multimodales", false);
+        } else if !is_multimodal && has_multimodal {
+            self.result.add_error("FLAGS", "Hay bloques multimodales pero IS_MULTIMODAL inactivo", false);
+        }
+    }
+    
+    /// IS_MOE frente al contenido: tensores moe.experts.* en el manifest o
+    /// moe_enabled en los hints. Sin el flag el engine no carga los expertos.
+    fn validate_model_flags(&mut self) {
+        let Some(header) = &self.result.header else { return };
+        let is_moe = header.flags.has(HeaderFlags::IS_MOE);
+        
+        let expert_tensors = self.result.manifest.as_ref()
+            .and_then(|m| m.get("tensors"))
+            .and_then(|v| v.as_array())
+            .map(|tensors| tensors.iter()
+                .filter_map(|t| t.get("name").and_then(|v| v.as_str()))
+                .filter(|name| name.contains("moe.experts."))
+                .count())
+            .unwrap_or(0);
+        
+        // moe_enabled en la raíz o en cualquier modalidad (text, cortex...)
+        let hints_moe = self.result.execution_hints.as_ref().is_some_and(|hints| {
+            let enabled = |h: &serde_json::Value| h.get("moe_enabled").and_then(|v| v.as_bool()).unwrap_or(false);
+            enabled(hints) || hints.as_object().is_some_and(|obj| obj.values().any(enabled))
+        });
+        
+        match (is_moe, expert_tensors > 0 || hints_moe) {
+            (false, true) => self.result.add_error("FLAGS", &format!(
+                "IS_MOE inactivo pero el modelo es MoE ({} tensores moe.experts.*, moe_enabled={})",
+                expert_tensors, hints_moe
+            ), false),
+            (true, false) => self.result.add_error("FLAGS",
+                "IS_MOE activo pero no hay tensores moe.experts.* ni moe_enabled", false),
+            (true, true) => self.log(&format!("✓ IS_MOE coherente ({} tensores de expertos)", expert_tensors)),
+            (false, false) => {}
         }
     }
     
@@ -553,27 +600,29 @@ impl HnfValidator {
             None => return,
         };
         
-        // Clone para evitar borrow conflict
-        let blocks = self.result.blocks.clone();
+        // Monótono por offset, no por índice: --metadata-first coloca hints y
+        // tokenizer antes que los bloques de pesos
+        let mut blocks: Vec<(usize, BlockEntry)> = self.result.blocks.iter()
+            .cloned()
+            .enumerate()
+            .filter(|(_, b)| b.size > 0)
+            .collect();
+        blocks.sort_by_key(|(_, b)| b.offset);
         let mut prev_end = (HNF_HEADER_SIZE + HNF_BLOCK_TABLE_SIZE) as u64;
         
-        for (i, block) in blocks.iter().enumerate() {
-            if block.size == 0 {
-                continue;
-            }
-            
+        for (i, block) in blocks.iter() {
             if block.offset < prev_end {
                 self.result.add_error("ORDER",
                     &format!("Bloque {}: offset {} < fin anterior {}", i, block.offset, prev_end), true);
             }
             
-            let gap = block.offset - prev_end;
+            let gap = block.offset.saturating_sub(prev_end);
             if gap > HNF_ALIGNMENT as u64 {
                 self.result.add_error("ORDER",
                     &format!("Bloque {}: hueco de {} bytes (max {})", i, gap, HNF_ALIGNMENT), false);
             }
             
-            prev_end = block.offset + block.size;
+            prev_end = block.offset.saturating_add(block.size);
         }
         
         if header.manifest_offset > 0 && header.manifest_offset < prev_end {
@@ -618,15 +667,12 @@ impl HnfValidator {
         }
         
         let block = &self.result.blocks[10];
-        let start = block.offset as usize;
-        let end = start + block.size as usize;
-        
-        if end > self.data.len() {
+        let Some(range) = span(self.data.len(), block.offset, block.size) else {
             self.result.add_error("EXEC_HINTS", "Bloque fuera de límites", true);
             return;
-        }
+        };
         
-        let hints_data = &self.data[start..end];
+        let hints_data = &self.data[range];
         
         let hints: serde_json::Value = match serde_json::from_slice(hints_data) {
             Ok(v) => v,
@@ -711,6 +757,14 @@ impl HnfValidator {
             }
         }
         
+        // Validar RoPE parcial: rope_dim = head_dim * partial_rotary_factor (±1 por redondeo)
+        self.validate_rope_dim("", &hints);
+        for scope in ["text", "code", "cortex", "vision", "audio"] {
+            if let Some(sub) = hints.get(scope) {
+                self.validate_rope_dim(scope, sub);
+            }
+        }
+        
         // Validar MoE
         if hints.get("moe_enabled").and_then(|v| v.as_bool()).unwrap_or(false) {
             if hints.get("num_experts").is_none() {
@@ -736,9 +790,81 @@ impl HnfValidator {
             }
         }
         
+        self.validate_binary_hints(&hints);
         self.result.execution_hints = Some(hints);
     }
     
+    /// Bloque 0xB frente a 0xA: el engine puede leer cualquiera de los dos,
+    /// así que los campos clave tienen que coincidir (divergencia = fatal)
+    fn validate_binary_hints(&mut self, hints: &serde_json::Value) {
+        let block = &self.result.blocks[11];
+        if block.size == 0 {
+            return;
+        }
+        let Some(range) = span(self.data.len(), block.offset, block.size) else {
+            self.result.add_error("EXEC_HINTS_BIN", "Bloque fuera de límites", true);
+            return;
+        };
+        let binary = &self.data[range];
+        
+        let Some(header) = ExecutionHintsBin::from_bytes(binary) else {
+            self.result.add_error("EXEC_HINTS_BIN", "Magic 'HINT' ausente o bloque truncado", true);
+            return;
+        };
+        let Some(stored) = binary.get(header.text_offset as usize..).and_then(TextModelConfigBin::from_bytes) else {
+            self.result.add_error("EXEC_HINTS_BIN",
+                &format!("text_offset {} sin TextModelConfigBin completo", header.text_offset), true);
+            return;
+        };
+        
+        // Hints combinados: el modelo de texto va bajo "text"
+        let text = hints.get("text").unwrap_or(hints);
+        let divergences = binary_hints_divergences(text, &stored);
+        for divergence in &divergences {
+            self.result.add_error("EXEC_HINTS_BIN", &format!("JSON y binario no coinciden: {}", divergence), true);
+        }
+        
+        if divergences.is_empty() {
+            self.log(&format!("✓ Hints binarios v{}.{}: campos clave coherentes con el JSON",
+                header.version_major, header.version_minor));
+            // Campos secundarios (flags, eps...): aviso, no fatal
+            if binary != build_execution_hints_binary(hints).as_slice() {
+                self.result.add_error("EXEC_HINTS_BIN",
+                    "Campos secundarios difieren de lo que genera el JSON actual", false);
+            }
+        }
+    }
+    
+    /// Comprueba rope_dim contra head_dim * partial_rotary_factor cuando
+    /// rope_partial es true. `scope` es la sección de los hints ("" = raíz).
+    fn validate_rope_dim(&mut self, scope: &str, hints: &serde_json::Value) {
+        if !hints.get("rope_partial").and_then(|v| v.as_bool()).unwrap_or(false) {
+            return;
+        }
+        let prefix = if scope.is_empty() { String::new() } else { format!("{}.", scope) };
+        
+        let (Some(rope_dim), Some(head_dim), Some(factor)) = (
+            hints.get("rope_dim").and_then(|v| v.as_u64()),
+            hints.get("head_dim").and_then(|v| v.as_u64()),
+            hints.get("partial_rotary_factor").and_then(|v| v.as_f64()),
+        ) else {
+            self.result.add_error("EXEC_HINTS",
+                &format!("{}rope_partial sin rope_dim/head_dim/partial_rotary_factor", prefix), false);
+            return;
+        };
+        
+        let expected = head_dim as f64 * factor;
+        if (rope_dim as f64 - expected).abs() > 1.0 {
+            self.result.add_error("EXEC_HINTS",
+                &format!(
+                    "{}rope_dim ({}) no coincide con head_dim ({}) * partial_rotary_factor ({}) = {:.1}",
+                    prefix, rope_dim, head_dim, factor, expected
+                ), true);
+        } else {
+            self.log(&format!("  {}rope_dim: {} ({} * {})", prefix, rope_dim, head_dim, factor));
+        }
+    }
+    
     fn validate_tokenizer(&mut self) {
         let header = match &self.result.header {
             Some(h) => h.clone(),
@@ -749,11 +875,16 @@ impl HnfValidator {
             return;
         }
         
+        if self.result.blocks[9].size > 0 {
+            self.validate_tokenizer_block();
+            return;
+        }
+        
         // Encontrar fin del último bloque
         let mut last_end = (HNF_HEADER_SIZE + HNF_BLOCK_TABLE_SIZE) as u64;
         for block in &self.result.blocks {
             if block.size > 0 {
-                let end = block.offset + block.size;
+                let end = block.offset.saturating_add(block.size);
                 if end > last_end {
                     last_end = end;
                 }
@@ -761,7 +892,7 @@ impl HnfValidator {
         }
         
         // Alinear a 32 bytes
-        last_end = (last_end + HNF_ALIGNMENT as u64 - 1) & !(HNF_ALIGNMENT as u64 - 1);
+        last_end = last_end.saturating_add(HNF_ALIGNMENT as u64 - 1) & !(HNF_ALIGNMENT as u64 - 1);
         
         let tokenizer_size = header.manifest_offset.saturating_sub(last_end) as usize;
         let tokenizer_offset = last_end as usize;
@@ -773,12 +904,10 @@ impl HnfValidator {
         
         self.log(&format!("  Tokenizer: offset {}, size {}", tokenizer_offset, format_size(tokenizer_size)));
         
-        if tokenizer_offset + 4 > self.data.len() {
+        let Some(magic) = span(self.data.len(), last_end, 4).map(|range| &self.data[range]) else {
             self.result.add_error("TOKENIZER", "Tokenizer fuera de límites", true);
             return;
-        }
-        
-        let magic = &self.data[tokenizer_offset..tokenizer_offset + 4];
+        };
         
         if magic == HTF_MAGIC_V2 {
             self.log("✓ HTF v2.x (Multi-Domain) detectado");
@@ -791,18 +920,56 @@ impl HnfValidator {
         }
     }
     
+    /// Bloque 0x9: checksum de la block table y checksum interno del HTF por
+    /// separado, para distinguir "tokenizer corrupto" de "block table obsoleta"
+    fn validate_tokenizer_block(&mut self) {
+        let block = self.result.blocks[9].clone();
+        let Some(range) = span(self.data.len(), block.offset, block.size) else {
+            self.result.add_error("TOKENIZER", "Bloque 0x9 fuera de límites del archivo", true);
+            return;
+        };
+        let blob = &self.data[range];
+        
+        let Some((stored, computed)) = inner_checksum(blob) else {
+            self.result.add_error("TOKENIZER",
+                &format!("Bloque 0x9 de {} bytes: no llega al header HTF ({})", blob.len(), HTF_HEADER_SIZE), true);
+            return;
+        };
+        if !matches!(&blob[..4], b"HTF1" | b"HTF2" | b"HTF3") {
+            self.result.add_error("TOKENIZER", &format!("Magic HTF inválido: {:?}", &blob[..4]), true);
+            return;
+        }
+        self.log(&format!("  Tokenizer: {} en bloque 0x9, {}",
+            String::from_utf8_lossy(&blob[..4]), format_size(blob.len())));
+        
+        // checksum 0 = bloque escrito sin checksum: solo cuenta el del HTF
+        let algo = self.checksum_algo();
+        let block_ok = block.checksum == 0 || algo.digest(blob) == block.checksum;
+        let htf_ok = stored == computed;
+        match (block_ok, htf_ok) {
+            (true, true) => self.log(&format!("✓ Checksum de bloque ({}) y checksum interno HTF válidos", algo.name())),
+            (true, false) => self.result.add_error("TOKENIZER_CRC",
+                &format!("Checksum interno HTF inválido (0x{:016X} != 0x{:016X}) con checksum de bloque válido: \
+                    el tokenizer ya estaba corrupto al escribirse; la block table es coherente", stored, computed), true),
+            (false, true) => self.result.add_error("TOKENIZER_CRC",
+                "Checksum de bloque 0x9 inválido con HTF íntegro: block table obsoleta, el tokenizer está bien", true),
+            (false, false) => self.result.add_error("TOKENIZER_CRC",
+                "Checksum de bloque 0x9 y checksum interno HTF inválidos: tokenizer dañado después de escribirse", true),
+        }
+    }
+    
     fn validate_htf_v2(&mut self, offset: usize, size: usize) {
         if size < HTF_HEADER_SIZE {
             self.result.add_error("HTF", &format!("HTF v2 muy pequeño: {} < {}", size, HTF_HEADER_SIZE), true);
             return;
         }
         
-        if offset + size > self.data.len() {
+        let Some(range) = span(self.data.len(), offset as u64, size as u64) else {
             self.result.add_error("HTF", "HTF fuera de límites del archivo", true);
             return;
-        }
+        };
         
-        let blob = &self.data[offset..offset + size];
+        let blob = &self.data[range];
         
         // Parse header
         let version = read_u16_le(blob, 4);
@@ -918,7 +1085,7 @@ impl HnfValidator {
             }
             
             // Verificar que no se sale del HTF
-            if data_offset + data_size > size as u64 {
+            if data_offset.checked_add(data_size).is_none_or(|end| end > size as u64) {
                 self.result.add_error("HTF", &format!("Domain[{}] data fuera de límites", i), true);
                 return;
             }
@@ -982,15 +1149,12 @@ impl HnfValidator {
             return;
         }
         
-        let start = header.manifest_offset as usize;
-        let end = start + header.manifest_size as usize;
-        
-        if end > self.data.len() {
+        let Some(range) = span(self.data.len(), header.manifest_offset, header.manifest_size) else {
             self.result.add_error("MANIFEST", "Manifest fuera de límites", true);
             return;
-        }
+        };
         
-        let manifest_data = &self.data[start..end];
+        let manifest_data = &self.data[range];
         
         let manifest: serde_json::Value = match serde_json::from_slice(manifest_data) {
             Ok(v) => v,
@@ -1025,6 +1189,42 @@ impl HnfValidator {
         self.result.manifest = Some(manifest);
     }
     
+    /// Features que la versión menor declarada no conoce: un lector de esa
+    /// versión no sabría interpretarlas
+    fn validate_version_features(&mut self) {
+        let header = match &self.result.header {
+            Some(h) => h.clone(),
+            None => return,
+        };
+        let minor = header.version_minor;
+        
+        if minor > VERSION_MINOR {
+            self.result.add_error("VERSION",
+                &format!("version_minor {} es más nueva que este validador ({}): features desconocidas sin validar",
+                    minor, VERSION_MINOR), false);
+            return;
+        }
+        
+        if header.flags.has(HeaderFlags::CHECKSUM_BLAKE3) && minor < MINOR_BLAKE3 {
+            self.result.add_error("VERSION",
+                &format!("Checksums BLAKE3 requieren 9.{} (el archivo declara 9.{})", MINOR_BLAKE3, minor), true);
+        }
+        
+        if self.result.blocks.get(11).is_some_and(|b| b.size > 0) && minor < MINOR_EXEC_HINTS_BIN {
+            self.result.add_error("VERSION",
+                &format!("Bloque 0xB requiere 9.{} (el archivo declara 9.{})", MINOR_EXEC_HINTS_BIN, minor), true);
+        }
+        
+        let has_segments = self.result.manifest.as_ref().is_some_and(|m| m.get("checksum_segments").is_some());
+        if has_segments && minor < MINOR_CHECKSUM_SEGMENTS {
+            self.result.add_error("VERSION",
+                &format!("manifest.checksum_segments es de 9.{} (el archivo declara 9.{}); los lectores antiguos no lo usan",
+                    MINOR_CHECKSUM_SEGMENTS, minor), false);
+        }
+        
+        self.log(&format!("✓ Features coherentes con 9.{}", minor));
+    }
+    
     fn validate_checksums(&mut self) {
         let header = match &self.result.header {
             Some(h) => h.clone(),
@@ -1033,10 +1233,33 @@ impl HnfValidator {
         
         self.log(&format!("  Header CRC32: 0x{:08X}", header.checksum));
         
+        let algo = ChecksumAlgo::from_flags(header.flags);
+        if let Some(expected) = self.expected_checksum.filter(|e| *e != algo) {
+            self.result.add_error("CHECKSUM",
+                &format!("Algoritmo de checksum distinto: el archivo usa {}, se esperaba {}",
+                    algo.name(), expected.name()), true);
+            return;
+        }
+        
+        // El manifest declara el algoritmo de checksum_segments (ausente = XXH3-64)
+        let segments_algo = self.result.manifest.as_ref()
+            .and_then(|m| m.get("checksum_segments"))
+            .and_then(|s| s.get("algorithm"))
+            .and_then(|v| v.as_str())
+            .map(parse_checksum_algo);
+        match segments_algo {
+            Some(Ok(seg)) if seg != algo => {
+                self.result.add_error("CHECKSUM",
+                    &format!("checksum_segments usa {} pero el header declara {}", seg.name(), algo.name()), true);
+            }
+            Some(Err(e)) => self.result.add_error("CHECKSUM", &format!("checksum_segments: {}", e), true),
+            _ => {}
+        }
+        
         // Clone para evitar borrow conflict
         let blocks = self.result.blocks.clone();
         
-        // XXH3-64 por bloque
+        // Checksum por bloque
         let mut verified = 0;
         
         for (i, block) in blocks.iter().enumerate() {
@@ -1044,27 +1267,76 @@ impl HnfValidator {
                 continue;
             }
             
-            let start = block.offset as usize;
-            let end = start + block.size as usize;
-            
-            if end > self.data.len() {
+            let Some(range) = span(self.data.len(), block.offset, block.size) else {
                 continue;
-            }
+            };
+            let (start, end) = (range.start, range.end);
             
-            let block_data = &self.data[start..end];
-            let calculated = xxh3_64(block_data);
+            let block_data = &self.data[range];
+            let calculated = algo.digest(block_data);
             
             if calculated == block.checksum {
                 verified += 1;
             } else {
                 self.result.add_error("CHECKSUM",
-                    &format!("Bloque {}: XXH3 esperado 0x{:016X}, calculado 0x{:016X}", 
-                        i, block.checksum, calculated), true);
+                    &format!("Bloque {}: {} esperado 0x{:016X}, calculado 0x{:016X} (bytes {}..{})", 
+                        i, algo.name(), block.checksum, calculated, start, end), true);
+                self.localize_checksum_mismatch(i, start, end);
             }
         }
         
         if verified > 0 {
-            self.log(&format!("✓ {} checksums XXH3-64 verificados", verified));
+            self.log(&format!("✓ {} checksums {} verificados", verified, algo.name()));
+        }
+    }
+    
+    /// Re-hashea el bloque por segmentos contra manifest["checksum_segments"]
+    /// para señalar la primera región divergente.
+    fn localize_checksum_mismatch(&mut self, block_idx: usize, start: usize, end: usize) {
+        let segments = self.result.manifest.as_ref()
+            .and_then(|m| m.get("checksum_segments"))
+            .cloned();
+        
+        let (segment_size, expected) = match segments.as_ref().and_then(|s| {
+            let size = s.get("segment_size")?.as_u64()? as usize;
+            let hashes = s.get("blocks")?.get(block_idx.to_string())?.as_array()?;
+            Some((size, hashes))
+        }) {
+            Some((size, hashes)) if size > 0 => (size, hashes.clone()),
+            _ => {
+                self.result.add_error("CHECKSUM",
+                    &format!("Bloque {}: sin checksum_segments en manifest, no se puede localizar", block_idx),
+                    false);
+                return;
+            }
+        };
+        
+        let algo = self.checksum_algo();
+        let mut differing = Vec::new();
+        for (k, chunk) in self.data[start..end].chunks(segment_size).enumerate() {
+            let stored = expected.get(k)
+                .and_then(|v| v.as_str())
+                .and_then(|h| u64::from_str_radix(h, 16).ok());
+            if stored != Some(algo.digest(chunk)) {
+                differing.push(k);
+            }
+        }
+        
+        match differing.first() {
+            Some(&k) => {
+                let seg_start = start.saturating_add(k.saturating_mul(segment_size));
+                let seg_end = seg_start.saturating_add(segment_size).min(end);
+                self.result.add_error("CHECKSUM",
+                    &format!("Bloque {}: primera divergencia en segmento {} (bytes {}..{} = 0x{:X}..0x{:X}), {} segmento(s) distintos",
+                        block_idx, k, seg_start, seg_end, seg_start, seg_end, differing.len()),
+                    true);
+            }
+            None => {
+                // Los segmentos cuadran: lo corrupto es el checksum de la block table
+                self.result.add_error("CHECKSUM",
+                    &format!("Bloque {}: datos coinciden con checksum_segments; el checksum de la block table está corrupto", block_idx),
+                    true);
+            }
         }
     }
     
@@ -1106,10 +1378,12 @@ impl HnfValidator {
                 tensor.get("offset").and_then(|v| v.as_u64()),
                 tensor.get("size").and_then(|v| v.as_u64()),
             ) {
-                if off + sz > self.data.len() as u64 {
-                    let name = tensor.get("name").and_then(|v| v.as_str()).unwrap_or("?");
+                let name = tensor.get("name").and_then(|v| v.as_str()).unwrap_or("?");
+                if off.saturating_add(sz) > self.data.len() as u64 {
                     self.result.add_error("TENSORS",
                         &format!("Tensor '{}' fuera de límites", name), true);
+                } else if let Some(block) = tensor.get("block").and_then(|v| v.as_str()) {
+                    self.check_tensor_in_block(name, block, off, sz);
                 }
             }
         }
@@ -1119,42 +1393,410 @@ impl HnfValidator {
         } else if errors > 5 {
             self.result.add_error("TENSORS", &format!("... y {} errores más", errors - 5), true);
         }
+        
+        if let Some(aliases) = manifest.get("aliases").and_then(|v| v.as_object()) {
+            self.validate_aliases(aliases, tensors);
+        }
+        
+        self.validate_layer_contiguity(tensors);
+        self.validate_group_dtypes(&manifest, tensors);
+    }
+    
+    /// Proyecciones hermanas de una capa (q/k/v/o, gate/up/down) en el mismo
+    /// formato: una sola en FP16 entre HQ5K suele ser un override o un mapper
+    /// incoherente, y rompe los kernels fusionados del engine. Solo aviso;
+    /// --target-size mezcla formatos por tensor a propósito y no se comprueba
+    fn validate_group_dtypes(&mut self, manifest: &serde_json::Value, tensors: &[serde_json::Value]) {
+        if manifest.get("quantization").and_then(|q| q.get("target_size")).is_some_and(|v| !v.is_null()) {
+            return;
+        }
+        
+        // (prefijo, capa, grupo) → dtype → proyecciones
+        let mut groups: BTreeMap<(String, u64, &str), BTreeMap<String, Vec<String>>> = BTreeMap::new();
+        for tensor in tensors {
+            let (Some(name), Some(dtype)) = (
+                tensor.get("name").and_then(|v| v.as_str()),
+                tensor.get("dtype").and_then(|v| v.as_str()),
+            ) else {
+                continue;
+            };
+            let Some((prefix, layer, suffix)) = split_layer_name(name) else { continue };
+            let Some(group) = projection_group(&suffix) else { continue };
+            let projection = suffix.trim_start_matches(group).trim_start_matches('.').trim_end_matches(".weight");
+            groups.entry((prefix, layer, group)).or_default()
+                .entry(dtype.to_string()).or_default()
+                .push(projection.to_string());
+        }
+        
+        let mixed: Vec<String> = groups.iter()
+            .filter(|(_, dtypes)| dtypes.len() > 1)
+            .map(|((prefix, layer, group), dtypes)| {
+                let scope = if prefix.is_empty() { String::new() } else { format!("{}.", prefix) };
+                let formats: Vec<String> = dtypes.iter()
+                    .map(|(dtype, projections)| format!("{}: {}", dtype, projections.join(", ")))
+                    .collect();
+                format!("{}layer{} {}: formatos mezclados ({})", scope, layer, group, formats.join("; "))
+            })
+            .collect();
+        for message in mixed.iter().take(5) {
+            self.result.add_error("QUANT", message, false);
+        }
+        if mixed.len() > 5 {
+            self.result.add_error("QUANT", &format!("... y {} grupos mezclados más", mixed.len() - 5), false);
+        }
+    }
+    
+    /// Capas `<prefijo>.layer{N}.*` por prefijo (text, vision...): índices
+    /// 0..num_hidden_layers sin huecos (fatal) y el mismo juego de tensores
+    /// en cada capa (aviso: las capas densas de DeepSeek no tienen moe.*)
+    fn validate_layer_contiguity(&mut self, tensors: &[serde_json::Value]) {
+        // prefijo → capa → sufijos
+        let mut groups: BTreeMap<String, BTreeMap<u64, BTreeSet<String>>> = BTreeMap::new();
+        for name in tensors.iter().filter_map(|t| t.get("name").and_then(|v| v.as_str())) {
+            if let Some((prefix, layer, suffix)) = split_layer_name(name) {
+                groups.entry(prefix).or_default().entry(layer).or_default().insert(suffix);
+            }
+        }
+        
+        for (prefix, layers) in &groups {
+            let found = layers.len() as u64;
+            let last = layers.keys().next_back().copied().unwrap_or(0);
+            let scope = if prefix.is_empty() { "layer" } else { prefix.as_str() };
+            
+            // text: raíz de los hints (o hints.text); resto: hints.<prefijo>
+            let declared = self.result.execution_hints.as_ref().and_then(|hints| {
+                let layers_of = |h: &serde_json::Value| h.get("num_hidden_layers").and_then(|v| v.as_u64());
+                match hints.get(prefix.as_str()) {
+                    Some(scoped) => layers_of(scoped),
+                    None if prefix == "text" || prefix.is_empty() => layers_of(hints),
+                    None => None,
+                }
+            });
+            let expected = declared.unwrap_or(last + 1);
+            
+            let missing: Vec<u64> = (0..expected).filter(|i| !layers.contains_key(i)).collect();
+            if !missing.is_empty() {
+                self.result.add_error("LAYERS", &format!(
+                    "{}: faltan {} de {} capas: {}",
+                    scope, missing.len(), expected, format_layer_ranges(&missing)
+                ), true);
+            }
+            let extra: Vec<u64> = layers.keys().copied().filter(|&i| i >= expected).collect();
+            if !extra.is_empty() {
+                self.result.add_error("LAYERS", &format!(
+                    "{}: capas {} más allá de num_hidden_layers = {}",
+                    scope, format_layer_ranges(&extra), expected
+                ), false);
+            }
+            
+            // Sufijos presentes en todas las capas menos alguna
+            let mut incomplete = 0;
+            for (layer, suffixes) in layers.iter().filter(|_| found >= 3) {
+                let absent: Vec<&String> = layers.values()
+                    .flatten()
+                    .filter(|s| !suffixes.contains(*s))
+                    .filter(|s| layers.values().filter(|other| other.contains(*s)).count() as u64 == found - 1)
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect();
+                if absent.is_empty() {
+                    continue;
+                }
+                if incomplete < 5 {
+                    self.result.add_error("LAYERS", &format!(
+                        "{}.layer{}: le faltan {:?}, presentes en el resto de capas",
+                        scope, layer, absent
+                    ), false);
+                }
+                incomplete += 1;
+            }
+            if incomplete > 5 {
+                self.result.add_error("LAYERS", &format!("... y {} capas incompletas más", incomplete - 5), false);
+            }
+            
+            if missing.is_empty() && incomplete == 0 {
+                self.log(&format!("✓ {}: {} capas contiguas", scope, found));
+            }
+        }
+    }
+    
+    /// [offset, offset+size) del tensor dentro del rango del bloque que declara
+    /// en el block table (manifest y block table desincronizados si no)
+    fn check_tensor_in_block(&mut self, name: &str, block: &str, off: u64, sz: u64) {
+        let Some(idx) = BLOCK_NAMES.iter().position(|b| *b == block) else {
+            self.result.add_error("TENSORS",
+                &format!("Tensor '{}': bloque desconocido '{}'", name, block), true);
+            return;
+        };
+        let Some(entry) = self.result.blocks.get(idx) else {
+            return;
+        };
+        let (start, end) = (entry.offset, entry.offset.saturating_add(entry.size));
+        if off < start || off.saturating_add(sz) > end {
+            self.result.add_error("TENSORS", &format!(
+                "Tensor '{}' [{}, {}) fuera del bloque {} [{}, {})",
+                name, off, off.saturating_add(sz), block, start, end
+            ), true);
+        }
+    }
+    
+    /// manifest.aliases: cada alias y su destino existen, el destino guarda
+    /// sus propios bytes y ambos tienen el mismo número de elementos
+    fn validate_aliases(&mut self, aliases: &serde_json::Map<String, serde_json::Value>, tensors: &[serde_json::Value]) {
+        let find = |name: &str| tensors.iter().find(|t| t.get("name").and_then(|v| v.as_str()) == Some(name));
+        let numel = |t: &serde_json::Value| -> Option<u64> {
+            t.get("shape")?.as_array()?.iter().map(|d| d.as_u64()).product()
+        };
+        let errors_before = self.result.errors.len();
+        
+        for (alias, target) in aliases {
+            let Some(target) = target.as_str() else {
+                self.result.add_error("TENSORS", &format!("Alias '{}': destino no es un string", alias), true);
+                continue;
+            };
+            let Some(alias_entry) = find(alias) else {
+                self.result.add_error("TENSORS", &format!("Alias '{}' no está en la lista de tensores", alias), true);
+                continue;
+            };
+            let Some(target_entry) = find(target) else {
+                self.result.add_error("TENSORS", &format!("Alias '{}' apunta a '{}', que no existe", alias, target), true);
+                continue;
+            };
+            if target_entry.get("alias_of").is_some() {
+                self.result.add_error("TENSORS", &format!("Alias '{}' apunta a otro alias ('{}')", alias, target), true);
+                continue;
+            }
+            if numel(alias_entry) != numel(target_entry) {
+                self.result.add_error("TENSORS", &format!(
+                    "Alias '{}' {:?} incompatible con '{}' {:?}",
+                    alias, alias_entry["shape"], target, target_entry["shape"]
+                ), true);
+            }
+        }
+        
+        if self.result.errors.len() == errors_before {
+            self.log(&format!("✓ {} alias validados", aliases.len()));
+        }
     }
     
     fn print_summary(&self) {
-        println!("\n{}", "=".repeat(72));
-        println!("RESUMEN HNF");
-        println!("{}", "=".repeat(72));
+        outln!("\n{}", "=".repeat(72));
+        outln!("RESUMEN HNF");
+        outln!("{}", "=".repeat(72));
         
         if self.result.is_valid() {
-            println!("\n  ✓ VÁLIDO");
+            outln!("\n  ✓ VÁLIDO");
         } else {
-            println!("\n  ✗ INVÁLIDO");
+            outln!("\n  ✗ INVÁLIDO");
         }
         
-        println!("    Errores fatales: {}", self.result.fatal_count());
-        println!("    Advertencias:    {}", self.result.warn_count());
+        outln!("    Errores fatales: {}", self.result.fatal_count());
+        outln!("    Advertencias:    {}", self.result.warn_count());
         
         if self.result.fatal_count() > 0 {
-            println!("\n  Errores:");
+            outln!("\n  Errores:");
             for err in &self.result.errors {
                 if err.fatal {
-                    println!("    • {}", err);
+                    outln!("    • {}", err);
                 }
             }
         }
         
         if self.result.warn_count() > 0 {
-            println!("\n  Advertencias:");
+            outln!("\n  Advertencias:");
             for err in &self.result.errors {
                 if !err.fatal {
-                    println!("    • {}", err);
+                    outln!("    • {}", err);
                 }
             }
         }
     }
 }
 
+/// Campos clave del TextModelConfigBin frente al JSON de texto, una línea por
+/// campo distinto. Si el JSON no trae el campo se compara con el default que
+/// usaría el builder al generar el binario
+fn binary_hints_divergences(text: &serde_json::Value, stored: &TextModelConfigBin) -> Vec<String> {
+    let expected = TextModelConfigBin::from_json(text);
+    let shown = |key: &str, value: String| match text.get(key) {
+        Some(_) => value,
+        None => format!("(default) {}", value),
+    };
+    let mut divergences = Vec::new();
+    
+    if expected.arch != stored.arch {
+        let arch = text.get("arch").and_then(|v| v.as_str()).unwrap_or("?");
+        divergences.push(format!("arch: JSON '{}' (código {}), binario {}", arch, expected.arch, stored.arch));
+    }
+    
+    let integers = [
+        ("num_hidden_layers", expected.num_hidden_layers, stored.num_hidden_layers),
+        ("hidden_size", expected.hidden_size, stored.hidden_size),
+        ("num_attention_heads", expected.num_attention_heads, stored.num_attention_heads),
+        ("num_key_value_heads", expected.num_key_value_heads, stored.num_key_value_heads),
+        ("head_dim", expected.head_dim, stored.head_dim),
+    ];
+    for (key, json, binary) in integers {
+        if json != binary {
+            divergences.push(format!("{}: JSON {}, binario {}", key, shown(key, json.to_string()), binary));
+        }
+    }
+    
+    // rope_theta viaja como f32: tolerancia relativa de f32
+    if (expected.rope_theta - stored.rope_theta).abs() > f32::EPSILON * expected.rope_theta.abs() {
+        divergences.push(format!("rope_theta: JSON {}, binario {}",
+            shown("rope_theta", expected.rope_theta.to_string()), stored.rope_theta));
+    }
+    
+    divergences
+}
+
+// ============================================================================
+// SPOT CHECK - Dequantizar tensores al azar (--spot-check N)
+// ============================================================================
+//
+// La validación estructural no ve dentro de los pesos: un header HQ con
+// scale NaN o bytes pisados pasa todos los checksums si se corrompió antes
+// de escribir. Aquí se dequantizan N tensores con hqs::dequantize y se mira
+// que los valores sean plausibles.
+
+/// |x| máximo razonable en un norm (pesos ~1, bias ~0)
+const SPOT_NORM_MAX_ABS: f32 = 100.0;
+/// Desviación típica máxima razonable de un tensor de pesos
+const SPOT_WEIGHT_MAX_STD: f64 = 10.0;
+
+/// Resultado de dequantizar un tensor
+#[derive(Debug)]
+struct SpotCheck {
+    name: String,
+    dtype: String,
+    /// Estadísticas (min, max, std) si se pudo dequantizar
+    stats: Option<(f32, f32, f64)>,
+    problems: Vec<String>,
+}
+
+impl SpotCheck {
+    fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+fn is_norm_tensor(name: &str) -> bool {
+    name.contains("norm") || name.contains(".ln_")
+}
+
+/// Dequantiza un tensor (bytes = su bloque) con el super-block del manifest
+/// y revisa numel, NaN/Inf y rango
+fn spot_check_tensor(tensor: &TensorManifest, block: &[u8], block_offset: u64, super_block: usize) -> SpotCheck {
+    let mut check = SpotCheck {
+        name: tensor.name.clone(),
+        dtype: tensor.dtype.clone(),
+        stats: None,
+        problems: Vec::new(),
+    };
+    
+    let Some(format) = QuantFormat::from_str(&tensor.dtype).filter(|f| *f != QuantFormat::HQ3K) else {
+        check.problems.push(format!("dtype '{}' no dequantizable", tensor.dtype));
+        return check;
+    };
+    
+    let shape_numel: usize = tensor.shape.iter().product();
+    let numel = if tensor.numel > 0 { tensor.numel } else { shape_numel };
+    if numel != shape_numel {
+        check.problems.push(format!("numel {} != producto del shape {:?} ({})", numel, tensor.shape, shape_numel));
+    }
+    let expected = format.size_for_super_block(numel, super_block);
+    if tensor.size != expected as u64 {
+        check.problems.push(format!("{} bytes, {} necesita {} para {} elementos",
+            tensor.size, format, expected, numel));
+        return check;
+    }
+    
+    let start = tensor.offset.checked_sub(block_offset);
+    let Some(bytes) = start.and_then(|s| block.get(s as usize..(s + tensor.size) as usize)) else {
+        check.problems.push("fuera de su bloque".to_string());
+        return check;
+    };
+    
+    let values = dequantize_with(bytes, format, numel, super_block);
+    if values.len() != numel {
+        check.problems.push(format!("dequantiza a {} elementos, declara {}", values.len(), numel));
+    }
+    
+    let non_finite = values.iter().filter(|x| !x.is_finite()).count();
+    if non_finite > 0 {
+        check.problems.push(format!("{} valores NaN/Inf", non_finite));
+        return check;
+    }
+    if values.is_empty() {
+        return check;
+    }
+    
+    let (min, max) = values.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+    let mean = values.iter().map(|&x| x as f64).sum::<f64>() / values.len() as f64;
+    let var = values.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    let std = var.sqrt();
+    check.stats = Some((min, max, std));
+    
+    if is_norm_tensor(&tensor.name) {
+        if min.abs().max(max.abs()) >= SPOT_NORM_MAX_ABS {
+            check.problems.push(format!("norm con valores fuera de ±{} (rango {:.3e}..{:.3e})", SPOT_NORM_MAX_ABS, min, max));
+        }
+    } else if std > SPOT_WEIGHT_MAX_STD {
+        check.problems.push(format!("std {:.3e} > {} (pesos desproporcionados)", std, SPOT_WEIGHT_MAX_STD));
+    }
+    
+    check
+}
+
+/// Dequantiza hasta `count` tensores elegidos al azar (todos los bloques)
+fn spot_check(reader: &HnfReader, count: usize, seed: u64) -> Vec<SpotCheck> {
+    use rand::SeedableRng;
+    
+    let tensors: Vec<(usize, TensorManifest)> = (0..HNF_BLOCK_COUNT)
+        .flat_map(|block_id| reader.block_tensors(block_id).into_iter().map(move |t| (block_id, t)))
+        .collect();
+    
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let mut picked = rand::seq::index::sample(&mut rng, tensors.len(), count.min(tensors.len())).into_vec();
+    picked.sort_unstable();
+    
+    picked.into_iter()
+        .map(|i| {
+            let (block_id, tensor) = &tensors[i];
+            let block_offset = reader.blocks()[*block_id].offset;
+            spot_check_tensor(tensor, reader.block_bytes(*block_id), block_offset, reader.super_block())
+        })
+        .collect()
+}
+
+/// Imprime el resultado del spot check; true si todos pasan
+fn print_spot_check(checks: &[SpotCheck], seed: u64) -> bool {
+    outln!("\n{}", "=".repeat(72));
+    outln!("SPOT CHECK: {} tensores dequantizados (seed {})", checks.len(), seed);
+    outln!("{}", "=".repeat(72));
+    
+    for check in checks {
+        let stats = check.stats
+            .map(|(min, max, std)| format!("min {:.3e}  max {:.3e}  std {:.3e}", min, max, std))
+            .unwrap_or_default();
+        if check.passed() {
+            outln!("  [OK] {} ({})  {}", check.name, check.dtype, stats);
+        } else {
+            outln!("  [FAIL] {} ({})  {}", check.name, check.dtype, stats);
+            for problem in &check.problems {
+                outln!("      - {}", problem);
+            }
+        }
+    }
+    
+    let failed = checks.iter().filter(|c| !c.passed()).count();
+    if failed > 0 {
+        outln!("\n  {} de {} tensores no pasan el spot check", failed, checks.len());
+    }
+    failed == 0
+}
+
 // ============================================================================
 // CLI
 // ============================================================================
@@ -1167,16 +1809,33 @@ struct Args {
     /// Archivo a validar (.hnf)
     file: PathBuf,
     
+    /// Exigir este algoritmo de checksum de bloques (xxh3, blake3)
+    #[arg(long, value_name = "ALGO", value_parser = parse_checksum_algo)]
+    checksum: Option<ChecksumAlgo>,
+    
+    /// Dequantizar N tensores al azar y revisar NaN/Inf, rango y numel
+    #[arg(long, value_name = "N", visible_alias = "verify-dequant")]
+    spot_check: Option<usize>,
+    
+    /// Semilla del muestreo de --spot-check (por defecto, al azar)
+    #[arg(long, value_name = "SEED", requires = "spot_check")]
+    spot_check_seed: Option<u64>,
+    
     /// Modo verbose
     #[arg(short, long)]
     verbose: bool,
+    
+    /// Salida ASCII plana ([OK]/[FAIL], sin cajas); automática si stdout no es un TTY
+    #[arg(long, visible_alias = "ascii")]
+    no_color: bool,
 }
 
 fn main() {
     let args = Args::parse();
+    term::init(args.no_color);
     
     if !args.file.exists() {
-        eprintln!("Error: Archivo no encontrado: {}", args.file.display());
+        eoutln!("Error: Archivo no encontrado: {}", args.file.display());
         std::process::exit(1);
     }
     
@@ -1184,40 +1843,617 @@ fn main() {
     let mut file = match File::open(&args.file) {
         Ok(f) => f,
         Err(e) => {
-            eprintln!("Error abriendo archivo: {}", e);
+            eoutln!("Error abriendo archivo: {}", e);
             std::process::exit(1);
         }
     };
     
     let mut data = Vec::new();
     if let Err(e) = file.read_to_end(&mut data) {
-        eprintln!("Error leyendo archivo: {}", e);
+        eoutln!("Error leyendo archivo: {}", e);
         std::process::exit(1);
     }
     
     // Detectar tipo por magic
     if data.len() < 8 {
-        eprintln!("Error: Archivo muy pequeño");
+        eoutln!("Error: Archivo muy pequeño");
         std::process::exit(1);
     }
     
     let magic = &data[0..8];
     
-    let result = if magic == HNF_MAGIC {
-        let validator = HnfValidator::new(data, args.verbose);
-        validator.validate()
+    let valid = if magic == MAGIC {
+        let validator = HnfValidator::new(data, args.verbose).with_expected_checksum(args.checksum);
+        let mut valid = validator.validate().is_valid();
+        
+        if let Some(count) = args.spot_check {
+            let seed = args.spot_check_seed.unwrap_or_else(rand::random);
+            match HnfReader::open(&args.file) {
+                Ok(reader) => valid &= print_spot_check(&spot_check(&reader, count, seed), seed),
+                Err(e) => {
+                    eoutln!("Error: spot check imposible: {:#}", e);
+                    valid = false;
+                }
+            }
+        }
+        valid
+    } else if &magic[..4] == HTF_MAGIC_V13 || &magic[..4] == HTF_MAGIC {
+        // Tokenizer suelto, fuera de un HNF
+        let result = validate_htf(&data);
+        print_validation_result(&result);
+        result.valid
     } else {
-        eprintln!("Error: Formato no reconocido (magic: {:?})", magic);
+        eoutln!("Error: Formato no reconocido (magic: {:?})", magic);
         std::process::exit(1);
     };
     
-    println!("\n{}", "=".repeat(72));
-    if result.is_valid() {
-        println!("✓ VALIDACIÓN EXITOSA");
+    outln!("\n{}", "=".repeat(72));
+    if valid {
+        outln!("✓ VALIDACIÓN EXITOSA");
     } else {
-        println!("✗ VALIDACIÓN FALLIDA");
+        outln!("✗ VALIDACIÓN FALLIDA");
+    }
+    outln!("{}\n", "=".repeat(72));
+    
+    std::process::exit(if valid { 0 } else { 1 });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helios_convert::hnf::{HnfWriter, repair_block_table, reorder_blocks, BLOCK_TEXT_MODEL, CHECKSUM_SEGMENT_SIZE, METADATA_BLOCKS};
+    
+    #[test]
+    fn test_checksum_mismatch_is_localized() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        
+        let payload: Vec<u8> = (0..3 * CHECKSUM_SEGMENT_SIZE).map(|i| (i % 251) as u8).collect();
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[payload.len() / 2], &payload).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&serde_json::json!({})).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        let mut data = std::fs::read(&path).unwrap();
+        let block_offset = read_u64_le(&data, HNF_BLOCK_TABLE_OFFSET + 8) as usize;
+        let corrupt_at = block_offset + 2 * CHECKSUM_SEGMENT_SIZE + 100;
+        data[corrupt_at] ^= 0xFF;
+        
+        let result = HnfValidator::new(data, false).validate();
+        let seg_start = block_offset + 2 * CHECKSUM_SEGMENT_SIZE;
+        let expected = format!("segmento 2 (bytes {}..{}", seg_start, seg_start + CHECKSUM_SEGMENT_SIZE);
+        assert!(
+            result.errors.iter().any(|e| e.category == "CHECKSUM" && e.message.contains(&expected)),
+            "{:?}", result.errors
+        );
+        assert!(result.errors.iter().any(|e| e.message.contains("1 segmento(s) distintos")));
+    }
+    
+    /// Hints con todos los campos obligatorios
+    fn minimal_htf() -> Vec<u8> {
+        let mut tokenizer = helios_convert::htf::HTFWriter::new_v13();
+        let vocab = [("a".to_string(), 0), ("b".to_string(), 1)].into();
+        tokenizer.add_text_domain(&vocab, &[], &serde_json::json!({}), true).unwrap();
+        tokenizer.build()
+    }
+    
+    fn minimal_hints() -> serde_json::Value {
+        serde_json::json!({
+            "arch": "llama", "dtype": "fp16", "num_hidden_layers": 1, "hidden_size": 8,
+            "intermediate_size": 16, "vocab_size": 4, "num_attention_heads": 2,
+            "num_key_value_heads": 2, "head_dim": 4, "attention_type": "mha",
+            "mlp_type": "swiglu", "mlp_activation": "silu", "norm_type": "rmsnorm",
+        })
+    }
+    
+    #[test]
+    fn test_inconsistent_partial_rope_dim_is_fatal() {
+        let dir = tempfile::tempdir().unwrap();
+        let validate_rope = |rope_dim: u64| {
+            let path = dir.path().join(format!("rope{}.hnf", rope_dim));
+            let mut text = minimal_hints();
+            text["head_dim"] = serde_json::json!(80);
+            text["rope_partial"] = serde_json::json!(true);
+            text["partial_rotary_factor"] = serde_json::json!(0.4);
+            text["rope_dim"] = serde_json::json!(rope_dim);
+            
+            let mut writer = HnfWriter::create(&path).unwrap();
+            writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[32], &[0u8; 64]).unwrap();
+            writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+            writer.write_execution_hints(&serde_json::json!({"text_enabled": true, "text": text})).unwrap();
+            writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+            HnfValidator::new(std::fs::read(&path).unwrap(), false).validate()
+        };
+        let rope_errors = |result: &ValidationResult| result.errors.iter()
+            .filter(|e| e.message.contains("rope_dim"))
+            .map(|e| (e.message.clone(), e.fatal))
+            .collect::<Vec<_>>();
+        
+        // 80 * 0.4 = 32; 33 entra en el margen de redondeo
+        assert!(rope_errors(&validate_rope(32)).is_empty());
+        assert!(rope_errors(&validate_rope(33)).is_empty());
+        
+        let errors = rope_errors(&validate_rope(64));
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].0.starts_with("text.rope_dim (64)"), "{:?}", errors);
+        assert!(errors[0].1);
+    }
+    
+    #[test]
+    fn test_hnf_9_0_target_passes_old_version_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = |path: &std::path::Path, minor: u16, algo: ChecksumAlgo| {
+            let mut writer = HnfWriter::create(path).unwrap();
+            writer.set_version_minor(minor).unwrap();
+            writer.set_checksum_algo(algo).unwrap();
+            writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[32], &[0u8; 64]).unwrap();
+            writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+            writer.write_execution_hints(&minimal_hints()).unwrap();
+            writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+            std::fs::read(path).unwrap()
+        };
+        
+        let old = build(&dir.path().join("old.hnf"), 0, ChecksumAlgo::Xxh3);
+        assert_eq!(read_u16_le(&old, 10), 0);
+        let result = HnfValidator::new(old, false).validate();
+        assert!(result.is_valid(), "{:?}", result.errors);
+        assert!(!result.errors.iter().any(|e| e.category == "VERSION"), "{:?}", result.errors);
+        assert!(result.manifest.as_ref().unwrap().get("checksum_segments").is_none());
+        
+        // Features de 9.1 no se pueden pedir al apuntar a 9.0
+        let mut writer = HnfWriter::create(dir.path().join("x.hnf")).unwrap();
+        writer.set_version_minor(0).unwrap();
+        assert!(writer.set_checksum_algo(ChecksumAlgo::Blake3).is_err());
+        assert!(writer.write_block(helios_convert::hnf::BLOCK_EXEC_HINTS_BIN, &[0u8; 32]).is_err());
+        
+        // Un 9.1 con BLAKE3 que se declara 9.0 no pasa
+        let mut lying = build(&dir.path().join("new.hnf"), 1, ChecksumAlgo::Blake3);
+        lying[10..12].copy_from_slice(&0u16.to_le_bytes());
+        let result = HnfValidator::new(lying, false).validate();
+        assert!(result.errors.iter().any(|e| e.category == "VERSION" && e.fatal && e.message.contains("BLAKE3")), "{:?}", result.errors);
+        assert!(result.errors.iter().any(|e| e.category == "VERSION" && !e.fatal && e.message.contains("checksum_segments")));
+    }
+    
+    #[test]
+    fn test_repair_block_table_restores_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[32], &[0u8; 64]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&minimal_hints()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        // Desordenar id/type de varias entradas (vacías y no vacías)
+        let mut data = std::fs::read(&path).unwrap();
+        for (i, bogus) in [(0usize, 7u32), (3, 12), (10, 0)] {
+            let entry = HNF_BLOCK_TABLE_OFFSET + i * HNF_BLOCK_ENTRY_SIZE;
+            data[entry..entry + 4].copy_from_slice(&bogus.to_le_bytes());
+            data[entry + 4..entry + 8].copy_from_slice(&(bogus + 1).to_le_bytes());
+        }
+        let scrambled = dir.path().join("scrambled.hnf");
+        std::fs::write(&scrambled, &data).unwrap();
+        assert!(!HnfValidator::new(data, false).validate().is_valid());
+        
+        let repaired = dir.path().join("repaired.hnf");
+        let fixes = repair_block_table(&scrambled, &repaired).unwrap();
+        assert_eq!(fixes.len(), 6);
+        
+        let result = HnfValidator::new(std::fs::read(&repaired).unwrap(), false).validate();
+        assert!(result.is_valid(), "{:?}", result.errors);
+        assert_eq!(std::fs::read(&repaired).unwrap(), std::fs::read(&path).unwrap());
+    }
+    
+    #[test]
+    fn test_metadata_first_layout_validates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[512], &[7u8; 1024]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&minimal_hints()).unwrap();
+        writer.write_tokenizer(&minimal_htf()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        let reordered = dir.path().join("reordered.hnf");
+        reorder_blocks(&path, &reordered, &METADATA_BLOCKS).unwrap();
+        
+        let data = std::fs::read(&reordered).unwrap();
+        let offset_of = |i: usize| read_u64_le(&data, HNF_BLOCK_TABLE_OFFSET + i * HNF_BLOCK_ENTRY_SIZE + 8);
+        assert!(offset_of(10) < offset_of(9));
+        assert!(offset_of(9) < offset_of(0));
+        
+        let result = HnfValidator::new(data, false).validate();
+        assert!(
+            !result.errors.iter().any(|e| e.category == "ORDER" || e.category == "CHECKSUM"),
+            "{:?}", result.errors
+        );
+        assert!(result.is_valid(), "{:?}", result.errors);
+    }
+    
+    #[test]
+    fn test_alias_target_must_exist_with_same_shape() {
+        let tensors = vec![
+            serde_json::json!({"name": "text.token_embedding.weight", "shape": [8, 16]}),
+            serde_json::json!({"name": "text.lm_head.weight", "shape": [8, 16], "alias_of": "text.token_embedding.weight"}),
+            serde_json::json!({"name": "text.norm.weight", "shape": [16], "alias_of": "text.token_embedding.weight"}),
+        ];
+        let check = |aliases: serde_json::Value| {
+            let mut validator = HnfValidator::new(Vec::new(), false);
+            validator.validate_aliases(aliases.as_object().unwrap(), &tensors);
+            validator.result.errors.iter().map(|e| e.message.clone()).collect::<Vec<_>>()
+        };
+        
+        assert!(check(serde_json::json!({"text.lm_head.weight": "text.token_embedding.weight"})).is_empty());
+        assert!(check(serde_json::json!({"text.lm_head.weight": "lm_head.missing"}))[0].contains("no existe"));
+        assert!(check(serde_json::json!({"text.norm.weight": "text.token_embedding.weight"}))[0].contains("incompatible"));
+    }
+    
+    #[test]
+    fn test_tensor_outside_its_block_is_fatal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[512], &[5u8; 1024]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&minimal_hints()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        let data = std::fs::read(&path).unwrap();
+        let result = HnfValidator::new(data.clone(), false).validate();
+        assert!(result.is_valid(), "{:?}", result.errors);
+        
+        // Mismo tensor apuntando a los bytes de execution_hints
+        let text = result.blocks[BLOCK_TEXT_MODEL].clone();
+        let hints = result.blocks[0xA].clone();
+        let mut manifest = result.manifest.clone().unwrap();
+        manifest["tensors"][0]["offset"] = serde_json::json!(hints.offset);
+        manifest["tensors"][0]["size"] = serde_json::json!(hints.size.min(text.size));
+        let mut validator = HnfValidator::new(data, false);
+        validator.result.blocks = result.blocks;
+        validator.result.manifest = Some(manifest);
+        validator.validate_tensors();
+        let escapes: Vec<_> = validator.result.errors.iter()
+            .filter(|e| e.category == "TENSORS" && e.message.contains("fuera del bloque text_model"))
+            .collect();
+        assert_eq!(escapes.len(), 1, "{:?}", validator.result.errors);
+        assert!(escapes[0].fatal);
+    }
+    
+    #[test]
+    fn test_missing_layer_is_fatal() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = |skip: Option<usize>| {
+            let path = dir.path().join(format!("layers{:?}.hnf", skip));
+            let mut writer = HnfWriter::create(&path).unwrap();
+            for layer in (0..8).filter(|&l| Some(l) != skip) {
+                for proj in ["attn.q_proj", "mlp.up_proj"] {
+                    let name = format!("text.layer{}.{}.weight", layer, proj);
+                    writer.write_tensor(BLOCK_TEXT_MODEL, &name, "fp16", &[16], &[1u8; 32]).unwrap();
+                }
+            }
+            writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+            let mut hints = minimal_hints();
+            hints["num_hidden_layers"] = serde_json::json!(8);
+            writer.write_execution_hints(&hints).unwrap();
+            writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+            HnfValidator::new(std::fs::read(&path).unwrap(), false).validate()
+        };
+        let layer_errors = |result: &ValidationResult| result.errors.iter()
+            .filter(|e| e.category == "LAYERS")
+            .map(|e| (e.fatal, e.message.clone()))
+            .collect::<Vec<_>>();
+        
+        assert!(layer_errors(&build(None)).is_empty());
+        let errors = layer_errors(&build(Some(5)));
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].0);
+        assert!(errors[0].1.contains("faltan 1 de 8 capas: 5"), "{}", errors[0].1);
+        assert_eq!(format_layer_ranges(&[3, 4, 5, 9]), "3-5, 9");
+    }
+    
+    #[test]
+    fn test_mixed_projection_formats_warn() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = |k_dtype: &str| {
+            let path = dir.path().join(format!("{}.hnf", k_dtype));
+            let mut writer = HnfWriter::create(&path).unwrap();
+            for layer in 0..2 {
+                for proj in ["q", "k", "v", "o"] {
+                    let dtype = if layer == 1 && proj == "k" { k_dtype } else { "hq5k" };
+                    let name = format!("text.layer{}.attn.{}_proj.weight", layer, proj);
+                    writer.write_tensor(BLOCK_TEXT_MODEL, &name, dtype, &[256], &[1u8; 288]).unwrap();
+                }
+                let name = format!("text.layer{}.ln_attn_in.weight", layer);
+                writer.write_tensor(BLOCK_TEXT_MODEL, &name, "fp16", &[16], &[1u8; 32]).unwrap();
+            }
+            writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+            writer.write_execution_hints(&minimal_hints()).unwrap();
+            writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+            HnfValidator::new(std::fs::read(&path).unwrap(), false).validate()
+        };
+        let quant_warnings = |result: &ValidationResult| result.errors.iter()
+            .filter(|e| e.category == "QUANT" && !e.fatal)
+            .map(|e| e.message.clone())
+            .collect::<Vec<_>>();
+        
+        assert!(quant_warnings(&build("hq5k")).is_empty());
+        let warnings = quant_warnings(&build("fp16"));
+        assert_eq!(warnings, ["text.layer1 attn: formatos mezclados (fp16: k_proj; hq5k: q_proj, v_proj, o_proj)"]);
+        assert_eq!(projection_group("mlp.down.weight"), Some("mlp"));
+        assert_eq!(projection_group("attn.q_proj.bias"), None);
+    }
+    
+    #[test]
+    fn test_moe_tensors_without_is_moe_flag_warn() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = |flags: u32| {
+            let path = dir.path().join(format!("moe{}.hnf", flags));
+            let mut writer = HnfWriter::create(&path).unwrap();
+            writer.write_tensor(BLOCK_TEXT_MODEL, "text.layer0.moe.experts.0.up.weight", "fp16", &[512], &[1u8; 1024]).unwrap();
+            writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+            writer.write_execution_hints(&minimal_hints()).unwrap();
+            writer.set_flags(flags);
+            writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+            HnfValidator::new(std::fs::read(&path).unwrap(), false).validate()
+        };
+        let moe_warning = |result: &ValidationResult| result.errors.iter()
+            .any(|e| e.category == "FLAGS" && e.message.contains("IS_MOE inactivo") && !e.fatal);
+        
+        assert!(moe_warning(&build(0)), "{:?}", build(0).errors);
+        assert!(!moe_warning(&build(HeaderFlags::IS_MOE)));
+        assert!(!build(HeaderFlags::IS_MOE).errors.iter().any(|e| e.category == "FLAGS"));
+    }
+    
+    #[test]
+    fn test_blake3_checksums_validate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.set_checksum_algo(ChecksumAlgo::Blake3).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[512], &[3u8; 1024]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&minimal_hints()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        let data = std::fs::read(&path).unwrap();
+        assert_ne!(read_u32_le(&data, 12) & HeaderFlags::CHECKSUM_BLAKE3, 0);
+        let block_checksum = read_u64_le(&data, HNF_BLOCK_TABLE_OFFSET + 24);
+        assert_eq!(block_checksum, ChecksumAlgo::Blake3.digest(&[3u8; 1024]));
+        
+        let result = HnfValidator::new(data.clone(), false).validate();
+        assert!(result.is_valid(), "{:?}", result.errors);
+        let result = HnfValidator::new(data.clone(), false)
+            .with_expected_checksum(Some(ChecksumAlgo::Blake3))
+            .validate();
+        assert!(result.is_valid(), "{:?}", result.errors);
+        
+        // Un validador que exige XXH3 lo rechaza por algoritmo, no por datos corruptos
+        let result = HnfValidator::new(data, false)
+            .with_expected_checksum(Some(ChecksumAlgo::Xxh3))
+            .validate();
+        assert!(!result.is_valid());
+        assert!(result.errors.iter().any(|e| e.category == "CHECKSUM"
+            && e.message.contains("el archivo usa blake3-64, se esperaba xxh3-64")), "{:?}", result.errors);
+    }
+    
+    #[test]
+    fn test_arbitrary_bytes_never_panic() {
+        use rand::{Rng, SeedableRng};
+        
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[512], &[9u8; 1024]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&minimal_hints()).unwrap();
+        writer.write_tokenizer(&minimal_htf()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        let valid = std::fs::read(&path).unwrap();
+        
+        let mut inputs: Vec<Vec<u8>> = Vec::new();
+        // Truncados
+        for len in (0..valid.len()).step_by(7) {
+            inputs.push(valid[..len].to_vec());
+        }
+        // Campos de header, block table y domain table con valores extremos
+        for field in (8..HNF_BLOCK_TABLE_OFFSET + HNF_BLOCK_TABLE_SIZE).step_by(4) {
+            for value in [0u32, 1, 0x7FFF_FFFF, u32::MAX, valid.len() as u32, valid.len() as u32 + 1] {
+                let mut data = valid.clone();
+                data[field..field + 4].copy_from_slice(&value.to_le_bytes());
+                inputs.push(data);
+            }
+        }
+        let htf_at = read_u64_le(&valid, HNF_BLOCK_TABLE_OFFSET + 9 * HNF_BLOCK_ENTRY_SIZE + 8) as usize;
+        for field in (htf_at..htf_at + 128).step_by(2) {
+            for value in [0u16, 0xFF, 0xFFFF] {
+                let mut data = valid.clone();
+                data[field..field + 2].copy_from_slice(&value.to_le_bytes());
+                inputs.push(data);
+            }
+        }
+        // Bytes al azar (con y sin magic)
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x4E4E);
+        for i in 0..200 {
+            let mut data = valid.clone();
+            for _ in 0..rng.gen_range(1..16) {
+                let at = rng.gen_range(0..data.len());
+                data[at] = rng.gen();
+            }
+            inputs.push(data);
+            let mut noise: Vec<u8> = (0..rng.gen_range(0..512)).map(|_| rng.gen()).collect();
+            if i % 2 == 0 && noise.len() >= 8 {
+                noise[..8].copy_from_slice(MAGIC);
+            }
+            inputs.push(noise);
+        }
+        
+        let panics: Vec<usize> = inputs.into_iter().enumerate()
+            .filter(|(_, data)| {
+                let data = data.clone();
+                std::panic::catch_unwind(move || {
+                    let _ = helios_convert::hnf::HnfReader::from_bytes(data.clone());
+                    HnfValidator::new(data, false).validate()
+                }).is_err()
+            })
+            .map(|(i, _)| i)
+            .collect();
+        assert!(panics.is_empty(), "{} inputs panicked: {:?}", panics.len(), panics);
     }
-    println!("{}\n", "=".repeat(72));
     
-    std::process::exit(if result.is_valid() { 0 } else { 1 });
+    #[test]
+    fn test_spot_check_catches_corrupted_hq_block() {
+        use rand::{Rng, SeedableRng};
+        use helios_convert::hqs::quantize;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let weights: Vec<f32> = (0..1024).map(|_| rng.gen_range(-0.1..0.1)).collect();
+        let hq = quantize(&weights, QuantFormat::HQ4K, false, false);
+        let norm: Vec<u8> = [1.0f32; 16].iter().flat_map(|&x| half::f16::from_f32(x).to_le_bytes()).collect();
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "layer0.attn.q_proj.weight", "hq4k", &[32, 32], &hq).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "layer0.ln_attn.weight", "fp16", &[16], &norm).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&minimal_hints()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        let checks = spot_check(&HnfReader::open(&path).unwrap(), 10, 0);
+        assert_eq!(checks.len(), 2);
+        assert!(checks.iter().all(SpotCheck::passed), "{:?}", checks);
+        
+        // Header del segundo super-bloque HQ4K a 0xFF: group params NaN
+        let mut data = std::fs::read(&path).unwrap();
+        let block_offset = read_u64_le(&data, HNF_BLOCK_TABLE_OFFSET + 8) as usize;
+        let at = block_offset + QuantFormat::HQ4K.block_size();
+        data[at..at + 8].fill(0xFF);
+        let reader = HnfReader::from_bytes(data).unwrap();
+        
+        let checks = spot_check(&reader, 10, 0);
+        let q = checks.iter().find(|c| c.name == "layer0.attn.q_proj.weight").unwrap();
+        assert!(!q.passed());
+        assert!(q.problems.iter().any(|p| p.contains("NaN/Inf")), "{:?}", q.problems);
+        assert!(checks.iter().find(|c| c.name == "layer0.ln_attn.weight").unwrap().passed());
+    }
+    
+    #[test]
+    fn test_binary_hints_written_and_consistent() {
+        use helios_convert::builder::{write_combined_hints, HintOverrides};
+        use helios_convert::hnf::{BLOCK_EXEC_HINTS_BIN, HnfReader};
+        use helios_convert::mapping::{llama::LlamaMapper, BlockType};
+        
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        let mapper = LlamaMapper::from_json(&serde_json::json!({
+            "num_hidden_layers": 1, "hidden_size": 8, "intermediate_size": 16,
+            "num_attention_heads": 2, "num_key_value_heads": 2, "vocab_size": 4,
+        }));
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[4, 8], &[0u8; 64]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        write_combined_hints(&mut writer, &[(&mapper, BlockType::TextModel)], &HintOverrides::default()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        // JSON (0xA) y binario (0xB) presentes, flag activo y mismo contenido
+        let reader = HnfReader::open(&path).unwrap();
+        assert!(reader.header().flags.has(HeaderFlags::HAS_EXEC_HINTS_BIN));
+        let hints = reader.execution_hints_json().unwrap().unwrap();
+        let binary = reader.block_bytes(BLOCK_EXEC_HINTS_BIN);
+        let header = ExecutionHintsBin::from_bytes(binary).unwrap();
+        let text = TextModelConfigBin::from_bytes(&binary[header.text_offset as usize..]).unwrap();
+        assert_eq!(text, TextModelConfigBin::from_json(&hints["text"]));
+        assert_eq!(text.hidden_size, 8);
+        
+        let data = std::fs::read(&path).unwrap();
+        let result = HnfValidator::new(data.clone(), false).validate();
+        assert!(!result.errors.iter().any(|e| e.category.starts_with("EXEC_HINTS_BIN") || e.category == "FLAGS"), "{:?}", result.errors);
+    }
+    
+    #[test]
+    fn test_binary_hints_cross_validation() {
+        let text = serde_json::json!({
+            "arch": "llama", "num_hidden_layers": 2, "hidden_size": 64, "num_attention_heads": 4,
+            "num_key_value_heads": 2, "head_dim": 16, "rope_theta": 500000.0,
+        });
+        let stored = TextModelConfigBin::from_json(&text);
+        assert!(binary_hints_divergences(&text, &stored).is_empty());
+        
+        let mut mismatched = stored;
+        mismatched.hidden_size = 4096;
+        assert_eq!(binary_hints_divergences(&text, &mismatched), ["hidden_size: JSON 64, binario 4096"]);
+        
+        // Sin rope_theta en el JSON vale el default del builder
+        let mut partial = text.clone();
+        partial.as_object_mut().unwrap().remove("rope_theta");
+        let defaulted = TextModelConfigBin::from_json(&partial);
+        assert!(binary_hints_divergences(&partial, &defaulted).is_empty());
+        assert!(binary_hints_divergences(&partial, &stored)[0].starts_with("rope_theta: JSON (default)"));
+        
+        // En un HNF: hidden_size pisado en 0xB es fatal
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[512], &[9u8; 1024]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&minimal_hints()).unwrap();
+        writer.write_execution_hints_binary(&minimal_hints()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        let mut data = std::fs::read(&path).unwrap();
+        assert!(HnfValidator::new(data.clone(), false).validate().is_valid());
+        
+        let bin_offset = read_u64_le(&data, HNF_BLOCK_TABLE_OFFSET + 11 * HNF_BLOCK_ENTRY_SIZE + 8) as usize;
+        let header = ExecutionHintsBin::from_bytes(&data[bin_offset..]).unwrap();
+        let at = bin_offset + header.text_offset as usize;
+        let mut config = TextModelConfigBin::from_bytes(&data[at..]).unwrap();
+        config.hidden_size = 4096;
+        data[at..at + TextModelConfigBin::SIZE].copy_from_slice(&config.to_bytes());
+        let result = HnfValidator::new(data, false).validate();
+        assert!(!result.is_valid());
+        assert!(result.errors.iter().any(|e| e.category == "EXEC_HINTS_BIN" && e.fatal && e.message.contains("hidden_size: JSON 8, binario 4096")), "{:?}", result.errors);
+    }
+    
+    #[test]
+    fn test_tokenizer_checksums_are_told_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[512], &[9u8; 1024]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&minimal_hints()).unwrap();
+        writer.write_tokenizer(&minimal_htf()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        let valid = std::fs::read(&path).unwrap();
+        assert!(HnfValidator::new(valid.clone(), false).validate().is_valid());
+        
+        let entry = HNF_BLOCK_TABLE_OFFSET + 9 * HNF_BLOCK_ENTRY_SIZE;
+        let offset = read_u64_le(&valid, entry + 8) as usize;
+        let size = read_u64_le(&valid, entry + 16) as usize;
+        let tokenizer_crc = |data: Vec<u8>| {
+            HnfValidator::new(data, false).validate().errors.into_iter()
+                .filter(|e| e.category == "TOKENIZER_CRC")
+                .map(|e| e.message)
+                .collect::<Vec<_>>()
+        };
+        
+        // Solo el checksum interno del HTF, con la block table recalculada
+        let mut data = valid.clone();
+        data[offset + 24] ^= 0xFF;
+        let rehashed = xxh3_64(&data[offset..offset + size]);
+        data[entry + 24..entry + 32].copy_from_slice(&rehashed.to_le_bytes());
+        let errors = tokenizer_crc(data);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("Checksum interno HTF inválido"), "{:?}", errors);
+        
+        // Solo la block table: el HTF está bien
+        let mut data = valid;
+        data[entry + 24] ^= 0xFF;
+        let errors = tokenizer_crc(data);
+        assert!(errors[0].contains("block table obsoleta"), "{:?}", errors);
+    }
 }
@@ -8,44 +8,520 @@
 // - NO decide cuantización (lo sugiere el mapper)
 // - Solo lee, cuantiza, escribe
 //
+// v9.4.0: --super-block: tamaño de super-block de HQ*K (GridConfig), en el manifest
+// v9.3.9: process_vlm: texto + visión de un VLM combinado con un solo reader
+// v9.3.8: map_and_quantize: mapear + resolver + cuantizar un tensor en memoria
+// v9.3.7: vocab_sources: config, embedding y tokenizer comparados al final
+// v9.3.6: --lora: pares lora_A/lora_B se fusionan en f32 antes de cuantizar
+// v9.3.5: attention_bias se corrige según los bias q/k/v escritos
+// v9.3.4: --calibration-data: grid search ponderado por importancia de canal
+// v9.3.3: write_combined_hints escribe también los hints binarios (0xB)
+// v9.3.2: combined_hints sobre manifests ya escritos; rebuild_incremental (--incremental)
+// v9.3.1: MappingRow con capa, categoría y bytes: --quant-report
+// v9.3.0: intermediate_size se parchea desde mlp.gate_up (÷2) o mlp.up
+// v9.2.9: lm_head.weight guardado [hidden, vocab] se transpone a [vocab, hidden]
+// v9.2.8: Tensores grandes se leen y cuantizan por trozos (chunk_bytes)
+// v9.2.7: --flat-names: nombres canónicos sin prefijo de modalidad
+// v9.2.6: ConversionObserver: progreso por tensor y cancelación (ConvertError::Cancelled)
+// v9.2.5: --tied-lm-head alias: lm_head.weight como alias del embedding
+// v9.2.4: Tensores en orden estable (iter_tensors_sorted): salida determinista
+// v9.2.3: projector.type/depth según los projector.vision.linearN escritos
+// v9.2.2: num_key_value_heads se corrige si k_proj trae K/V pre-repetidos
+// v9.2.1: --target-size: estimate_model + fit_quant_plan eligen HQ5K/HQ4K por tensor
+// v9.2.0: --keep-unmapped guarda los tensores sin mapear (FP16, nombre original)
+// v9.1.9: --anneal-quant sube a HQ5K las primeras/últimas K capas
+// v9.1.8: --max-memory cuantiza en paralelo por lotes acotados en bytes f32
+// v9.1.7: Tensores fuente que comparten data_offsets se guardan una vez (alias_of)
+// v9.1.6: write_combined_hints acepta HintOverrides (--max-position)
+// v9.1.5: vocab_size se parchea desde el embedding de cualquier bloque
+// v9.1.4: BuildOptions::canonical_report registra cada decisión source→canonical
+// v9.1.3: process_model_with_mapper para extraer una torre de un VLM combinado
+// v9.1.2: Aborta si kv_heads no divide a heads (config mal leído)
+// v9.1.1: --quant-min-bytes guarda como FP16 los tensores pequeños
+// v9.1.0: BuildOptions agrupa los parámetros de process_model; --hash-sources
+// v9.0.9: set_tokenizer reemplaza el bloque 0x9 de un HNF ya construido
+// v9.0.8: Tensores menores que un super-block se bajan a FP16
+// v9.0.7: norm_type/norm_bias se corrigen según los tensores escritos
+// v9.0.6: Cuenta tensores ignorados aparte y expone check_skip_ratio
 // v9.0.5: Prefijo text. para consistencia (todas las modalidades tienen prefijo)
 // v9.0.4: Añade prefijos code./cortex. a tensores según bloque
 // v9.0.3: Parchea vocab_size desde tensor real
 //
 // ============================================================================
 
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use anyhow::{Result, Context};
+use rayon::prelude::*;
 
-use crate::hqs::{self, QuantFormat};
-use crate::hnf::HnfWriter;
-use crate::mapping::{ModelMapper, BlockType, create_mapper};
+use crate::hints::{apply_max_position, apply_stored_kv_heads, check_gqa_ratio, detect_attention_bias, detect_norm_type, detect_projector_depth, build_execution_hints_binary};
+use crate::calibration;
+use crate::lora::LoraAdapter;
+use crate::hqs::{self, ChannelWeights, GridConfig, QuantFormat};
+use crate::hnf::{HnfWriter, HnfReader, TensorManifest, rewrite_blocks, BLOCK_EXEC_HINTS, BLOCK_EXEC_HINTS_BIN, BLOCK_NAMES, BLOCK_TEXT_MODEL, BLOCK_TOKENIZER};
+use crate::incremental::{RebuildPlan, SourceFile};
+use crate::htf::{self, DomainType};
+use crate::mapping::{ModelMapper, BlockType, TensorCategory, create_mapper, create_tower_mapper, load_config};
 use crate::safetensor::SafetensorReader;
 
+/// --tied-lm-head: qué hacer con lm_head cuando tie_word_embeddings = true
+/// y el checkpoint no lo trae
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TiedLmHead {
+    /// No se escribe: el engine reutiliza el embedding por convención
+    #[default]
+    Omit,
+    /// lm_head.weight se registra como alias de token_embedding.weight
+    Alias,
+}
+
+impl TiedLmHead {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "omit" => Some(Self::Omit),
+            "alias" => Some(Self::Alias),
+            _ => None,
+        }
+    }
+}
+
+/// Tope por defecto de f32 decodificados de un solo tensor (512 MB)
+pub const DEFAULT_CHUNK_BYTES: usize = 512 * 1024 * 1024;
+
+/// Opciones de conversión de process_model
+#[derive(Debug, Clone)]
+pub struct BuildOptions {
+    pub default_quant: QuantFormat,
+    pub use_mse: bool,
+    /// HQ4K/HQ5K sin zero-point (min = -scale/2)
+    pub symmetric: bool,
+    pub verbose: bool,
+    /// Calcular XXH3-64 de cada shard fuente (lee los archivos completos)
+    pub hash_sources: bool,
+    /// Tensores con menos bytes fuente que esto se guardan en FP16 (0 = desactivado)
+    pub quant_min_bytes: usize,
+    /// Registrar cada tensor escrito en BuildStats::mapping
+    pub canonical_report: bool,
+    /// Cuantizar tensores en paralelo con este tope de bytes f32 en vuelo
+    /// (None = secuencial)
+    pub max_memory: Option<usize>,
+    /// --anneal-quant: las K primeras y K últimas capas en HQ5K, el resto en HQ4K
+    pub anneal_layers: Option<usize>,
+    /// --keep-unmapped: guardar los tensores sin mapear (FP16, nombre original)
+    /// al final del bloque en vez de descartarlos
+    pub keep_unmapped: bool,
+    /// --target-size: formato por nombre final (ver fit_quant_plan)
+    pub quant_plan: Option<HashMap<String, QuantFormat>>,
+    /// --tied-lm-head
+    pub tied_lm_head: TiedLmHead,
+    /// --flat-names: nombres canónicos tal cual, sin "text."/"code."/...
+    pub flat_names: bool,
+    /// Tensores con más bytes f32 que esto se leen y cuantizan por trozos
+    /// de super-bloques completos (embeddings gigantes)
+    pub chunk_bytes: usize,
+    /// --calibration-data: secuencias de tokens para ponderar el error de
+    /// cuantización por importancia de canal (solo bloque de texto)
+    pub calibration: Option<Vec<Vec<u32>>>,
+    /// --lora: adaptador a fusionar en los pesos base (solo bloque de texto)
+    pub lora: Option<Arc<LoraAdapter>>,
+    /// --super-block: elementos por super-block HQ*K (múltiplo de 8)
+    pub super_block: usize,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            default_quant: QuantFormat::HQ5K,
+            use_mse: true,
+            symmetric: false,
+            verbose: false,
+            hash_sources: false,
+            quant_min_bytes: 0,
+            canonical_report: false,
+            max_memory: None,
+            anneal_layers: None,
+            keep_unmapped: false,
+            quant_plan: None,
+            tied_lm_head: TiedLmHead::Omit,
+            flat_names: false,
+            chunk_bytes: DEFAULT_CHUNK_BYTES,
+            calibration: None,
+            lora: None,
+            super_block: hqs::SUPER_BLOCK_SIZE,
+        }
+    }
+}
+
+impl BuildOptions {
+    /// Simetría y super-block comunes a todos los formatos HQ*K
+    pub fn grid(&self) -> GridConfig {
+        GridConfig::default()
+            .with_symmetric(self.symmetric)
+            .with_super_block(self.super_block)
+    }
+}
+
+/// Ajustes manuales sobre los execution_hints generados por los mappers
+#[derive(Debug, Clone, Default)]
+pub struct HintOverrides {
+    /// --max-position: contexto extendido (solo bloques de texto)
+    pub max_position: Option<usize>,
+}
+
+/// Tensor planificado: decisiones tomadas, datos aún sin leer
+struct PlannedTensor<'a> {
+    /// Posición en el safetensors (para el progreso)
+    idx: usize,
+    name: &'a str,
+    final_name: String,
+    quant: QuantFormat,
+    shape: Vec<usize>,
+    /// Nombre final del tensor con el que comparte almacenamiento
+    alias_of: Option<String>,
+    layer: Option<usize>,
+    category: TensorCategory,
+    /// Fuente 2D guardada traspuesta: se transpone antes de cuantizar
+    /// (`shape` ya es la forma corregida)
+    transpose: bool,
+}
+
+impl PlannedTensor<'_> {
+    /// Memoria estimada mientras se cuantiza (datos decodificados a f32;
+    /// los tensores grandes nunca pasan de un trozo)
+    fn f32_bytes(&self, chunk_bytes: usize) -> usize {
+        if self.alias_of.is_some() {
+            0
+        } else {
+            let bytes = self.shape.iter().product::<usize>() * 4;
+            // Transponer necesita el tensor entero (origen + copia)
+            if self.transpose { bytes * 2 } else { bytes.min(chunk_bytes) }
+        }
+    }
+}
+
+/// Lee y cuantiza un tensor. Si sus f32 superan `chunk_bytes` se procesa por
+/// trozos de super-bloques completos: HQ*K cuantiza cada super-bloque por
+/// separado y FP16 elemento a elemento, así que el resultado es idéntico
+/// byte a byte al del tensor entero.
+///
+/// `grid`: simetría y tamaño de super-block (BuildOptions::grid)
+/// `importance`: peso por canal de entrada (columna) para el grid search
+pub fn read_quantized(
+    reader: &SafetensorReader,
+    name: &str,
+    quant: QuantFormat,
+    use_mse: bool,
+    grid: GridConfig,
+    chunk_bytes: usize,
+    importance: Option<&[f32]>,
+) -> Result<Vec<u8>> {
+    let weights = |offset| importance.map(|channels| ChannelWeights { channels, offset });
+    let numel: usize = reader.shape(name)
+        .with_context(|| format!("Tensor '{}' not found", name))?
+        .iter().product();
+    let chunk_elems = (chunk_bytes / 4 / grid.super_block).max(1) * grid.super_block;
+    
+    if numel <= chunk_elems {
+        let data = reader.read(name)?;
+        return Ok(hqs::quantize_with(&data, quant, use_mse, grid, weights(0)));
+    }
+    
+    let mut out = Vec::new();
+    let mut start = 0;
+    while start < numel {
+        let count = chunk_elems.min(numel - start);
+        let data = reader.read_range(name, start, count)?;
+        out.extend_from_slice(&hqs::quantize_with(&data, quant, use_mse, grid, weights(start)));
+        start += count;
+    }
+    Ok(out)
+}
+
+/// Un tensor en memoria por el mismo camino que process_model (mapear →
+/// resolver formato → cuantizar con el grid de `options`), sin archivos ni
+/// writer. None si el mapper lo ignora o no lo mapea; error si `data` no
+/// cuadra con `shape`. Devuelve (nombre canónico, formato, bytes).
+pub fn map_and_quantize(
+    mapper: &dyn ModelMapper,
+    name: &str,
+    data: &[f32],
+    shape: &[usize],
+    options: &BuildOptions,
+) -> Result<Option<(String, QuantFormat, Vec<u8>)>> {
+    if mapper.should_ignore(name) {
+        return Ok(None);
+    }
+    let Some(mapping) = mapper.map_tensor(name) else {
+        return Ok(None);
+    };
+    let numel: usize = shape.iter().product();
+    if data.len() != numel {
+        anyhow::bail!("'{}': {} values for shape {:?} ({} elements)", name, data.len(), shape, numel);
+    }
+    
+    // Igual que plan_tensors: menos de un super-block no compensa HQxK
+    let mut quant = mapping.quant_hint.resolve(options.default_quant);
+    if numel < quant.min_elements_for(options.super_block) {
+        quant = QuantFormat::FP16;
+    }
+    let bytes = hqs::quantize_with(data, quant, options.use_mse, options.grid(), None);
+    Ok(Some((mapping.canonical_name, quant, bytes)))
+}
+
+/// Formato de una capa con --anneal-quant: las primeras y últimas `edge`
+/// capas son más sensibles a la cuantización.
+pub fn anneal_format(layer: usize, num_layers: usize, edge: usize) -> QuantFormat {
+    if layer < edge || layer + edge >= num_layers {
+        QuantFormat::HQ5K
+    } else {
+        QuantFormat::HQ4K
+    }
+}
+
+/// Agrupa tensores consecutivos en lotes cuyo coste sumado no pasa de
+/// `max_bytes`. Un tensor que por sí solo lo supera va en un lote propio.
+pub fn memory_batches(costs: &[usize], max_bytes: usize) -> Vec<Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut in_flight = 0usize;
+    for (i, &cost) in costs.iter().enumerate() {
+        if i > start && in_flight + cost > max_bytes {
+            batches.push(start..i);
+            start = i;
+            in_flight = 0;
+        }
+        in_flight += cost;
+    }
+    if start < costs.len() {
+        batches.push(start..costs.len());
+    }
+    batches
+}
+
+/// Shard fuente que contribuyó a la salida (para builds reproducibles)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SourceHash {
+    pub file: String,
+    pub size: u64,
+    /// XXH3-64 en hex
+    pub xxh3: String,
+}
+
+/// Decisión del builder para un tensor escrito (--canonical-report)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MappingRow {
+    pub source: String,
+    /// Nombre final en el HNF (con prefijo de bloque)
+    pub canonical: String,
+    pub block: String,
+    pub quant: String,
+    pub shape: Vec<usize>,
+    /// Para --quant-report (no van al canonical report)
+    #[serde(skip)]
+    pub layer: Option<usize>,
+    #[serde(skip)]
+    pub category: TensorCategory,
+    /// Bytes guardados (0 = alias)
+    #[serde(skip)]
+    pub bytes: usize,
+}
+
+/// Bytes guardados frente a su equivalente FP16
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReportBucket {
+    pub stored: u64,
+    pub fp16: u64,
+}
+
+impl ReportBucket {
+    fn add(&mut self, row: &MappingRow) {
+        self.stored += row.bytes as u64;
+        if row.bytes > 0 {
+            self.fp16 += row.shape.iter().product::<usize>() as u64 * 2;
+        }
+    }
+    
+    /// FP16 / guardado (2.0 = la mitad que FP16)
+    pub fn ratio(&self) -> f64 {
+        if self.stored == 0 { 0.0 } else { self.fp16 as f64 / self.stored as f64 }
+    }
+}
+
+/// --quant-report: bytes por capa y por categoría de tensor
+#[derive(Debug, Default)]
+pub struct QuantReport {
+    /// None = tensores fuera de capas (embedding, final_norm, lm_head...)
+    pub layers: BTreeMap<Option<usize>, ReportBucket>,
+    pub categories: BTreeMap<String, ReportBucket>,
+    pub total: ReportBucket,
+}
+
+/// Agrega las filas de BuildStats::mapping (aliases sin bytes propios)
+pub fn quant_report(rows: &[MappingRow]) -> QuantReport {
+    let mut report = QuantReport::default();
+    for row in rows {
+        report.layers.entry(row.layer).or_default().add(row);
+        report.categories.entry(row.category.name().to_string()).or_default().add(row);
+        report.total.add(row);
+    }
+    report
+}
+
+/// Progreso de process_model_observed: un evento por tensor escrito
+#[derive(Debug, Clone, Copy)]
+pub struct Progress<'a> {
+    pub done: usize,
+    pub total: usize,
+    /// Nombre final del último tensor escrito
+    pub tensor: &'a str,
+}
+
+/// Enganche para GUIs/servidores: progreso y cancelación entre tensores.
+/// Un `AtomicBool` sirve directamente como flag de cancelación.
+pub trait ConversionObserver {
+    fn on_progress(&self, _progress: Progress<'_>) {}
+    
+    fn should_cancel(&self) -> bool {
+        false
+    }
+}
+
+/// Sin observador (CLI)
+impl ConversionObserver for () {}
+
+impl ConversionObserver for AtomicBool {
+    fn should_cancel(&self) -> bool {
+        self.load(Ordering::Relaxed)
+    }
+}
+
+/// Errores de conversión que el llamador distingue con `downcast_ref`
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    /// ConversionObserver::should_cancel; el archivo de salida ya se borró
+    #[error("conversion cancelled")]
+    Cancelled,
+}
+
 /// Estadísticas de conversión
 #[derive(Debug, Default)]
 pub struct BuildStats {
     pub fp16_count: usize,
     pub hq5k_count: usize,
+    pub hq6k_count: usize,
     pub hq4k_count: usize,
     pub skipped_count: usize,
+    /// Tensores descartados a propósito (rotary_emb, inv_freq...), no cuentan como skip
+    pub ignored_count: usize,
     pub total_bytes: usize,
+    /// Tensores que quedaron en FP16 por --quant-min-bytes
+    pub below_min_bytes_count: usize,
+    /// Tensores menores que un super-block, guardados en FP16
+    pub below_super_block_count: usize,
+    /// Nombres que comparten almacenamiento con otro ya escrito (no ocupan bytes)
+    pub aliased_count: usize,
+    /// Tensores sin mapear guardados con --keep-unmapped (no cuentan como skip)
+    pub extras_count: usize,
+    /// Pesos base con un par LoRA fusionado (--lora)
+    pub lora_merged: usize,
+    /// Solo con BuildOptions::hash_sources
+    pub sources: Vec<SourceHash>,
+    /// Solo con BuildOptions::canonical_report
+    pub mapping: Vec<MappingRow>,
 }
 
 impl BuildStats {
     pub fn total_tensors(&self) -> usize {
-        self.fp16_count + self.hq5k_count + self.hq4k_count
+        self.fp16_count + self.hq6k_count + self.hq5k_count + self.hq4k_count
     }
     
     pub fn record(&mut self, format: QuantFormat, size: usize) {
         match format {
             QuantFormat::FP16 => self.fp16_count += 1,
+            QuantFormat::HQ6K => self.hq6k_count += 1,
             QuantFormat::HQ5K => self.hq5k_count += 1,
             QuantFormat::HQ4K => self.hq4k_count += 1,
             _ => {}
         }
         self.total_bytes += size;
     }
+    
+    /// Fracción de tensores no mapeados sobre los candidatos (escritos + no mapeados).
+    /// Los ignorados por allowlist del mapper no entran en la cuenta.
+    pub fn skip_ratio(&self) -> f64 {
+        let candidates = self.total_tensors() + self.skipped_count;
+        if candidates == 0 {
+            return 0.0;
+        }
+        self.skipped_count as f64 / candidates as f64
+    }
+}
+
+/// Falla si la proporción de tensores no mapeados supera `max_ratio`.
+///
+/// Red de seguridad contra huecos de cobertura del mapper: un fine-tune que
+/// pierde el 30% de sus tensores "convierte" igual pero produce un modelo roto.
+pub fn check_skip_ratio(stats: &BuildStats, max_ratio: f64) -> Result<()> {
+    let ratio = stats.skip_ratio();
+    if ratio > max_ratio {
+        anyhow::bail!(
+            "Skip ratio {:.2}% exceeds --max-skip-ratio {:.2}% ({} of {} tensors unmapped)",
+            ratio * 100.0,
+            max_ratio * 100.0,
+            stats.skipped_count,
+            stats.total_tensors() + stats.skipped_count
+        );
+    }
+    Ok(())
+}
+
+/// Capas de 0..num_layers sin ningún tensor `layer{N}.` escrito en el bloque.
+///
+/// Un checkpoint recortado (shards que faltan, capas podadas a mano) convierte
+/// sin errores pero el archivo queda parcial: el CLI lo marca en el manifest.
+pub fn missing_layers(tensors: &[TensorManifest], num_layers: usize) -> Vec<usize> {
+    let written: std::collections::HashSet<usize> = tensors.iter()
+        .filter_map(|t| {
+            t.name.split('.')
+                .find_map(|segment| segment.strip_prefix("layer")?.parse().ok())
+        })
+        .collect();
+    (0..num_layers).filter(|layer| !written.contains(layer)).collect()
+}
+
+/// Escribe el informe source→canonical: CSV si la extensión es .csv, si no JSON
+pub fn write_canonical_report(path: &Path, rows: &[MappingRow]) -> Result<()> {
+    let is_csv = path.extension()
+        .map(|e| e.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false);
+    
+    let content = if is_csv {
+        let mut csv = String::from("source,canonical,block,quant,shape\n");
+        for row in rows {
+            let shape: Vec<String> = row.shape.iter().map(|d| d.to_string()).collect();
+            let fields = [&row.source, &row.canonical, &row.block, &row.quant, &shape.join("x")];
+            let escaped: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+            csv.push_str(&escaped.join(","));
+            csv.push('\n');
+        }
+        csv
+    } else {
+        serde_json::to_string_pretty(rows)?
+    };
+    
+    std::fs::write(path, content)
+        .with_context(|| format!("Cannot write canonical report {}", path.display()))
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
 }
 
 /// Resuelve el nombre final del tensor con prefijo según bloque.
@@ -81,7 +557,8 @@ fn resolve_tensor_name(canonical_name: &str, target_block: BlockType) -> String
             }
         }
         BlockType::Vision => {
-            if canonical_name.starts_with("vision.") {
+            // projector.vision.* ya lleva la modalidad (nombre del diccionario)
+            if canonical_name.starts_with("vision.") || canonical_name.starts_with("projector.vision.") {
                 canonical_name.to_string()
             } else {
                 format!("vision.{}", canonical_name)
@@ -98,177 +575,2013 @@ fn resolve_tensor_name(canonical_name: &str, target_block: BlockType) -> String
     }
 }
 
-/// Procesa un modelo y escribe al bloque especificado
-pub fn process_model(
-    model_path: &Path,
+/// PLAN: nombre final, formato y alias de cada tensor (sin leer datos).
+/// Devuelve los tensores canónicos y los extras de --keep-unmapped.
+fn plan_tensors<'r>(
+    reader: &'r SafetensorReader,
+    mapper: &dyn ModelMapper,
     target_block: BlockType,
-    writer: &mut HnfWriter,
-    default_quant: QuantFormat,
-    use_mse: bool,
-    verbose: bool,
-) -> Result<BuildStats> {
-    let mut stats = BuildStats::default();
-    
-    // Crear mapper para la arquitectura
-    let mapper = create_mapper(model_path)
-        .with_context(|| format!("Failed to create mapper for {}", model_path.display()))?;
-    
-    if verbose {
-        println!("  Mapper: {}", mapper.name());
-        println!("  Layers: {}", mapper.num_layers());
-        println!("  Target block: {} (0x{:X})", target_block.name(), target_block.as_usize());
-    }
-    
-    // Abrir safetensors
-    let reader = SafetensorReader::from_folder(model_path)
-        .with_context(|| format!("Failed to open model {}", model_path.display()))?;
+    options: &BuildOptions,
+    stats: &mut BuildStats,
+) -> (Vec<PlannedTensor<'r>>, Vec<(&'r str, Vec<usize>)>) {
+    let BuildOptions { default_quant, verbose, .. } = *options;
     
-    let total_tensors = reader.len();
-    if verbose {
-        println!("  Tensors: {}", total_tensors);
-    }
+    // (shard, data_offsets) → (nombre final, formato) del primero que se escribe
+    let mut written_storage: HashMap<(usize, [usize; 2]), (String, QuantFormat)> = HashMap::new();
+    let mut planned: Vec<PlannedTensor> = Vec::new();
+    // --keep-unmapped: (nombre original, shape), se escriben tras los canónicos
+    let mut extras: Vec<(&str, Vec<usize>)> = Vec::new();
     
-    // Procesar cada tensor
-    for (idx, (name, info)) in reader.iter_tensors().enumerate() {
+    // Orden estable: el header safetensors es un HashMap y el HNF debe salir
+    // idéntico byte a byte entre ejecuciones (tests/determinism.rs)
+    for (idx, (name, info)) in reader.iter_tensors_sorted().enumerate() {
+        // Tensores de la allowlist (rotary_emb, inv_freq...) no cuentan como skip
+        if mapper.should_ignore(name) {
+            stats.ignored_count += 1;
+            continue;
+        }
+        
         // El mapper decide nombre canónico y sugiere cuantización
         let mapping = match mapper.map_tensor(name) {
             Some(m) => m,
+            None if options.keep_unmapped => {
+                if verbose {
+                    println!("    [EXTRA] {} (unmapped, kept verbatim)", name);
+                }
+                extras.push((name, info.shape.clone()));
+                continue;
+            }
             None => {
+                if verbose {
+                    println!("    [SKIP] {} (unmapped)", name);
+                }
                 stats.skipped_count += 1;
                 continue;
             }
         };
         
         // ═══════════════════════════════════════════════════════════════════
-        // RESOLVER NOMBRE FINAL CON PREFIJO SEGÚN BLOQUE
+        // RESOLVER NOMBRE FINAL CON PREFIJO SEGÚN BLOQUE (salvo --flat-names)
         // ═══════════════════════════════════════════════════════════════════
-        let final_name = resolve_tensor_name(&mapping.canonical_name, target_block);
+        let final_name = if options.flat_names {
+            mapping.canonical_name.clone()
+        } else {
+            resolve_tensor_name(&mapping.canonical_name, target_block)
+        };
         
         // Resolver cuantización (mapper sugiere, default resuelve)
-        let quant = mapping.quant_hint.resolve(default_quant);
+        let mut quant = mapping.quant_hint.resolve(default_quant);
         
-        // Leer datos
-        let data = reader.read(name)?;
+        // --anneal-quant: la posición de la capa manda sobre la sugerencia (FP16 se respeta)
+        if let (Some(edge), Some(layer)) = (options.anneal_layers, mapping.layer_idx) {
+            if quant != QuantFormat::FP16 {
+                quant = anneal_format(layer, mapper.num_layers(), edge);
+            }
+        }
+        
+        // --target-size: el plan por tensor manda sobre sugerencia y anneal
+        if let Some(&planned_quant) = options.quant_plan.as_ref().and_then(|plan| plan.get(&final_name)) {
+            quant = planned_quant;
+        }
         
-        // Cuantizar
-        let quantized = hqs::quantize(&data, quant, use_mse);
-        let quantized_size = quantized.len();
+        // Tensores menores que un super-block: HQxK solo añade padding, usar FP16
+        let numel: usize = info.shape.iter().product();
+        if numel < quant.min_elements_for(options.super_block) {
+            if verbose {
+                println!("    [FP16] {} ({} elements < {} for {})",
+                    name, numel, quant.min_elements_for(options.super_block), quant);
+            }
+            quant = QuantFormat::FP16;
+            stats.below_super_block_count += 1;
+        }
         
-        // Escribir al bloque con nombre final (incluye prefijo si aplica)
-        writer.write_tensor(
-            target_block.as_usize(),
-            &final_name,
-            &quant.to_string().to_lowercase(),
-            &info.shape,
-            &quantized,
-        )?;
+        // Umbral de tamaño: la sobrecarga por bloque no compensa en matrices pequeñas
+        let source_bytes = info.data_offsets[1] - info.data_offsets[0];
+        if quant != QuantFormat::FP16 && source_bytes < options.quant_min_bytes {
+            if verbose {
+                println!("    [FP16] {} ({} bytes < --quant-min-bytes)", name, source_bytes);
+            }
+            quant = QuantFormat::FP16;
+            stats.below_min_bytes_count += 1;
+        }
         
-        stats.record(quant, quantized_size);
+        // Alias: mismos bytes fuente ya planificados con el mismo formato → no duplicar.
+        // Un peso con LoRA deja de ser igual a su fuente: nunca comparte almacenamiento.
+        let merged = options.lora.as_ref().is_some_and(|lora| target_block == BlockType::TextModel && lora.has(name));
+        let alias_of = match reader.storage_key(name).filter(|_| !merged) {
+            Some(key) => match written_storage.get(&key) {
+                Some((target, target_quant)) if *target_quant == quant => Some(target.clone()),
+                Some(_) => None,
+                None => {
+                    written_storage.insert(key, (final_name.clone(), quant));
+                    None
+                }
+            },
+            None => None,
+        };
         
-        // Progress
-        if verbose && (idx + 1) % 20 == 0 {
-            println!("    [{}/{}] {}", idx + 1, total_tensors, final_name);
+        planned.push(PlannedTensor {
+            idx,
+            name,
+            final_name,
+            quant,
+            shape: info.shape.clone(),
+            alias_of,
+            layer: mapping.layer_idx,
+            category: mapping.category,
+            transpose: false,
+        });
+    }
+    
+    fix_transposed_lm_head(&mut planned);
+    (planned, extras)
+}
+
+/// lm_head.weight exportado como [hidden, vocab] en vez de [vocab, hidden]
+/// (dims cruzadas respecto a token_embedding.weight): se marca para
+/// transponer y se corrige su shape, así la proyección de salida y los
+/// hints leen [vocab, hidden] como en el resto de modelos.
+fn fix_transposed_lm_head(planned: &mut [PlannedTensor]) {
+    let is = |t: &PlannedTensor, suffix: &str| t.final_name == suffix || t.final_name.ends_with(&format!(".{}", suffix));
+    let Some(embedding) = planned.iter().find(|t| is(t, "token_embedding.weight")).map(|t| t.shape.clone()) else {
+        return;
+    };
+    let Some(lm_head) = planned.iter_mut().find(|t| is(t, "lm_head.weight")) else {
+        return;
+    };
+    if let ([vocab, hidden], [rows, cols]) = (embedding.as_slice(), lm_head.shape.as_slice()) {
+        if vocab != hidden && rows == hidden && cols == vocab && lm_head.alias_of.is_none() {
+            eprintln!(
+                "[WARN] {} is stored transposed ({:?}, embedding is {:?}): transposing to [{}, {}]",
+                lm_head.name, lm_head.shape, embedding, vocab, hidden
+            );
+            lm_head.shape = vec![*vocab, *hidden];
+            lm_head.transpose = true;
+        }
+    }
+}
+
+/// Transpone una matriz row-major de `rows` × `cols`
+fn transpose_2d(data: &[f32], rows: usize, cols: usize) -> Vec<f32> {
+    let mut out = vec![0.0; data.len()];
+    for (r, row) in data.chunks_exact(cols).enumerate().take(rows) {
+        for (c, &x) in row.iter().enumerate() {
+            out[c * rows + r] = x;
         }
     }
+    out
+}
+
+/// Procesa un modelo y escribe al bloque especificado
+pub fn process_model(
+    model_path: &Path,
+    target_block: BlockType,
+    writer: &mut HnfWriter,
+    options: &BuildOptions,
+) -> Result<BuildStats> {
+    // Crear mapper para la arquitectura
+    let mapper = create_mapper(model_path)
+        .with_context(|| format!("Failed to create mapper for {}", model_path.display()))?;
     
-    // Finalizar bloque (calcula checksum)
-    writer.finalize_block(target_block.as_usize())?;
+    process_model_with_mapper(model_path, target_block, writer, mapper.as_ref(), options)
+}
+
+/// Igual que process_model pero con un mapper ya construido
+/// (p.ej. un TowerMapper que solo acepta una torre de un VLM combinado)
+pub fn process_model_with_mapper(
+    model_path: &Path,
+    target_block: BlockType,
+    writer: &mut HnfWriter,
+    mapper: &dyn ModelMapper,
+    options: &BuildOptions,
+) -> Result<BuildStats> {
+    process_model_observed(model_path, target_block, writer, mapper, options, &())
+}
+
+/// process_model_with_mapper con observador: informa tras cada tensor y
+/// consulta should_cancel entre tensores. Al cancelar borra el archivo del
+/// writer (HnfWriter::discard) y devuelve ConvertError::Cancelled.
+pub fn process_model_observed(
+    model_path: &Path,
+    target_block: BlockType,
+    writer: &mut HnfWriter,
+    mapper: &dyn ModelMapper,
+    options: &BuildOptions,
+    observer: &dyn ConversionObserver,
+) -> Result<BuildStats> {
+    check_mapper(model_path, target_block, mapper, options.verbose)?;
+    let (reader, sources) = open_sources(model_path, options)?;
+    let stats = process_reader(&reader, model_path, target_block, writer, mapper, options, observer)?;
+    Ok(BuildStats { sources, ..stats })
+}
+
+/// Torre de un VLM combinado escrita por process_vlm
+pub struct VlmTower {
+    pub block: BlockType,
+    pub mapper: Box<dyn ModelMapper>,
+    pub stats: BuildStats,
+}
+
+/// VLM combinado (texto, visión y projector en el mismo set de safetensors):
+/// abre el directorio una vez y reparte cada tensor por prefijo con los
+/// TowerMapper de texto (`language_model.`/`model.`) y visión
+/// (`vision_tower.`/`vision_model.`, más `multi_modal_projector.`).
+/// Escribe el bloque de texto y luego el de visión; las fuentes se hashean
+/// una sola vez (stats de la torre de texto).
+pub fn process_vlm(
+    model_path: &Path,
+    writer: &mut HnfWriter,
+    options: &BuildOptions,
+) -> Result<Vec<VlmTower>> {
+    let config = load_config(model_path)?;
+    if config.get("vision_config").is_none() {
+        anyhow::bail!("{} is not a combined VLM (config.json has no vision_config)", model_path.display());
+    }
     
-    Ok(stats)
+    let mut towers = Vec::new();
+    for block in [BlockType::TextModel, BlockType::Vision] {
+        let mapper = create_tower_mapper(model_path, block)
+            .with_context(|| format!("Failed to create {} mapper for {}", block.name(), model_path.display()))?;
+        check_mapper(model_path, block, mapper.as_ref(), options.verbose)?;
+        towers.push((block, mapper));
+    }
+    
+    let (reader, mut sources) = open_sources(model_path, options)?;
+    let mut written = Vec::new();
+    for (block, mapper) in towers {
+        let stats = process_reader(&reader, model_path, block, writer, mapper.as_ref(), options, &())?;
+        let stats = BuildStats { sources: std::mem::take(&mut sources), ..stats };
+        written.push(VlmTower { block, mapper, stats });
+    }
+    Ok(written)
 }
 
-/// Escribe execution_hints combinados de múltiples mappers
-/// v9.0.5: TEXT también va bajo "text" con "text_enabled" para consistencia
-/// v9.0.3: Parchea vocab_size desde el tensor real token_embedding.weight
-pub fn write_combined_hints(
+/// head_dim exacto y GQA entero antes de escribir nada
+fn check_mapper(model_path: &Path, target_block: BlockType, mapper: &dyn ModelMapper, verbose: bool) -> Result<()> {
+    mapper.check_config()
+        .with_context(|| format!("Invalid attention config in {}", model_path.display()))?;
+    let hints = mapper.execution_hints();
+    if let (Some(heads), Some(kv_heads)) = (
+        hints.get("num_attention_heads").and_then(|v| v.as_u64()),
+        hints.get("num_key_value_heads").and_then(|v| v.as_u64()),
+    ) {
+        check_gqa_ratio(heads as usize, kv_heads as usize)
+            .with_context(|| format!("Invalid attention config in {}", model_path.display()))?;
+    }
+    
+    if verbose {
+        println!("  Mapper: {}", mapper.name());
+        println!("  Layers: {}", mapper.num_layers());
+        println!("  Target block: {} (0x{:X})", target_block.name(), target_block.as_usize());
+    }
+    Ok(())
+}
+
+/// Abre los safetensors y, con --hash-sources, hashea cada shard
+fn open_sources(model_path: &Path, options: &BuildOptions) -> Result<(SafetensorReader, Vec<SourceHash>)> {
+    let reader = SafetensorReader::from_folder(model_path)
+        .with_context(|| format!("Failed to open model {}", model_path.display()))?;
+    reader.check_not_prequantized()
+        .with_context(|| format!("Cannot convert {}", model_path.display()))?;
+    
+    if options.verbose {
+        println!("  Tensors: {}", reader.len());
+    }
+    
+    // Hash de los shards fuente (auditoría de supply-chain)
+    let mut sources = Vec::new();
+    if options.hash_sources {
+        for file in reader.files() {
+            let name = file.path.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let hash = file.hash_xxh3();
+            if options.verbose {
+                println!("  Source: {} ({} bytes) xxh3={:016x}", name, file.file_size(), hash);
+            }
+            sources.push(SourceHash {
+                file: name,
+                size: file.file_size(),
+                xxh3: format!("{:016x}", hash),
+            });
+        }
+    }
+    Ok((reader, sources))
+}
+
+/// Escribe al bloque los tensores de `reader` que acepta el mapper
+fn process_reader(
+    reader: &SafetensorReader,
+    model_path: &Path,
+    target_block: BlockType,
     writer: &mut HnfWriter,
-    mappers: &[(&dyn ModelMapper, BlockType)],
-) -> Result<()> {
-    let mut combined = serde_json::Map::new();
+    mapper: &dyn ModelMapper,
+    options: &BuildOptions,
+    observer: &dyn ConversionObserver,
+) -> Result<BuildStats> {
+    let mut stats = BuildStats::default();
+    let BuildOptions { use_mse, verbose, .. } = *options;
+    let grid = options.grid();
+    let hints = mapper.execution_hints();
+    let total_tensors = reader.len();
     
-    // Obtener manifests para parchear vocab_size
-    let manifests = writer.tensor_manifests();
+    let (planned, extras) = plan_tensors(reader, mapper, target_block, options, &mut stats);
     
-    for (mapper, block) in mappers {
-        let mut hints = mapper.execution_hints();
-        
-        // ═══════════════════════════════════════════════════════════════════
-        // PARCHEAR vocab_size DESDE TENSOR REAL
-        // ═══════════════════════════════════════════════════════════════════
-        if let Some(obj) = hints.as_object_mut() {
-            let block_idx = block.as_usize();
-            
-            // v9.0.5: Determinar nombre del embedding según bloque (con prefijo)
-            let embedding_names: Vec<&str> = match block {
-                BlockType::TextModel => vec!["text.token_embedding.weight", "token_embedding.weight"],
-                BlockType::CodeExec => vec!["code.token_embedding.weight", "token_embedding.weight"],
-                BlockType::Cortex => vec!["cortex.token_embedding.weight", "token_embedding.weight"],
-                _ => vec!["token_embedding.weight"],
-            };
-            
-            // Buscar token_embedding.weight en este bloque
-            if let Some(tensors) = manifests.get(block_idx) {
-                for t in tensors {
-                    if embedding_names.contains(&t.name.as_str()) {
-                        if let Some(&vocab) = t.shape.first() {
-                            let old_vocab = obj.get("vocab_size")
-                                .and_then(|v| v.as_u64())
-                                .unwrap_or(0);
-                            
-                            if vocab != old_vocab as usize {
-                                eprintln!(
-                                    "[INFO] Patching vocab_size: {} -> {} (from tensor shape in {})",
-                                    old_vocab, vocab, block.name()
-                                );
-                            }
-                            
-                            obj.insert(
-                                "vocab_size".to_string(), 
-                                serde_json::json!(vocab)
-                            );
-                        }
-                        break;
+    // --calibration-data: importancia por canal desde el embedding de texto
+    let importance = match &options.calibration {
+        Some(sequences) if target_block == BlockType::TextModel => {
+            match planned.iter().find(|t| t.category == TensorCategory::Embedding && t.shape.len() == 2 && t.alias_of.is_none()) {
+                Some(embedding) => {
+                    let importance = calibration::embedding_importance(reader, embedding.name, sequences)
+                        .with_context(|| format!("Calibration failed for {}", model_path.display()))?;
+                    if verbose {
+                        let max = importance.iter().cloned().fold(0.0f32, f32::max);
+                        crate::outln!("  Calibration: {} sequences, max channel importance {:.2}×", sequences.len(), max);
                     }
+                    Some(importance)
+                }
+                None => {
+                    eprintln!("[WARN] --calibration-data: no token embedding in {}, using plain MSE", model_path.display());
+                    None
                 }
             }
         }
-        
-        // v9.0.5: Insertar hints - TODAS las modalidades usan el mismo patrón
-        match block {
-            BlockType::TextModel => {
-                // v9.0.5: TEXT también va bajo "text" para consistencia
-                combined.insert("text_enabled".to_string(), serde_json::Value::Bool(true));
-                combined.insert("text".to_string(), hints);
-            }
-            BlockType::Vision => {
-                combined.insert("vision_enabled".to_string(), serde_json::Value::Bool(true));
-                combined.insert("vision".to_string(), hints);
-            }
-            BlockType::Audio => {
-                combined.insert("audio_enabled".to_string(), serde_json::Value::Bool(true));
-                combined.insert("audio".to_string(), hints);
-            }
-            BlockType::Cortex => {
-                combined.insert("cortex_enabled".to_string(), serde_json::Value::Bool(true));
-                combined.insert("cortex".to_string(), hints);
-            }
-            BlockType::CodeExec => {
-                combined.insert("code_enabled".to_string(), serde_json::Value::Bool(true));
-                combined.insert("code".to_string(), hints);
-            }
-            _ => {
-                combined.insert(block.name().to_string(), hints);
-            }
+        _ => None,
+    };
+    // Solo proyecciones que leen el residual stream ([out, hidden])
+    let importance_for = |t: &PlannedTensor| importance.as_deref().filter(|imp| {
+        t.shape.len() == 2 && t.shape[1] == imp.len() && calibration::reads_residual_stream(&t.final_name)
+    });
+    
+    // --lora: pares sin peso base en este modelo no se aplican
+    let lora = options.lora.as_deref().filter(|_| target_block == BlockType::TextModel);
+    if let Some(lora) = lora {
+        let unmatched = lora.unmatched(planned.iter().map(|t| t.name));
+        if !unmatched.is_empty() {
+            eprintln!("[WARN] LoRA: {} adapter pairs have no base weight (ignored): {}", unmatched.len(), unmatched.join(", "));
+        }
+        if !lora.unsupported.is_empty() {
+            eprintln!("[WARN] LoRA: {} adapter tensors are not lora_A/lora_B (ignored): {}", lora.unsupported.len(), lora.unsupported.join(", "));
+        }
+        stats.lora_merged = planned.iter().filter(|t| t.alias_of.is_none() && lora.has(t.name)).count();
+        if verbose {
+            println!("  LoRA: {} weights merged (alpha {})", stats.lora_merged, lora.alpha);
         }
     }
     
-    writer.write_execution_hints(&serde_json::Value::Object(combined))?;
-    Ok(())
-}
+    // ═══════════════════════════════════════════════════════════════════════
+    // EJECUTAR: leer + cuantizar (en paralelo por lotes si --max-memory),
+    // escribir siempre en orden de plan
+    // ═══════════════════════════════════════════════════════════════════════
+    
+    let batches = match options.max_memory {
+        Some(max_bytes) => {
+            let costs: Vec<usize> = planned.iter().map(|t| t.f32_bytes(options.chunk_bytes)).collect();
+            memory_batches(&costs, max_bytes)
+        }
+        None => (0..planned.len()).map(|i| i..i + 1).collect(),
+    };
+    
+    let total_written = planned.len() + extras.len();
+    let mut done = 0;
+    let cancel = |writer: &mut HnfWriter| -> Result<BuildStats> {
+        writer.discard()?;
+        Err(ConvertError::Cancelled.into())
+    };
+    
+    for range in batches {
+        if observer.should_cancel() {
+            return cancel(writer);
+        }
+        let batch = &planned[range];
+        let quantized: Vec<Option<Vec<u8>>> = batch.par_iter()
+            .map(|t| -> Result<Option<Vec<u8>>> {
+                if t.alias_of.is_some() {
+                    return Ok(None);
+                }
+                // LoRA: tensor entero en f32 (orientación fuente), W + (alpha/r)·B@A
+                if let Some(lora) = lora.filter(|lora| lora.has(t.name)) {
+                    let mut data = reader.read(t.name)?;
+                    let source_shape = reader.shape(t.name).unwrap_or_default();
+                    lora.merge_into(t.name, &mut data, source_shape)?;
+                    if t.transpose {
+                        data = transpose_2d(&data, t.shape[1], t.shape[0]);
+                    }
+                    let weights = importance_for(t).map(|channels| ChannelWeights { channels, offset: 0 });
+                    return Ok(Some(hqs::quantize_with(&data, t.quant, use_mse, grid, weights)));
+                }
+                if t.transpose {
+                    // shape ya corregida: la fuente es [shape[1], shape[0]]
+                    let data = transpose_2d(&reader.read(t.name)?, t.shape[1], t.shape[0]);
+                    let weights = importance_for(t).map(|channels| ChannelWeights { channels, offset: 0 });
+                    return Ok(Some(hqs::quantize_with(&data, t.quant, use_mse, grid, weights)));
+                }
+                read_quantized(reader, t.name, t.quant, use_mse, grid, options.chunk_bytes, importance_for(t)).map(Some)
+            })
+            .collect::<Result<_>>()?;
+        
+        for (t, quantized) in batch.iter().zip(quantized) {
+            let bytes = quantized.as_ref().map_or(0, |q| q.len());
+            match (&t.alias_of, quantized) {
+                (Some(target), _) => {
+                    if verbose {
+                        crate::outln!("    [ALIAS] {} → {}", t.final_name, target);
+                    }
+                    writer.write_alias(target_block.as_usize(), &t.final_name, target, &t.shape)?;
+                    stats.aliased_count += 1;
+                }
+                (None, Some(quantized)) => {
+                    // Escribir al bloque con nombre final (incluye prefijo si aplica)
+                    writer.write_tensor(
+                        target_block.as_usize(),
+                        &t.final_name,
+                        &t.quant.to_string().to_lowercase(),
+                        &t.shape,
+                        &quantized,
+                    )?;
+                    stats.record(t.quant, quantized.len());
+                    if verbose && reader.dtype(t.name) == Some("F64") {
+                        crate::outln!("    [F64] {} narrowed to F32", t.name);
+                    }
+                }
+                (None, None) => unreachable!("non-alias tensors are always quantized"),
+            }
+            
+            if options.canonical_report {
+                stats.mapping.push(MappingRow {
+                    source: t.name.to_string(),
+                    canonical: t.final_name.clone(),
+                    block: target_block.name().to_string(),
+                    quant: t.quant.to_string(),
+                    shape: t.shape.clone(),
+                    layer: t.layer,
+                    category: t.category,
+                    bytes,
+                });
+            }
+            
+            // Progress
+            if verbose && (t.idx + 1) % 20 == 0 {
+                println!("    [{}/{}] {}", t.idx + 1, total_tensors, t.final_name);
+            }
+            done += 1;
+            observer.on_progress(Progress { done, total: total_written, tensor: &t.final_name });
+            if observer.should_cancel() {
+                return cancel(writer);
+            }
+        }
+    }
+    
+    // Región de extras: al final del bloque, FP16 sin cuantizar, nombre original.
+    // No pasan por resolve_tensor_name ni por el diccionario.
+    for (name, shape) in extras {
+        let fp16 = read_quantized(reader, name, QuantFormat::FP16, false, GridConfig::default(), options.chunk_bytes, None)?;
+        writer.write_extra_tensor(target_block.as_usize(), name, "fp16", &shape, &fp16)?;
+        stats.record(QuantFormat::FP16, fp16.len());
+        stats.extras_count += 1;
+        done += 1;
+        observer.on_progress(Progress { done, total: total_written, tensor: name });
+        if observer.should_cancel() {
+            return cancel(writer);
+        }
+        
+        if options.canonical_report {
+            stats.mapping.push(MappingRow {
+                source: name.to_string(),
+                canonical: name.to_string(),
+                block: target_block.name().to_string(),
+                quant: QuantFormat::FP16.to_string(),
+                shape,
+                layer: None,
+                category: TensorCategory::Other,
+                bytes: fp16.len(),
+            });
+        }
+    }
+    
+    // Pesos atados sin lm_head propio: alias al embedding (mismo prefijo)
+    let tied = hints.get("tie_word_embeddings").and_then(|v| v.as_bool()).unwrap_or(false);
+    if options.tied_lm_head == TiedLmHead::Alias && tied {
+        if let Some((name, target, shape)) = tied_lm_head_alias(&writer.tensor_manifests()[target_block.as_usize()]) {
+            if verbose {
+                crate::outln!("    [ALIAS] {} → {} (tie_word_embeddings)", name, target);
+            }
+            writer.write_alias(target_block.as_usize(), &name, &target, &shape)?;
+            stats.aliased_count += 1;
+        }
+    }
+    
+    // Finalizar bloque (calcula checksum)
+    writer.finalize_block(target_block.as_usize())?;
+    
+    Ok(stats)
+}
+
+/// (lm_head, embedding, shape) a registrar como alias si el bloque tiene
+/// embedding de entrada pero no lm_head
+fn tied_lm_head_alias(tensors: &[TensorManifest]) -> Option<(String, String, Vec<usize>)> {
+    let embedding = tensors.iter()
+        .find(|t| t.name == "token_embedding.weight" || t.name.ends_with(".token_embedding.weight"))?;
+    let prefix = &embedding.name[..embedding.name.len() - "token_embedding.weight".len()];
+    let lm_head = format!("{}lm_head.weight", prefix);
+    
+    if tensors.iter().any(|t| t.name == lm_head) {
+        return None;
+    }
+    Some((lm_head, embedding.name.clone(), embedding.shape.clone()))
+}
+
+/// Tensor tal y como lo escribiría la conversión (para --target-size)
+#[derive(Debug, Clone)]
+pub struct TensorEstimate {
+    pub final_name: String,
+    pub numel: usize,
+    pub quant: QuantFormat,
+    /// Comparte almacenamiento con este tensor (no ocupa bytes propios)
+    pub alias_of: Option<String>,
+    /// Elementos por super-block HQ*K (--super-block)
+    pub super_block: usize,
+}
+
+impl TensorEstimate {
+    /// Bytes que ocupa en el bloque con el formato actual
+    pub fn bytes(&self) -> usize {
+        if self.alias_of.is_some() {
+            0
+        } else {
+            self.quant.size_for_super_block(self.numel, self.super_block)
+        }
+    }
+}
+
+/// Plan de un modelo sin leer datos: mismo nombre final, formato y alias que
+/// process_model_with_mapper. Los extras de --keep-unmapped van como FP16.
+pub fn estimate_model(
+    model_path: &Path,
+    target_block: BlockType,
+    mapper: &dyn ModelMapper,
+    options: &BuildOptions,
+) -> Result<Vec<TensorEstimate>> {
+    let reader = SafetensorReader::from_folder(model_path)
+        .with_context(|| format!("Failed to open model {}", model_path.display()))?;
+    reader.check_not_prequantized()
+        .with_context(|| format!("Cannot convert {}", model_path.display()))?;
+    let mut stats = BuildStats::default();
+    let (planned, extras) = plan_tensors(&reader, mapper, target_block, options, &mut stats);
+    
+    let mut estimates: Vec<TensorEstimate> = planned.into_iter()
+        .map(|t| TensorEstimate {
+            numel: t.shape.iter().product(),
+            final_name: t.final_name,
+            quant: t.quant,
+            alias_of: t.alias_of,
+            super_block: options.super_block,
+        })
+        .collect();
+    estimates.extend(extras.into_iter().map(|(name, shape)| TensorEstimate {
+        final_name: name.to_string(),
+        numel: shape.iter().product(),
+        quant: QuantFormat::FP16,
+        alias_of: None,
+        super_block: options.super_block,
+    }));
+    Ok(estimates)
+}
+
+/// --target-size: parte de `estimates` (planificados con HQ5K por defecto) y
+/// baja un escalón los tensores que más ahorran hasta que los bytes de
+/// tensores caben en `budget`: primero HQ6K → HQ5K (sugeridos por el mapper),
+/// luego HQ5K → HQ4K. FP16 (normas, tensores pequeños) no se toca.
+/// Devuelve el plan por nombre final; `estimates` queda con el formato elegido.
+pub fn fit_quant_plan(estimates: &mut [TensorEstimate], budget: usize) -> Result<HashMap<String, QuantFormat>> {
+    let mut total: usize = estimates.iter().map(TensorEstimate::bytes).sum();
+    
+    for (from, to) in [(QuantFormat::HQ6K, QuantFormat::HQ5K), (QuantFormat::HQ5K, QuantFormat::HQ4K)] {
+        // Candidatos: tensores propios en `from`, de mayor a menor ahorro
+        let mut candidates: Vec<usize> = (0..estimates.len())
+            .filter(|&i| estimates[i].quant == from && estimates[i].alias_of.is_none())
+            .collect();
+        let saving = |e: &TensorEstimate| e.bytes() - to.size_for_super_block(e.numel, e.super_block);
+        candidates.sort_by_key(|&i| std::cmp::Reverse(saving(&estimates[i])));
+        
+        for i in candidates {
+            if total <= budget {
+                break;
+            }
+            total -= saving(&estimates[i]);
+            estimates[i].quant = to;
+            // Los alias siguen al tensor que comparten (mismo formato o se rompe el alias)
+            let target = estimates[i].final_name.clone();
+            for e in estimates.iter_mut().filter(|e| e.alias_of.as_deref() == Some(&target)) {
+                e.quant = to;
+            }
+        }
+    }
+    
+    if total > budget {
+        anyhow::bail!(
+            "--target-size {} bytes is too small: all quantizable tensors in HQ4K still need {} bytes",
+            budget, total
+        );
+    }
+    
+    Ok(estimates.iter()
+        .map(|e| (e.final_name.clone(), e.quant))
+        .collect())
+}
+
+/// Filas del embedding de entrada de un bloque, sea cual sea su prefijo
+/// ("token_embedding.weight", "text.token_embedding.weight", "foo.token_embedding.weight"...)
+pub fn embedding_rows(tensors: &[TensorManifest]) -> Option<usize> {
+    tensors.iter()
+        .find(|t| t.name == "token_embedding.weight" || t.name.ends_with(".token_embedding.weight"))
+        .and_then(|t| t.shape.first().copied())
+}
+
+/// Las tres fuentes de vocab_size del modelo de texto
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VocabSources {
+    /// vocab_size de config.json
+    pub config: Option<usize>,
+    /// Filas de token_embedding.weight
+    pub embedding: Option<usize>,
+    /// IDs del tokenizer (mayor ID + 1, antes de recortar)
+    pub tokenizer: Option<usize>,
+}
+
+impl VocabSources {
+    /// Aviso si config o tokenizer apuntan más allá del embedding: esos IDs
+    /// no tienen fila y la inferencia indexa fuera. Un embedding mayor
+    /// (relleno a múltiplo de 64/128) es normal y no avisa.
+    pub fn disagreement(&self) -> Option<String> {
+        let rows = self.embedding?;
+        let beyond = [self.config, self.tokenizer].into_iter().flatten().any(|n| n > rows);
+        if !beyond {
+            return None;
+        }
+        let show = |n: Option<usize>| n.map_or("?".to_string(), |n| n.to_string());
+        Some(format!(
+            "vocab_size disagrees: config.json {}, embedding rows {}, tokenizer {} (ids >= {} have no embedding)",
+            show(self.config), rows, show(self.tokenizer), rows
+        ))
+    }
+}
+
+/// vocab_size según config.json (hints del mapper), filas del embedding de
+/// `tensors` y tokenizer de `tokenizer_dir`
+pub fn vocab_sources(
+    mapper: &dyn ModelMapper,
+    tensors: &[TensorManifest],
+    tokenizer_dir: Option<&Path>,
+) -> Result<VocabSources> {
+    Ok(VocabSources {
+        config: mapper.execution_hints().get("vocab_size").and_then(|v| v.as_u64()).map(|v| v as usize),
+        embedding: embedding_rows(tensors),
+        tokenizer: tokenizer_dir.map(htf::tokenizer_id_count).transpose()?.flatten(),
+    })
+}
+
+/// intermediate_size según los pesos: filas de mlp.gate_up fusionado ÷ 2
+/// o filas de mlp.up (los expertos MoE no cuentan)
+pub fn mlp_intermediate_rows(tensors: &[TensorManifest]) -> Option<usize> {
+    let rows = |suffix: &str| tensors.iter()
+        .find(|t| t.name.ends_with(suffix) && t.alias_of.is_none())
+        .and_then(|t| t.shape.first().copied());
+    rows(".mlp.gate_up.weight").map(|r| r / 2).or_else(|| rows(".mlp.up.weight"))
+}
+
+/// Escribe execution_hints combinados de múltiples mappers
+/// v9.3.3: también la versión binaria en 0xB (HNF 9.1+)
+/// v9.3.0: intermediate_size desde mlp.gate_up/mlp.up
+/// v9.1.6: HintOverrides (--max-position)
+/// v9.1.5: vocab_size se parchea en cualquier bloque con *token_embedding.weight
+/// v9.0.5: TEXT también va bajo "text" con "text_enabled" para consistencia
+/// v9.0.3: Parchea vocab_size desde el tensor real token_embedding.weight
+pub fn write_combined_hints(
+    writer: &mut HnfWriter,
+    mappers: &[(&dyn ModelMapper, BlockType)],
+    overrides: &HintOverrides,
+) -> Result<()> {
+    let combined = combined_hints(mappers, writer.tensor_manifests(), overrides);
+    writer.write_execution_hints(&combined)?;
+    // Versión binaria (0xB): el engine la prefiere al JSON si existe
+    if writer.supports_exec_hints_bin() {
+        writer.write_execution_hints_binary(&combined)?;
+    }
+    Ok(())
+}
+
+/// execution_hints combinados, parcheados con los tensores de cada bloque
+/// (`manifests` indexado por bloque: los del writer o los de un HNF ya escrito)
+pub fn combined_hints(
+    mappers: &[(&dyn ModelMapper, BlockType)],
+    manifests: &[Vec<TensorManifest>],
+    overrides: &HintOverrides,
+) -> serde_json::Value {
+    let mut combined = serde_json::Map::new();
+    
+    for (mapper, block) in mappers {
+        let mut hints = mapper.execution_hints();
+        
+        // ═══════════════════════════════════════════════════════════════════
+        // PARCHEAR vocab_size DESDE TENSOR REAL
+        // ═══════════════════════════════════════════════════════════════════
+        if let Some(obj) = hints.as_object_mut() {
+            let block_idx = block.as_usize();
+            
+            // Buscar token_embedding.weight en este bloque (cualquier prefijo)
+            if let Some(tensors) = manifests.get(block_idx) {
+                if let Some(vocab) = embedding_rows(tensors) {
+                    let old_vocab = obj.get("vocab_size")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(0);
+                    
+                    if vocab != old_vocab as usize {
+                        eprintln!(
+                            "[INFO] Patching vocab_size: {} -> {} (from tensor shape in {})",
+                            old_vocab, vocab, block.name()
+                        );
+                    }
+                    
+                    obj.insert(
+                        "vocab_size".to_string(), 
+                        serde_json::json!(vocab)
+                    );
+                }
+                
+                // ═══════════════════════════════════════════════════════════
+                // CORREGIR intermediate_size SEGÚN LOS PESOS DEL MLP
+                // ═══════════════════════════════════════════════════════════
+                if let Some(intermediate) = mlp_intermediate_rows(tensors) {
+                    if let Some(old) = obj.get("intermediate_size").and_then(|v| v.as_u64()) {
+                        if old as usize != intermediate {
+                            eprintln!(
+                                "[WARN] Patching intermediate_size: {} -> {} (config.json disagrees with MLP weights in {})",
+                                old, intermediate, block.name()
+                            );
+                        }
+                    }
+                    obj.insert("intermediate_size".to_string(), serde_json::json!(intermediate));
+                }
+                
+                // ═══════════════════════════════════════════════════════════
+                // CORREGIR norm_type SEGÚN TENSORES ESCRITOS
+                // ═══════════════════════════════════════════════════════════
+                if let Some((norm_type, norm_bias)) = detect_norm_type(tensors.iter().map(|t| t.name.as_str())) {
+                    let old_type = obj.get("norm_type").and_then(|v| v.as_str()).unwrap_or("");
+                    if old_type != norm_type {
+                        eprintln!(
+                            "[INFO] Patching norm_type: {} -> {} (from norm tensors in {})",
+                            old_type, norm_type, block.name()
+                        );
+                    }
+                    obj.insert("norm_type".to_string(), serde_json::json!(norm_type));
+                    obj.insert("norm_bias".to_string(), serde_json::json!(norm_bias));
+                }
+                
+                // attention_bias: lo que digan los q/k/v escritos, no config.json
+                if let (Some(bias), Some(old)) = (
+                    detect_attention_bias(tensors.iter().map(|t| t.name.as_str())),
+                    obj.get("attention_bias").and_then(|v| v.as_bool()),
+                ) {
+                    if old != bias {
+                        eprintln!("[INFO] Patching attention_bias: {} -> {} (from q/k/v tensors in {})", old, bias, block.name());
+                        obj.insert("attention_bias".to_string(), serde_json::json!(bias));
+                    }
+                }
+                
+                // ═══════════════════════════════════════════════════════════
+                // PROJECTOR: tipo/profundidad según los linearN escritos
+                // ═══════════════════════════════════════════════════════════
+                if let (Some(depth), Some(projector)) = (
+                    detect_projector_depth(tensors.iter().map(|t| t.name.as_str())),
+                    obj.get_mut("projector").and_then(|p| p.as_object_mut()),
+                ) {
+                    let kind = if depth == 1 { "linear" } else { "mlp" };
+                    projector.insert("type".to_string(), serde_json::json!(kind));
+                    projector.insert("depth".to_string(), serde_json::json!(depth));
+                }
+            }
+        }
+        
+        // K/V pre-repetidos: las filas de k_proj mandan sobre num_key_value_heads
+        let k_rows = manifests.get(block.as_usize())
+            .and_then(|tensors| tensors.iter().find(|t| t.name.ends_with("attn.k_proj.weight")))
+            .and_then(|t| t.shape.first().copied());
+        if let Some(k_rows) = k_rows {
+            if let Some(warning) = apply_stored_kv_heads(&mut hints, k_rows) {
+                eprintln!("[WARN] {} ({})", warning, block.name());
+            }
+        }
+        
+        // Contexto extendido: solo aplica a los bloques con modelo de texto
+        let is_text_like = matches!(block, BlockType::TextModel | BlockType::CodeExec | BlockType::Cortex);
+        if let (Some(max_position), true) = (overrides.max_position, is_text_like) {
+            if let Some(warning) = apply_max_position(&mut hints, max_position) {
+                eprintln!("[WARN] {} ({})", warning, block.name());
+            }
+        }
+        
+        // v9.0.5: Insertar hints - TODAS las modalidades usan el mismo patrón
+        match block {
+            BlockType::TextModel => {
+                // v9.0.5: TEXT también va bajo "text" para consistencia
+                combined.insert("text_enabled".to_string(), serde_json::Value::Bool(true));
+                combined.insert("text".to_string(), hints);
+            }
+            BlockType::Vision => {
+                combined.insert("vision_enabled".to_string(), serde_json::Value::Bool(true));
+                combined.insert("vision".to_string(), hints);
+            }
+            BlockType::Audio => {
+                combined.insert("audio_enabled".to_string(), serde_json::Value::Bool(true));
+                combined.insert("audio".to_string(), hints);
+            }
+            BlockType::Cortex => {
+                combined.insert("cortex_enabled".to_string(), serde_json::Value::Bool(true));
+                combined.insert("cortex".to_string(), hints);
+            }
+            BlockType::CodeExec => {
+                combined.insert("code_enabled".to_string(), serde_json::Value::Bool(true));
+                combined.insert("code".to_string(), hints);
+            }
+            _ => {
+                combined.insert(block.name().to_string(), hints);
+            }
+        }
+    }
+    
+    serde_json::Value::Object(combined)
+}
+
+/// Resultado de --incremental: nombres de bloque reconstruidos y reutilizados
+#[derive(Debug, Default)]
+pub struct IncrementalOutcome {
+    pub rebuilt: Vec<&'static str>,
+    pub reused: Vec<&'static str>,
+}
+
+/// Reconstruye en `hnf` los bloques de metadatos que marca `plan` y registra
+/// los `sources` actuales en el manifest. Los pesos se copian tal cual; un
+/// bloque regenerado idéntico al existente cuenta como reutilizado.
+pub fn rebuild_incremental(
+    hnf: &Path,
+    plan: &RebuildPlan,
+    mappers: &[(&dyn ModelMapper, BlockType)],
+    overrides: &HintOverrides,
+    tok_sources: &[(&Path, DomainType, bool)],
+    htf_options: &htf::HtfOptions,
+    sources: &[SourceFile],
+) -> Result<IncrementalOutcome> {
+    if plan.full {
+        anyhow::bail!("Weights changed: {} needs a full conversion", hnf.display());
+    }
+    
+    let source = HnfReader::open(hnf)?;
+    let mut replacements: Vec<(usize, Vec<u8>)> = Vec::new();
+    
+    if plan.hints {
+        let manifests: Vec<Vec<TensorManifest>> = (0..BLOCK_NAMES.len())
+            .map(|block| source.block_tensors(block))
+            .collect();
+        let hints = combined_hints(mappers, &manifests, overrides);
+        replacements.push((BLOCK_EXEC_HINTS, serde_json::to_vec(&hints)?));
+        // Los binarios solo si el HNF ya los llevaba (si no, la versión no los admite)
+        if !source.block_bytes(BLOCK_EXEC_HINTS_BIN).is_empty() {
+            replacements.push((BLOCK_EXEC_HINTS_BIN, build_execution_hints_binary(&hints)));
+        }
+    }
+    
+    if plan.tokenizer && !tok_sources.is_empty() {
+        let options = htf::HtfOptions {
+            max_vocab: embedding_rows(&source.block_tensors(BLOCK_TEXT_MODEL)),
+            ..htf_options.clone()
+        };
+        replacements.push((BLOCK_TOKENIZER, htf::build_htf_multi_with(tok_sources, &options)?));
+    }
+    
+    // Sin cambios de bytes no hace falta sustituir el bloque
+    replacements.retain(|(block, bytes)| source.block_bytes(*block) != bytes.as_slice());
+    
+    let mut outcome = IncrementalOutcome::default();
+    for entry in source.blocks().iter().filter(|e| e.size > 0) {
+        let block = entry.block_id as usize;
+        if replacements.iter().any(|(b, _)| *b == block) {
+            outcome.rebuilt.push(BLOCK_NAMES[block]);
+        } else {
+            outcome.reused.push(BLOCK_NAMES[block]);
+        }
+    }
+    drop(source);
+    
+    // Escribir al lado y renombrar: input y output son el mismo archivo
+    let mut partial = hnf.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = std::path::PathBuf::from(partial);
+    let sources = serde_json::to_value(sources)?;
+    rewrite_blocks(hnf, &partial, &replacements, |manifest| {
+        manifest["source_files"] = sources;
+    })?;
+    std::fs::rename(&partial, hnf)
+        .with_context(|| format!("Cannot replace {}", hnf.display()))?;
+    
+    Ok(outcome)
+}
+
+/// Reconstruye el HTF desde `tokenizer_dir` y lo sustituye en el bloque 0x9.
+///
+/// Los pesos se copian tal cual. Devuelve los avisos de consistencia
+/// (vocab del tokenizer vs filas del embedding de texto).
+pub fn set_tokenizer(input: &Path, tokenizer_dir: &Path, output: &Path, options: &htf::HtfOptions) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    
+    let htf_bytes = htf::build_htf_multi_with(&[(tokenizer_dir, DomainType::Text, true)], options)?;
+    let htf_info = htf::validate::validate_htf(&htf_bytes);
+    let new_vocab = htf_info.info.domains.first().map(|d| d.vocab_size as usize).unwrap_or(0);
+    if new_vocab == 0 {
+        anyhow::bail!("No tokenizer found in {}", tokenizer_dir.display());
+    }
+    
+    // Comparar con las filas del embedding del modelo de texto
+    let source = HnfReader::open(input)?;
+    let embedding_rows = embedding_rows(&source.block_tensors(BLOCK_TEXT_MODEL));
+    let num_domains = htf_info.info.num_domains;
+    drop(source);
+    
+    match embedding_rows {
+        Some(rows) if new_vocab > rows => warnings.push(format!(
+            "Tokenizer vocab_size {} exceeds text embedding rows {}: ids >= {} have no embedding",
+            new_vocab, rows, rows
+        )),
+        Some(_) => {}
+        None => warnings.push("No text token_embedding found; vocab_size not verified".to_string()),
+    }
+    for w in &warnings {
+        eprintln!("[WARN] {}", w);
+    }
+    
+    rewrite_blocks(input, output, &[(BLOCK_TOKENIZER, htf_bytes)], |manifest| {
+        if let Some(obj) = manifest.as_object_mut() {
+            obj.insert("tokenizer".to_string(), serde_json::json!({
+                "multi_domain": true,
+                "domains": num_domains,
+                "vocab_size": new_vocab,
+            }));
+        }
+    })?;
+    
+    Ok(warnings)
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::safetensor::{write_test_safetensors, write_test_safetensors_aliased};
+    
+    /// Crea un modelo llama mínimo en un directorio temporal
+    fn make_llama_fixture(dir: &Path, tensors: &[(&str, Vec<usize>, Vec<f32>)]) {
+        make_llama_fixture_with(dir, tensors, |_| {});
+    }
+    
+    /// Igual que make_llama_fixture pero permite retocar config.json
+    fn make_llama_fixture_with(
+        dir: &Path,
+        tensors: &[(&str, Vec<usize>, Vec<f32>)],
+        patch: impl FnOnce(&mut serde_json::Value),
+    ) {
+        let mut config = serde_json::json!({
+            "model_type": "llama",
+            "num_hidden_layers": 1,
+            "hidden_size": 16,
+            "intermediate_size": 32,
+            "num_attention_heads": 2,
+            "num_key_value_heads": 2,
+            "vocab_size": 8,
+        });
+        patch(&mut config);
+        std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+        write_test_safetensors(&dir.join("model.safetensors"), tensors).unwrap();
+    }
+    
+    fn fast_options() -> BuildOptions {
+        BuildOptions { use_mse: false, ..Default::default() }
+    }
+    
+    #[test]
+    fn test_hash_sources_match_file_contents() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.norm.weight", vec![16], vec![1.0; 16]),
+        ]);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let options = BuildOptions { hash_sources: true, ..fast_options() };
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        
+        let bytes = std::fs::read(model_dir.path().join("model.safetensors")).unwrap();
+        assert_eq!(stats.sources.len(), 1);
+        assert_eq!(stats.sources[0].file, "model.safetensors");
+        assert_eq!(stats.sources[0].size, bytes.len() as u64);
+        assert_eq!(stats.sources[0].xxh3, format!("{:016x}", xxhash_rust::xxh3::xxh3_64(&bytes)));
+        
+        // Sin el flag no se hashea nada
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &fast_options()).unwrap();
+        assert!(stats.sources.is_empty());
+    }
+    
+    #[test]
+    fn test_quant_min_bytes_keeps_small_tensors_fp16() {
+        let model_dir = tempfile::tempdir().unwrap();
+        // F32: 128000 elems = 500KB, 1310720 elems = 5MB
+        make_llama_fixture(model_dir.path(), &[
+            ("model.layers.0.self_attn.q_proj.weight", vec![250, 512], vec![0.25; 128_000]),
+            ("model.layers.0.self_attn.k_proj.weight", vec![2560, 512], vec![0.25; 1_310_720]),
+        ]);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let options = BuildOptions { quant_min_bytes: 1024 * 1024, ..fast_options() };
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        
+        assert_eq!(stats.below_min_bytes_count, 1);
+        let tensors = &writer.tensor_manifests()[BlockType::TextModel.as_usize()];
+        let q = tensors.iter().find(|t| t.name.ends_with("q_proj.weight")).unwrap();
+        assert_eq!(q.dtype, "fp16");
+        let k = tensors.iter().find(|t| t.name.ends_with("k_proj.weight")).unwrap();
+        assert_eq!(k.dtype, "hq5k");
+    }
+    
+    #[test]
+    fn test_canonical_report_matches_stats() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.layers.0.self_attn.q_proj.weight", vec![16, 16], vec![0.5; 256]),
+            ("model.layers.0.input_layernorm.weight", vec![16], vec![1.0; 16]),
+            ("model.layers.0.self_attn.rotary_emb.inv_freq", vec![8], vec![1.0; 8]),
+            ("model.unknown_extra.weight", vec![4], vec![1.0; 4]),
+        ]);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let options = BuildOptions { canonical_report: true, ..fast_options() };
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        
+        assert_eq!(stats.mapping.len(), stats.total_tensors());
+        let written = &writer.tensor_manifests()[BlockType::TextModel.as_usize()];
+        assert_eq!(stats.mapping.len(), written.len());
+        let fp16 = stats.mapping.iter().filter(|r| r.quant == "FP16").count();
+        assert_eq!(fp16, stats.fp16_count);
+        
+        let q = stats.mapping.iter()
+            .find(|r| r.source == "model.layers.0.self_attn.q_proj.weight")
+            .unwrap();
+        assert_eq!(q.canonical, "text.layer0.attn.q_proj.weight");
+        assert_eq!(q.shape, vec![16, 16]);
+        
+        let report_dir = tempfile::tempdir().unwrap();
+        let csv_path = report_dir.path().join("report.csv");
+        write_canonical_report(&csv_path, &stats.mapping).unwrap();
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        assert_eq!(csv.lines().count(), 1 + stats.total_tensors());
+        assert!(csv.contains("model.layers.0.self_attn.q_proj.weight,text.layer0.attn.q_proj.weight,"));
+        
+        let json_path = report_dir.path().join("report.json");
+        write_canonical_report(&json_path, &stats.mapping).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), stats.total_tensors());
+    }
+    
+    #[test]
+    fn test_quant_report_sums_match_manifest() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.embed_tokens.weight", vec![16, 16], vec![0.25; 256]),
+            ("model.layers.0.self_attn.q_proj.weight", vec![16, 16], vec![0.5; 256]),
+            ("model.layers.0.mlp.up_proj.weight", vec![16, 16], vec![0.5; 256]),
+            ("model.layers.1.input_layernorm.weight", vec![16], vec![1.0; 16]),
+            ("model.unknown_extra.weight", vec![4], vec![1.0; 4]),
+        ]);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let options = BuildOptions { canonical_report: true, ..fast_options() };
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        let report = quant_report(&stats.mapping);
+        
+        let written = &writer.tensor_manifests()[BlockType::TextModel.as_usize()];
+        let manifest_bytes: u64 = written.iter()
+            .filter(|t| t.alias_of.is_none())
+            .map(|t| t.size)
+            .sum();
+        let category_bytes: u64 = report.categories.values().map(|b| b.stored).sum();
+        let layer_bytes: u64 = report.layers.values().map(|b| b.stored).sum();
+        assert_eq!(category_bytes, manifest_bytes);
+        assert_eq!(layer_bytes, manifest_bytes);
+        assert_eq!(report.total.stored, manifest_bytes);
+        
+        assert!(report.layers.contains_key(&Some(0)));
+        assert!(report.layers.contains_key(&Some(1)));
+        assert!(report.categories["attention"].ratio() > 1.0);
+        assert_eq!(report.categories["norm"].ratio(), 1.0);
+    }
+    
+    #[test]
+    fn test_pre_repeated_kv_heads_patch_hints() {
+        // Config GQA (2 heads, 1 kv head, head_dim 8) pero k/v guardados con 2 heads
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture_with(model_dir.path(), &[
+            ("model.layers.0.self_attn.q_proj.weight", vec![16, 16], vec![0.5; 256]),
+            ("model.layers.0.self_attn.k_proj.weight", vec![16, 16], vec![0.5; 256]),
+            ("model.layers.0.self_attn.v_proj.weight", vec![16, 16], vec![0.5; 256]),
+        ], |config| config["num_key_value_heads"] = serde_json::json!(1));
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        process_model(model_dir.path(), BlockType::TextModel, &mut writer, &fast_options()).unwrap();
+        let mapper = create_mapper(model_dir.path()).unwrap();
+        assert_eq!(mapper.execution_hints()["num_key_value_heads"], 1);
+        write_combined_hints(&mut writer, &[(mapper.as_ref(), BlockType::TextModel)], &HintOverrides::default()).unwrap();
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        let source = HnfReader::open(out.path()).unwrap();
+        let hints: serde_json::Value = serde_json::from_slice(source.block_bytes(crate::hnf::BLOCK_EXEC_HINTS)).unwrap();
+        assert_eq!(hints["text"]["num_key_value_heads"], 2);
+        assert_eq!(hints["text"]["attention_type"], "mha");
+        
+        // Forma que no encaja con ninguno: aviso, sin tocar los hints
+        let mut hints = serde_json::json!({"num_attention_heads": 4, "num_key_value_heads": 2, "head_dim": 8});
+        assert!(crate::hints::apply_stored_kv_heads(&mut hints, 16).is_none());
+        assert!(crate::hints::apply_stored_kv_heads(&mut hints, 24).unwrap().contains("24 rows"));
+        assert_eq!(hints["num_key_value_heads"], 2);
+    }
+    
+    #[test]
+    fn test_max_position_reaches_written_hints() {
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let text = crate::mapping::llama::LlamaMapper::from_json(&serde_json::json!({
+            "num_hidden_layers": 32,
+            "num_attention_heads": 32,
+            "num_key_value_heads": 8,
+            "hidden_size": 4096,
+            "max_position_embeddings": 8192,
+            "rope_scaling": {"type": "linear", "factor": 4.0},
+        }));
+        let vision = crate::mapping::llama::LlamaMapper::from_json(&serde_json::json!({"max_position_embeddings": 576}));
+        let overrides = HintOverrides { max_position: Some(32768) };
+        write_combined_hints(&mut writer, &[(&text, BlockType::TextModel), (&vision, BlockType::Vision)], &overrides).unwrap();
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        let source = HnfReader::open(out.path()).unwrap();
+        let hints = source.execution_hints_json().unwrap().unwrap();
+        assert_eq!(hints["text"]["max_position_embeddings"], 32768);
+        // 2 × 32 capas × 8 kv × 128 × 2 bytes × 1024 tokens = 128 MB
+        assert_eq!(hints["text"]["kv_cache_mb_per_1k_tokens"], 128);
+        assert_eq!(hints["text"]["kv_cache_mb_max"], 4096);
+        // Solo los bloques de texto
+        assert_eq!(hints["vision"]["max_position_embeddings"], 576);
+        
+        let binary = source.block_bytes(crate::hnf::BLOCK_EXEC_HINTS_BIN);
+        let text_offset = crate::hints::binary::ExecutionHintsBin::SIZE;
+        assert_eq!(u32::from_le_bytes(binary[text_offset + 40..text_offset + 44].try_into().unwrap()), 32768);
+        
+        // Sin rope_scaling: se aplica pero avisa
+        let mut plain = serde_json::json!({"max_position_embeddings": 4096});
+        assert!(apply_max_position(&mut plain, 16384).is_some());
+        assert_eq!(plain["max_position_embeddings"], 16384);
+    }
+    
+    #[test]
+    fn test_vocab_size_patched_for_any_prefix() {
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        // Bloque sin lista de embeddings conocida y prefijo no estándar
+        let block = BlockType::Audio;
+        writer.write_tensor(block.as_usize(), "speech.token_embedding.weight", "fp16", &[12, 8], &[0u8; 192]).unwrap();
+        writer.write_tensor(block.as_usize(), "speech.layer0.attn.q_proj.weight", "fp16", &[8, 8], &[0u8; 128]).unwrap();
+        writer.finalize_block(block.as_usize()).unwrap();
+        
+        let mapper = crate::mapping::llama::LlamaMapper::from_json(&serde_json::json!({"vocab_size": 8}));
+        write_combined_hints(&mut writer, &[(&mapper, block)], &HintOverrides::default()).unwrap();
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        let source = HnfReader::open(out.path()).unwrap();
+        let hints: serde_json::Value = serde_json::from_slice(source.block_bytes(crate::hnf::BLOCK_EXEC_HINTS)).unwrap();
+        assert_eq!(hints["audio"]["vocab_size"], 12);
+    }
+    
+    #[test]
+    fn test_aliased_tensors_stored_once() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[]);
+        // k_proj apunta a los mismos data_offsets que q_proj
+        write_test_safetensors_aliased(
+            &model_dir.path().join("model.safetensors"),
+            &[("model.layers.0.self_attn.q_proj.weight", vec![16, 16], (0..256).map(|i| i as f32 / 256.0).collect())],
+            &[("model.layers.0.self_attn.k_proj.weight", "model.layers.0.self_attn.q_proj.weight")],
+        ).unwrap();
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &fast_options()).unwrap();
+        
+        assert_eq!(stats.total_tensors(), 1);
+        assert_eq!(stats.aliased_count, 1);
+        
+        let tensors = &writer.tensor_manifests()[BlockType::TextModel.as_usize()];
+        assert_eq!(tensors.len(), 2);
+        let stored = tensors.iter().find(|t| t.alias_of.is_none()).unwrap();
+        let alias = tensors.iter().find(|t| t.alias_of.is_some()).unwrap();
+        assert_eq!(alias.alias_of.as_deref(), Some(stored.name.as_str()));
+        assert_eq!((alias.offset, alias.size), (stored.offset, stored.size));
+        assert_eq!(stats.total_bytes as u64, stored.size);
+        
+        writer.finalize(serde_json::json!({})).unwrap();
+        let source = HnfReader::open(out.path()).unwrap();
+        let entries = source.manifest["tensors"].as_array().unwrap();
+        assert_eq!(entries.iter().filter(|t| t.get("alias_of").is_some()).count(), 1);
+    }
+    
+    #[test]
+    fn test_tied_lm_head_alias_in_manifest() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture_with(model_dir.path(), &[
+            ("model.embed_tokens.weight", vec![8, 16], (0..128).map(|i| i as f32 / 128.0).collect()),
+        ], |c| c["tie_word_embeddings"] = serde_json::json!(true));
+        
+        // Por defecto no se registra nada
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &fast_options()).unwrap();
+        assert_eq!(stats.aliased_count, 0);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let options = BuildOptions { tied_lm_head: TiedLmHead::Alias, ..fast_options() };
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        assert_eq!(stats.aliased_count, 1);
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        let source = HnfReader::open(out.path()).unwrap();
+        let aliases = source.manifest["aliases"].as_object().unwrap();
+        assert_eq!(aliases.len(), 1);
+        let (lm_head, target) = aliases.iter().next().unwrap();
+        assert!(lm_head.ends_with("lm_head.weight"));
+        
+        // El destino es un tensor real con bytes propios y la misma forma
+        let entries = source.manifest["tensors"].as_array().unwrap();
+        let find = |name: &str| entries.iter().find(|t| t["name"] == name).unwrap();
+        let stored = find(target.as_str().unwrap());
+        assert!(stored.get("alias_of").is_none());
+        assert_eq!(stored["shape"], serde_json::json!([8, 16]));
+        assert_eq!(find(lm_head)["offset"], stored["offset"]);
+        assert_eq!(TiedLmHead::parse("Alias"), Some(TiedLmHead::Alias));
+    }
+    
+    #[test]
+    fn test_cancel_mid_conversion_removes_output() {
+        /// Pide cancelar en cuanto se han escrito `after` tensores
+        struct CancelAfter {
+            after: usize,
+            seen: std::sync::Mutex<Vec<(usize, usize)>>,
+            cancel: AtomicBool,
+        }
+        impl ConversionObserver for CancelAfter {
+            fn on_progress(&self, progress: Progress<'_>) {
+                self.seen.lock().unwrap().push((progress.done, progress.total));
+                if progress.done >= self.after {
+                    self.cancel.store(true, Ordering::Relaxed);
+                }
+            }
+            fn should_cancel(&self) -> bool {
+                self.cancel.should_cancel()
+            }
+        }
+        
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.norm.weight", vec![16], vec![1.0; 16]),
+            ("model.layers.0.input_layernorm.weight", vec![16], vec![1.0; 16]),
+            ("model.layers.0.post_attention_layernorm.weight", vec![16], vec![1.0; 16]),
+            ("model.layers.0.self_attn.q_proj.weight", vec![16, 16], vec![0.5; 256]),
+        ]);
+        let out_dir = tempfile::tempdir().unwrap();
+        let out = out_dir.path().join("model.hnf");
+        let mapper = create_mapper(model_dir.path()).unwrap();
+        
+        let observer = CancelAfter { after: 2, seen: Default::default(), cancel: AtomicBool::new(false) };
+        let mut writer = HnfWriter::create(&out).unwrap();
+        let err = process_model_observed(
+            model_dir.path(), BlockType::TextModel, &mut writer, mapper.as_ref(), &fast_options(), &observer,
+        ).unwrap_err();
+        
+        assert!(matches!(err.downcast_ref::<ConvertError>(), Some(ConvertError::Cancelled)), "{}", err);
+        assert_eq!(*observer.seen.lock().unwrap(), vec![(1, 4), (2, 4)]);
+        assert!(!out.exists());
+        assert!(writer.finalize(serde_json::json!({})).is_err());
+        
+        // Un flag ya levantado cancela antes de escribir nada
+        let mut writer = HnfWriter::create(&out).unwrap();
+        let flag = AtomicBool::new(true);
+        assert!(process_model_observed(
+            model_dir.path(), BlockType::TextModel, &mut writer, mapper.as_ref(), &fast_options(), &flag,
+        ).is_err());
+        assert!(!out.exists());
+    }
+    
+    #[test]
+    fn test_flat_names_drop_text_prefix() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.embed_tokens.weight", vec![12, 16], vec![0.25; 192]),
+            ("model.layers.0.self_attn.q_proj.weight", vec![16, 16], vec![0.5; 256]),
+        ]);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let options = BuildOptions { flat_names: true, ..fast_options() };
+        process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        
+        let names: Vec<&str> = writer.tensor_manifests()[BlockType::TextModel.as_usize()].iter()
+            .map(|t| t.name.as_str())
+            .collect();
+        assert_eq!(names, ["token_embedding.weight", "layer0.attn.q_proj.weight"]);
+        
+        // vocab_size sale del embedding sin prefijo (config.json dice 8)
+        let mapper = create_mapper(model_dir.path()).unwrap();
+        write_combined_hints(&mut writer, &[(mapper.as_ref(), BlockType::TextModel)], &HintOverrides::default()).unwrap();
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        let source = HnfReader::open(out.path()).unwrap();
+        let hints: serde_json::Value = serde_json::from_slice(source.block_bytes(crate::hnf::BLOCK_EXEC_HINTS)).unwrap();
+        assert_eq!(hints["text"]["vocab_size"], 12);
+    }
+    
+    #[test]
+    fn test_transposed_lm_head_is_corrected() {
+        // lm_head [hidden=16, vocab=12]: lm_head[h][v] = v + h / 100
+        let lm_head_t: Vec<f32> = (0..16).flat_map(|h| (0..12).map(move |v| v as f32 + h as f32 / 100.0)).collect();
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.embed_tokens.weight", vec![12, 16], vec![0.25; 192]),
+            ("lm_head.weight", vec![16, 12], lm_head_t),
+        ]);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let options = BuildOptions { default_quant: QuantFormat::FP16, ..fast_options() };
+        process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        
+        let lm_head = writer.tensor_manifests()[BlockType::TextModel.as_usize()].iter()
+            .find(|t| t.name == "text.lm_head.weight")
+            .cloned()
+            .unwrap();
+        assert_eq!(lm_head.shape, [12, 16]);
+        
+        let mapper = create_mapper(model_dir.path()).unwrap();
+        write_combined_hints(&mut writer, &[(mapper.as_ref(), BlockType::TextModel)], &HintOverrides::default()).unwrap();
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        // Fila v de la salida = columna v de la fuente
+        let data = std::fs::read(out.path()).unwrap();
+        let bytes = &data[lm_head.offset as usize..(lm_head.offset + lm_head.size) as usize];
+        let values: Vec<f32> = bytes.chunks_exact(2)
+            .map(|b| half::f16::from_le_bytes([b[0], b[1]]).to_f32())
+            .collect();
+        assert_eq!(values[0..2], [0.0, half::f16::from_f32(0.01).to_f32()]);
+        assert_eq!(values[16], 1.0);
+        assert_eq!(values[11 * 16], 11.0);
+        
+        let hints = HnfReader::open(out.path()).unwrap().execution_hints_json().unwrap().unwrap();
+        assert_eq!(hints["text"]["vocab_size"], 12);
+    }
+    
+    #[test]
+    fn test_lora_merged_into_base_weight() {
+        // W = 0.25, A [2, 16], B [16, 2]; alpha 4, r 2 → escala 2
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.layers.0.self_attn.q_proj.weight", vec![16, 16], vec![0.25; 256]),
+            ("model.layers.0.self_attn.k_proj.weight", vec![16, 16], vec![0.25; 256]),
+        ]);
+        let a: Vec<f32> = (0..32).map(|i| (i % 5) as f32 * 0.01).collect();
+        let b: Vec<f32> = (0..32).map(|i| (i % 3) as f32 * 0.1).collect();
+        let adapter_dir = tempfile::tempdir().unwrap();
+        write_test_safetensors(&adapter_dir.path().join("adapter_model.safetensors"), &[
+            ("base_model.model.model.layers.0.self_attn.q_proj.lora_A.weight", vec![2, 16], a.clone()),
+            ("base_model.model.model.layers.0.self_attn.q_proj.lora_B.weight", vec![16, 2], b.clone()),
+            ("base_model.model.model.layers.9.self_attn.q_proj.lora_A.weight", vec![2, 16], a.clone()),
+            ("base_model.model.model.layers.9.self_attn.q_proj.lora_B.weight", vec![16, 2], b.clone()),
+        ]).unwrap();
+        std::fs::write(adapter_dir.path().join("adapter_config.json"), r#"{"r": 2, "lora_alpha": 4}"#).unwrap();
+        let lora = LoraAdapter::open(adapter_dir.path(), None).unwrap();
+        assert_eq!(lora.alpha, 4.0);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        // --quant-min-bytes al máximo: todo en FP16 para comparar valores
+        let options = BuildOptions { quant_min_bytes: usize::MAX, lora: Some(Arc::new(lora)), ..fast_options() };
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        // El par de layers.9 no tiene peso base: aviso, no error
+        assert_eq!(stats.lora_merged, 1);
+        
+        let tensors = writer.tensor_manifests()[BlockType::TextModel.as_usize()].clone();
+        writer.finalize(serde_json::json!({})).unwrap();
+        let data = std::fs::read(out.path()).unwrap();
+        let values = |suffix: &str| -> Vec<f32> {
+            let t = tensors.iter().find(|t| t.name.ends_with(suffix)).unwrap();
+            data[t.offset as usize..(t.offset + t.size) as usize].chunks_exact(2)
+                .map(|b| half::f16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect()
+        };
+        
+        let q = values("q_proj.weight");
+        for row in 0..16 {
+            for col in 0..16 {
+                let delta: f32 = (0..2).map(|k| b[row * 2 + k] * a[k * 16 + col]).sum();
+                let expected = 0.25 + 2.0 * delta;
+                assert!((q[row * 16 + col] - expected).abs() < 1e-3, "[{}, {}] {} vs {}", row, col, q[row * 16 + col], expected);
+            }
+        }
+        assert!(q.iter().any(|&v| v > 0.26));
+        // Sin par: intacto
+        assert!(values("k_proj.weight").iter().all(|&v| v == 0.25));
+    }
+    
+    #[test]
+    fn test_intermediate_size_patched_from_mlp_weights() {
+        // config.json dice 32; up_proj/gate_proj tienen 24 filas
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.layers.0.mlp.gate_proj.weight", vec![24, 16], vec![0.5; 384]),
+            ("model.layers.0.mlp.up_proj.weight", vec![24, 16], vec![0.5; 384]),
+        ]);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        process_model(model_dir.path(), BlockType::TextModel, &mut writer, &fast_options()).unwrap();
+        let mapper = create_mapper(model_dir.path()).unwrap();
+        write_combined_hints(&mut writer, &[(mapper.as_ref(), BlockType::TextModel)], &HintOverrides::default()).unwrap();
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        let hints = HnfReader::open(out.path()).unwrap().execution_hints_json().unwrap().unwrap();
+        assert_eq!(hints["text"]["intermediate_size"], 24);
+        
+        // gate_up fusionado: la mitad de sus filas
+        let fused = |rows: usize| TensorManifest {
+            name: "text.layer0.mlp.gate_up.weight".to_string(),
+            dtype: "hq4k".to_string(),
+            shape: vec![rows, 16],
+            offset: 0,
+            size: 0,
+            numel: rows * 16,
+            alias_of: None,
+            non_canonical: false,
+        };
+        assert_eq!(mlp_intermediate_rows(&[fused(64)]), Some(32));
+        assert_eq!(mlp_intermediate_rows(&[]), None);
+    }
+    
+    #[test]
+    fn test_memory_batches_isolate_giant_tensor() {
+        // Tope de 1 KiB: los pequeños (256 B) se agrupan, el gigante va solo
+        let costs = [256, 256, 256, 64 * 1024, 256, 256];
+        let batches = memory_batches(&costs, 1024);
+        assert_eq!(batches, vec![0..3, 3..4, 4..6]);
+        assert!(memory_batches(&[], 1024).is_empty());
+        
+        // El camino paralelo escribe los mismos tensores que el secuencial
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.layers.0.self_attn.q_proj.weight", vec![16, 16], vec![0.5; 256]),
+            ("model.layers.0.self_attn.k_proj.weight", vec![16, 16], vec![-0.25; 256]),
+            ("model.norm.weight", vec![16], vec![1.0; 16]),
+        ]);
+        let build = |max_memory| {
+            let out = tempfile::NamedTempFile::new().unwrap();
+            let mut writer = HnfWriter::create(out.path()).unwrap();
+            let options = BuildOptions { max_memory, ..fast_options() };
+            process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+            writer.tensor_manifests()[BlockType::TextModel.as_usize()].iter()
+                .map(|t| (t.name.clone(), (t.dtype.clone(), t.size)))
+                .collect::<HashMap<_, _>>()
+        };
+        assert_eq!(build(Some(2048)), build(None));
+    }
+    
+    #[test]
+    fn test_anneal_quant_edges_get_higher_precision() {
+        let model_dir = tempfile::tempdir().unwrap();
+        let weights = vec![0.1f32; 256];
+        make_llama_fixture_with(model_dir.path(), &[
+            ("model.layers.0.mlp.down_proj.weight", vec![16, 16], weights.clone()),
+            ("model.layers.1.mlp.down_proj.weight", vec![16, 16], weights.clone()),
+            ("model.layers.2.self_attn.q_proj.weight", vec![16, 16], weights.clone()),
+            ("model.layers.3.mlp.down_proj.weight", vec![16, 16], weights),
+            ("model.layers.3.input_layernorm.weight", vec![16], vec![1.0; 16]),
+        ], |config| config["num_hidden_layers"] = 4.into());
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let options = BuildOptions { anneal_layers: Some(1), ..fast_options() };
+        process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        
+        let dtypes: HashMap<String, String> = writer.tensor_manifests()[BlockType::TextModel.as_usize()].iter()
+            .map(|t| (t.name.clone(), t.dtype.clone()))
+            .collect();
+        // MLP sugiere HQ4K y atención HQ5K; con anneal solo importa la capa
+        assert_eq!(dtypes["text.layer0.mlp.down.weight"], "hq5k");
+        assert_eq!(dtypes["text.layer1.mlp.down.weight"], "hq4k");
+        assert_eq!(dtypes["text.layer2.attn.q_proj.weight"], "hq4k");
+        assert_eq!(dtypes["text.layer3.mlp.down.weight"], "hq5k");
+        assert_eq!(dtypes["text.layer3.ln_attn_in.weight"], "fp16");
+    }
+    
+    /// Escribe un tokenizer.json BPE mínimo con `n` tokens
+    fn make_tokenizer_dir(dir: &Path, n: usize) {
+        let vocab: serde_json::Map<String, serde_json::Value> = (0..n)
+            .map(|i| (format!("t{}", i), serde_json::json!(i)))
+            .collect();
+        let tokenizer = serde_json::json!({"model": {"type": "BPE", "vocab": vocab, "merges": []}});
+        std::fs::write(dir.join("tokenizer.json"), tokenizer.to_string()).unwrap();
+    }
+    
+    #[test]
+    fn test_map_and_quantize_qwen2_q_proj() {
+        let mapper = crate::mapping::qwen2::Qwen2Mapper::from_json(&serde_json::json!({
+            "model_type": "qwen2",
+            "num_hidden_layers": 1,
+            "hidden_size": 32,
+            "num_attention_heads": 4,
+            "num_key_value_heads": 2,
+        }));
+        let data: Vec<f32> = (0..32 * 32).map(|i| (i % 17) as f32 * 0.01 - 0.08).collect();
+        
+        let options = BuildOptions { default_quant: QuantFormat::HQ4K, use_mse: false, ..Default::default() };
+        let (name, quant, bytes) = map_and_quantize(
+            &mapper, "model.layers.0.self_attn.q_proj.weight", &data, &[32, 32], &options,
+        ).unwrap().unwrap();
+        assert_eq!(name, "layer0.attn.q_proj.weight");
+        assert_eq!(quant, QuantFormat::HQ5K);
+        assert_eq!(bytes.len(), QuantFormat::HQ5K.size_for(32 * 32));
+        
+        // Mismo grid que process_model: --super-block cambia el tamaño
+        let options = BuildOptions { super_block: 512, ..options };
+        let (_, _, bytes) = map_and_quantize(
+            &mapper, "model.layers.0.self_attn.q_proj.weight", &data, &[32, 32], &options,
+        ).unwrap().unwrap();
+        assert_eq!(bytes.len(), QuantFormat::HQ5K.size_for_super_block(32 * 32, 512));
+        
+        // Sin mapeo: None; datos que no cuadran con la shape: error
+        assert!(map_and_quantize(&mapper, "model.unknown.weight", &data, &[32, 32], &options).unwrap().is_none());
+        assert!(map_and_quantize(&mapper, "model.layers.0.self_attn.q_proj.weight", &data, &[32, 16], &options).is_err());
+    }
+    
+    #[test]
+    fn test_vocab_sources_tokenizer_beyond_embedding() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.embed_tokens.weight", vec![8, 16], vec![0.25; 128]),
+        ]);
+        make_tokenizer_dir(model_dir.path(), 10);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        process_model(model_dir.path(), BlockType::TextModel, &mut writer, &fast_options()).unwrap();
+        let mapper = create_mapper(model_dir.path()).unwrap();
+        let tensors = &writer.tensor_manifests()[BlockType::TextModel.as_usize()];
+        
+        let sources = vocab_sources(mapper.as_ref(), tensors, Some(model_dir.path())).unwrap();
+        assert_eq!(sources, VocabSources { config: Some(8), embedding: Some(8), tokenizer: Some(10) });
+        let warning = sources.disagreement().unwrap();
+        assert!(warning.contains("config.json 8, embedding rows 8, tokenizer 10"), "{}", warning);
+        
+        // Tokenizer menor que el embedding (filas de relleno): sin aviso
+        make_tokenizer_dir(model_dir.path(), 6);
+        let sources = vocab_sources(mapper.as_ref(), tensors, Some(model_dir.path())).unwrap();
+        assert_eq!(sources.tokenizer, Some(6));
+        assert_eq!(sources.disagreement(), None);
+    }
+    
+    #[test]
+    fn test_set_tokenizer_larger_vocab_warns() {
+        let dir = tempfile::tempdir().unwrap();
+        let old_tok = dir.path().join("old_tok");
+        let new_tok = dir.path().join("new_tok");
+        std::fs::create_dir_all(&old_tok).unwrap();
+        std::fs::create_dir_all(&new_tok).unwrap();
+        make_tokenizer_dir(&old_tok, 4);
+        make_tokenizer_dir(&new_tok, 6);
+        
+        // HNF con embedding de 4 filas + tokenizer de 4 tokens
+        let input = dir.path().join("model.hnf");
+        let embedding: Vec<u8> = (0..64u8).collect();
+        let mut writer = HnfWriter::create(&input).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[4, 8], &embedding).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_tokenizer(&htf::build_htf(&old_tok).unwrap()).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        let output = dir.path().join("out.hnf");
+        let options = htf::HtfOptions {
+            special_overrides: vec![("eos_token_id".to_string(), 5)],
+            ..Default::default()
+        };
+        let warnings = set_tokenizer(&input, &new_tok, &output, &options).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("exceeds text embedding rows 4"), "{}", warnings[0]);
+        
+        let out = HnfReader::open(&output).unwrap();
+        let htf_result = htf::validate::validate_htf(out.block_bytes(BLOCK_TOKENIZER));
+        assert!(htf_result.valid, "{:?}", htf_result.errors);
+        assert_eq!(htf_result.info.domains[0].vocab_size, 6);
+        assert_eq!(out.manifest["tokenizer"]["vocab_size"], 6);
+        // --set-special también se aplica al tokenizer reemplazado
+        let htf_bytes = out.block_bytes(BLOCK_TOKENIZER);
+        let eos_at = htf_result.info.domains[0].data_offset as usize + 4;
+        assert_eq!(i32::from_le_bytes(htf_bytes[eos_at..eos_at + 4].try_into().unwrap()), 5);
+        
+        // Los pesos se conservan y el offset del manifest sigue apuntando a ellos
+        let t = &out.block_tensors(BLOCK_TEXT_MODEL)[0];
+        assert_eq!(out.block_bytes(BLOCK_TEXT_MODEL), &embedding[..]);
+        assert_eq!(t.offset, out.block_table.entries[BLOCK_TEXT_MODEL].offset);
+    }
+    
+    #[test]
+    fn test_set_tokenizer_prefer_generation_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let tok = dir.path().join("tok");
+        std::fs::create_dir_all(&tok).unwrap();
+        make_tokenizer_dir(&tok, 4);
+        std::fs::write(tok.join("tokenizer_config.json"), r#"{"eos_token_id": 1}"#).unwrap();
+        std::fs::write(tok.join("generation_config.json"), r#"{"eos_token_id": 3}"#).unwrap();
+        
+        let input = dir.path().join("model.hnf");
+        let mut writer = HnfWriter::create(&input).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[4, 8], &[0u8; 64]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        // --prefer-config llega al HTF reemplazado
+        let eos = |prefer_config| {
+            let output = dir.path().join("out.hnf");
+            let options = htf::HtfOptions { prefer_config, ..Default::default() };
+            set_tokenizer(&input, &tok, &output, &options).unwrap();
+            let out = HnfReader::open(&output).unwrap();
+            let htf_bytes = out.block_bytes(BLOCK_TOKENIZER);
+            let at = htf::validate::validate_htf(htf_bytes).info.domains[0].data_offset as usize + 4;
+            i32::from_le_bytes(htf_bytes[at..at + 4].try_into().unwrap())
+        };
+        assert_eq!(eos(htf::ConfigPreference::Tokenizer), 1);
+        assert_eq!(eos(htf::ConfigPreference::Generation), 3);
+    }
+    
+    #[test]
+    fn test_select_vision_from_combined_checkpoint() {
+        use crate::mapping::create_tower_mapper;
+        
+        let model_dir = tempfile::tempdir().unwrap();
+        let config = serde_json::json!({
+            "model_type": "llava",
+            "text_config": {"model_type": "llama", "hidden_size": 16, "num_attention_heads": 2, "num_key_value_heads": 2},
+            "vision_config": {"model_type": "clip_vision_model", "hidden_size": 16, "num_attention_heads": 2},
+        });
+        std::fs::write(model_dir.path().join("config.json"), config.to_string()).unwrap();
+        write_test_safetensors(&model_dir.path().join("model.safetensors"), &[
+            ("language_model.model.norm.weight", vec![16], vec![1.0; 16]),
+            ("language_model.model.layers.0.self_attn.q_proj.weight", vec![16, 16], vec![0.1; 256]),
+            ("vision_tower.vision_model.post_layernorm.weight", vec![16], vec![1.0; 16]),
+            ("vision_tower.vision_model.encoder.layers.0.mlp.fc1.weight", vec![16, 16], vec![0.1; 256]),
+            ("multi_modal_projector.linear_1.weight", vec![16, 16], vec![0.1; 256]),
+        ]).unwrap();
+        
+        let mapper = create_tower_mapper(model_dir.path(), BlockType::Vision).unwrap();
+        assert_eq!(mapper.name(), "clip");
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let stats = process_model_with_mapper(
+            model_dir.path(), BlockType::Vision, &mut writer, mapper.as_ref(), &fast_options(),
+        ).unwrap();
+        
+        // El projector viaja con la torre de visión
+        assert_eq!(stats.total_tensors(), 3);
+        assert_eq!(stats.ignored_count, 2);
+        assert_eq!(stats.skipped_count, 0);
+        
+        let manifests = writer.tensor_manifests();
+        assert!(manifests[BlockType::TextModel.as_usize()].is_empty());
+        let mut names: Vec<&str> = manifests[BlockType::Vision.as_usize()].iter().map(|t| t.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["projector.vision.linear1.weight", "vision.layer0.mlp.fc1.weight", "vision.post_layernorm.weight"]);
+    }
+    
+    #[test]
+    fn test_process_vlm_single_pass() {
+        let model_dir = tempfile::tempdir().unwrap();
+        let config = serde_json::json!({
+            "model_type": "llava",
+            "text_config": {"model_type": "llama", "hidden_size": 16, "num_attention_heads": 2, "num_key_value_heads": 2},
+            "vision_config": {"model_type": "clip_vision_model", "hidden_size": 16, "num_attention_heads": 2},
+        });
+        std::fs::write(model_dir.path().join("config.json"), config.to_string()).unwrap();
+        write_test_safetensors(&model_dir.path().join("model.safetensors"), &[
+            ("language_model.model.norm.weight", vec![16], vec![1.0; 16]),
+            ("language_model.model.layers.0.self_attn.q_proj.weight", vec![16, 16], vec![0.1; 256]),
+            ("vision_tower.vision_model.post_layernorm.weight", vec![16], vec![1.0; 16]),
+            ("vision_tower.vision_model.encoder.layers.0.mlp.fc1.weight", vec![16, 16], vec![0.1; 256]),
+            ("multi_modal_projector.linear_1.weight", vec![16, 16], vec![0.1; 256]),
+        ]).unwrap();
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let options = BuildOptions { hash_sources: true, ..fast_options() };
+        let towers = process_vlm(model_dir.path(), &mut writer, &options).unwrap();
+        
+        let blocks: Vec<BlockType> = towers.iter().map(|t| t.block).collect();
+        assert_eq!(blocks, [BlockType::TextModel, BlockType::Vision]);
+        assert_eq!((towers[0].stats.total_tensors(), towers[1].stats.total_tensors()), (2, 3));
+        assert!(towers.iter().all(|t| t.stats.skipped_count == 0));
+        // Un solo hash de fuentes para las dos torres
+        assert_eq!((towers[0].stats.sources.len(), towers[1].stats.sources.len()), (1, 0));
+        
+        let manifests = writer.tensor_manifests();
+        let names = |block: BlockType| {
+            let mut names: Vec<String> = manifests[block.as_usize()].iter().map(|t| t.name.clone()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(BlockType::TextModel), ["text.final_norm.weight", "text.layer0.attn.q_proj.weight"]);
+        assert_eq!(names(BlockType::Vision), ["projector.vision.linear1.weight", "vision.layer0.mlp.fc1.weight", "vision.post_layernorm.weight"]);
+        
+        // Un checkpoint de una sola torre no es un VLM
+        let text_only = tempfile::tempdir().unwrap();
+        make_llama_fixture(text_only.path(), &[("model.norm.weight", vec![16], vec![1.0; 16])]);
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        assert!(process_vlm(text_only.path(), &mut writer, &options).is_err());
+    }
+    
+    #[test]
+    fn test_projector_depth_from_mapped_tensors() {
+        use crate::mapping::create_tower_mapper;
+        
+        let projector_hints = |linears: &[&str]| {
+            let model_dir = tempfile::tempdir().unwrap();
+            let config = serde_json::json!({
+                "model_type": "llava",
+                "vision_config": {"model_type": "clip_vision_model", "hidden_size": 16, "num_attention_heads": 2},
+            });
+            std::fs::write(model_dir.path().join("config.json"), config.to_string()).unwrap();
+            let mut tensors = vec![("vision_tower.vision_model.post_layernorm.weight", vec![16], vec![1.0; 16])];
+            for name in linears {
+                tensors.push((name, vec![16, 16], vec![0.1; 256]));
+            }
+            write_test_safetensors(&model_dir.path().join("model.safetensors"), &tensors).unwrap();
+            
+            let mapper = create_tower_mapper(model_dir.path(), BlockType::Vision).unwrap();
+            let out = tempfile::NamedTempFile::new().unwrap();
+            let mut writer = HnfWriter::create(out.path()).unwrap();
+            process_model_with_mapper(model_dir.path(), BlockType::Vision, &mut writer, mapper.as_ref(), &fast_options()).unwrap();
+            write_combined_hints(&mut writer, &[(mapper.as_ref(), BlockType::Vision)], &HintOverrides::default()).unwrap();
+            writer.finalize(serde_json::json!({})).unwrap();
+            
+            let source = HnfReader::open(out.path()).unwrap();
+            let hints: serde_json::Value = serde_json::from_slice(source.block_bytes(crate::hnf::BLOCK_EXEC_HINTS)).unwrap();
+            hints["vision"]["projector"].clone()
+        };
+        
+        let single = projector_hints(&["multi_modal_projector.linear_1.weight"]);
+        assert_eq!((single["type"].as_str(), single["depth"].as_u64()), (Some("linear"), Some(1)));
+        
+        let two = projector_hints(&[
+            "multi_modal_projector.linear_1.weight",
+            "multi_modal_projector.linear_2.weight",
+            "multi_modal_projector.linear_2.bias",
+        ]);
+        assert_eq!((two["type"].as_str(), two["depth"].as_u64()), (Some("mlp"), Some(2)));
+    }
+    
+    #[test]
+    fn test_non_dividing_gqa_aborts_conversion() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture_with(model_dir.path(), &[
+            ("model.norm.weight", vec![16], vec![1.0; 16]),
+        ], |c| {
+            c["hidden_size"] = serde_json::json!(14 * 4);
+            c["num_attention_heads"] = serde_json::json!(14);
+            c["num_key_value_heads"] = serde_json::json!(3);
+        });
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let err = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &fast_options())
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("not divisible by num_key_value_heads (3)"), "{:#}", err);
+        assert!(writer.tensor_manifests()[BlockType::TextModel.as_usize()].is_empty());
+    }
+    
+    #[test]
+    fn test_non_divisible_hidden_size_aborts_conversion() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture_with(model_dir.path(), &[
+            ("model.norm.weight", vec![900], vec![1.0; 900]),
+        ], |c| {
+            c["hidden_size"] = serde_json::json!(900);
+            c["num_attention_heads"] = serde_json::json!(14);
+            c["num_key_value_heads"] = serde_json::json!(14);
+        });
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let err = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &fast_options())
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("hidden_size (900) is not divisible by num_attention_heads (14)"), "{:#}", err);
+        assert!(writer.tensor_manifests()[BlockType::TextModel.as_usize()].is_empty());
+        
+        // Con head_dim explícito la división no se usa
+        assert_eq!(crate::hints::resolve_head_dim(900, 14, Some(64)).unwrap(), 64);
+    }
+    
+    #[test]
+    fn test_keep_unmapped_preserves_original_name() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.norm.weight", vec![16], vec![1.0; 16]),
+            ("model.experimental_gate.weight", vec![4, 4], vec![0.5; 16]),
+        ]);
+        
+        // Sin el flag se descarta
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &fast_options()).unwrap();
+        assert_eq!(stats.skipped_count, 1);
+        
+        let options = BuildOptions { keep_unmapped: true, ..fast_options() };
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, &options).unwrap();
+        assert_eq!(stats.skipped_count, 0);
+        assert_eq!(stats.extras_count, 1);
+        
+        let tensors = &writer.tensor_manifests()[BlockType::TextModel.as_usize()];
+        let extra = tensors.iter().find(|t| t.name == "model.experimental_gate.weight").unwrap();
+        assert!(extra.non_canonical);
+        assert_eq!((extra.dtype.as_str(), extra.shape.as_slice()), ("fp16", &[4, 4][..]));
+        assert!(!crate::dictionary::validate_tensor_name(&extra.name));
+        assert!(tensors.iter().filter(|t| t.name != extra.name).all(|t| !t.non_canonical));
+        
+        writer.finalize(serde_json::json!({})).unwrap();
+        let manifest = HnfReader::open(out.path()).unwrap().manifest;
+        let entry = manifest["tensors"].as_array().unwrap().iter()
+            .find(|t| t["name"] == "model.experimental_gate.weight").unwrap();
+        assert_eq!(entry["non_canonical"], true);
+    }
+    
+    #[test]
+    fn test_target_size_tight_budget_shrinks_output() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.layers.0.self_attn.q_proj.weight", vec![64, 64], (0..4096).map(|i| (i % 17) as f32 * 0.1).collect()),
+            ("model.layers.0.self_attn.k_proj.weight", vec![16, 64], vec![0.5; 1024]),
+            ("model.norm.weight", vec![16], vec![1.0; 16]),
+        ]);
+        let mapper = create_mapper(model_dir.path()).unwrap();
+        
+        let build = |options: &BuildOptions| {
+            let out = tempfile::NamedTempFile::new().unwrap();
+            let mut writer = HnfWriter::create(out.path()).unwrap();
+            let stats = process_model(model_dir.path(), BlockType::TextModel, &mut writer, options).unwrap();
+            writer.finalize(serde_json::json!({})).unwrap();
+            (stats, std::fs::metadata(out.path()).unwrap().len())
+        };
+        let (default_stats, default_size) = build(&fast_options());
+        
+        // Presupuesto un byte por debajo del HQ5K completo: basta con bajar q_proj
+        let mut estimates = estimate_model(model_dir.path(), BlockType::TextModel, mapper.as_ref(), &fast_options()).unwrap();
+        let predicted: usize = estimates.iter().map(TensorEstimate::bytes).sum();
+        assert_eq!(predicted, default_stats.total_bytes);
+        let plan = fit_quant_plan(&mut estimates, predicted - 1).unwrap();
+        let planned = |suffix: &str| plan.iter().find(|(name, _)| name.ends_with(suffix)).map(|(_, q)| *q);
+        assert_eq!(planned("q_proj.weight"), Some(QuantFormat::HQ4K));
+        assert_eq!(planned("k_proj.weight"), Some(QuantFormat::HQ5K));
+        assert_eq!(planned("final_norm.weight"), Some(QuantFormat::FP16));
+        
+        let options = BuildOptions { quant_plan: Some(plan), ..fast_options() };
+        let (stats, size) = build(&options);
+        assert_eq!(stats.total_bytes, estimates.iter().map(TensorEstimate::bytes).sum::<usize>());
+        assert_eq!((stats.hq4k_count, stats.hq5k_count), (1, 1));
+        assert!(size < default_size, "{} >= {}", size, default_size);
+        
+        // Ni todo en HQ4K cabe
+        assert!(fit_quant_plan(&mut estimates, 16).is_err());
+        
+        // Con --super-block la estimación sigue al layout real (q_proj rellena a 3 × 1536)
+        let options = BuildOptions { super_block: 1536, ..fast_options() };
+        let estimates = estimate_model(model_dir.path(), BlockType::TextModel, mapper.as_ref(), &options).unwrap();
+        let (stats, _) = build(&options);
+        assert_eq!(estimates.iter().map(TensorEstimate::bytes).sum::<usize>(), stats.total_bytes);
+    }
+    
+    #[test]
+    fn test_fit_quant_plan_steps_hq6k_down() {
+        let estimate = |name: &str, quant| TensorEstimate {
+            final_name: name.to_string(),
+            numel: 4096,
+            quant,
+            alias_of: None,
+            super_block: hqs::SUPER_BLOCK_SIZE,
+        };
+        let size = |quant: QuantFormat| quant.size_for_super_block(4096, hqs::SUPER_BLOCK_SIZE);
+        let mut estimates = vec![estimate("lm_head.weight", QuantFormat::HQ6K), estimate("q.weight", QuantFormat::HQ5K)];
+        
+        // Un escalón basta: HQ6K → HQ5K antes de tocar los HQ5K
+        let plan = fit_quant_plan(&mut estimates, 2 * size(QuantFormat::HQ5K)).unwrap();
+        assert_eq!(plan["lm_head.weight"], QuantFormat::HQ5K);
+        assert_eq!(plan["q.weight"], QuantFormat::HQ5K);
+        
+        // Presupuesto de todo HQ4K: el HQ6K baja dos escalones
+        let mut estimates = vec![estimate("lm_head.weight", QuantFormat::HQ6K), estimate("q.weight", QuantFormat::HQ5K)];
+        let plan = fit_quant_plan(&mut estimates, 2 * size(QuantFormat::HQ4K)).unwrap();
+        assert_eq!(plan["lm_head.weight"], QuantFormat::HQ4K);
+        assert_eq!(plan["q.weight"], QuantFormat::HQ4K);
+    }
+    
+    #[test]
+    fn test_tiny_tensor_downgraded_to_fp16() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.layers.0.self_attn.q_proj.weight", vec![4, 4], vec![0.5; 16]),
+            ("model.layers.0.self_attn.k_proj.weight", vec![16, 16], vec![0.5; 256]),
+        ]);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let stats = process_model(
+            model_dir.path(), BlockType::TextModel, &mut writer, &fast_options(),
+        ).unwrap();
+        
+        assert_eq!(stats.fp16_count, 1);
+        assert_eq!(stats.hq5k_count, 1);
+        assert_eq!(stats.below_super_block_count, 1);
+        
+        let tensors = &writer.tensor_manifests()[BlockType::TextModel.as_usize()];
+        let q = tensors.iter().find(|t| t.name.ends_with("q_proj.weight")).unwrap();
+        assert_eq!(q.dtype, "fp16");
+        assert_eq!(q.size, 16 * 2);
+        let k = tensors.iter().find(|t| t.name.ends_with("k_proj.weight")).unwrap();
+        assert_eq!(k.dtype, "hq5k");
+    }
+    
+    #[test]
+    fn test_skip_ratio_trips_on_poor_coverage() {
+        let model_dir = tempfile::tempdir().unwrap();
+        make_llama_fixture(model_dir.path(), &[
+            ("model.embed_tokens.weight", vec![8, 16], vec![0.1; 128]),
+            ("model.norm.weight", vec![16], vec![1.0; 16]),
+            ("model.layers.0.adapter.down.weight", vec![4, 16], vec![0.2; 64]),
+            ("model.layers.0.adapter.up.weight", vec![16, 4], vec![0.2; 64]),
+            ("model.layers.0.adapter.scale", vec![4], vec![1.0; 4]),
+            ("model.layers.0.self_attn.rotary_emb.inv_freq", vec![4], vec![1.0; 4]),
+        ]);
+        
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = HnfWriter::create(out.path()).unwrap();
+        let stats = process_model(
+            model_dir.path(), BlockType::TextModel, &mut writer, &fast_options(),
+        ).unwrap();
+        
+        assert_eq!(stats.total_tensors(), 2);
+        assert_eq!(stats.skipped_count, 3);
+        assert_eq!(stats.ignored_count, 1);
+        assert!((stats.skip_ratio() - 0.6).abs() < 1e-9);
+        
+        let err = check_skip_ratio(&stats, 0.05).unwrap_err().to_string();
+        assert!(err.contains("60.00%"), "{}", err);
+        assert!(check_skip_ratio(&stats, 0.75).is_ok());
+    }
+    
+    #[test]
+    fn test_chunked_quantization_matches_whole_tensor() {
+        // 10 super-bloques y uno parcial: el último trozo queda incompleto
+        let numel = 10 * hqs::SUPER_BLOCK_SIZE + 100;
+        let data: Vec<f32> = (0..numel).map(|i| ((i * 37 % 101) as f32 - 50.0) / 13.0).collect();
+        let dir = tempfile::tempdir().unwrap();
+        write_test_safetensors(&dir.path().join("model.safetensors"), &[
+            ("model.embed_tokens.weight", vec![numel / 4, 4], data),
+        ]).unwrap();
+        let reader = SafetensorReader::from_folder(dir.path()).unwrap();
+        
+        for quant in [QuantFormat::FP16, QuantFormat::HQ4K, QuantFormat::HQ5K, QuantFormat::HQ6K] {
+            let whole = read_quantized(&reader, "model.embed_tokens.weight", quant, true, GridConfig::default(), DEFAULT_CHUNK_BYTES, None).unwrap();
+            // 3 super-bloques por trozo; 1 byte se redondea a un super-bloque
+            for chunk_bytes in [3 * hqs::SUPER_BLOCK_SIZE * 4, 1] {
+                let chunked = read_quantized(&reader, "model.embed_tokens.weight", quant, true, GridConfig::default(), chunk_bytes, None).unwrap();
+                assert!(chunked == whole, "{:?} chunk_bytes = {}", quant, chunk_bytes);
+            }
+        }
+    }
     
     #[test]
     fn test_resolve_tensor_name_text() {
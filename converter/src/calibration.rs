@@ -0,0 +1,123 @@
+// src/calibration.rs
+// ============================================================================
+// CALIBRATION - Importancia por canal para cuantización tipo AWQ
+// ============================================================================
+//
+// Con --calibration-data tokens.json el grid search de HQS no minimiza el
+// MSE plano sino Σ wⱼ·(x - x̂)², con wⱼ la importancia del canal de entrada j.
+//
+// Aproximación de primer orden: la activación de entrada de cada proyección
+// que lee del residual stream se estima con el embedding de los tokens de
+// calibración (sin propagar por las capas):
+//
+//   wⱼ = mean_t |E[t, j]|  normalizado a media 1
+//
+// Solo se pondera q/k/v, gate/up y lm_head, cuya entrada es hidden_size. o_proj
+// y down leen otras activaciones y se cuantizan como siempre.
+//
+// Formato de tokens.json:
+//   [[1, 2, 3], [4, 5]]                     secuencias de token ids
+//   {"sequences": [[1, 2, 3], [4, 5]]}      idem con envoltorio
+//
+// ============================================================================
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+use crate::safetensor::SafetensorReader;
+
+/// Suelo de importancia: un canal sin activación en la calibración no se
+/// ignora del todo
+const MIN_IMPORTANCE: f32 = 1e-3;
+
+/// Proyecciones (nombre canónico) cuya entrada es el residual stream
+const RESIDUAL_INPUTS: &[&str] = &[
+    ".attn.q_proj.", ".attn.k_proj.", ".attn.v_proj.", ".attn.qkv_proj.",
+    ".mlp.gate.", ".mlp.up.", ".mlp.gate_up.",
+];
+
+/// Secuencias de token ids de calibración
+pub fn load_calibration_tokens(path: &Path) -> Result<Vec<Vec<u32>>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read calibration data {}", path.display()))?;
+    let json: Value = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+    let sequences = json.get("sequences").unwrap_or(&json);
+    
+    let Some(sequences) = sequences.as_array() else {
+        bail!("{}: expected [[token ids]] or {{\"sequences\": [[token ids]]}}", path.display());
+    };
+    let sequences = sequences.iter()
+        .enumerate()
+        .map(|(i, seq)| {
+            seq.as_array()
+                .and_then(|ids| ids.iter().map(|id| id.as_u64().and_then(|id| u32::try_from(id).ok())).collect::<Option<Vec<u32>>>())
+                .with_context(|| format!("{}: sequence {} is not an array of token ids", path.display(), i))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    if sequences.iter().all(|s| s.is_empty()) {
+        bail!("{}: no calibration tokens", path.display());
+    }
+    Ok(sequences)
+}
+
+/// ¿La entrada de este tensor es el residual stream (hidden_size)?
+pub fn reads_residual_stream(final_name: &str) -> bool {
+    final_name.ends_with("lm_head.weight")
+        || (final_name.ends_with(".weight") && RESIDUAL_INPUTS.iter().any(|p| final_name.contains(p)))
+}
+
+/// Importancia por canal a partir de filas del embedding [vocab, hidden]
+/// (`row(t)` = activaciones del token t). Media 1, con suelo MIN_IMPORTANCE.
+pub fn channel_importance(
+    hidden: usize,
+    sequences: &[Vec<u32>],
+    mut row: impl FnMut(u32) -> Result<Vec<f32>>,
+) -> Result<Vec<f32>> {
+    // Cada token distinto se lee una vez y pesa tantas veces como aparece
+    let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for &token in sequences.iter().flatten() {
+        *counts.entry(token).or_default() += 1;
+    }
+    
+    let mut sums = vec![0.0f64; hidden];
+    let mut total = 0usize;
+    for (&token, &count) in &counts {
+        let values = row(token)?;
+        if values.len() != hidden {
+            bail!("Embedding row for token {} has {} values, expected {}", token, values.len(), hidden);
+        }
+        for (sum, v) in sums.iter_mut().zip(&values) {
+            if v.is_finite() {
+                *sum += v.abs() as f64 * count as f64;
+            }
+        }
+        total += count;
+    }
+    
+    let means: Vec<f64> = sums.iter().map(|s| s / total.max(1) as f64).collect();
+    let average = means.iter().sum::<f64>() / hidden.max(1) as f64;
+    if average <= 0.0 {
+        return Ok(vec![1.0; hidden]);
+    }
+    Ok(means.iter().map(|m| ((m / average) as f32).max(MIN_IMPORTANCE)).collect())
+}
+
+/// Importancia desde el embedding del safetensors (`name`, [vocab, hidden])
+pub fn embedding_importance(reader: &SafetensorReader, name: &str, sequences: &[Vec<u32>]) -> Result<Vec<f32>> {
+    let shape = reader.shape(name)
+        .with_context(|| format!("Tensor '{}' not found", name))?;
+    let &[vocab, hidden] = shape else {
+        bail!("Embedding '{}' is not 2D: {:?}", name, shape);
+    };
+    channel_importance(hidden, sequences, |token| {
+        if token as usize >= vocab {
+            bail!("Calibration token {} out of range (vocab {})", token, vocab);
+        }
+        reader.read_range(name, token as usize * hidden, hidden)
+    })
+}
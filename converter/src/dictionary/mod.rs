@@ -5,9 +5,12 @@
 //
 // Cualquier tensor que no esté aquí es INVÁLIDO y no debe escribirse.
 //
+// La validación usa un RegexSet: todos los patrones en una sola pasada por
+// nombre en lugar de O(nombres × patrones).
+//
 // ============================================================================
 
-use regex::Regex;
+use regex::{Regex, RegexSet};
 use std::collections::HashSet;
 use std::sync::LazyLock;
 
@@ -42,7 +45,9 @@ pub const TEXT_MODEL_PATTERNS: &[&str] = &[
     "layer{N}.attn.qkv_proj.weight",  // QKV fusionado (Phi3/4)
     "layer{N}.attn.qkv_proj.bias",
     "layer{N}.attn.q_norm.weight",
+    "layer{N}.attn.q_norm.bias",       // qk-norm LayerNorm (Persimmon)
     "layer{N}.attn.k_norm.weight",
+    "layer{N}.attn.k_norm.bias",
     
     // §2.4 LAYER NORMS (por capa)
     "layer{N}.ln_attn_in.weight",
@@ -111,6 +116,8 @@ pub const VISION_PATTERNS: &[&str] = &[
     "projector.vision.linear1.bias",
     "projector.vision.linear2.weight",
     "projector.vision.linear2.bias",
+    "projector.vision.patch_linear.weight",  // patches → hidden sin encoder (Fuyu)
+    "projector.vision.patch_linear.bias",
 ];
 
 pub const AUDIO_PATTERNS: &[&str] = &[
@@ -184,62 +191,52 @@ fn pattern_to_regex(pattern: &str) -> String {
     format!("^{}$", regex)
 }
 
-static ALL_PATTERNS_REGEX: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+/// Todos los patrones del diccionario ya convertidos a regex anclada
+static ALL_PATTERN_STRINGS: LazyLock<Vec<String>> = LazyLock::new(|| {
     let mut patterns = Vec::new();
     
-    // Text model
-    for p in TEXT_MODEL_PATTERNS {
-        patterns.push(Regex::new(&pattern_to_regex(p)).unwrap());
-    }
-    
-    // Vision
-    for p in VISION_PATTERNS {
-        patterns.push(Regex::new(&pattern_to_regex(p)).unwrap());
-    }
-    
-    // Audio
-    for p in AUDIO_PATTERNS {
-        patterns.push(Regex::new(&pattern_to_regex(p)).unwrap());
-    }
-    
-    // Video
-    for p in VIDEO_PATTERNS {
-        patterns.push(Regex::new(&pattern_to_regex(p)).unwrap());
-    }
-    
-    // Spatial 3D
-    for p in SPATIAL_3D_PATTERNS {
-        patterns.push(Regex::new(&pattern_to_regex(p)).unwrap());
-    }
-    
-    // Expert router
-    for p in EXPERT_ROUTER_PATTERNS {
-        patterns.push(Regex::new(&pattern_to_regex(p)).unwrap());
-    }
-    
-    // Code exec (text model con prefijo "code.")
-    for p in TEXT_MODEL_PATTERNS {
-        let prefixed = format!("code.{}", p);
-        patterns.push(Regex::new(&pattern_to_regex(&prefixed)).unwrap());
+    // Text, vision, audio, video, spatial 3D, expert router
+    for group in [
+        TEXT_MODEL_PATTERNS,
+        VISION_PATTERNS,
+        AUDIO_PATTERNS,
+        VIDEO_PATTERNS,
+        SPATIAL_3D_PATTERNS,
+        EXPERT_ROUTER_PATTERNS,
+    ] {
+        for p in group {
+            patterns.push(pattern_to_regex(p));
+        }
     }
     
-    // Cortex (text model con prefijo "cortex.")
-    for p in TEXT_MODEL_PATTERNS {
-        let prefixed = format!("cortex.{}", p);
-        patterns.push(Regex::new(&pattern_to_regex(&prefixed)).unwrap());
+    // Code exec / cortex (text model con prefijo "code." / "cortex.")
+    for prefix in ["code.", "cortex."] {
+        for p in TEXT_MODEL_PATTERNS {
+            patterns.push(pattern_to_regex(&format!("{}{}", prefix, p)));
+        }
     }
     
     patterns
 });
 
+static ALL_PATTERNS_REGEX: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    ALL_PATTERN_STRINGS.iter().map(|p| Regex::new(p).unwrap()).collect()
+});
+
+/// Todos los patrones en un único autómata: una pasada por nombre
+static ALL_PATTERNS_SET: LazyLock<RegexSet> = LazyLock::new(|| {
+    RegexSet::new(ALL_PATTERN_STRINGS.iter()).unwrap()
+});
+
 /// Valida que un nombre canónico exista en el diccionario HELIOS.
 pub fn validate_tensor_name(name: &str) -> bool {
-    for regex in ALL_PATTERNS_REGEX.iter() {
-        if regex.is_match(name) {
-            return true;
-        }
-    }
-    false
+    ALL_PATTERNS_SET.is_match(name)
+}
+
+/// Igual que `validate_tensor_name` probando regex a regex.
+/// Referencia para tests de equivalencia y benchmarks.
+pub fn validate_tensor_name_linear(name: &str) -> bool {
+    ALL_PATTERNS_REGEX.iter().any(|regex| regex.is_match(name))
 }
 
 /// Validador con caché y reporte de errores
@@ -273,7 +270,7 @@ impl DictionaryValidator {
         } else {
             self.invalid.insert(name.to_string());
             if self.strict {
-                eprintln!("[DICT ERROR] Tensor no válido: {}", name);
+                crate::eoutln!("[DICT ERROR] Tensor no válido: {}", name);
             }
             false
         }
@@ -354,4 +351,22 @@ mod tests {
         assert!(validate_tensor_name("cortex.token_embedding.weight"));
         assert!(validate_tensor_name("cortex.layer0.mlp.down.weight"));
     }
+    
+    #[test]
+    fn test_regex_set_matches_linear() {
+        let names = [
+            "token_embedding.weight",
+            "layer3.attn.k_proj.weight",
+            "vision.layer7.mlp.fc2.bias",
+            "code.final_norm.weight",
+            "cortex.layer0.attn.q_proj.weight",
+            "expert_router.layer2.aux_loss",
+            "layer0.attn.qproj.weight",
+            "text.layer0.attn.q_proj.weight",
+            "",
+        ];
+        for name in names {
+            assert_eq!(validate_tensor_name(name), validate_tensor_name_linear(name), "{}", name);
+        }
+    }
 }
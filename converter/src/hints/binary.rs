@@ -12,6 +12,9 @@
 
 use serde_json::Value;
 
+use crate::hints::vision_num_image_tokens;
+use crate::mapping::normalize_arch;
+
 // ============================================================================
 // CONSTANTS
 // ============================================================================
@@ -36,6 +39,8 @@ pub const ARCH_MIXTRAL: u32 = 11;
 pub const ARCH_DEEPSEEK: u32 = 12;
 pub const ARCH_CLIP: u32 = 13;
 pub const ARCH_SIGLIP: u32 = 14;
+pub const ARCH_PERSIMMON: u32 = 15;
+pub const ARCH_FUYU: u32 = 16;
 
 // DType enum
 pub const DTYPE_FP16: u32 = 0;
@@ -50,6 +55,7 @@ pub const ATTN_MQA: u32 = 2;
 // QKVLayout enum
 pub const QKV_SEPARATE: u32 = 0;
 pub const QKV_FUSED: u32 = 1;
+pub const QKV_FUSED_PER_HEAD: u32 = 2;   // [head][q|k|v][head_dim] (Persimmon)
 
 // MLPType enum
 pub const MLP_SWIGLU: u32 = 0;
@@ -63,6 +69,9 @@ pub const ACT_SILU: u32 = 0;
 pub const ACT_GELU: u32 = 1;
 pub const ACT_GELU_NEW: u32 = 2;
 pub const ACT_RELU: u32 = 3;
+pub const ACT_RELU2: u32 = 4;            // relu(x)² (Persimmon)
+pub const ACT_GELU_FAST: u32 = 5;
+pub const ACT_QUICK_GELU: u32 = 6;        // x·σ(1.702x) (CLIP)
 
 // NormType enum
 pub const NORM_RMSNORM: u32 = 0;
@@ -86,6 +95,7 @@ pub const FLAG_USE_QK_NORM: u32 = 0x0008;
 pub const FLAG_PARALLEL_ATTENTION: u32 = 0x0010;
 pub const FLAG_TIE_WORD_EMBEDDINGS: u32 = 0x0020;
 pub const FLAG_ROPE_PARTIAL: u32 = 0x0040;
+pub const FLAG_SCALE_EMBEDDINGS: u32 = 0x0080;  // embeddings × embedding_scale (Gemma)
 
 // ============================================================================
 // HEADER (64 bytes)
@@ -93,7 +103,7 @@ pub const FLAG_ROPE_PARTIAL: u32 = 0x0040;
 
 /// ExecutionHintsBin header - 64 bytes, alineado a 8
 #[repr(C, align(8))]
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct ExecutionHintsBin {
     // Magic + Version (8 bytes)
     pub magic: u32,                     // 0x48494E54 = "HINT"
@@ -157,6 +167,32 @@ impl ExecutionHintsBin {
         // [44..64] reserved, already zeros
         buf
     }
+    
+    /// Inverso de to_bytes; None si no hay 64 bytes o el magic no es "HINT"
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        let buf: &[u8; Self::SIZE] = buf.get(..Self::SIZE)?.try_into().ok()?;
+        let u16_at = |at: usize| u16::from_le_bytes([buf[at], buf[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes(buf[at..at + 4].try_into().unwrap());
+        
+        let header = Self {
+            magic: u32_at(0),
+            version_major: u16_at(4),
+            version_minor: u16_at(6),
+            text_offset: u32_at(8),
+            vision_offset: u32_at(12),
+            audio_offset: u32_at(16),
+            code_offset: u32_at(20),
+            cortex_offset: u32_at(24),
+            spatial_offset: u32_at(28),
+            num_text_models: u16_at(32),
+            num_vision_models: u16_at(34),
+            num_audio_models: u16_at(36),
+            num_code_models: u16_at(38),
+            flags: u32_at(40),
+            reserved: buf[44..64].try_into().unwrap(),
+        };
+        (header.magic == HINTS_MAGIC).then_some(header)
+    }
 }
 
 // ============================================================================
@@ -165,7 +201,7 @@ impl ExecutionHintsBin {
 
 /// TextModelConfigBin - 128 bytes, alineado a 8
 #[repr(C, align(8))]
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct TextModelConfigBin {
     // Floats primero (24 bytes)
     pub rope_theta: f32,
@@ -173,7 +209,7 @@ pub struct TextModelConfigBin {
     pub partial_rotary_factor: f32,
     pub rms_norm_eps: f32,
     pub layer_norm_eps: f32,
-    pub reserved_float: f32,
+    pub embedding_scale: f32,           // 0 = sin escalar (Gemma: sqrt(hidden_size))
     
     // Dimensions (24 bytes)
     pub num_hidden_layers: u32,
@@ -188,7 +224,7 @@ pub struct TextModelConfigBin {
     pub num_key_value_heads: u32,
     pub head_dim: u32,
     pub attention_type: u32,            // Enum: MHA=0, GQA=1, MQA=2
-    pub qkv_layout: u32,                // Enum: SEPARATE=0, FUSED=1
+    pub qkv_layout: u32,                // Enum: SEPARATE=0, FUSED=1, FUSED_PER_HEAD=2
     
     // Identity + Types (16 bytes)
     pub arch: u32,                      // Enum: LLAMA=1, QWEN2=5, etc.
@@ -209,6 +245,7 @@ pub struct TextModelConfigBin {
     // bit 4: parallel_attention
     // bit 5: tie_word_embeddings
     // bit 6: rope_partial
+    // bit 7: scale_embeddings
     
     // Reserved (28 bytes)
     pub reserved: [u8; 28],
@@ -219,12 +256,12 @@ impl TextModelConfigBin {
     
     pub fn from_json(config: &Value) -> Self {
         let arch_str = config.get("arch").and_then(|v| v.as_str()).unwrap_or("unknown");
-        let arch = match arch_str {
+        let arch = match normalize_arch(arch_str) {
             "llama" => ARCH_LLAMA,
             "llama2" => ARCH_LLAMA2,
-            "llama3" | "llama3.1" | "llama3.2" => ARCH_LLAMA3,
+            "llama3" => ARCH_LLAMA3,
             "qwen" => ARCH_QWEN,
-            "qwen2" | "qwen2.5" => ARCH_QWEN2,
+            "qwen2" => ARCH_QWEN2,
             "phi3" => ARCH_PHI3,
             "phi4" | "phi" => ARCH_PHI4,
             "gemma" => ARCH_GEMMA,
@@ -232,6 +269,8 @@ impl TextModelConfigBin {
             "mistral" => ARCH_MISTRAL,
             "mixtral" => ARCH_MIXTRAL,
             "deepseek" | "deepseek2" => ARCH_DEEPSEEK,
+            "persimmon" => ARCH_PERSIMMON,
+            "fuyu" => ARCH_FUYU,
             _ => ARCH_UNKNOWN,
         };
         
@@ -251,6 +290,7 @@ impl TextModelConfigBin {
         
         let qkv_layout = match config.get("qkv_layout").and_then(|v| v.as_str()).unwrap_or("separate") {
             "fused" => QKV_FUSED,
+            "fused_per_head" => QKV_FUSED_PER_HEAD,
             _ => QKV_SEPARATE,
         };
         
@@ -268,7 +308,13 @@ impl TextModelConfigBin {
             "gelu" => ACT_GELU,
             "gelu_new" => ACT_GELU_NEW,
             "relu" => ACT_RELU,
-            _ => ACT_SILU,
+            "relu2" => ACT_RELU2,
+            "gelu_fast" => ACT_GELU_FAST,
+            "quick_gelu" => ACT_QUICK_GELU,
+            other => {
+                eprintln!("[WARN] mlp_activation '{}' has no binary code; using silu", other);
+                ACT_SILU
+            }
         };
         
         let norm_type = match config.get("norm_type").and_then(|v| v.as_str()).unwrap_or("rmsnorm") {
@@ -307,6 +353,10 @@ impl TextModelConfigBin {
         if config.get("tie_word_embeddings").and_then(|v| v.as_bool()).unwrap_or(false) {
             flags |= FLAG_TIE_WORD_EMBEDDINGS;
         }
+        let embedding_scale = config.get("embedding_scale").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
+        if config.get("scale_embeddings").and_then(|v| v.as_bool()).unwrap_or(false) {
+            flags |= FLAG_SCALE_EMBEDDINGS;
+        }
         
         Self {
             rope_theta: config.get("rope_theta").and_then(|v| v.as_f64()).unwrap_or(10000.0) as f32,
@@ -314,7 +364,7 @@ impl TextModelConfigBin {
             partial_rotary_factor: config.get("partial_rotary_factor").and_then(|v| v.as_f64()).unwrap_or(1.0) as f32,
             rms_norm_eps: config.get("rms_norm_eps").and_then(|v| v.as_f64()).unwrap_or(1e-6) as f32,
             layer_norm_eps: config.get("layer_norm_eps").and_then(|v| v.as_f64()).unwrap_or(1e-5) as f32,
-            reserved_float: 0.0,
+            embedding_scale,
             
             num_hidden_layers: config.get("num_hidden_layers").and_then(|v| v.as_u64()).unwrap_or(32) as u32,
             hidden_size: config.get("hidden_size").and_then(|v| v.as_u64()).unwrap_or(4096) as u32,
@@ -353,7 +403,7 @@ impl TextModelConfigBin {
         buf[8..12].copy_from_slice(&self.partial_rotary_factor.to_le_bytes());
         buf[12..16].copy_from_slice(&self.rms_norm_eps.to_le_bytes());
         buf[16..20].copy_from_slice(&self.layer_norm_eps.to_le_bytes());
-        buf[20..24].copy_from_slice(&self.reserved_float.to_le_bytes());
+        buf[20..24].copy_from_slice(&self.embedding_scale.to_le_bytes());
         
         // Dimensions (24 bytes)
         buf[24..28].copy_from_slice(&self.num_hidden_layers.to_le_bytes());
@@ -386,6 +436,41 @@ impl TextModelConfigBin {
         // Reserved [96..128] already zeros
         buf
     }
+    
+    /// Inverso de to_bytes; None si hay menos de 128 bytes
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        let buf: &[u8; Self::SIZE] = buf.get(..Self::SIZE)?.try_into().ok()?;
+        let u32_at = |at: usize| u32::from_le_bytes(buf[at..at + 4].try_into().unwrap());
+        let f32_at = |at: usize| f32::from_bits(u32_at(at));
+        
+        Some(Self {
+            rope_theta: f32_at(0),
+            rope_scaling_factor: f32_at(4),
+            partial_rotary_factor: f32_at(8),
+            rms_norm_eps: f32_at(12),
+            layer_norm_eps: f32_at(16),
+            embedding_scale: f32_at(20),
+            num_hidden_layers: u32_at(24),
+            hidden_size: u32_at(28),
+            intermediate_size: u32_at(32),
+            vocab_size: u32_at(36),
+            max_position_embeddings: u32_at(40),
+            rope_dim: u32_at(44),
+            num_attention_heads: u32_at(48),
+            num_key_value_heads: u32_at(52),
+            head_dim: u32_at(56),
+            attention_type: u32_at(60),
+            qkv_layout: u32_at(64),
+            arch: u32_at(68),
+            dtype: u32_at(72),
+            mlp_type: u32_at(76),
+            mlp_activation: u32_at(80),
+            norm_type: u32_at(84),
+            rope_type: u32_at(88),
+            flags: u32_at(92),
+            reserved: buf[96..124].try_into().unwrap(),
+        })
+    }
 }
 
 // ============================================================================
@@ -440,7 +525,7 @@ impl VisionModelConfigBin {
                 "resampler" => 2,
                 _ => 0,
             },
-            num_image_tokens: config.get("num_image_tokens").and_then(|v| v.as_u64()).unwrap_or(196) as u32,
+            num_image_tokens: vision_num_image_tokens(config),
             image_token_id: config.get("image_token_id").and_then(|v| v.as_i64()).unwrap_or(-1) as i32,
             flags: 0,
             reserved: [0; 8],
@@ -472,7 +557,9 @@ impl VisionModelConfigBin {
 // BUILD BINARY HINTS
 // ============================================================================
 
-/// Construye bloque binario [0xB] desde JSON de execution_hints
+/// Construye bloque binario [0xB] desde JSON de execution_hints.
+/// Acepta los hints combinados del builder (texto bajo "text") o los
+/// planos de un solo modelo.
 pub fn build_execution_hints_binary(hints_json: &Value) -> Vec<u8> {
     let mut buf = Vec::new();
     
@@ -494,7 +581,7 @@ pub fn build_execution_hints_binary(hints_json: &Value) -> Vec<u8> {
     buf.extend_from_slice(&header.to_bytes());
     
     // 2. TextModelConfigBin (128 bytes)
-    let text_config = TextModelConfigBin::from_json(hints_json);
+    let text_config = TextModelConfigBin::from_json(hints_json.get("text").unwrap_or(hints_json));
     buf.extend_from_slice(&text_config.to_bytes());
     
     // 3. VisionModelConfigBin (64 bytes) si existe
@@ -555,4 +642,14 @@ mod tests {
         assert!(binary.len() >= 192);
         assert_eq!(binary.len() % 32, 0);
     }
+    
+    #[test]
+    fn test_mlp_activation_codes() {
+        // Todas las activaciones que normalize_mlp_activation puede emitir tienen código propio
+        let code = |act: &str| TextModelConfigBin::from_json(&serde_json::json!({"mlp_activation": act})).mlp_activation;
+        assert_eq!(code("gelu_fast"), ACT_GELU_FAST);
+        assert_eq!(code("quick_gelu"), ACT_QUICK_GELU);
+        assert_eq!(code("relu2"), ACT_RELU2);
+        assert_eq!(code("silu"), ACT_SILU);
+    }
 }
@@ -7,6 +7,13 @@
 //   [0xA] execution_hints     - JSON (obligatorio, compatibilidad)
 //   [0xB] exec_hints_bin      - Binario (preferido, O(1) parsing)
 //
+// norm_type se infiere de los tensores presentes cuando hay norms:
+// LayerNorm lleva .bias, RMSNorm no. El arch string es solo fallback.
+// Igual tie_word_embeddings: si hay tensores manda la presencia de
+// lm_head.weight; si no, config.json o el default de la arquitectura.
+// mlp_activation sale de hidden_act/hidden_activation de config.json; el
+// default por arquitectura (swiglu → silu, geglu → gelu) solo si falta.
+//
 // ============================================================================
 
 pub mod binary;
@@ -15,14 +22,14 @@ use std::path::Path;
 use anyhow::Result;
 use serde_json::{json, Value};
 
+use crate::safetensor::SafetensorReader;
+
 pub use binary::{build_execution_hints_binary, ExecutionHintsBin, TextModelConfigBin, VisionModelConfigBin};
 
 /// Lee config.json de HuggingFace y genera execution_hints
 pub fn build_execution_hints(model_dir: impl AsRef<Path>) -> Result<Value> {
     let config_path = model_dir.as_ref().join("config.json");
-    let config: Value = serde_json::from_str(
-        &std::fs::read_to_string(&config_path)?
-    )?;
+    let config = crate::json::parse(&std::fs::read_to_string(&config_path)?, "config.json")?;
     
     // Extraer valores con defaults
     let arch = config.get("model_type")
@@ -54,10 +61,11 @@ pub fn build_execution_hints(model_dir: impl AsRef<Path>) -> Result<Value> {
         .and_then(|v| v.as_u64())
         .unwrap_or(num_attention_heads as u64) as usize;
     
-    let head_dim = config.get("head_dim")
-        .and_then(|v| v.as_u64())
-        .map(|v| v as usize)
-        .unwrap_or(hidden_size / num_attention_heads);
+    let head_dim = resolve_head_dim(
+        hidden_size,
+        num_attention_heads,
+        config.get("head_dim").and_then(|v| v.as_u64()).map(|v| v as usize),
+    )?;
     
     let rope_theta = config.get("rope_theta")
         .and_then(|v| v.as_f64())
@@ -71,9 +79,7 @@ pub fn build_execution_hints(model_dir: impl AsRef<Path>) -> Result<Value> {
         .and_then(|v| v.as_u64())
         .unwrap_or(4096) as usize;
     
-    let tie_word_embeddings = config.get("tie_word_embeddings")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
+    check_gqa_ratio(num_attention_heads, num_key_value_heads)?;
     
     // Detectar attention_type
     let attention_type = if num_key_value_heads == num_attention_heads {
@@ -84,27 +90,37 @@ pub fn build_execution_hints(model_dir: impl AsRef<Path>) -> Result<Value> {
         "gqa"
     };
     
-    // Detectar mlp_type y activation basado en arquitectura
-    let (mlp_type, mlp_activation) = match arch.as_str() {
+    // mlp_type por arquitectura; la activación la dice config.json si la trae
+    let (mlp_type, default_activation) = match arch.as_str() {
         "gemma" | "gemma2" => ("geglu", "gelu"),
         _ => ("swiglu", "silu"),
     };
+    let mlp_activation = config_mlp_activation(&config).unwrap_or(default_activation);
     
-    // Detectar norm_type
-    let norm_type = if arch.contains("bert") || arch.contains("gpt2") {
-        "layernorm"
-    } else {
-        "rmsnorm"
-    };
+    // Detectar norm_type: primero por tensores reales, luego por arch
+    let tensor_names: Vec<String> = SafetensorReader::from_folder(model_dir.as_ref())
+        .map(|r| r.iter_tensors_sorted().map(|(name, _)| name.to_string()).collect())
+        .unwrap_or_default();
+    let tie_word_embeddings = resolve_tie_word_embeddings(
+        &arch,
+        config.get("tie_word_embeddings").and_then(|v| v.as_bool()),
+        tensor_names.iter().map(|s| s.as_str()),
+    );
+    let (norm_type, norm_bias) = detect_norm_type(tensor_names.iter().map(|s| s.as_str()))
+        .unwrap_or(if arch.contains("bert") || arch.contains("gpt2") {
+            ("layernorm", true)
+        } else {
+            ("rmsnorm", false)
+        });
     
-    // Detectar rope_type
-    let rope_type = if arch.contains("llama3") {
-        "llama3"
+    // Detectar rope_type: el de config.json (cualquier grafía) o por arch
+    let rope_type = rope_scaling_type(&config).unwrap_or_else(|| if arch.contains("llama3") {
+        "llama3".to_string()
     } else if arch.contains("phi") {
-        "su"
+        "su".to_string()
     } else {
-        "default"
-    };
+        "default".to_string()
+    });
     
     // Detectar si tiene biases
     let attention_bias = config.get("attention_bias")
@@ -116,7 +132,7 @@ pub fn build_execution_hints(model_dir: impl AsRef<Path>) -> Result<Value> {
         .unwrap_or(false);
     
     // Construir JSON
-    let hints = json!({
+    let mut hints = json!({
         "arch": arch,
         "dtype": "bf16",
         
@@ -144,7 +160,7 @@ pub fn build_execution_hints(model_dir: impl AsRef<Path>) -> Result<Value> {
         
         // Normalization
         "norm_type": norm_type,
-        "norm_bias": false,
+        "norm_bias": norm_bias,
         "rms_norm_eps": rms_norm_eps,
         "pre_norm": true,
         "final_norm": true,
@@ -187,5 +203,433 @@ pub fn build_execution_hints(model_dir: impl AsRef<Path>) -> Result<Value> {
         }
     });
     
+    // Tied: no hay lm_head que precargar, el engine reutiliza token_embedding
+    if tie_word_embeddings {
+        if let Some(priority) = hints["startup"]["priority_tensors"].as_array_mut() {
+            priority.retain(|t| t != "lm_head.weight");
+        }
+    }
+    
+    // Gemma escala los embeddings por sqrt(hidden_size) (ver GemmaMapper)
+    if arch.starts_with("gemma") {
+        hints["scale_embeddings"] = json!(true);
+        hints["embedding_scale"] = json!((hidden_size as f64).sqrt());
+    }
+    
     Ok(hints)
 }
+
+/// tie_word_embeddings cuando config.json no lo trae: lo que asume la clase
+/// de config de HF para cada arquitectura. PretrainedConfig usa true, pero
+/// PhiConfig/Phi3Config y la mayoría lo fijan a false; Gemma lo deja en true.
+pub fn default_tie_word_embeddings(arch: &str) -> bool {
+    arch.starts_with("gemma")
+}
+
+/// Nombre de activación de HF → el de execution_hints (VALID_MLP_ACTIVATIONS
+/// del validador). None si no se conoce.
+pub fn normalize_mlp_activation(name: &str) -> Option<&'static str> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "silu" | "swish" => "silu",
+        "gelu" => "gelu",
+        "gelu_new" | "gelu_pytorch_tanh" | "gelu_tanh" => "gelu_new",
+        "gelu_fast" => "gelu_fast",
+        "quick_gelu" => "quick_gelu",
+        "relu" => "relu",
+        "relu2" | "relu_squared" | "squared_relu" => "relu2",
+        _ => return None,
+    })
+}
+
+/// mlp_activation explícita de config.json (hidden_activation, que es la que
+/// usa Gemma, o hidden_act). None si falta o no se reconoce (con aviso).
+pub fn config_mlp_activation(config: &Value) -> Option<&'static str> {
+    let name = config.get("hidden_activation")
+        .or_else(|| config.get("hidden_act"))
+        .and_then(|v| v.as_str())?;
+    let activation = normalize_mlp_activation(name);
+    if activation.is_none() {
+        eprintln!("[WARN] Unknown hidden_act '{}' in config.json: using the architecture default", name);
+    }
+    activation
+}
+
+/// tie_word_embeddings efectivo. Con tensores, la presencia de lm_head.weight
+/// es la verdad (avisa si config.json dice otra cosa); sin ellos, el valor
+/// explícito o default_tie_word_embeddings.
+pub fn resolve_tie_word_embeddings<'a>(
+    arch: &str,
+    explicit: Option<bool>,
+    tensor_names: impl IntoIterator<Item = &'a str>,
+) -> bool {
+    let mut names = tensor_names.into_iter().peekable();
+    if names.peek().is_none() {
+        return explicit.unwrap_or_else(|| default_tie_word_embeddings(arch));
+    }
+    
+    let tied = !names.any(|name| name.ends_with("lm_head.weight"));
+    if let Some(explicit) = explicit.filter(|&e| e != tied) {
+        eprintln!("[WARN] config.json says tie_word_embeddings={} but lm_head.weight is {}: using {}",
+            explicit, if tied { "missing" } else { "present" }, tied);
+    }
+    tied
+}
+
+/// Verifica que kv_heads divida a heads (GQA).
+///
+/// Un ratio no entero casi siempre es un config mal leído; el engine agrupa
+/// heads con división entera y haría strides incorrectos.
+pub fn check_gqa_ratio(num_attention_heads: usize, num_key_value_heads: usize) -> Result<()> {
+    if num_key_value_heads == 0 || !num_attention_heads.is_multiple_of(num_key_value_heads) {
+        anyhow::bail!(
+            "num_attention_heads ({}) is not divisible by num_key_value_heads ({}): \
+             GQA groups must be whole, check config.json",
+            num_attention_heads, num_key_value_heads
+        );
+    }
+    Ok(())
+}
+
+/// Tipo de rope_scaling con cualquiera de sus grafías en config.json:
+/// rope_scaling.rope_type (transformers >= 4.43, Llama 3.1), rope_scaling.type
+/// o rope_type en la raíz. En minúsculas; None si no aparece ninguna.
+pub fn rope_scaling_type(config: &Value) -> Option<String> {
+    config.get("rope_scaling")
+        .filter(|rs| !rs.is_null())
+        .and_then(|rs| rs.get("rope_type").or_else(|| rs.get("type")))
+        .or_else(|| config.get("rope_type"))
+        .and_then(|v| v.as_str())
+        .map(str::to_lowercase)
+}
+
+/// Parámetros de rope_scaling además del tipo y el factor
+/// (low_freq_factor, high_freq_factor, original_max_position_embeddings, ...)
+pub fn rope_scaling_params(rope_scaling: &Value) -> serde_json::Map<String, Value> {
+    rope_scaling.as_object()
+        .map(|rs| rs.iter()
+            .filter(|(k, _)| !matches!(k.as_str(), "type" | "rope_type" | "factor"))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// Tokens de imagen del encoder: un token por patch, más el CLS si lo hay
+/// (CLIP sí, SigLIP no).
+pub fn compute_num_image_tokens(image_size: usize, patch_size: usize, has_cls: bool) -> usize {
+    if patch_size == 0 {
+        return 0;
+    }
+    (image_size / patch_size).pow(2) + has_cls as usize
+}
+
+/// num_image_tokens de unos hints de visión: el explícito o, si falta,
+/// compute_num_image_tokens con image_size/patch_size y has_cls_token
+/// (sin él, CLS salvo encoder_type "siglip")
+pub fn vision_num_image_tokens(config: &Value) -> u32 {
+    if let Some(tokens) = config.get("num_image_tokens").and_then(|v| v.as_u64()) {
+        return tokens as u32;
+    }
+    let image_size = config.get("image_size").and_then(|v| v.as_u64()).unwrap_or(224) as usize;
+    let patch_size = config.get("patch_size").and_then(|v| v.as_u64()).unwrap_or(14) as usize;
+    let has_cls = config.get("has_cls_token").and_then(|v| v.as_bool())
+        .unwrap_or(config.get("encoder_type").and_then(|v| v.as_str()) != Some("siglip"));
+    compute_num_image_tokens(image_size, patch_size, has_cls) as u32
+}
+
+/// head_dim explícito o hidden_size / num_attention_heads.
+///
+/// Sin head_dim en config.json, una división no exacta truncaría dimensiones:
+/// casi siempre es un config mal leído, así que es error.
+pub fn resolve_head_dim(hidden_size: usize, num_attention_heads: usize, explicit: Option<usize>) -> Result<usize> {
+    if let Some(head_dim) = explicit {
+        return Ok(head_dim);
+    }
+    if num_attention_heads == 0 || !hidden_size.is_multiple_of(num_attention_heads) {
+        anyhow::bail!(
+            "hidden_size ({}) is not divisible by num_attention_heads ({}) and config.json \
+             has no head_dim: refusing to truncate head_dim, check config.json",
+            hidden_size, num_attention_heads
+        );
+    }
+    Ok(hidden_size / num_attention_heads)
+}
+
+/// MB de KV cache por cada 1k tokens (K + V en FP16), desde las dimensiones de los hints
+pub fn kv_cache_mb_per_1k_tokens(hints: &Value) -> Option<u64> {
+    let get = |k: &str| hints.get(k).and_then(|v| v.as_u64());
+    let layers = get("num_hidden_layers")?;
+    let kv_heads = get("num_key_value_heads").or_else(|| get("num_attention_heads"))?;
+    let head_dim = get("head_dim")?;
+    let bytes = 2 * layers * kv_heads * head_dim * 2 * 1024;
+    Some(bytes.div_ceil(1024 * 1024))
+}
+
+/// Sobrescribe max_position_embeddings (contexto extendido vía RoPE scaling)
+/// y recalcula los campos de memoria derivados.
+///
+/// Devuelve un aviso si se amplía el contexto sin rope_scaling: el modelo no
+/// fue entrenado para esas posiciones y casi seguro es un error.
+pub fn apply_max_position(hints: &mut Value, max_position: usize) -> Option<String> {
+    let base = hints.get("max_position_embeddings").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+    let has_scaling = hints.get("rope_scaling").is_some_and(|v| !v.is_null())
+        || hints.get("rope_scaling_factor").and_then(|v| v.as_f64()).is_some_and(|f| f > 1.0);
+    
+    let warning = (max_position > base && !has_scaling).then(|| format!(
+        "--max-position {} exceeds max_position_embeddings {} but the model has no rope_scaling",
+        max_position, base
+    ));
+    
+    hints["max_position_embeddings"] = json!(max_position);
+    if let Some(per_1k) = kv_cache_mb_per_1k_tokens(hints) {
+        hints["kv_cache_mb_per_1k_tokens"] = json!(per_1k);
+        hints["kv_cache_mb_max"] = json!((per_1k * max_position as u64).div_ceil(1024));
+    }
+    
+    warning
+}
+
+/// Contrasta num_key_value_heads con las filas de k_proj.weight ([kv_heads * head_dim, hidden]).
+///
+/// Algunos exports guardan K/V ya repetidos a num_attention_heads aunque el
+/// config diga GQA: en ese caso el tensor manda y los hints pasan a MHA.
+/// Devuelve un aviso si se corrige algo o si la forma no encaja con nada.
+pub fn apply_stored_kv_heads(hints: &mut Value, k_rows: usize) -> Option<String> {
+    let get = |k: &str| hints.get(k).and_then(|v| v.as_u64()).map(|v| v as usize);
+    let (heads, kv_heads, head_dim) = (get("num_attention_heads")?, get("num_key_value_heads")?, get("head_dim")?);
+    if head_dim == 0 || k_rows == kv_heads * head_dim {
+        return None;
+    }
+    
+    if k_rows == heads * head_dim {
+        hints["num_key_value_heads"] = json!(heads);
+        hints["attention_type"] = json!("mha");
+        if let (Some(per_1k), true) = (kv_cache_mb_per_1k_tokens(hints), hints.get("kv_cache_mb_per_1k_tokens").is_some()) {
+            hints["kv_cache_mb_per_1k_tokens"] = json!(per_1k);
+            if let Some(max_position) = hints.get("max_position_embeddings").and_then(|v| v.as_u64()) {
+                hints["kv_cache_mb_max"] = json!((per_1k * max_position).div_ceil(1024));
+            }
+        }
+        return Some(format!(
+            "k_proj.weight has {} rows = num_attention_heads ({}) * head_dim ({}): K/V stored pre-repeated, \
+             patching num_key_value_heads {} -> {}",
+            k_rows, heads, head_dim, kv_heads, heads
+        ));
+    }
+    
+    Some(format!(
+        "k_proj.weight has {} rows but num_key_value_heads ({}) * head_dim ({}) = {}",
+        k_rows, kv_heads, head_dim, kv_heads * head_dim
+    ))
+}
+
+/// ¿Es un tensor de normalización? (excluye q_norm/k_norm, que son QK-norm)
+fn is_norm_stem(stem: &str) -> bool {
+    if stem == "q_norm" || stem == "k_norm" {
+        return false;
+    }
+    stem.contains("norm") || stem.starts_with("ln")
+}
+
+/// attention_bias según los tensores escritos: q/k/v con bias → true; pesos
+/// de atención sin esos bias → false. o_proj.bias no cuenta (Qwen2 no lo
+/// lleva y otras variantes sí, sin que cambie el flag). None sin atención.
+pub fn detect_attention_bias<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<bool> {
+    let mut has_attention = false;
+    for name in names {
+        if [".attn.q_proj.bias", ".attn.k_proj.bias", ".attn.v_proj.bias"].iter().any(|b| name.ends_with(b)) {
+            return Some(true);
+        }
+        has_attention |= name.contains(".attn.") && name.ends_with("_proj.weight");
+    }
+    has_attention.then_some(false)
+}
+
+/// Infiere (norm_type, norm_bias) de los nombres de tensores.
+///
+/// Si algún norm tiene `.bias` es LayerNorm; si solo hay `.weight`, RMSNorm.
+/// Devuelve None si no hay tensores de norm (ambiguo, usar fallback por arch).
+pub fn detect_norm_type<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<(&'static str, bool)> {
+    let mut has_weight = false;
+    
+    for name in names {
+        let Some((prefix, param)) = name.rsplit_once('.') else { continue };
+        let stem = prefix.rsplit('.').next().unwrap_or(prefix);
+        if !is_norm_stem(stem) {
+            continue;
+        }
+        match param {
+            "bias" => return Some(("layernorm", true)),
+            "weight" => has_weight = true,
+            _ => {}
+        }
+    }
+    
+    has_weight.then_some(("rmsnorm", false))
+}
+
+/// Profundidad del projector de visión: número de `projector.vision.linearN.weight`
+/// distintos (cualquier prefijo). None si no hay ninguno.
+pub fn detect_projector_depth<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<usize> {
+    const STEM: &str = "projector.vision.linear";
+    let layers: std::collections::BTreeSet<usize> = names.into_iter()
+        .filter_map(|name| {
+            let rest = &name[name.find(STEM)? + STEM.len()..];
+            rest.strip_suffix(".weight")?.parse().ok()
+        })
+        .collect();
+    (!layers.is_empty()).then_some(layers.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safetensor::write_test_safetensors;
+    
+    #[test]
+    fn test_detect_norm_type() {
+        assert_eq!(detect_norm_type(["layer0.ln_attn_in.weight", "final_norm.weight"]), Some(("rmsnorm", false)));
+        assert_eq!(detect_norm_type(["final_norm.weight", "final_norm.bias"]), Some(("layernorm", true)));
+        assert_eq!(detect_norm_type(["layer0.attn.q_norm.weight", "layer0.attn.q_norm.bias"]), None);
+        assert_eq!(detect_norm_type(["lm_head.weight"]), None);
+    }
+    
+    #[test]
+    fn test_check_gqa_ratio() {
+        assert!(check_gqa_ratio(32, 8).is_ok());
+        assert!(check_gqa_ratio(32, 32).is_ok());
+        assert!(check_gqa_ratio(14, 3).is_err());
+        assert!(check_gqa_ratio(14, 0).is_err());
+    }
+    
+    #[test]
+    fn test_num_image_tokens_siglip_vs_clip() {
+        use crate::hints::binary::VisionModelConfigBin;
+        use crate::htf::binary::VisionDomainConfigBin;
+        use crate::mapping::{clip::ClipMapper, ModelMapper};
+        
+        let siglip = ClipMapper::from_json(&json!({
+            "model_type": "siglip_vision_model",
+            "image_size": 384,
+            "patch_size": 16,
+            "hidden_size": 768,
+            "num_attention_heads": 12,
+        })).execution_hints();
+        let clip = ClipMapper::from_json(&json!({
+            "model_type": "clip_vision_model",
+            "image_size": 224,
+            "patch_size": 14,
+            "hidden_size": 1024,
+            "num_attention_heads": 16,
+        })).execution_hints();
+        
+        assert_eq!(compute_num_image_tokens(384, 16, false), 576);
+        assert_eq!(compute_num_image_tokens(224, 14, true), 257);
+        for (hints, expected) in [(&siglip, 576), (&clip, 257)] {
+            assert_eq!(hints["num_image_tokens"], expected);
+            
+            // Sin el campo explícito, los dos binarios lo recalculan igual
+            let mut implicit = hints.clone();
+            implicit.as_object_mut().unwrap().remove("num_image_tokens");
+            assert_eq!(VisionModelConfigBin::from_json(&implicit).num_image_tokens, expected);
+            assert_eq!(VisionDomainConfigBin::from_config(&implicit).num_image_tokens, expected);
+        }
+    }
+    
+    #[test]
+    fn test_llama31_rope_type_key() {
+        use crate::mapping::llama::{LlamaConfig, LlamaMapper};
+        use crate::mapping::ModelMapper;
+        
+        let config = json!({
+            "model_type": "llama",
+            "hidden_size": 64,
+            "num_attention_heads": 4,
+            "rope_scaling": {
+                "factor": 8.0,
+                "low_freq_factor": 1.0,
+                "high_freq_factor": 4.0,
+                "original_max_position_embeddings": 8192,
+                "rope_type": "llama3"
+            }
+        });
+        assert_eq!(rope_scaling_type(&config).as_deref(), Some("llama3"));
+        assert_eq!(rope_scaling_type(&json!({"rope_scaling": {"type": "YaRN"}})).as_deref(), Some("yarn"));
+        assert_eq!(rope_scaling_type(&json!({"rope_scaling": null, "rope_type": "dynamic"})).as_deref(), Some("dynamic"));
+        assert_eq!(rope_scaling_type(&json!({})), None);
+        
+        let hints = LlamaMapper::new(LlamaConfig::from_json(&config)).execution_hints();
+        assert_eq!(hints["rope_type"], "llama3");
+        assert_eq!(hints["rope_scaling"]["type"], "llama3");
+        assert_eq!(hints["rope_scaling"]["factor"], 8.0);
+        assert_eq!(hints["rope_scaling"]["low_freq_factor"], 1.0);
+        assert_eq!(hints["rope_scaling"]["high_freq_factor"], 4.0);
+        assert_eq!(hints["rope_scaling"]["original_max_position_embeddings"], 8192);
+        assert_eq!(TextModelConfigBin::from_json(&hints).rope_type, binary::ROPE_LLAMA3);
+    }
+    
+    #[test]
+    fn test_norm_bias_flips_to_layernorm() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("config.json"), r#"{"model_type": "mystery"}"#).unwrap();
+        
+        write_test_safetensors(&dir.path().join("model.safetensors"), &[
+            ("model.norm.weight", vec![4], vec![1.0; 4]),
+        ]).unwrap();
+        let hints = build_execution_hints(dir.path()).unwrap();
+        assert_eq!(hints["norm_type"], "rmsnorm");
+        assert_eq!(hints["norm_bias"], false);
+        
+        write_test_safetensors(&dir.path().join("model.safetensors"), &[
+            ("model.norm.weight", vec![4], vec![1.0; 4]),
+            ("model.norm.bias", vec![4], vec![0.0; 4]),
+        ]).unwrap();
+        let hints = build_execution_hints(dir.path()).unwrap();
+        assert_eq!(hints["norm_type"], "layernorm");
+        assert_eq!(hints["norm_bias"], true);
+    }
+    
+    #[test]
+    fn test_tie_word_embeddings_arch_default() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("config.json"), r#"{"model_type": "gemma", "hidden_size": 64, "num_attention_heads": 4}"#).unwrap();
+        
+        // Sin tensores: default de la arquitectura
+        let hints = build_execution_hints(dir.path()).unwrap();
+        assert_eq!(hints["tie_word_embeddings"], true);
+        assert!(!hints["startup"]["priority_tensors"].as_array().unwrap().iter().any(|t| t == "lm_head.weight"));
+        
+        // Sin lm_head.weight también tied, aunque config.json diga lo contrario
+        std::fs::write(dir.path().join("config.json"), r#"{"model_type": "gemma", "hidden_size": 64, "num_attention_heads": 4, "tie_word_embeddings": false}"#).unwrap();
+        write_test_safetensors(&dir.path().join("model.safetensors"), &[
+            ("model.embed_tokens.weight", vec![2, 4], vec![0.5; 8]),
+        ]).unwrap();
+        assert_eq!(build_execution_hints(dir.path()).unwrap()["tie_word_embeddings"], true);
+        
+        assert!(!default_tie_word_embeddings("llama"));
+        assert!(!default_tie_word_embeddings("phi3"));
+        assert!(!resolve_tie_word_embeddings("qwen2", None, []));
+        assert!(!resolve_tie_word_embeddings("gemma", Some(true), ["model.embed_tokens.weight", "lm_head.weight"]));
+    }
+    
+    #[test]
+    fn test_hidden_act_overrides_arch_activation() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = serde_json::json!({"model_type": "qwen2", "hidden_size": 64, "num_attention_heads": 4, "hidden_act": "gelu"});
+        std::fs::write(dir.path().join("config.json"), config.to_string()).unwrap();
+        let hints = build_execution_hints(dir.path()).unwrap();
+        assert_eq!(hints["mlp_type"], "swiglu");
+        assert_eq!(hints["mlp_activation"], "gelu");
+        
+        // Mappers: mismo config, misma activación
+        let qwen2 = crate::mapping::qwen2::Qwen2Mapper::from_json(&config);
+        assert_eq!(crate::mapping::ModelMapper::execution_hints(&qwen2)["mlp_activation"], "gelu");
+        
+        // Sin hidden_act: default de la arquitectura
+        std::fs::write(dir.path().join("config.json"), r#"{"model_type": "qwen2", "hidden_size": 64, "num_attention_heads": 4}"#).unwrap();
+        assert_eq!(build_execution_hints(dir.path()).unwrap()["mlp_activation"], "silu");
+        
+        assert_eq!(normalize_mlp_activation("gelu_pytorch_tanh"), Some("gelu_new"));
+        assert_eq!(normalize_mlp_activation("relu2"), Some("relu2"));
+        assert_eq!(config_mlp_activation(&serde_json::json!({"hidden_act": "mystery"})), None);
+    }
+}
@@ -0,0 +1,112 @@
+// src/hnf/checksum.rs
+// ============================================================================
+// HNF CHECKSUM - Algoritmo de checksum de bloques seleccionable
+// ============================================================================
+//
+// El checksum de la block table es un u64. Por defecto XXH3-64 (rápido, no
+// criptográfico). Con HeaderFlags::CHECKSUM_BLAKE3 se usa BLAKE3 truncado a
+// los primeros 8 bytes (little-endian) para integridad criptográfica.
+//
+// El mismo algoritmo se aplica a los hashes por segmento del manifest
+// (checksum_segments), que además lo declaran en "algorithm".
+//
+// ============================================================================
+
+use xxhash_rust::xxh3::Xxh3;
+
+use super::header::HeaderFlags;
+
+/// Algoritmo de checksum de bloques
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumAlgo {
+    #[default]
+    Xxh3,
+    Blake3,
+}
+
+impl ChecksumAlgo {
+    /// Algoritmo declarado en los flags del header
+    pub fn from_flags(flags: HeaderFlags) -> Self {
+        if flags.has(HeaderFlags::CHECKSUM_BLAKE3) {
+            Self::Blake3
+        } else {
+            Self::Xxh3
+        }
+    }
+    
+    /// Bits de header que lo declaran
+    pub fn flag(&self) -> u32 {
+        match self {
+            Self::Xxh3 => 0,
+            Self::Blake3 => HeaderFlags::CHECKSUM_BLAKE3,
+        }
+    }
+    
+    /// Nombre en manifest / CLI
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Xxh3 => "xxh3-64",
+            Self::Blake3 => "blake3-64",
+        }
+    }
+    
+    /// Parsea el valor de --checksum
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "xxh3" | "xxh3-64" => Some(Self::Xxh3),
+            "blake3" | "blake3-64" => Some(Self::Blake3),
+            _ => None,
+        }
+    }
+    
+    /// Checksum de un buffer completo
+    pub fn digest(&self, data: &[u8]) -> u64 {
+        let mut hasher = BlockHasher::new(*self);
+        hasher.update(data);
+        hasher.digest()
+    }
+}
+
+/// Hasher incremental del algoritmo elegido
+pub enum BlockHasher {
+    Xxh3(Box<Xxh3>),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl BlockHasher {
+    pub fn new(algo: ChecksumAlgo) -> Self {
+        match algo {
+            ChecksumAlgo::Xxh3 => Self::Xxh3(Box::new(Xxh3::new())),
+            ChecksumAlgo::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+    
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Xxh3(h) => h.update(data),
+            Self::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+    
+    /// u64 del estado actual (BLAKE3: primeros 8 bytes del hash)
+    pub fn digest(&self) -> u64 {
+        match self {
+            Self::Xxh3(h) => h.digest(),
+            Self::Blake3(h) => {
+                let hash = h.finalize();
+                u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap())
+            }
+        }
+    }
+    
+    pub fn reset(&mut self) {
+        match self {
+            Self::Xxh3(h) => h.reset(),
+            Self::Blake3(h) => {
+                h.reset();
+            }
+        }
+    }
+}
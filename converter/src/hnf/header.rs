@@ -13,12 +13,42 @@ pub const MAGIC: &[u8; 8] = b"HNFv9\x00\x00\x00";
 pub const VERSION_MAJOR: u16 = 9;
 pub const VERSION_MINOR: u16 = 1;
 
+/// Primera versión menor con cada feature opcional. Con --hnf-version 9.0
+/// el writer las omite (o rechaza) para lectores antiguos.
+pub const MINOR_CHECKSUM_SEGMENTS: u16 = 1;  // manifest["checksum_segments"]
+pub const MINOR_BLAKE3: u16 = 1;             // HeaderFlags::CHECKSUM_BLAKE3
+pub const MINOR_EXEC_HINTS_BIN: u16 = 1;     // bloque 0xB
+
+/// "9.0" / "9.1" → versión menor (solo major 9, hasta VERSION_MINOR)
+pub fn parse_hnf_version(s: &str) -> Result<u16, String> {
+    let (major, minor) = s.trim().split_once('.')
+        .ok_or_else(|| format!("invalid HNF version '{}' (expected 9.N)", s))?;
+    if major != VERSION_MAJOR.to_string() {
+        return Err(format!("unsupported HNF major version '{}' (only {})", major, VERSION_MAJOR));
+    }
+    let minor: u16 = minor.parse().map_err(|_| format!("invalid HNF minor version '{}'", minor))?;
+    if minor > VERSION_MINOR {
+        return Err(format!("HNF {}.{} is newer than this writer ({}.{})", major, minor, VERSION_MAJOR, VERSION_MINOR));
+    }
+    Ok(minor)
+}
+
 /// Número fijo de bloques
 pub const BLOCK_COUNT: u32 = 16;
 
 /// Tamaño del header
 pub const HEADER_SIZE: u32 = 64;
 
+/// Valor de todos los campos reservados (el validador rechaza cualquier otro)
+pub const RESERVED_FILL: u8 = 0x00;
+
+/// Campo reservado del header: [60:64]. La block table no tiene reservados
+/// (id, type, offset, size, checksum ocupan los 32 bytes de cada entrada).
+pub const HEADER_RESERVED: std::ops::Range<usize> = 60..64;
+
+/// Tamaño de segmento para los hashes parciales del manifest (checksum_segments)
+pub const CHECKSUM_SEGMENT_SIZE: usize = 1024 * 1024;
+
 /// Índices de bloques - HNFv9.1
 pub const BLOCK_TEXT_MODEL: usize = 0x0;
 pub const BLOCK_VISION: usize = 0x1;
@@ -76,6 +106,8 @@ impl HeaderFlags {
     pub const HAS_EXPERT_ROUTER: u32 = 1 << 11;
     pub const IS_MOE: u32 = 1 << 12;
     pub const IS_MULTIMODAL: u32 = 1 << 13;
+    /// Checksums de bloque en BLAKE3 truncado a 64 bits (por defecto XXH3-64)
+    pub const CHECKSUM_BLAKE3: u32 = 1 << 16;
     
     pub fn set(&mut self, flag: u32) {
         self.0 |= flag;
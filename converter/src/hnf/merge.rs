@@ -0,0 +1,273 @@
+// src/hnf/merge.rs
+// ============================================================================
+// HNF MERGE - Une varios HNF construidos por separado en uno solo
+// ============================================================================
+//
+// Caso típico: text.hnf + vision.hnf → combined.hnf sin reconvertir pesos.
+//
+//   - Bloques de tensores (0x0-0x8, 0xC-0xF): se copian del único origen que
+//     los tiene. Si varios los tienen es conflicto, salvo que `prefer` indique
+//     el origen que gana.
+//   - Hints JSON (0xA): unión de claves; ante claves distintas gana el origen
+//     preferido (o el primero), con aviso.
+//   - Hints binarios (0xB): se regeneran desde los JSON unidos (una copia
+//     quedaría desfasada) si la versión destino admite el bloque.
+//   - Tokenizer (0x9): unión de dominios HTF (ver htf::merge_htf).
+//   - Flags: los de bloque los recalcula finalize(); los de modelo (IS_MOE)
+//     se unen.
+//   - quantization.super_block: debe coincidir en todas las entradas.
+//
+// ============================================================================
+
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use super::checksum::ChecksumAlgo;
+use super::header::*;
+use super::reader::HnfReader;
+use super::writer::HnfWriter;
+use crate::htf;
+
+/// Une `inputs` en `output`. `prefer` es el índice del origen que gana los
+/// conflictos. Devuelve los avisos (hints distintos, bloques descartados).
+pub fn merge_hnf(inputs: &[&Path], output: &Path, prefer: Option<usize>) -> Result<Vec<String>> {
+    if inputs.len() < 2 {
+        anyhow::bail!("--merge needs at least two .hnf files");
+    }
+    if let Some(p) = prefer {
+        if p >= inputs.len() {
+            anyhow::bail!("--prefer index {} out of range ({} inputs)", p, inputs.len());
+        }
+    }
+    
+    let sources = inputs.iter()
+        .map(HnfReader::open)
+        .collect::<Result<Vec<_>>>()?;
+    
+    // El manifest unido guarda un solo super_block: con dos distintos el
+    // engine decodificaría los bloques de uno con el tamaño del otro
+    for (source, path) in sources.iter().zip(inputs).skip(1) {
+        if source.super_block() != sources[0].super_block() {
+            anyhow::bail!(
+                "Cannot merge {} (super_block {}) with {} (super_block {}): HQ super-block sizes differ",
+                inputs[0].display(), sources[0].super_block(), path.display(), source.super_block()
+            );
+        }
+    }
+    
+    // Orden de prioridad: preferido primero, el resto en el orden dado
+    let mut priority: Vec<usize> = (0..sources.len()).collect();
+    if let Some(p) = prefer {
+        priority.retain(|&i| i != p);
+        priority.insert(0, p);
+    }
+    
+    let mut warnings = Vec::new();
+    let mut writer = HnfWriter::create(output)?;
+    // La versión más antigua de las entradas: todas siguen siendo legibles
+    let minor = sources.iter().map(|s| s.header.version_minor).min().unwrap_or(VERSION_MINOR);
+    writer.set_version_minor(minor.min(VERSION_MINOR))?;
+    writer.set_checksum_algo(ChecksumAlgo::from_flags(sources[priority[0]].header.flags))?;
+    
+    for (block_id, block_name) in BLOCK_NAMES.iter().enumerate() {
+        let holders: Vec<usize> = priority.iter()
+            .copied()
+            .filter(|&i| !sources[i].block_table.entries[block_id].is_empty())
+            .collect();
+        if holders.is_empty() {
+            continue;
+        }
+        
+        match block_id {
+            BLOCK_EXEC_HINTS => {
+                let hints = merge_hints(&sources, &holders, inputs, &mut warnings)?;
+                writer.write_execution_hints(&hints)?;
+                if writer.supports_exec_hints_bin() {
+                    writer.write_execution_hints_binary(&hints)?;
+                }
+            }
+            BLOCK_TOKENIZER => {
+                let blobs: Vec<&[u8]> = holders.iter().map(|&i| sources[i].block_bytes(block_id)).collect();
+                let (merged, dropped) = htf::merge_htf(&blobs)?;
+                for d in dropped {
+                    warnings.push(format!("Tokenizer {} duplicated, keeping the preferred one", d));
+                }
+                writer.write_tokenizer(&merged)?;
+            }
+            // Ya escrito junto al JSON (0xA)
+            BLOCK_EXEC_HINTS_BIN => {}
+            _ => {
+                if holders.len() > 1 && prefer != Some(holders[0]) {
+                    anyhow::bail!(
+                        "Conflicting block {} in {} and {} (use --prefer to pick one)",
+                        block_name,
+                        inputs[holders[0]].display(),
+                        inputs[holders[1]].display(),
+                    );
+                }
+                let source = &sources[holders[0]];
+                writer.copy_block(
+                    block_id,
+                    source.block_bytes(block_id),
+                    source.block_table.entries[block_id].offset,
+                    source.block_tensors(block_id),
+                )?;
+            }
+        }
+    }
+    
+    let model_flags = sources.iter().fold(0, |acc, s| acc | (s.header.flags.0 & HeaderFlags::IS_MOE));
+    writer.set_flags(model_flags);
+    
+    // Manifest: unión de claves de nivel superior, mismo criterio que los hints
+    let mut manifest = serde_json::Map::new();
+    for &i in &priority {
+        if let Some(obj) = sources[i].manifest.as_object() {
+            for (key, value) in obj {
+                manifest.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
+    manifest.insert(
+        "merged_from".to_string(),
+        Value::Array(inputs.iter().map(|p| Value::String(p.display().to_string())).collect()),
+    );
+    
+    for w in &warnings {
+        eprintln!("[WARN] {}", w);
+    }
+    
+    writer.finalize(Value::Object(manifest))?;
+    Ok(warnings)
+}
+
+/// Une los hints JSON de `holders` (ya en orden de prioridad)
+fn merge_hints(
+    sources: &[HnfReader],
+    holders: &[usize],
+    inputs: &[&Path],
+    warnings: &mut Vec<String>,
+) -> Result<Value> {
+    let mut merged = serde_json::Map::new();
+    let mut owner = std::collections::HashMap::new();
+    
+    for &i in holders {
+        let hints: Value = serde_json::from_slice(sources[i].block_bytes(BLOCK_EXEC_HINTS))
+            .map_err(|e| anyhow::anyhow!("{}: invalid execution hints: {}", inputs[i].display(), e))?;
+        let Value::Object(obj) = hints else {
+            anyhow::bail!("{}: execution hints are not a JSON object", inputs[i].display());
+        };
+        
+        for (key, value) in obj {
+            match merged.get(&key) {
+                None => {
+                    owner.insert(key.clone(), i);
+                    merged.insert(key, value);
+                }
+                Some(existing) if *existing != value => warnings.push(format!(
+                    "Hint '{}' differs between {} and {}; keeping the former",
+                    key, inputs[owner[&key]].display(), inputs[i].display()
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+    
+    Ok(Value::Object(merged))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// HNF mínimo con un tensor en `block_id`, hints y (opcional) tokenizer
+    fn write_fixture(path: &Path, block_id: usize, name: &str, hints: Value, tokenizer: Option<&Path>) {
+        let mut writer = HnfWriter::create(path).unwrap();
+        let data: Vec<u8> = (0..64u8).collect();
+        writer.write_tensor(block_id, name, "fp16", &[4, 8], &data).unwrap();
+        writer.finalize_block(block_id).unwrap();
+        writer.write_execution_hints(&hints).unwrap();
+        if let Some(dir) = tokenizer {
+            writer.write_tokenizer(&htf::build_htf(dir).unwrap()).unwrap();
+        }
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+    }
+    
+    #[test]
+    fn test_merge_text_and_vision_into_multimodal() {
+        let dir = tempfile::tempdir().unwrap();
+        let tok_dir = dir.path().join("tok");
+        std::fs::create_dir_all(&tok_dir).unwrap();
+        let tokenizer = serde_json::json!({"model": {"type": "BPE", "vocab": {"a": 0, "b": 1}, "merges": []}});
+        std::fs::write(tok_dir.join("tokenizer.json"), tokenizer.to_string()).unwrap();
+        
+        let text = dir.path().join("text.hnf");
+        let vision = dir.path().join("vision.hnf");
+        write_fixture(
+            &text, BLOCK_TEXT_MODEL, "text.token_embedding.weight",
+            serde_json::json!({"text_enabled": true, "text": {"hidden_size": 8}}),
+            Some(&tok_dir),
+        );
+        write_fixture(
+            &vision, BLOCK_VISION, "vision.post_layernorm.weight",
+            serde_json::json!({"vision_enabled": true, "vision": {"hidden_size": 8}}),
+            None,
+        );
+        
+        let output = dir.path().join("combined.hnf");
+        let warnings = merge_hnf(&[&text, &vision], &output, None).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        
+        let out = HnfReader::open(&output).unwrap();
+        assert!(out.header.flags.has(HeaderFlags::HAS_VISION));
+        assert!(out.header.flags.has(HeaderFlags::IS_MULTIMODAL));
+        assert_eq!(out.block_tensors(BLOCK_TEXT_MODEL)[0].name, "text.token_embedding.weight");
+        assert_eq!(out.block_tensors(BLOCK_VISION)[0].name, "vision.post_layernorm.weight");
+        assert_eq!(out.block_bytes(BLOCK_VISION), &(0..64u8).collect::<Vec<_>>()[..]);
+        
+        let hints: Value = serde_json::from_slice(out.block_bytes(BLOCK_EXEC_HINTS)).unwrap();
+        assert_eq!(hints["text_enabled"], true);
+        assert_eq!(hints["vision_enabled"], true);
+        
+        let htf_result = htf::validate::validate_htf(out.block_bytes(BLOCK_TOKENIZER));
+        assert!(htf_result.valid, "{:?}", htf_result.errors);
+        
+        // Dos modelos de texto: conflicto salvo --prefer
+        let text2 = dir.path().join("text2.hnf");
+        write_fixture(
+            &text2, BLOCK_TEXT_MODEL, "text.final_norm.weight",
+            serde_json::json!({"text_enabled": true, "text": {"hidden_size": 16}}),
+            None,
+        );
+        assert!(merge_hnf(&[&text, &text2], &output, None).is_err());
+        let warnings = merge_hnf(&[&text, &text2], &output, Some(1)).unwrap();
+        assert_eq!(warnings.len(), 1);
+        let out = HnfReader::open(&output).unwrap();
+        assert_eq!(out.block_tensors(BLOCK_TEXT_MODEL)[0].name, "text.final_norm.weight");
+    }
+    
+    #[test]
+    fn test_merge_rejects_different_super_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, block_id: usize, super_block: usize| {
+            let path = dir.path().join(name);
+            let mut writer = HnfWriter::create(&path).unwrap();
+            writer.write_tensor(block_id, "t.weight", "hq4k", &[64], &[0u8; 64]).unwrap();
+            writer.finalize_block(block_id).unwrap();
+            writer.finalize(serde_json::json!({"quantization": {"super_block": super_block}})).unwrap();
+            path
+        };
+        let text = write("text.hnf", BLOCK_TEXT_MODEL, 64);
+        let vision = write("vision.hnf", BLOCK_VISION, 256);
+        let output = dir.path().join("merged.hnf");
+        
+        let err = merge_hnf(&[&text, &vision], &output, None).unwrap_err().to_string();
+        assert!(err.contains("super_block 64") && err.contains("super_block 256"), "{}", err);
+        
+        let vision = write("vision64.hnf", BLOCK_VISION, 64);
+        merge_hnf(&[&text, &vision], &output, None).unwrap();
+        assert_eq!(HnfReader::open(&output).unwrap().super_block(), 64);
+    }
+}
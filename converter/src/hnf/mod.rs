@@ -4,7 +4,17 @@
 // ============================================================================
 
 pub mod header;
+pub mod checksum;
 pub mod writer;
+pub mod reader;
+pub mod rewrite;
+pub mod repair;
+pub mod merge;
 
 pub use header::*;
-pub use writer::{HnfWriter, TensorManifest};
+pub use checksum::{BlockHasher, ChecksumAlgo};
+pub use writer::{HnfWriter, TensorManifest, BLOCK_MODEL_CARD, MODEL_CARD_MAX_SIZE, RAW_BLOCKS};
+pub use reader::{span, HnfReader};
+pub use rewrite::{prune_blocks, rewrite_blocks, reorder_blocks, PruneReport, METADATA_BLOCKS};
+pub use repair::{check_block_layout, repair_block_table};
+pub use merge::merge_hnf;
@@ -0,0 +1,235 @@
+// src/hnf/reader.rs
+// ============================================================================
+// HNF READER - Lectura de archivos HNFv9 existentes
+// ============================================================================
+//
+// mmap del archivo completo: header y block table validados al abrir,
+// manifest parseado; los bloques se devuelven como slices sin copiar.
+// from_bytes hace lo mismo sobre un buffer en memoria (fuzzing, tests):
+// cualquier entrada malformada es un Err, nunca un pánico.
+//
+// Base común de rewrite, merge y los binarios (inspect) en vez de repetir
+// la aritmética de offsets en cada uno.
+//
+// ============================================================================
+
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use serde_json::Value;
+
+use super::header::*;
+use super::writer::{TensorManifest, BLOCK_MODEL_CARD};
+
+/// Bytes del HNF: mmap del archivo o buffer propio
+enum Backing {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl std::ops::Deref for Backing {
+    type Target = [u8];
+    
+    fn deref(&self) -> &[u8] {
+        match self {
+            Backing::Mapped(mmap) => mmap,
+            Backing::Owned(data) => data,
+        }
+    }
+}
+
+/// HNF existente abierto para lectura (mmap)
+pub struct HnfReader {
+    pub header: HnfHeader,
+    pub block_table: BlockTable,
+    pub manifest: Value,
+    data: Backing,
+}
+
+/// [offset, offset + size) si cabe en `len` bytes (sin desbordar)
+pub fn span(len: usize, offset: u64, size: u64) -> Option<std::ops::Range<usize>> {
+    let end = offset.checked_add(size)?;
+    (end <= len as u64).then_some(offset as usize..end as usize)
+}
+
+impl HnfReader {
+    /// Abre y valida header + block table + manifest
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Cannot open {}", path.display()))?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        Self::parse(Backing::Mapped(mmap)).map_err(|e| anyhow::anyhow!("{}: {:#}", path.display(), e))
+    }
+    
+    /// Igual que open, sobre un HNF ya en memoria
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        Self::parse(Backing::Owned(data))
+    }
+    
+    fn parse(data: Backing) -> Result<Self> {
+        let table_end = HEADER_SIZE as usize + 512;
+        if data.len() < table_end {
+            anyhow::bail!("too small for HNF header + block table");
+        }
+        
+        let header = HnfHeader::from_bytes(&data[..HEADER_SIZE as usize])?;
+        header.validate().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let block_table = BlockTable::from_bytes(&data[HEADER_SIZE as usize..table_end])?;
+        
+        let manifest_range = span(data.len(), header.manifest_offset, header.manifest_size)
+            .context("manifest exceeds file size")?;
+        let manifest: Value = serde_json::from_slice(&data[manifest_range])
+            .context("invalid manifest JSON")?;
+        
+        // También los vacíos: block_bytes corta offset..offset aunque size sea 0
+        for (i, entry) in block_table.entries.iter().enumerate() {
+            if span(data.len(), entry.offset, entry.size).is_none() {
+                anyhow::bail!("block 0x{:X} exceeds file size", i);
+            }
+        }
+        
+        Ok(Self { header, block_table, manifest, data })
+    }
+    
+    /// Bytes de un bloque (vacío si no existe)
+    pub fn block_bytes(&self, block_id: usize) -> &[u8] {
+        let entry = &self.block_table.entries[block_id];
+        span(self.data.len(), entry.offset, entry.size).map_or(&[], |range| &self.data[range])
+    }
+    
+    /// Tensores del manifest que pertenecen a un bloque
+    pub fn block_tensors(&self, block_id: usize) -> Vec<TensorManifest> {
+        let block_name = BLOCK_NAMES[block_id];
+        self.manifest.get("tensors")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter(|t| t.get("block").and_then(|v| v.as_str()) == Some(block_name))
+            .filter_map(|t| serde_json::from_value(t.clone()).ok())
+            .collect()
+    }
+    
+    pub fn header(&self) -> &HnfHeader {
+        &self.header
+    }
+    
+    /// Las 16 entradas de la block table, indexadas por block_id
+    pub fn blocks(&self) -> &[BlockEntry] {
+        &self.block_table.entries
+    }
+    
+    pub fn manifest(&self) -> &Value {
+        &self.manifest
+    }
+    
+    /// Elementos por super-block HQ*K (manifest quantization.super_block;
+    /// 256 en archivos anteriores a --super-block)
+    pub fn super_block(&self) -> usize {
+        self.manifest.get("quantization")
+            .and_then(|q| q.get("super_block"))
+            .and_then(|v| v.as_u64())
+            .map_or(crate::hqs::SUPER_BLOCK_SIZE, |v| v as usize)
+    }
+    
+    /// execution_hints (bloque 0xA) parseado; None si el bloque está vacío
+    pub fn execution_hints_json(&self) -> Result<Option<Value>> {
+        let data = self.block_bytes(BLOCK_EXEC_HINTS);
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let hints = serde_json::from_slice(data).context("execution_hints is not valid JSON")?;
+        Ok(Some(hints))
+    }
+    
+    /// Model card guardado con --model-card; None si no hay o no es UTF-8
+    pub fn model_card(&self) -> Option<&str> {
+        Some(self.block_bytes(BLOCK_MODEL_CARD))
+            .filter(|data| !data.is_empty())
+            .and_then(|data| std::str::from_utf8(data).ok())
+    }
+    
+    /// Bytes del archivo fuera de header, block table, bloques y manifest
+    /// (relleno de alineación o espacio perdido por el writer)
+    pub fn padding_bytes(&self) -> u64 {
+        let blocks: u64 = self.block_table.entries.iter().map(|e| e.size).sum();
+        let used = HEADER_SIZE as u64 + 512 + blocks + self.header.manifest_size;
+        (self.data.len() as u64).saturating_sub(used)
+    }
+    
+    /// Cota de padding de un writer correcto: menos de 32 bytes de alineación
+    /// antes de cada bloque no vacío y del manifest
+    pub fn padding_bound(&self) -> u64 {
+        let sections = self.block_table.entries.iter().filter(|e| e.size > 0).count() as u64 + 1;
+        32 * sections
+    }
+    
+    /// Bytes HTF del tokenizer (bloque 0x9); None si no hay tokenizer
+    pub fn tokenizer_bytes(&self) -> Option<&[u8]> {
+        Some(self.block_bytes(BLOCK_TOKENIZER)).filter(|data| !data.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hnf::HnfWriter;
+    
+    #[test]
+    fn test_round_trip_every_accessor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        let hints = serde_json::json!({"arch": "llama", "num_hidden_layers": 2});
+        let mut tokenizer = crate::htf::HTFWriter::new_v13();
+        tokenizer.add_text_domain(&[("a".to_string(), 0)].into(), &[], &serde_json::json!({}), true).unwrap();
+        let htf = tokenizer.build();
+        let weights: Vec<u8> = (0..64u8).collect();
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.token_embedding.weight", "fp16", &[4, 8], &weights).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&hints).unwrap();
+        writer.write_tokenizer(&htf).unwrap();
+        writer.set_flags(HeaderFlags::IS_MOE);
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        let reader = HnfReader::open(&path).unwrap();
+        assert_eq!(&reader.header().magic, MAGIC);
+        assert!(reader.header().flags.has(HeaderFlags::IS_MOE));
+        assert_eq!(reader.header().file_size, std::fs::metadata(&path).unwrap().len());
+        
+        assert_eq!(reader.blocks().len(), 16);
+        assert_eq!(reader.blocks()[BLOCK_TEXT_MODEL].size, 64);
+        assert_eq!(reader.blocks()[BLOCK_VISION].size, 0);
+        assert_eq!(reader.block_bytes(BLOCK_TEXT_MODEL), &weights[..]);
+        assert!(reader.block_bytes(BLOCK_VISION).is_empty());
+        
+        assert_eq!(reader.manifest()["format"], "HNFv9");
+        let tensors = reader.block_tensors(BLOCK_TEXT_MODEL);
+        assert_eq!(tensors.len(), 1);
+        assert_eq!(tensors[0].name, "text.token_embedding.weight");
+        assert_eq!(tensors[0].offset, reader.blocks()[BLOCK_TEXT_MODEL].offset);
+        
+        assert_eq!(reader.execution_hints_json().unwrap(), Some(hints));
+        assert_eq!(reader.tokenizer_bytes(), Some(&htf[..]));
+    }
+    
+    #[test]
+    fn test_optional_blocks_absent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.hnf");
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.norm.weight", "fp16", &[16], &[0u8; 32]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.finalize(serde_json::json!({"format": "HNFv9"})).unwrap();
+        
+        let reader = HnfReader::open(&path).unwrap();
+        assert_eq!(reader.execution_hints_json().unwrap(), None);
+        assert_eq!(reader.tokenizer_bytes(), None);
+        
+        std::fs::write(&path, b"not an hnf file").unwrap();
+        assert!(HnfReader::open(&path).is_err());
+    }
+}
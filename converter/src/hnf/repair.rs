@@ -0,0 +1,132 @@
+// src/hnf/repair.rs
+// ============================================================================
+// HNF REPAIR - Reparaciones estructurales sin reescribir bloques
+// ============================================================================
+//
+// block_id y block_type son redundantes: la entrada i de la block table
+// siempre es id=i, type=i. Si un writer con bugs los desordena, basta con
+// reescribirlos; offsets, tamaños y checksums no se tocan.
+//
+// Antes de reparar se comprueba que los offsets siguen formando un layout
+// válido (alineados, sin solapes, entre la block table y el manifest). Si no,
+// el archivo no es reparable por esta vía.
+//
+// ============================================================================
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::header::*;
+
+/// Alineación de bloques que usa HnfWriter
+const BLOCK_ALIGNMENT: u64 = 32;
+
+/// Comprueba que los offsets/tamaños de la tabla forman un layout válido
+pub fn check_block_layout(header: &HnfHeader, table: &BlockTable) -> Result<()> {
+    let table_end = header.block_table_offset + BLOCK_COUNT as u64 * 32;
+    
+    let mut used: Vec<(usize, &BlockEntry)> = table.entries.iter()
+        .enumerate()
+        .filter(|(_, e)| !e.is_empty())
+        .collect();
+    used.sort_by_key(|(_, e)| e.offset);
+    
+    let mut prev_end = table_end;
+    for (i, entry) in used {
+        if entry.offset % BLOCK_ALIGNMENT != 0 {
+            anyhow::bail!("Block 0x{:X}: offset {} not aligned to {} bytes", i, entry.offset, BLOCK_ALIGNMENT);
+        }
+        if entry.offset < prev_end {
+            anyhow::bail!("Block 0x{:X}: offset {} overlaps previous data ending at {}", i, entry.offset, prev_end);
+        }
+        prev_end = entry.offset.checked_add(entry.size)
+            .ok_or_else(|| anyhow::anyhow!("Block 0x{:X}: offset + size overflows", i))?;
+    }
+    
+    if prev_end > header.manifest_offset {
+        anyhow::bail!("Blocks end at {} past manifest offset {}", prev_end, header.manifest_offset);
+    }
+    if header.manifest_offset + header.manifest_size > header.file_size {
+        anyhow::bail!("Manifest ends past file_size {}", header.file_size);
+    }
+    
+    Ok(())
+}
+
+/// Copia `input` a `output` reescribiendo block_id/block_type = índice.
+///
+/// Devuelve una línea por campo corregido (vacío si ya estaba bien).
+/// El CRC32 del header no se recalcula: el writer lo calcula sobre un header
+/// intermedio y ningún lector lo verifica.
+pub fn repair_block_table(input: &Path, output: &Path) -> Result<Vec<String>> {
+    let table_end = (HEADER_SIZE + BLOCK_COUNT * 32) as usize;
+    let mut head = vec![0u8; table_end];
+    File::open(input)
+        .with_context(|| format!("Cannot open {}", input.display()))?
+        .read_exact(&mut head)
+        .with_context(|| format!("{} too small for HNF header + block table", input.display()))?;
+    
+    let header = HnfHeader::from_bytes(&head[..HEADER_SIZE as usize])?;
+    header.validate().map_err(|e| anyhow::anyhow!("{}: {}", input.display(), e))?;
+    if header.block_table_offset != HEADER_SIZE as u64 {
+        anyhow::bail!("{}: unexpected block_table_offset {}", input.display(), header.block_table_offset);
+    }
+    let actual_size = fs::metadata(input)?.len();
+    if header.file_size != actual_size {
+        anyhow::bail!("{}: file_size {} != actual {} (truncated?)", input.display(), header.file_size, actual_size);
+    }
+    
+    let mut table = BlockTable::from_bytes(&head[HEADER_SIZE as usize..])?;
+    check_block_layout(&header, &table)
+        .with_context(|| format!("{}: block layout is not repairable", input.display()))?;
+    
+    let mut fixes = Vec::new();
+    for (i, entry) in table.entries.iter_mut().enumerate() {
+        if entry.block_id != i as u32 {
+            fixes.push(format!("block {:2}: block_id {} → {}", i, entry.block_id, i));
+            entry.block_id = i as u32;
+        }
+        if entry.block_type != i as u32 {
+            fixes.push(format!("block {:2}: block_type {} → {}", i, entry.block_type, i));
+            entry.block_type = i as u32;
+        }
+    }
+    
+    if input != output {
+        fs::copy(input, output)
+            .with_context(|| format!("Cannot copy {} to {}", input.display(), output.display()))?;
+    }
+    let mut file = OpenOptions::new().write(true).open(output)?;
+    file.seek(SeekFrom::Start(header.block_table_offset))?;
+    file.write_all(&table.to_bytes())?;
+    file.sync_all()?;
+    
+    Ok(fixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_layout_rejects_overlap() {
+        let header = HnfHeader {
+            manifest_offset: 4096,
+            manifest_size: 10,
+            file_size: 4106,
+            ..Default::default()
+        };
+        let mut table = BlockTable::default();
+        table.entries[0].offset = 576;
+        table.entries[0].size = 1000;
+        table.entries[9].offset = 1024;
+        table.entries[9].size = 100;
+        assert!(check_block_layout(&header, &table).is_err());
+        
+        table.entries[9].offset = 1600;
+        assert!(check_block_layout(&header, &table).is_ok());
+    }
+}
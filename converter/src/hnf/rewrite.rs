@@ -0,0 +1,156 @@
+// src/hnf/rewrite.rs
+// ============================================================================
+// HNF REWRITE - Reescribe un HNF existente reemplazando bloques
+// ============================================================================
+//
+// Copia los bloques del archivo origen en su orden físico, sustituyendo los
+// que se indiquen, y reconstruye manifest + header al final. Los offsets de
+// los tensores del manifest se rebasan al nuevo offset de su bloque.
+//
+// Base para operaciones post-build (--set-tokenizer, --metadata-first,
+// --prune) sin reconvertir pesos.
+//
+// ============================================================================
+
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use super::checksum::ChecksumAlgo;
+use super::header::*;
+use super::reader::HnfReader;
+use super::writer::HnfWriter;
+
+/// Reescribe `input` en `output` sustituyendo los bloques de `replacements`.
+///
+/// Un reemplazo vacío elimina el bloque. `patch_manifest` permite ajustar el
+/// manifest antes de escribirlo (la lista de tensores se regenera siempre).
+pub fn rewrite_blocks(
+    input: &Path,
+    output: &Path,
+    replacements: &[(usize, Vec<u8>)],
+    patch_manifest: impl FnOnce(&mut Value),
+) -> Result<()> {
+    rewrite_impl(input, output, replacements, &[], patch_manifest)
+}
+
+/// Bloques de metadatos que --metadata-first coloca al principio del archivo
+pub const METADATA_BLOCKS: [usize; 3] = [BLOCK_EXEC_HINTS, BLOCK_EXEC_HINTS_BIN, BLOCK_TOKENIZER];
+
+/// Copia `input` en `output` colocando físicamente primero los bloques de
+/// `first` (en ese orden). La block table sigue indexada por id; solo cambian
+/// los offsets.
+pub fn reorder_blocks(input: &Path, output: &Path, first: &[usize]) -> Result<()> {
+    rewrite_impl(input, output, &[], first, |_| {})
+}
+
+/// Flag de header de cada bloque opcional
+const BLOCK_FLAGS: [(usize, u32); 12] = [
+    (BLOCK_VISION, HeaderFlags::HAS_VISION),
+    (BLOCK_AUDIO, HeaderFlags::HAS_AUDIO),
+    (BLOCK_VIDEO, HeaderFlags::HAS_VIDEO),
+    (BLOCK_SPATIAL_3D, HeaderFlags::HAS_SPATIAL),
+    (BLOCK_PERSONALITY, HeaderFlags::HAS_PERSONALITY),
+    (BLOCK_MEMORY, HeaderFlags::HAS_MEMORY),
+    (BLOCK_CORTEX, HeaderFlags::HAS_CORTEX),
+    (BLOCK_CODE_EXEC, HeaderFlags::HAS_CODE_EXEC),
+    (BLOCK_TOKENIZER, HeaderFlags::HAS_TOKENIZER),
+    (BLOCK_EXEC_HINTS_BIN, HeaderFlags::HAS_EXEC_HINTS_BIN),
+    (BLOCK_TOOLS, HeaderFlags::HAS_TOOLS),
+    (BLOCK_EXPERT_ROUTER, HeaderFlags::HAS_EXPERT_ROUTER),
+];
+
+/// Qué quitó --prune
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    /// Bloques con flag activo pero tamaño 0 (flag quitado)
+    pub cleared_blocks: Vec<&'static str>,
+    /// Bytes de hueco entre bloques (y antes del manifest) más allá de la
+    /// alineación de 32
+    pub gap_bytes: u64,
+    pub input_size: u64,
+    pub output_size: u64,
+}
+
+/// Copia mínima de `input`: sin flags de bloques vacíos ni huecos entre
+/// bloques; offsets, checksums y manifest recalculados por el writer.
+pub fn prune_blocks(input: &Path, output: &Path) -> Result<PruneReport> {
+    if input == output {
+        anyhow::bail!("--prune cannot rewrite {} in place", input.display());
+    }
+    let source = HnfReader::open(input)?;
+    let mut report = PruneReport {
+        input_size: source.header.file_size,
+        ..Default::default()
+    };
+    
+    for (block_id, flag) in BLOCK_FLAGS {
+        if source.header.flags.has(flag) && source.block_table.entries[block_id].is_empty() {
+            report.cleared_blocks.push(BLOCK_NAMES[block_id]);
+        }
+    }
+    
+    let mut used: Vec<&BlockEntry> = source.block_table.entries.iter().filter(|e| !e.is_empty()).collect();
+    used.sort_by_key(|e| e.offset);
+    let mut end = source.header.block_table_offset + BLOCK_COUNT as u64 * 32;
+    for entry in used {
+        report.gap_bytes += entry.offset.saturating_sub(end.next_multiple_of(32));
+        end = entry.offset + entry.size;
+    }
+    report.gap_bytes += source.header.manifest_offset.saturating_sub(end.next_multiple_of(32));
+    
+    rewrite_impl(input, output, &[], &[], |_| {})?;
+    report.output_size = std::fs::metadata(output)?.len();
+    Ok(report)
+}
+
+fn rewrite_impl(
+    input: &Path,
+    output: &Path,
+    replacements: &[(usize, Vec<u8>)],
+    first: &[usize],
+    patch_manifest: impl FnOnce(&mut Value),
+) -> Result<()> {
+    let source = HnfReader::open(input)?;
+    let mut writer = HnfWriter::create(output)?;
+    writer.set_version_minor(source.header.version_minor.min(VERSION_MINOR))?;
+    writer.set_checksum_algo(ChecksumAlgo::from_flags(source.header.flags))?;
+    
+    // Orden físico del origen; los bloques nuevos van al final
+    let mut order: Vec<usize> = (0..BLOCK_COUNT as usize)
+        .filter(|&i| !source.block_table.entries[i].is_empty())
+        .collect();
+    order.sort_by_key(|&i| source.block_table.entries[i].offset);
+    for (id, _) in replacements {
+        if !order.contains(id) {
+            order.push(*id);
+        }
+    }
+    
+    // Adelantar los pedidos (estable: el resto mantiene su orden relativo)
+    order.sort_by_key(|id| first.iter().position(|f| f == id).unwrap_or(first.len()));
+    
+    for block_id in order {
+        match replacements.iter().find(|(id, _)| *id == block_id) {
+            Some((_, data)) if data.is_empty() => {}
+            Some((_, data)) => writer.write_block(block_id, data)?,
+            None => {
+                let entry = &source.block_table.entries[block_id];
+                writer.copy_block(
+                    block_id,
+                    source.block_bytes(block_id),
+                    entry.offset,
+                    source.block_tensors(block_id),
+                )?;
+            }
+        }
+    }
+    
+    // Los flags de bloque los recalcula finalize(); solo se arrastran los de modelo
+    writer.set_flags(source.header.flags.0 & HeaderFlags::IS_MOE);
+    
+    let mut manifest = source.manifest.clone();
+    patch_manifest(&mut manifest);
+    writer.finalize(manifest)
+}
@@ -5,32 +5,181 @@
 
 use std::fs::File;
 use std::io::{BufWriter, Write, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
-use xxhash_rust::xxh3::{xxh3_64, Xxh3};
-
+use super::checksum::{BlockHasher, ChecksumAlgo};
 use super::header::*;
+use crate::htf::{HTFWriter, HTF_DOMAIN_ENTRY_SIZE, HTF_ENTRY_RESERVED, HTF_HEADER_RESERVED, HTF_HEADER_SIZE, HTF_MAGIC, HTF_MAGIC_V13};
+use crate::hints::build_execution_hints_binary;
+
+/// Bloques sin estructura propia en el manifest: write_raw_block acepta
+/// bytes arbitrarios en ellos
+pub const RAW_BLOCKS: [usize; 6] = [
+    BLOCK_PERSONALITY, BLOCK_MEMORY, BLOCK_TOOLS, BLOCK_EXPERT_ROUTER, BLOCK_RESERVED_0, BLOCK_RESERVED_1,
+];
+
+/// Model card (markdown/texto de licencia y uso): primer bloque reservado.
+/// 0xD ya es expert_router en header.rs.
+pub const BLOCK_MODEL_CARD: usize = BLOCK_RESERVED_0;
+/// Tope del model card (1 MB)
+pub const MODEL_CARD_MAX_SIZE: usize = 1024 * 1024;
+
+/// Writer que cuenta los bytes que pasan por él
+struct CountingWriter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+    
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Bytes de un bloque camino del archivo: checksum total y por segmentos al vuelo
+struct BlockSink<W> {
+    inner: W,
+    hasher: BlockHasher,
+    segments: SegmentHasher,
+    size: u64,
+}
+
+impl<W> BlockSink<W> {
+    fn new(inner: W, algo: ChecksumAlgo) -> Self {
+        Self { inner, hasher: BlockHasher::new(algo), segments: SegmentHasher::new(algo), size: 0 }
+    }
+}
+
+impl<W: Write> Write for BlockSink<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.segments.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+    
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
 
 /// Información de un tensor para el manifest
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TensorManifest {
     pub name: String,
     pub dtype: String,
     pub shape: Vec<usize>,
     pub offset: u64,
     pub size: u64,
+    #[serde(default)]
     pub numel: usize,
+    /// Comparte offset/size con este tensor (mismo almacenamiento en la fuente)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<String>,
+    /// Nombre original sin mapear (--keep-unmapped): fuera del diccionario canónico
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub non_canonical: bool,
+}
+
+/// Checksum por segmentos de CHECKSUM_SEGMENT_SIZE.
+/// Permite al validador localizar dónde diverge un bloque corrupto.
+struct SegmentHasher {
+    hasher: BlockHasher,
+    filled: usize,
+    digests: Vec<u64>,
+}
+
+impl SegmentHasher {
+    fn new(algo: ChecksumAlgo) -> Self {
+        Self { hasher: BlockHasher::new(algo), filled: 0, digests: Vec::new() }
+    }
+    
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let take = (CHECKSUM_SEGMENT_SIZE - self.filled).min(data.len());
+            self.hasher.update(&data[..take]);
+            self.filled += take;
+            data = &data[take..];
+            
+            if self.filled == CHECKSUM_SEGMENT_SIZE {
+                self.digests.push(self.hasher.digest());
+                self.hasher.reset();
+                self.filled = 0;
+            }
+        }
+    }
+    
+    fn finish(mut self) -> Vec<u64> {
+        if self.filled > 0 {
+            self.digests.push(self.hasher.digest());
+        }
+        self.digests
+    }
+}
+
+/// Header serializado con los campos reservados a RESERVED_FILL, sea cual
+/// sea el valor de `header.reserved` (p.ej. copiado de un archivo externo)
+fn sealed_header_bytes(header: &HnfHeader) -> Vec<u8> {
+    let mut bytes = header.to_bytes();
+    bytes[HEADER_RESERVED].fill(RESERVED_FILL);
+    bytes
+}
+
+/// Único camino por el que header y block table llegan al disco
+fn write_header_and_table(file: &mut impl Write, header: &HnfHeader, block_table: &BlockTable) -> Result<()> {
+    file.write_all(&sealed_header_bytes(header))?;
+    file.write_all(&block_table.to_bytes())?;
+    Ok(())
+}
+
+/// Los reservados del HTF van dentro de su checksum: aquí no se pueden
+/// rellenar, solo rechazar. Blobs que no son HTF se dejan pasar.
+fn check_htf_reserved(htf: &[u8]) -> Result<()> {
+    if htf.len() < HTF_HEADER_SIZE || (&htf[0..4] != HTF_MAGIC && &htf[0..4] != HTF_MAGIC_V13) {
+        return Ok(());
+    }
+    let dirty = |range: std::ops::Range<usize>| htf.get(range).is_some_and(|b| b.iter().any(|&x| x != RESERVED_FILL));
+    if dirty(HTF_HEADER_RESERVED) {
+        anyhow::bail!("HTF header reserved bytes [9:16] are not zero");
+    }
+    for idx in 0..htf[8] as usize {
+        let entry = HTF_HEADER_SIZE + idx * HTF_DOMAIN_ENTRY_SIZE;
+        if dirty(entry + HTF_ENTRY_RESERVED.start..entry + HTF_ENTRY_RESERVED.end) {
+            anyhow::bail!("HTF domain {} reserved bytes [2:4] are not zero", idx);
+        }
+    }
+    Ok(())
 }
 
 /// Builder para archivos HNFv9
 pub struct HnfWriter {
-    file: BufWriter<File>,
+    /// None tras discard(): el archivo ya está cerrado y borrado
+    file: Option<BufWriter<File>>,
     header: HnfHeader,
     block_table: BlockTable,
     current_offset: u64,
     tensor_manifests: Vec<Vec<TensorManifest>>,  // Por bloque
-    block_hashers: Vec<Option<Xxh3>>,  // Hasher incremental por bloque
+    block_hashers: Vec<Option<BlockHasher>>,  // Hasher incremental por bloque
+    segment_hashers: Vec<Option<SegmentHasher>>,
+    block_segments: Vec<Vec<u64>>,     // Hashes por segmento de bloques cerrados
+    checksum_algo: ChecksumAlgo,
+    path: PathBuf,
+    /// execution_hints escritos (bloque 0xA), para capabilities
+    exec_hints: Option<serde_json::Value>,
 }
 
 impl HnfWriter {
@@ -43,11 +192,8 @@ impl HnfWriter {
         let header = HnfHeader::default();
         let block_table = BlockTable::default();
         
-        // Escribir header placeholder
-        file.write_all(&header.to_bytes())?;
-        
-        // Escribir block table placeholder
-        file.write_all(&block_table.to_bytes())?;
+        // Escribir header + block table placeholder
+        write_header_and_table(&mut file, &header, &block_table)?;
         
         // Offset actual: después de header + block table
         let current_offset = HEADER_SIZE as u64 + 512;
@@ -57,24 +203,91 @@ impl HnfWriter {
         
         // Inicializar hashers como None
         let block_hashers = (0..16).map(|_| None).collect();
+        let segment_hashers = (0..16).map(|_| None).collect();
+        let block_segments = (0..16).map(|_| Vec::new()).collect();
         
         Ok(Self {
-            file,
+            file: Some(file),
             header,
             block_table,
             current_offset,
             tensor_manifests,
             block_hashers,
+            segment_hashers,
+            block_segments,
+            checksum_algo: ChecksumAlgo::default(),
+            path: path.as_ref().to_path_buf(),
+            exec_hints: None,
         })
     }
     
+    /// Borra el archivo a medio escribir (conversión cancelada o fallida).
+    /// El writer queda inutilizable: finalize devuelve error.
+    pub fn discard(&mut self) -> Result<()> {
+        if let Some(file) = self.file.take() {
+            // Cerrar antes de borrar (Windows no borra archivos abiertos);
+            // into_parts descarta el buffer pendiente sin escribirlo
+            drop(file.into_parts());
+            if self.path.exists() {
+                std::fs::remove_file(&self.path)
+                    .with_context(|| format!("Cannot remove {}", self.path.display()))?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Archivo de salida abierto; error si el writer fue descartado
+    fn out(&mut self) -> Result<&mut BufWriter<File>> {
+        let path = &self.path;
+        self.file.as_mut().with_context(|| format!("{} was discarded", path.display()))
+    }
+    
+    /// Selecciona el algoritmo de checksum de bloques (antes de escribir datos)
+    pub fn set_checksum_algo(&mut self, algo: ChecksumAlgo) -> Result<()> {
+        if self.block_table.entries.iter().any(|e| e.size > 0) {
+            anyhow::bail!("Checksum algorithm must be set before writing any block");
+        }
+        if algo != ChecksumAlgo::Xxh3 && self.header.version_minor < MINOR_BLAKE3 {
+            anyhow::bail!(
+                "{} checksums need HNF {}.{} (targeting {}.{})",
+                algo.name(), VERSION_MAJOR, MINOR_BLAKE3, VERSION_MAJOR, self.header.version_minor
+            );
+        }
+        self.checksum_algo = algo;
+        Ok(())
+    }
+    
+    /// --hnf-version: versión menor del header. Las features posteriores
+    /// (checksum_segments, BLAKE3, bloque 0xB) se omiten o se rechazan.
+    pub fn set_version_minor(&mut self, minor: u16) -> Result<()> {
+        if minor > VERSION_MINOR {
+            anyhow::bail!("HNF {}.{} is newer than this writer ({}.{})", VERSION_MAJOR, minor, VERSION_MAJOR, VERSION_MINOR);
+        }
+        if self.block_table.entries.iter().any(|e| e.size > 0) {
+            anyhow::bail!("HNF version must be set before writing any block");
+        }
+        self.header.version_minor = minor;
+        Ok(())
+    }
+    
+    /// Falla si el bloque no existe en la versión destino
+    fn check_block_supported(&self, block_id: usize) -> Result<()> {
+        if block_id == BLOCK_EXEC_HINTS_BIN && self.header.version_minor < MINOR_EXEC_HINTS_BIN {
+            anyhow::bail!(
+                "Block 0xB (binary hints) needs HNF {}.{} (targeting {}.{})",
+                VERSION_MAJOR, MINOR_EXEC_HINTS_BIN, VERSION_MAJOR, self.header.version_minor
+            );
+        }
+        Ok(())
+    }
+    
     /// Alinea el offset actual a múltiplo de 32
     fn align_32(&mut self) -> Result<()> {
         let remainder = self.current_offset % 32;
         if remainder != 0 {
             let padding = 32 - remainder;
             let zeros = vec![0u8; padding as usize];
-            self.file.write_all(&zeros)?;
+            self.out()?.write_all(&zeros)?;
             self.current_offset += padding;
         }
         Ok(())
@@ -82,9 +295,27 @@ impl HnfWriter {
     
     /// Escribe datos de un bloque
     pub fn write_block(&mut self, block_id: usize, data: &[u8]) -> Result<()> {
+        self.write_block_with(block_id, |out| out.write_all(data))?;
+        
+        // Hints por cualquier camino (write_execution_hints, rewrite, merge)
+        if block_id == BLOCK_EXEC_HINTS {
+            self.exec_hints = serde_json::from_slice(data).ok();
+        }
+        
+        Ok(())
+    }
+    
+    /// Escribe un bloque emitido en streaming por `emit`: checksums y block
+    /// table se calculan sobre la marcha. Devuelve el tamaño del bloque.
+    fn write_block_with(
+        &mut self,
+        block_id: usize,
+        emit: impl FnOnce(&mut dyn Write) -> std::io::Result<()>,
+    ) -> Result<u64> {
         if block_id >= 16 {
             anyhow::bail!("Invalid block_id: {}", block_id);
         }
+        self.check_block_supported(block_id)?;
         
         // Alinear
         self.align_32()?;
@@ -92,21 +323,56 @@ impl HnfWriter {
         // Guardar offset inicial
         let block_offset = self.current_offset;
         
-        // Escribir datos
-        self.file.write_all(data)?;
-        
-        // Calcular checksum XXH3-64
-        let checksum = xxh3_64(data);
+        // Escribir datos (checksum total + por segmentos)
+        let algo = self.checksum_algo;
+        let mut sink = BlockSink::new(self.out()?, algo);
+        emit(&mut sink)?;
+        let BlockSink { hasher, segments, size, .. } = sink;
+        self.block_segments[block_id] = segments.finish();
         
         // Actualizar block table
         self.block_table.entries[block_id].offset = block_offset;
-        self.block_table.entries[block_id].size = data.len() as u64;
-        self.block_table.entries[block_id].checksum = checksum;
+        self.block_table.entries[block_id].size = size;
+        self.block_table.entries[block_id].checksum = hasher.digest();
         
         // Actualizar offset
-        self.current_offset += data.len() as u64;
-        
-        Ok(())
+        self.current_offset += size;
+        Ok(size)
+    }
+    
+    /// Escribe bytes arbitrarios en un bloque libre (RAW_BLOCKS: personality,
+    /// memory, tools, expert_router y reservados). Los bloques con estructura
+    /// (tensores, tokenizer, hints) tienen su propio writer; para saltarse
+    /// esa comprobación está write_raw_block_forced.
+    pub fn write_raw_block(&mut self, block_id: usize, data: &[u8]) -> Result<()> {
+        if block_id < 16 && !RAW_BLOCKS.contains(&block_id) {
+            anyhow::bail!(
+                "Block 0x{:X} ({}) has its own structure: use its writer or write_raw_block_forced",
+                block_id, BLOCK_NAMES[block_id]
+            );
+        }
+        self.write_raw_block_forced(block_id, data)
+    }
+    
+    /// write_raw_block en cualquier bloque (prototipos); solo impide
+    /// escribir dos veces el mismo
+    pub fn write_raw_block_forced(&mut self, block_id: usize, data: &[u8]) -> Result<()> {
+        if self.block_table.entries.get(block_id).is_some_and(|e| e.size > 0) {
+            anyhow::bail!("Block 0x{:X} already written", block_id);
+        }
+        self.write_block(block_id, data)
+    }
+    
+    /// Guarda el model card (UTF-8, hasta MODEL_CARD_MAX_SIZE) en BLOCK_MODEL_CARD
+    pub fn write_model_card(&mut self, card: &[u8]) -> Result<()> {
+        if card.len() > MODEL_CARD_MAX_SIZE {
+            anyhow::bail!(
+                "Model card is {} bytes, the limit is {} bytes (1 MB): trim it or link to the full text",
+                card.len(), MODEL_CARD_MAX_SIZE
+            );
+        }
+        std::str::from_utf8(card).context("Model card must be UTF-8 text (markdown or plain text)")?;
+        self.write_raw_block(BLOCK_MODEL_CARD, card)
     }
     
     /// Escribe un tensor cuantizado a un bloque específico
@@ -126,19 +392,23 @@ impl HnfWriter {
         if self.block_table.entries[block_id].size == 0 {
             self.align_32()?;
             self.block_table.entries[block_id].offset = self.current_offset;
-            self.block_hashers[block_id] = Some(Xxh3::new());
+            self.block_hashers[block_id] = Some(BlockHasher::new(self.checksum_algo));
+            self.segment_hashers[block_id] = Some(SegmentHasher::new(self.checksum_algo));
         }
         
         let tensor_offset = self.current_offset;
         
         // Escribir datos
-        self.file.write_all(data)?;
+        self.out()?.write_all(data)?;
         self.current_offset += data.len() as u64;
         
         // Actualizar hasher incremental
         if let Some(ref mut hasher) = self.block_hashers[block_id] {
             hasher.update(data);
         }
+        if let Some(ref mut segments) = self.segment_hashers[block_id] {
+            segments.update(data);
+        }
         
         // Actualizar size del bloque
         self.block_table.entries[block_id].size = 
@@ -153,11 +423,48 @@ impl HnfWriter {
             offset: tensor_offset,
             size: data.len() as u64,
             numel,
+            alias_of: None,
+            non_canonical: false,
         });
         
         Ok(())
     }
     
+    /// Escribe un tensor sin mapear con su nombre original, marcado como
+    /// no canónico en el manifest (el diccionario no lo valida)
+    pub fn write_extra_tensor(
+        &mut self,
+        block_id: usize,
+        name: &str,
+        dtype: &str,
+        shape: &[usize],
+        data: &[u8],
+    ) -> Result<()> {
+        self.write_tensor(block_id, name, dtype, shape, data)?;
+        if let Some(t) = self.tensor_manifests[block_id].last_mut() {
+            t.non_canonical = true;
+        }
+        Ok(())
+    }
+    
+    /// Registra `name` como alias de un tensor ya escrito en el mismo bloque
+    /// (misma región de bytes, sin duplicar datos)
+    pub fn write_alias(&mut self, block_id: usize, name: &str, target: &str, shape: &[usize]) -> Result<()> {
+        let original = self.tensor_manifests.get(block_id)
+            .and_then(|tensors| tensors.iter().find(|t| t.name == target))
+            .ok_or_else(|| anyhow::anyhow!("Alias target '{}' not written in block {}", target, block_id))?;
+        
+        let alias = TensorManifest {
+            name: name.to_string(),
+            shape: shape.to_vec(),
+            numel: shape.iter().product(),
+            alias_of: Some(target.to_string()),
+            ..original.clone()
+        };
+        self.tensor_manifests[block_id].push(alias);
+        Ok(())
+    }
+    
     /// Finaliza un bloque (calcula checksum con el hasher incremental)
     pub fn finalize_block(&mut self, block_id: usize) -> Result<()> {
         if block_id >= 16 {
@@ -174,10 +481,38 @@ impl HnfWriter {
             let checksum = hasher.digest();
             self.block_table.entries[block_id].checksum = checksum;
         }
+        if let Some(segments) = self.segment_hashers[block_id].take() {
+            self.block_segments[block_id] = segments.finish();
+        }
+        
+        Ok(())
+    }
+    
+    /// Copia un bloque ya construido (de otro HNF) rebasando sus tensores.
+    /// `old_offset` es el offset del bloque en el archivo de origen.
+    pub fn copy_block(
+        &mut self,
+        block_id: usize,
+        data: &[u8],
+        old_offset: u64,
+        tensors: Vec<TensorManifest>,
+    ) -> Result<()> {
+        self.write_block(block_id, data)?;
+        let new_offset = self.block_table.entries[block_id].offset;
         
+        for mut t in tensors {
+            t.offset = t.offset - old_offset + new_offset;
+            t.numel = t.shape.iter().product();
+            self.tensor_manifests[block_id].push(t);
+        }
         Ok(())
     }
     
+    /// Añade flags de header que no se derivan de los bloques (IS_MOE...)
+    pub fn set_flags(&mut self, flags: u32) {
+        self.header.flags.set(flags);
+    }
+    
     /// Escribe execution_hints (bloque 0xA)
     pub fn write_execution_hints(&mut self, hints: &serde_json::Value) -> Result<()> {
         let json = serde_json::to_vec(hints)?;
@@ -185,62 +520,36 @@ impl HnfWriter {
         Ok(())
     }
     
+    /// Escribe los execution_hints binarios (bloque 0xB) generados desde el
+    /// JSON; exige HNF 9.1+ (ver supports_exec_hints_bin)
+    pub fn write_execution_hints_binary(&mut self, hints: &serde_json::Value) -> Result<()> {
+        self.write_block(BLOCK_EXEC_HINTS_BIN, &build_execution_hints_binary(hints))
+    }
+    
+    /// La versión destino tiene bloque 0xB (no lo tiene con --hnf-version 9.0)
+    pub fn supports_exec_hints_bin(&self) -> bool {
+        self.check_block_supported(BLOCK_EXEC_HINTS_BIN).is_ok()
+    }
+    
     /// Escribe tokenizer HTF (bloque 0x9 - BLOCK_TOKENIZER)
+    ///
+    /// El checksum de la block table se calcula sobre estos bytes exactos y es
+    /// independiente del checksum interno HTF (header[24..32]).
     pub fn write_tokenizer(&mut self, htf_data: &[u8]) -> Result<()> {
+        check_htf_reserved(htf_data)?;
         self.write_block(BLOCK_TOKENIZER, htf_data)?;
         Ok(())
     }
     
-    /// Finaliza el archivo escribiendo manifest y actualizando header
-    pub fn finalize(mut self, mut manifest: serde_json::Value) -> Result<()> {
-        // Alinear antes del manifest
-        self.align_32()?;
-        
-        // Construir lista de tensores para el manifest
-        let tensor_list: Vec<serde_json::Value> = self.tensor_manifests
-            .iter()
-            .enumerate()
-            .flat_map(|(block_id, tensors)| {
-                let block_name = BLOCK_NAMES[block_id];
-                tensors.iter().map(move |t| serde_json::json!({
-                    "name": t.name,
-                    "block": block_name,
-                    "offset": t.offset,
-                    "size": t.size,
-                    "dtype": t.dtype,
-                    "shape": t.shape,
-                }))
-            })
-            .collect();
-        
-        // Añadir tensores al manifest
-        if let Some(obj) = manifest.as_object_mut() {
-            obj.insert("tensors".to_string(), serde_json::Value::Array(tensor_list));
-        }
-        
-        // Escribir manifest
-        let manifest_offset = self.current_offset;
-        let manifest_bytes = serde_json::to_vec_pretty(&manifest)?;
-        self.file.write_all(&manifest_bytes)?;
-        let manifest_size = manifest_bytes.len() as u64;
-        
-        // Calcular tamaño total
-        let file_size = self.current_offset + manifest_size;
-        
-        // Calcular CRC32 (simplificado - sobre header + block table)
-        let checksum = {
-            let mut data = self.header.to_bytes();
-            data.extend(self.block_table.to_bytes());
-            crc32fast::hash(&data)
-        };
-        
-        // Actualizar header
-        self.header.manifest_offset = manifest_offset;
-        self.header.manifest_size = manifest_size;
-        self.header.file_size = file_size;
-        self.header.checksum = checksum;
-        
-        // Actualizar flags basado en bloques no vacíos
+    /// Escribe el HTF de `htf` directamente en el bloque 0x9, sin ensamblar
+    /// el blob en memoria (HTFWriter ya deja los reservados a cero).
+    /// Devuelve el tamaño escrito.
+    pub fn write_tokenizer_from(&mut self, htf: &HTFWriter) -> Result<u64> {
+        self.write_block_with(BLOCK_TOKENIZER, |out| htf.build_to(out).map(|_| ()))
+    }
+    
+    /// Flags de header según los bloques no vacíos
+    fn derive_block_flags(&mut self) {
         if self.block_table.entries[BLOCK_VISION].size > 0 {
             self.header.flags.set(HeaderFlags::HAS_VISION);
             self.header.flags.set(HeaderFlags::IS_MULTIMODAL);
@@ -269,22 +578,180 @@ impl HnfWriter {
         if self.block_table.entries[BLOCK_CODE_EXEC].size > 0 {
             self.header.flags.set(HeaderFlags::HAS_CODE_EXEC);
         }
+        if self.block_table.entries[BLOCK_EXEC_HINTS_BIN].size > 0 {
+            self.header.flags.set(HeaderFlags::HAS_EXEC_HINTS_BIN);
+        }
         if self.block_table.entries[BLOCK_TOOLS].size > 0 {
             self.header.flags.set(HeaderFlags::HAS_TOOLS);
         }
         if self.block_table.entries[BLOCK_EXPERT_ROUTER].size > 0 {
             self.header.flags.set(HeaderFlags::HAS_EXPERT_ROUTER);
         }
+    }
+    
+    /// Resumen para que un engine decida de antemano si puede cargar el
+    /// archivo: formatos de cuantización, arquitectura y features presentes
+    fn capabilities(&self) -> serde_json::Value {
+        let flags = self.header.flags;
+        let quant_formats: std::collections::BTreeSet<&str> = self.tensor_manifests.iter()
+            .flatten()
+            .filter(|t| t.alias_of.is_none())
+            .map(|t| t.dtype.as_str())
+            .collect();
+        
+        // Modelo de lenguaje principal: text, si no code o cortex
+        let hints = self.exec_hints.as_ref();
+        let lm = ["text", "code", "cortex"].iter()
+            .find_map(|key| hints.and_then(|h| h.get(*key)).filter(|v| v.is_object()));
+        let field = |key: &str| lm.and_then(|h| h.get(key)).cloned().unwrap_or(serde_json::Value::Null);
+        let moe_hint = ["text", "code", "cortex"].iter()
+            .any(|key| hints.and_then(|h| h.get(*key)).and_then(|v| v.get("moe_enabled")).and_then(|v| v.as_bool()) == Some(true));
+        
+        serde_json::json!({
+            "hnf_version": format!("{}.{}", self.header.version_major, self.header.version_minor),
+            "hnf_minor": self.header.version_minor,
+            "quant_formats": quant_formats,
+            "arch": field("arch"),
+            "rope_type": field("rope_type"),
+            "attention_type": field("attention_type"),
+            "moe": flags.has(HeaderFlags::IS_MOE) || moe_hint,
+            "multimodal": flags.has(HeaderFlags::IS_MULTIMODAL),
+            "tools": flags.has(HeaderFlags::HAS_TOOLS),
+            "code": flags.has(HeaderFlags::HAS_CODE_EXEC),
+            "cortex": flags.has(HeaderFlags::HAS_CORTEX),
+        })
+    }
+    
+    /// Finaliza el archivo escribiendo manifest y actualizando header
+    pub fn finalize(mut self, mut manifest: serde_json::Value) -> Result<()> {
+        if self.file.is_none() {
+            anyhow::bail!("{} was discarded", self.path.display());
+        }
+        
+        // Alinear antes del manifest
+        self.align_32()?;
+        
+        // Construir lista de tensores para el manifest
+        let tensor_list: Vec<serde_json::Value> = self.tensor_manifests
+            .iter()
+            .enumerate()
+            .flat_map(|(block_id, tensors)| {
+                let block_name = BLOCK_NAMES[block_id];
+                tensors.iter().map(move |t| {
+                    let mut entry = serde_json::json!({
+                        "name": t.name,
+                        "block": block_name,
+                        "offset": t.offset,
+                        "size": t.size,
+                        "dtype": t.dtype,
+                        "shape": t.shape,
+                    });
+                    if let Some(target) = &t.alias_of {
+                        entry["alias_of"] = serde_json::json!(target);
+                    }
+                    if t.non_canonical {
+                        entry["non_canonical"] = serde_json::json!(true);
+                    }
+                    entry
+                })
+            })
+            .collect();
+        
+        // Almacenamiento compartido: alias → tensor que guarda los bytes
+        let aliases: serde_json::Map<String, serde_json::Value> = self.tensor_manifests
+            .iter()
+            .flatten()
+            .filter_map(|t| t.alias_of.as_ref().map(|target| (t.name.clone(), serde_json::json!(target))))
+            .collect();
+        
+        // Bloques de tensores que no pasaron por finalize_block
+        for block_id in 0..16 {
+            self.finalize_block(block_id)?;
+        }
+        
+        // Hashes por segmento, indexados por número de bloque
+        let segment_map: serde_json::Map<String, serde_json::Value> = self.block_segments
+            .iter()
+            .enumerate()
+            .filter(|(block_id, segs)| !segs.is_empty() && self.block_table.entries[*block_id].size > 0)
+            .map(|(block_id, segs)| {
+                let hex: Vec<String> = segs.iter().map(|h| format!("{:016x}", h)).collect();
+                (block_id.to_string(), serde_json::json!(hex))
+            })
+            .collect();
+        
+        // Flags antes del manifest: capabilities los resume
+        self.derive_block_flags();
+        let capabilities = self.capabilities();
+        
+        // Añadir tensores al manifest
+        if let Some(obj) = manifest.as_object_mut() {
+            obj.insert("tensors".to_string(), serde_json::Value::Array(tensor_list));
+            obj.insert("capabilities".to_string(), capabilities);
+            if aliases.is_empty() {
+                obj.remove("aliases");
+            } else {
+                obj.insert("aliases".to_string(), serde_json::Value::Object(aliases));
+            }
+            if self.header.version_minor >= MINOR_CHECKSUM_SEGMENTS {
+                obj.insert("checksum_segments".to_string(), serde_json::json!({
+                    "algorithm": self.checksum_algo.name(),
+                    "segment_size": CHECKSUM_SEGMENT_SIZE,
+                    "blocks": segment_map,
+                }));
+            } else {
+                // Manifest copiado de un HNF más nuevo (rewrite/merge)
+                obj.remove("checksum_segments");
+            }
+        }
         
-        // Reescribir header al inicio
-        self.file.seek(SeekFrom::Start(0))?;
-        self.file.write_all(&self.header.to_bytes())?;
+        // Escribir manifest: se dimensiona, se escribe en streaming (sin copia
+        // en memoria, puede ser enorme) y se exige que ambos tamaños coincidan
+        let manifest_offset = self.current_offset;
+        let mut sizing = CountingWriter::new(std::io::sink());
+        serde_json::to_writer_pretty(&mut sizing, &manifest)?;
+        let manifest_size = sizing.count;
+        
+        let mut counted = CountingWriter::new(self.out()?);
+        serde_json::to_writer_pretty(&mut counted, &manifest)?;
+        if counted.count != manifest_size {
+            anyhow::bail!("Manifest serialization is not deterministic: sized {} bytes, wrote {}",
+                manifest_size, counted.count);
+        }
+        
+        // Calcular tamaño total
+        let file_size = manifest_offset.checked_add(manifest_size)
+            .context("Manifest end does not fit in a u64 offset")?;
         
-        // Reescribir block table
-        self.file.write_all(&self.block_table.to_bytes())?;
+        self.header.flags.set(self.checksum_algo.flag());
+        
+        // Calcular CRC32 (simplificado - sobre header + block table)
+        let checksum = {
+            let mut data = sealed_header_bytes(&self.header);
+            data.extend(self.block_table.to_bytes());
+            crc32fast::hash(&data)
+        };
+        
+        // Actualizar header
+        self.header.manifest_offset = manifest_offset;
+        self.header.manifest_size = manifest_size;
+        self.header.file_size = file_size;
+        self.header.checksum = checksum;
+        
+        // Reescribir header + block table al inicio
+        let mut file = self.file.take().expect("checked at the start of finalize");
+        file.seek(SeekFrom::Start(0))?;
+        write_header_and_table(&mut file, &self.header, &self.block_table)?;
         
         // Flush
-        self.file.flush()?;
+        file.flush()?;
+        
+        // Manifest al final del archivo: el validador lo exige
+        let on_disk = file.get_ref().metadata()?.len();
+        if on_disk != file_size {
+            anyhow::bail!("{}: {} bytes on disk but manifest ends at {} (offset {} + size {})",
+                self.path.display(), on_disk, file_size, manifest_offset, manifest_size);
+        }
         
         Ok(())
     }
@@ -294,3 +761,185 @@ impl HnfWriter {
         &self.tensor_manifests
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htf::HTFWriter;
+    
+    #[test]
+    fn test_reserved_regions_are_zero() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("minimal.hnf");
+        
+        let mut tokenizer = HTFWriter::new_v13();
+        tokenizer.add_text_domain(&[("a".to_string(), 0)].into(), &[], &serde_json::json!({}), true).unwrap();
+        let htf = tokenizer.build();
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        // Un header con basura en reserved (p.ej. copiado de otro archivo) no llega al disco
+        writer.header.reserved = 0xDEAD_BEEF;
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.final_norm.weight", "fp16", &[8], &[1u8; 16]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&serde_json::json!({})).unwrap();
+        writer.write_tokenizer(&htf).unwrap();
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        let data = std::fs::read(&path).unwrap();
+        assert!(data[HEADER_RESERVED].iter().all(|&b| b == RESERVED_FILL));
+        
+        let table = BlockTable::from_bytes(&data[HEADER_SIZE as usize..HEADER_SIZE as usize + 512]).unwrap();
+        for (idx, entry) in table.entries.iter().enumerate().filter(|(_, e)| e.is_empty()) {
+            assert_eq!((entry.offset, entry.checksum), (0, 0), "empty block {}", idx);
+        }
+        
+        let tok = &table.entries[BLOCK_TOKENIZER];
+        let stored = &data[tok.offset as usize..(tok.offset + tok.size) as usize];
+        assert!(stored[HTF_HEADER_RESERVED].iter().all(|&b| b == 0));
+        let entry = &stored[HTF_HEADER_SIZE..HTF_HEADER_SIZE + HTF_DOMAIN_ENTRY_SIZE];
+        assert!(entry[HTF_ENTRY_RESERVED].iter().all(|&b| b == 0));
+        
+        // Un HTF con reservados sucios se rechaza (no se puede parchear sin romper su checksum)
+        let mut dirty = htf.clone();
+        dirty[HTF_HEADER_RESERVED.start] = 0x55;
+        let mut writer = HnfWriter::create(dir.path().join("dirty.hnf")).unwrap();
+        assert!(writer.write_tokenizer(&dirty).is_err());
+    }
+    
+    #[test]
+    fn test_streamed_tokenizer_block_matches_in_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tokenizer = HTFWriter::new_v13();
+        let vocab = (0..3000u32).map(|i| (format!("tok{}", i), i)).collect();
+        tokenizer.add_text_domain(&vocab, &[], &serde_json::json!({}), true).unwrap();
+        
+        let write = |name: &str, streamed: bool| {
+            let path = dir.path().join(name);
+            let mut writer = HnfWriter::create(&path).unwrap();
+            writer.write_tensor(BLOCK_TEXT_MODEL, "text.final_norm.weight", "fp16", &[8], &[1u8; 16]).unwrap();
+            writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+            if streamed {
+                writer.write_tokenizer_from(&tokenizer).unwrap();
+            } else {
+                writer.write_tokenizer(&tokenizer.build()).unwrap();
+            }
+            writer.finalize(serde_json::json!({})).unwrap();
+            crate::hnf::HnfReader::open(&path).unwrap()
+        };
+        let (streamed, in_memory) = (write("streamed.hnf", true), write("in_memory.hnf", false));
+        
+        assert_eq!(streamed.block_bytes(BLOCK_TOKENIZER), &tokenizer.build()[..]);
+        let entry = |reader: &crate::hnf::HnfReader| {
+            let e = &reader.blocks()[BLOCK_TOKENIZER];
+            (e.offset, e.size, e.checksum)
+        };
+        assert_eq!(entry(&streamed), entry(&in_memory));
+        assert_eq!(streamed.header.flags.0, in_memory.header.flags.0);
+    }
+    
+    #[test]
+    fn test_raw_block_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("raw.hnf");
+        let payload: Vec<u8> = (0..100u8).collect();
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.final_norm.weight", "fp16", &[8], &[1u8; 16]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_execution_hints(&serde_json::json!({})).unwrap();
+        writer.write_raw_block(0xD, &payload).unwrap();
+        
+        // Bloques con estructura, repetidos o fuera de rango: rechazados
+        assert!(writer.write_raw_block(BLOCK_TOKENIZER, b"junk").is_err());
+        assert!(writer.write_raw_block(0xD, b"again").is_err());
+        assert!(writer.write_raw_block(16, b"junk").is_err());
+        writer.write_raw_block_forced(BLOCK_CODE_EXEC, b"prototype").unwrap();
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        let reader = crate::hnf::HnfReader::open(&path).unwrap();
+        assert_eq!(reader.block_bytes(0xD), &payload[..]);
+        assert_eq!(reader.block_bytes(BLOCK_CODE_EXEC), b"prototype");
+        let entry = &reader.blocks()[0xD];
+        assert_eq!(entry.offset % 32, 0);
+        assert_eq!(entry.checksum, ChecksumAlgo::Xxh3.digest(&payload));
+    }
+    
+    #[test]
+    fn test_large_manifest_ends_at_eof() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.hnf");
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        for i in 0..2000 {
+            writer.write_tensor(BLOCK_TEXT_MODEL, &format!("layer{}.mlp.up.weight", i), "fp16", &[4], &[0u8; 8]).unwrap();
+        }
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        // Metadata sintética de varios MB además de los tensores
+        let extra: serde_json::Map<String, serde_json::Value> = (0..50_000)
+            .map(|i| (format!("key{:06}", i), serde_json::json!({"value": i, "note": "ñandú ✓"})))
+            .collect();
+        writer.finalize(serde_json::json!({"format": "HNFv9", "extra": extra})).unwrap();
+        
+        let data = std::fs::read(&path).unwrap();
+        let header = HnfHeader::from_bytes(&data).unwrap();
+        assert!(header.manifest_size > 2 * 1024 * 1024);
+        assert_eq!(header.manifest_offset + header.manifest_size, data.len() as u64);
+        assert_eq!(header.file_size, data.len() as u64);
+        let manifest: serde_json::Value = serde_json::from_slice(&data[header.manifest_offset as usize..]).unwrap();
+        assert_eq!(manifest["extra"].as_object().unwrap().len(), 50_000);
+        assert_eq!(manifest["tensors"].as_array().unwrap().len(), 2000);
+    }
+    
+    #[test]
+    fn test_capabilities_of_multimodal_moe() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vlm_moe.hnf");
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.layer0.moe.experts.0.up.weight", "hq4k", &[256], &[0u8; 160]).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "text.final_norm.weight", "fp16", &[8], &[0u8; 16]).unwrap();
+        writer.write_alias(BLOCK_TEXT_MODEL, "text.lm_head.weight", "text.final_norm.weight", &[8]).unwrap();
+        writer.finalize_block(BLOCK_TEXT_MODEL).unwrap();
+        writer.write_tensor(BLOCK_VISION, "vision.patch_embed.weight", "hq5k", &[256], &[0u8; 192]).unwrap();
+        writer.finalize_block(BLOCK_VISION).unwrap();
+        writer.write_execution_hints(&serde_json::json!({
+            "text_enabled": true,
+            "text": {"arch": "qwen2_moe", "rope_type": "yarn", "attention_type": "gqa", "moe_enabled": true},
+            "vision_enabled": true,
+            "vision": {"arch": "siglip", "attention_type": "mha"},
+        })).unwrap();
+        writer.set_flags(HeaderFlags::IS_MOE);
+        writer.finalize(serde_json::json!({})).unwrap();
+        
+        let reader = crate::hnf::HnfReader::open(&path).unwrap();
+        let caps = &reader.manifest()["capabilities"];
+        assert_eq!(caps["quant_formats"], serde_json::json!(["fp16", "hq4k", "hq5k"]));
+        assert_eq!(caps["arch"], "qwen2_moe");
+        assert_eq!(caps["rope_type"], "yarn");
+        assert_eq!(caps["attention_type"], "gqa");
+        assert_eq!(caps["moe"], true);
+        assert_eq!(caps["multimodal"], true);
+        assert_eq!(caps["tools"], false);
+        assert_eq!(caps["code"], false);
+        assert_eq!(caps["cortex"], false);
+        assert_eq!(caps["hnf_minor"], reader.header.version_minor);
+        // El header refleja los mismos flags que capabilities
+        assert!(reader.header.flags.has(HeaderFlags::IS_MULTIMODAL));
+    }
+    
+    #[test]
+    fn test_discard_removes_file_and_blocks_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cancelled.hnf");
+        
+        let mut writer = HnfWriter::create(&path).unwrap();
+        writer.write_tensor(BLOCK_TEXT_MODEL, "w", "fp16", &[4], &[0u8; 8]).unwrap();
+        writer.discard().unwrap();
+        assert!(!path.exists());
+        // Idempotente, y el writer ya no escribe ni recrea el archivo
+        writer.discard().unwrap();
+        assert!(writer.write_tensor(BLOCK_TEXT_MODEL, "x", "fp16", &[4], &[0u8; 8]).is_err());
+        assert!(writer.finalize(serde_json::json!({})).is_err());
+        assert!(!path.exists());
+    }
+}
@@ -11,12 +11,19 @@
 // Tamaños:
 //   HQ4K: 128 header + 128 payload = 256 bytes (1:2 vs FP16)
 //   HQ5K: 128 header + 160 payload = 288 bytes (1:1.78 vs FP16)
+//   HQ6K: 128 header + 192 payload = 320 bytes (1:1.6 vs FP16)
+//
+// v6.2: Super-block configurable (--super-block, múltiplo de GROUP_SIZE):
+//       header = 4 bytes × (super_block / 8), payload = bits × super_block / 8.
+//       El manifest guarda el tamaño; 256 es el layout por defecto.
 //
 // ============================================================================
 
 use half::f16;
 
 pub const SUPER_BLOCK_SIZE: usize = 256;
+/// Límite de --super-block (los índices de un super-block viven en la pila del cuantizador)
+pub const MAX_SUPER_BLOCK_SIZE: usize = 4096;
 pub const GROUP_SIZE: usize = 8;    // NUCLEAR: era 16
 pub const NUM_GROUPS: usize = 32;   // NUCLEAR: era 16
 pub const EPS: f32 = 1e-7;
@@ -30,6 +37,9 @@ pub const HQ4K_BLOCK_SIZE: usize = HEADER_SIZE + HQ4K_PAYLOAD; // 256
 pub const HQ5K_PAYLOAD: usize = 160;  // 5 bits × 256
 pub const HQ5K_BLOCK_SIZE: usize = HEADER_SIZE + HQ5K_PAYLOAD; // 288
 
+pub const HQ6K_PAYLOAD: usize = 192;  // 6 bits × 256
+pub const HQ6K_BLOCK_SIZE: usize = HEADER_SIZE + HQ6K_PAYLOAD; // 320
+
 // HQ3K placeholder (not implemented in v6)
 pub const HQ3K_BLOCK_SIZE: usize = 192;
 
@@ -58,12 +68,38 @@ impl Default for GridSearchResult {
     }
 }
 
+/// --super-block válido: múltiplo de GROUP_SIZE entre GROUP_SIZE y MAX_SUPER_BLOCK_SIZE
+pub fn check_super_block(super_block: usize) -> Result<(), String> {
+    if super_block == 0 || !super_block.is_multiple_of(GROUP_SIZE) || super_block > MAX_SUPER_BLOCK_SIZE {
+        return Err(format!(
+            "super-block size {} must be a multiple of {} between {} and {}",
+            super_block, GROUP_SIZE, GROUP_SIZE, MAX_SUPER_BLOCK_SIZE
+        ));
+    }
+    Ok(())
+}
+
+/// Bytes de header (min/scale FP16 por grupo) de un super-block
+pub fn header_size(super_block: usize) -> usize {
+    super_block / GROUP_SIZE * 4
+}
+
+/// Bytes de un super-block de `bits` bits por elemento
+pub fn superblock_bytes(bits: u8, super_block: usize) -> usize {
+    header_size(super_block) + super_block * bits as usize / 8
+}
+
 pub fn pad_to_superblock(data: &[f32]) -> Vec<f32> {
-    let remainder = data.len() % SUPER_BLOCK_SIZE;
+    pad_to_multiple(data, SUPER_BLOCK_SIZE)
+}
+
+/// Rellena con ceros hasta un múltiplo de `super_block` elementos
+pub fn pad_to_multiple(data: &[f32], super_block: usize) -> Vec<f32> {
+    let remainder = data.len() % super_block;
     if remainder == 0 {
         data.to_vec()
     } else {
-        let padding = SUPER_BLOCK_SIZE - remainder;
+        let padding = super_block - remainder;
         let mut result = data.to_vec();
         result.extend(std::iter::repeat(0.0f32).take(padding));
         result
@@ -91,40 +127,49 @@ pub fn compute_group_params(group: &[f32]) -> GroupParams {
     }
 }
 
-pub fn encode_header(group_params: &[GroupParams; NUM_GROUPS]) -> [u8; HEADER_SIZE] {
-    let mut header = [0u8; HEADER_SIZE];
+/// min de un grupo simétrico: el cero queda en el punto medio del rango
+#[inline]
+pub fn symmetric_min(scale: f32) -> f32 {
+    // Dividir por 2 es exacto en f16 (salvo subnormales), el decoder puede
+    // reconstruir min desde scale sin leerlo
+    f16::from_f32(-scale * 0.5).to_f32()
+}
+
+/// Versión simétrica de compute_group_params: rango [-absmax, +absmax]
+#[inline]
+pub fn compute_group_params_symmetric(group: &[f32]) -> GroupParams {
+    let abs_max = group.iter().fold(0.0f32, |m, &v| m.max(v.abs()));
     
-    for g in 0..NUM_GROUPS {
-        let offset = g * 4;
-        
-        let min_bytes = f16::from_f32(group_params[g].min).to_le_bytes();
-        header[offset] = min_bytes[0];
-        header[offset + 1] = min_bytes[1];
-        
-        let scale_bytes = f16::from_f32(group_params[g].scale).to_le_bytes();
-        header[offset + 2] = scale_bytes[0];
-        header[offset + 3] = scale_bytes[1];
-    }
+    let scale_f16 = f16::from_f32((2.0 * abs_max).max(EPS)).to_f32().max(EPS);
     
-    header
+    GroupParams {
+        min: symmetric_min(scale_f16),
+        scale: scale_f16,
+    }
 }
 
-pub fn decode_header(header: &[u8; HEADER_SIZE]) -> [GroupParams; NUM_GROUPS] {
-    let mut group_params = [GroupParams::default(); NUM_GROUPS];
+pub fn encode_header(group_params: &[GroupParams]) -> Vec<u8> {
+    let mut header = vec![0u8; group_params.len() * 4];
     
-    for g in 0..NUM_GROUPS {
-        let offset = g * 4;
-        
-        let min = f16::from_le_bytes([header[offset], header[offset + 1]]).to_f32();
-        let scale = f16::from_le_bytes([header[offset + 2], header[offset + 3]]).to_f32();
-        
-        group_params[g] = GroupParams {
-            min,
-            scale: scale.max(EPS),
-        };
+    for (entry, params) in header.chunks_exact_mut(4).zip(group_params) {
+        entry[..2].copy_from_slice(&f16::from_f32(params.min).to_le_bytes());
+        entry[2..].copy_from_slice(&f16::from_f32(params.scale).to_le_bytes());
     }
     
-    group_params
+    header
+}
+
+pub fn decode_header(header: &[u8]) -> Vec<GroupParams> {
+    header.chunks_exact(4)
+        .map(|entry| {
+            let min = f16::from_le_bytes([entry[0], entry[1]]).to_f32();
+            let scale = f16::from_le_bytes([entry[2], entry[3]]).to_f32();
+            GroupParams {
+                min,
+                scale: scale.max(EPS),
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -156,5 +201,11 @@ mod tests {
         assert_eq!(HQ4K_BLOCK_SIZE, 256);
         assert_eq!(HQ5K_BLOCK_SIZE, 288);
         assert_eq!(NUM_GROUPS * GROUP_SIZE, SUPER_BLOCK_SIZE);
+        assert_eq!(superblock_bytes(4, SUPER_BLOCK_SIZE), HQ4K_BLOCK_SIZE);
+        assert_eq!(superblock_bytes(5, SUPER_BLOCK_SIZE), HQ5K_BLOCK_SIZE);
+        assert_eq!(superblock_bytes(6, SUPER_BLOCK_SIZE), HQ6K_BLOCK_SIZE);
+        assert!(check_super_block(64).is_ok());
+        assert!(check_super_block(100).is_err());
+        assert!(check_super_block(0).is_err());
     }
 }
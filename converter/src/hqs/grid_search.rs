@@ -2,6 +2,12 @@
 // ============================================================================
 // HQS GRID SEARCH v6 - NUCLEAR
 // ============================================================================
+//
+// v6.2: GridConfig lleva el tamaño de super-block (--super-block)
+// v6.1: Error ponderado por importancia de canal (--calibration-data): el
+//       grid search minimiza Σ wᵢ·(x - x̂)² en vez del MSE plano
+//
+// ============================================================================
 
 use rayon::prelude::*;
 use half::f16;
@@ -10,15 +16,45 @@ use crate::hqs::common::*;
 #[derive(Debug, Clone, Copy)]
 pub struct GridConfig {
     pub bits: u8,
+    /// Sin zero-point: min fijado a -scale/2 (punto medio del bloque)
+    pub symmetric: bool,
+    /// Elementos por super-block (múltiplo de GROUP_SIZE, ver check_super_block)
+    pub super_block: usize,
+}
+
+impl Default for GridConfig {
+    /// Layout por defecto; `bits` lo fija cada formato en quantize_with
+    fn default() -> Self {
+        Self::hq4k()
+    }
 }
 
 impl GridConfig {
     pub fn hq4k() -> Self {
-        Self { bits: 4 }
+        Self { bits: 4, symmetric: false, super_block: SUPER_BLOCK_SIZE }
     }
     
     pub fn hq5k() -> Self {
-        Self { bits: 5 }
+        Self { bits: 5, symmetric: false, super_block: SUPER_BLOCK_SIZE }
+    }
+    
+    pub fn hq6k() -> Self {
+        Self { bits: 6, symmetric: false, super_block: SUPER_BLOCK_SIZE }
+    }
+    
+    pub fn with_symmetric(mut self, symmetric: bool) -> Self {
+        self.symmetric = symmetric;
+        self
+    }
+    
+    pub fn with_bits(mut self, bits: u8) -> Self {
+        self.bits = bits;
+        self
+    }
+    
+    pub fn with_super_block(mut self, super_block: usize) -> Self {
+        self.super_block = super_block;
+        self
     }
     
     #[inline]
@@ -27,9 +63,47 @@ impl GridConfig {
     }
 }
 
+/// Pesos neutros: el error ponderado coincide bit a bit con el MSE plano
+const UNIT_WEIGHTS: [f32; GROUP_SIZE] = [1.0; GROUP_SIZE];
+
+/// Importancia por canal de entrada de un tensor 2D [out, in] aplanado:
+/// el elemento plano k pertenece al canal k % in
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelWeights<'a> {
+    /// Un peso por canal de entrada (media 1)
+    pub channels: &'a [f32],
+    /// Índice plano del primer elemento de los datos (cuantización por trozos)
+    pub offset: usize,
+}
+
+impl ChannelWeights<'_> {
+    /// Pesos de los elementos del super-block `block_idx` (de `super_block` elementos)
+    pub fn superblock(&self, block_idx: usize, super_block: usize) -> Vec<f32> {
+        let start = self.offset + block_idx * super_block;
+        (0..super_block).map(|i| self.channels[(start + i) % self.channels.len()]).collect()
+    }
+}
+
+/// Error cuadrático ponderado de cuantizar `group` con (min, scale)
+#[inline]
+fn weighted_error(group: &[f32], weights: &[f32], min: f32, scale: f32, q_max: f32) -> f32 {
+    group.iter().zip(weights)
+        .map(|(&val, &w)| {
+            let q = ((val - min) / scale * q_max).round().clamp(0.0, q_max);
+            let diff = val - (min + q / q_max * scale);
+            w * diff * diff
+        })
+        .sum()
+}
+
 /// Grid search para grupos de 8 elementos
 /// Con grupos tan pequeños, min/max directo + ±4 ULP debería ser suficiente
 pub fn optimize_group(group: &[f32], config: &GridConfig) -> GroupParams {
+    optimize_group_weighted(group, &UNIT_WEIGHTS[..group.len()], config)
+}
+
+/// optimize_group con un peso por elemento
+pub fn optimize_group_weighted(group: &[f32], weights: &[f32], config: &GridConfig) -> GroupParams {
     let q_max = config.q_max();
     
     // Con solo 8 elementos, min/max directo es óptimo
@@ -62,14 +136,7 @@ pub fn optimize_group(group: &[f32], config: &GridConfig) -> GroupParams {
             let test_scale = f16::from_bits(scale_bits as u16).to_f32();
             if test_scale < EPS { continue; }
             
-            let mut mse = 0.0f32;
-            for &val in group.iter() {
-                let q = ((val - test_min) / test_scale * q_max).round().clamp(0.0, q_max);
-                let recon = test_min + q / q_max * test_scale;
-                let diff = val - recon;
-                mse += diff * diff;
-            }
-            mse /= group.len() as f32;
+            let mse = weighted_error(group, weights, test_min, test_scale, q_max);
             
             if mse < best_mse {
                 best_mse = mse;
@@ -85,36 +152,125 @@ pub fn optimize_group(group: &[f32], config: &GridConfig) -> GroupParams {
     }
 }
 
-pub fn optimize_superblock(
-    block: &[f32; SUPER_BLOCK_SIZE],
+/// Grid search simétrico: solo se busca el scale, min = -scale/2
+/// Rango más amplio que el asimétrico porque hay una sola dimensión
+pub fn optimize_group_symmetric(group: &[f32], config: &GridConfig) -> GroupParams {
+    optimize_group_symmetric_weighted(group, &UNIT_WEIGHTS[..group.len()], config)
+}
+
+/// optimize_group_symmetric con un peso por elemento
+pub fn optimize_group_symmetric_weighted(group: &[f32], weights: &[f32], config: &GridConfig) -> GroupParams {
+    let q_max = config.q_max();
+    
+    let start = compute_group_params_symmetric(group);
+    let scale_f16 = f16::from_f32(start.scale);
+    
+    let mut best_mse = f32::INFINITY;
+    let mut best = start;
+    
+    let search_range: i32 = 8;
+    
+    for scale_delta in -search_range..=search_range {
+        let scale_bits = scale_f16.to_bits() as i32 + scale_delta;
+        if scale_bits <= 0 { continue; }
+        let test_scale = f16::from_bits(scale_bits as u16).to_f32();
+        if test_scale < EPS { continue; }
+        let test_min = symmetric_min(test_scale);
+        
+        let mse = weighted_error(group, weights, test_min, test_scale, q_max);
+        
+        if mse < best_mse {
+            best_mse = mse;
+            best = GroupParams { min: test_min, scale: test_scale };
+        }
+    }
+    
+    best
+}
+
+/// Pesos del grupo `g` (neutros si no hay importancia)
+#[inline]
+fn group_weights(weights: Option<&[f32]>, g: usize) -> &[f32] {
+    match weights {
+        Some(w) => &w[g * GROUP_SIZE..(g + 1) * GROUP_SIZE],
+        None => &UNIT_WEIGHTS,
+    }
+}
+
+pub fn optimize_superblock(block: &[f32], config: &GridConfig) -> Vec<GroupParams> {
+    optimize_superblock_weighted(block, None, config)
+}
+
+/// optimize_superblock con un peso por elemento
+pub fn optimize_superblock_weighted(
+    block: &[f32],
+    weights: Option<&[f32]>,
     config: &GridConfig,
-) -> [GroupParams; NUM_GROUPS] {
-    let results: Vec<GroupParams> = (0..NUM_GROUPS)
+) -> Vec<GroupParams> {
+    (0..block.len() / GROUP_SIZE)
         .into_par_iter()
         .map(|g| {
-            let start = g * GROUP_SIZE;
-            let end = start + GROUP_SIZE;
-            optimize_group(&block[start..end], config)
+            let group = &block[g * GROUP_SIZE..(g + 1) * GROUP_SIZE];
+            if config.symmetric {
+                optimize_group_symmetric_weighted(group, group_weights(weights, g), config)
+            } else {
+                optimize_group_weighted(group, group_weights(weights, g), config)
+            }
         })
-        .collect();
-    
-    let mut params = [GroupParams::default(); NUM_GROUPS];
-    for (g, p) in results.into_iter().enumerate() {
-        params[g] = p;
-    }
-    
-    params
+        .collect()
 }
 
-pub fn fast_superblock(block: &[f32; SUPER_BLOCK_SIZE]) -> [GroupParams; NUM_GROUPS] {
-    let mut params = [GroupParams::default(); NUM_GROUPS];
-    
-    for g in 0..NUM_GROUPS {
-        let start = g * GROUP_SIZE;
-        let end = start + GROUP_SIZE;
-        params[g] = compute_group_params(&block[start..end]);
+pub fn fast_superblock(block: &[f32]) -> Vec<GroupParams> {
+    block.chunks_exact(GROUP_SIZE).map(compute_group_params).collect()
+}
+
+pub fn fast_superblock_symmetric(block: &[f32]) -> Vec<GroupParams> {
+    block.chunks_exact(GROUP_SIZE).map(compute_group_params_symmetric).collect()
+}
+
+/// Error cuadrático (ponderado) de reconstruir `group` con `params`
+fn group_error(group: &[f32], weights: &[f32], params: &GroupParams, q_max: f32) -> f32 {
+    weighted_error(group, weights, params.min, params.scale, q_max)
+}
+
+/// Parámetros del super-block según modo (MSE/fast × asimétrico/simétrico)
+pub fn superblock_params(
+    block: &[f32],
+    config: &GridConfig,
+    use_mse: bool,
+) -> Vec<GroupParams> {
+    superblock_params_weighted(block, config, use_mse, None)
+}
+
+/// superblock_params con importancia por elemento: solo afecta a la
+/// búsqueda MSE (fast es min/max directo)
+pub fn superblock_params_weighted(
+    block: &[f32],
+    config: &GridConfig,
+    use_mse: bool,
+    weights: Option<&[f32]>,
+) -> Vec<GroupParams> {
+    let fast = if config.symmetric {
+        fast_superblock_symmetric(block)
+    } else {
+        fast_superblock(block)
+    };
+    if !use_mse {
+        return fast;
     }
     
+    // Bloques degenerados (todo igual, rango subnormal) pueden dejar al grid
+    // search peor que min/max directo: por grupo se queda el de menor error,
+    // así MSE nunca pierde contra fast
+    let mut params = optimize_superblock_weighted(block, weights, config);
+    let q_max = config.q_max();
+    for (g, (param, fast_param)) in params.iter_mut().zip(&fast).enumerate() {
+        let group = &block[g * GROUP_SIZE..(g + 1) * GROUP_SIZE];
+        let w = group_weights(weights, g);
+        if group_error(group, w, fast_param, q_max) <= group_error(group, w, param, q_max) {
+            *param = *fast_param;
+        }
+    }
     params
 }
 
@@ -164,4 +320,54 @@ mod tests {
         println!("Fast MSE: {:.6}, Optimized MSE: {:.6}", fast_mse, opt_mse);
         assert!(opt_mse <= fast_mse + 1e-6);
     }
+    
+    #[test]
+    fn test_mse_falls_back_to_fast_on_all_equal_block() {
+        // 0.5 es exacto en f16: fast reconstruye sin error, MSE no puede mejorar
+        let block = [0.5f32; SUPER_BLOCK_SIZE];
+        for config in [GridConfig::hq4k(), GridConfig::hq5k()] {
+            let fast = superblock_params(&block, &config, false);
+            let mse = superblock_params(&block, &config, true);
+            for (m, f) in mse.iter().zip(&fast) {
+                assert_eq!(m.min.to_bits(), f.min.to_bits());
+                assert_eq!(m.scale.to_bits(), f.scale.to_bits());
+            }
+        }
+    }
+    
+    #[test]
+    fn test_channel_importance_changes_chosen_scale() {
+        // Tensor [4, 256]: el canal 5 domina la activación de entrada
+        let cols = SUPER_BLOCK_SIZE;
+        let data: Vec<f32> = (0..4 * cols).map(|i| ((i * 7919 % 1000) as f32 / 500.0 - 1.0) * 0.8).collect();
+        let mut channels = vec![0.5f32; cols];
+        channels[5] = 100.0;
+        let weights = ChannelWeights { channels: &channels, offset: 0 };
+        let config = GridConfig::hq4k();
+        
+        let mut changed = 0;
+        let mut plain_err = 0.0f32;
+        let mut weighted_err = 0.0f32;
+        for (b, chunk) in data.chunks_exact(SUPER_BLOCK_SIZE).enumerate() {
+            let block: &[f32; SUPER_BLOCK_SIZE] = chunk.try_into().unwrap();
+            let block_weights = weights.superblock(b, SUPER_BLOCK_SIZE);
+            let plain = superblock_params(block, &config, true);
+            let weighted = superblock_params_weighted(block, &config, true, Some(block_weights.as_slice()));
+            
+            // Solo el grupo del canal dominante puede cambiar: el resto tiene pesos uniformes
+            let g = 5 / GROUP_SIZE;
+            changed += (plain[g].scale != weighted[g].scale || plain[g].min != weighted[g].min) as usize;
+            let dominant = &block[g * GROUP_SIZE + 5 % GROUP_SIZE..][..1];
+            plain_err += group_error(dominant, &[1.0], &plain[g], config.q_max());
+            weighted_err += group_error(dominant, &[1.0], &weighted[g], config.q_max());
+        }
+        assert!(changed > 0, "importance weighting never changed the chosen params");
+        assert!(weighted_err < plain_err, "dominant channel error {} vs plain {}", weighted_err, plain_err);
+        
+        // Pesos uniformes = MSE plano, bit a bit
+        let uniform = vec![1.0f32; cols];
+        let uniform = ChannelWeights { channels: &uniform, offset: 0 };
+        assert_eq!(crate::hqs::quantize_weighted(&data, crate::hqs::QuantFormat::HQ4K, true, false, Some(uniform)),
+            crate::hqs::quantize(&data, crate::hqs::QuantFormat::HQ4K, true, false));
+    }
 }
@@ -9,19 +9,17 @@ use crate::hqs::grid_search::*;
 
 const Q_MAX: f32 = 15.0;
 
-fn quantize_superblock(block: &[f32; SUPER_BLOCK_SIZE], use_mse: bool) -> Vec<u8> {
-    let config = GridConfig::hq4k();
+fn quantize_superblock(
+    block: &[f32],
+    config: &GridConfig,
+    use_mse: bool,
+    weights: Option<&[f32]>,
+) -> Vec<u8> {
+    let group_params = superblock_params_weighted(block, config, use_mse, weights);
     
-    let group_params = if use_mse {
-        optimize_superblock(block, &config)
-    } else {
-        fast_superblock(block)
-    };
+    let mut q_indices = vec![0u8; block.len()];
     
-    let mut q_indices = [0u8; SUPER_BLOCK_SIZE];
-    
-    for g in 0..NUM_GROUPS {
-        let gp = &group_params[g];
+    for (g, gp) in group_params.iter().enumerate() {
         let start = g * GROUP_SIZE;
         
         for i in 0..GROUP_SIZE {
@@ -31,31 +29,46 @@ fn quantize_superblock(block: &[f32; SUPER_BLOCK_SIZE], use_mse: bool) -> Vec<u8
         }
     }
     
-    let mut output = vec![0u8; HQ4K_BLOCK_SIZE];
-    
-    let header = encode_header(&group_params);
-    output[..HEADER_SIZE].copy_from_slice(&header);
+    let header_len = header_size(block.len());
+    let mut output = vec![0u8; superblock_bytes(4, block.len())];
+    output[..header_len].copy_from_slice(&encode_header(&group_params));
     
-    for i in 0..HQ4K_PAYLOAD {
+    for i in 0..block.len() / 2 {
         let even = q_indices[i * 2] & 0x0F;
         let odd = q_indices[i * 2 + 1] & 0x0F;
-        output[HEADER_SIZE + i] = (even << 4) | odd;
+        output[header_len + i] = (even << 4) | odd;
     }
     
     output
 }
 
 pub fn quantize_hq4k(data: &[f32]) -> Vec<u8> {
-    quantize_hq4k_internal(data, true)
+    quantize_hq4k_internal(data, true, GridConfig::hq4k(), None)
 }
 
 pub fn quantize_hq4k_fast(data: &[f32]) -> Vec<u8> {
-    quantize_hq4k_internal(data, false)
+    quantize_hq4k_internal(data, false, GridConfig::hq4k(), None)
+}
+
+/// Variante simétrica (sin zero-point). Mismo layout: min = -scale/2
+pub fn quantize_hq4k_symmetric(data: &[f32], use_mse: bool) -> Vec<u8> {
+    quantize_hq4k_internal(data, use_mse, GridConfig::hq4k().with_symmetric(true), None)
+}
+
+/// Error del grid search ponderado por importancia de canal (--calibration-data)
+pub fn quantize_hq4k_weighted(data: &[f32], use_mse: bool, symmetric: bool, weights: ChannelWeights) -> Vec<u8> {
+    quantize_hq4k_internal(data, use_mse, GridConfig::hq4k().with_symmetric(symmetric), Some(weights))
 }
 
-fn quantize_hq4k_internal(data: &[f32], use_mse: bool) -> Vec<u8> {
-    let padded = pad_to_superblock(data);
-    let num_blocks = padded.len() / SUPER_BLOCK_SIZE;
+/// Simetría y tamaño de super-block de `grid` (los bits los fija el formato)
+pub fn quantize_hq4k_with(data: &[f32], use_mse: bool, grid: GridConfig, weights: Option<ChannelWeights>) -> Vec<u8> {
+    quantize_hq4k_internal(data, use_mse, grid.with_bits(4), weights)
+}
+
+fn quantize_hq4k_internal(data: &[f32], use_mse: bool, config: GridConfig, weights: Option<ChannelWeights>) -> Vec<u8> {
+    let super_block = config.super_block;
+    let padded = pad_to_multiple(data, super_block);
+    let num_blocks = padded.len() / super_block;
     
     if num_blocks == 0 {
         return Vec::new();
@@ -64,17 +77,15 @@ fn quantize_hq4k_internal(data: &[f32], use_mse: bool) -> Vec<u8> {
     let results: Vec<Vec<u8>> = (0..num_blocks)
         .into_par_iter()
         .map(|b| {
-            let start = b * SUPER_BLOCK_SIZE;
-            let mut block = [0.0f32; SUPER_BLOCK_SIZE];
-            for i in 0..SUPER_BLOCK_SIZE {
-                let val = padded[start + i];
-                block[i] = if val.is_finite() { val } else { 0.0 };
-            }
-            quantize_superblock(&block, use_mse)
+            let block: Vec<f32> = padded[b * super_block..(b + 1) * super_block].iter()
+                .map(|&val| if val.is_finite() { val } else { 0.0 })
+                .collect();
+            let block_weights = weights.map(|w| w.superblock(b, super_block));
+            quantize_superblock(&block, &config, use_mse, block_weights.as_deref())
         })
         .collect();
     
-    let mut output = Vec::with_capacity(num_blocks * HQ4K_BLOCK_SIZE);
+    let mut output = Vec::with_capacity(num_blocks * superblock_bytes(4, super_block));
     for block_data in results {
         output.extend(block_data);
     }
@@ -83,32 +94,33 @@ fn quantize_hq4k_internal(data: &[f32], use_mse: bool) -> Vec<u8> {
 }
 
 pub fn dequantize_hq4k(data: &[u8], numel: usize) -> Vec<f32> {
+    dequantize_hq4k_with(data, numel, SUPER_BLOCK_SIZE)
+}
+
+/// dequantize_hq4k con super-blocks de `super_block` elementos (manifest)
+pub fn dequantize_hq4k_with(data: &[u8], numel: usize, super_block: usize) -> Vec<f32> {
     if data.is_empty() {
         return vec![0.0; numel];
     }
     
-    let num_blocks = data.len() / HQ4K_BLOCK_SIZE;
-    let mut output = Vec::with_capacity(num_blocks * SUPER_BLOCK_SIZE);
+    let block_size = superblock_bytes(4, super_block);
+    let header_len = header_size(super_block);
+    let num_blocks = data.len() / block_size;
+    let mut output = Vec::with_capacity(num_blocks * super_block);
+    let mut q_indices = vec![0u8; super_block];
     
     for b in 0..num_blocks {
-        let block_start = b * HQ4K_BLOCK_SIZE;
-        let header: [u8; HEADER_SIZE] = data[block_start..block_start + HEADER_SIZE]
-            .try_into()
-            .unwrap();
+        let block_start = b * block_size;
+        let group_params = decode_header(&data[block_start..block_start + header_len]);
         
-        let group_params = decode_header(&header);
-        
-        let payload_start = block_start + HEADER_SIZE;
-        let mut q_indices = [0u8; SUPER_BLOCK_SIZE];
-        
-        for i in 0..HQ4K_PAYLOAD {
+        let payload_start = block_start + header_len;
+        for i in 0..super_block / 2 {
             let byte = data[payload_start + i];
             q_indices[i * 2] = (byte >> 4) & 0x0F;
             q_indices[i * 2 + 1] = byte & 0x0F;
         }
         
-        for g in 0..NUM_GROUPS {
-            let gp = &group_params[g];
+        for (g, gp) in group_params.iter().enumerate() {
             let start = g * GROUP_SIZE;
             
             for i in 0..GROUP_SIZE {
@@ -124,8 +136,11 @@ pub fn dequantize_hq4k(data: &[u8], numel: usize) -> Vec<f32> {
 }
 
 pub fn hq4k_size(numel: usize) -> usize {
-    let num_blocks = (numel + SUPER_BLOCK_SIZE - 1) / SUPER_BLOCK_SIZE;
-    num_blocks * HQ4K_BLOCK_SIZE
+    hq4k_size_with(numel, SUPER_BLOCK_SIZE)
+}
+
+pub fn hq4k_size_with(numel: usize, super_block: usize) -> usize {
+    numel.div_ceil(super_block) * superblock_bytes(4, super_block)
 }
 
 pub fn validate_hq4k(data: &[u8], numel: usize) -> Result<(), String> {
@@ -195,4 +210,35 @@ mod tests {
         // Target: <5% con grupos de 8
         assert!(relative_error < 0.05, "Error {:.2}% exceeds 5%", relative_error * 100.0);
     }
+    
+    fn mse(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f32>() / a.len() as f32
+    }
+    
+    #[test]
+    fn test_symmetric_vs_asymmetric_mse() {
+        let mut rng = rand::thread_rng();
+        let centered: Vec<f32> = (0..4096).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let skewed: Vec<f32> = (0..4096).map(|_| rng.gen_range(1.0..3.0)).collect();
+        
+        let asym_centered = mse(&centered, &dequantize_hq4k(&quantize_hq4k(&centered), centered.len()));
+        let sym_centered = mse(&centered, &dequantize_hq4k(&quantize_hq4k_symmetric(&centered, true), centered.len()));
+        let asym_skewed = mse(&skewed, &dequantize_hq4k(&quantize_hq4k(&skewed), skewed.len()));
+        let sym_skewed = mse(&skewed, &dequantize_hq4k(&quantize_hq4k_symmetric(&skewed, true), skewed.len()));
+        
+        println!("Centered: asym {:.3e}  sym {:.3e}", asym_centered, sym_centered);
+        println!("Skewed:   asym {:.3e}  sym {:.3e}", asym_skewed, sym_skewed);
+        
+        // Centrado: el simétrico queda cerca (solo pierde el rango no usado del grupo)
+        assert!(sym_centered < asym_centered * 2.0, "symmetric too lossy on centered data");
+        // Sesgado: la mitad negativa de la rejilla se desperdicia
+        assert!(sym_skewed > asym_skewed * 4.0, "symmetric should lose on skewed data");
+        
+        // El fast simétrico también mantiene min = -scale/2
+        let fast = quantize_hq4k_symmetric(&centered, false);
+        let header: [u8; HEADER_SIZE] = fast[..HEADER_SIZE].try_into().unwrap();
+        for gp in decode_header(&header) {
+            assert_eq!(gp.min, symmetric_min(gp.scale));
+        }
+    }
 }
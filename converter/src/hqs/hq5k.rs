@@ -9,19 +9,17 @@ use crate::hqs::grid_search::*;
 
 const Q_MAX: f32 = 31.0;
 
-fn quantize_superblock(block: &[f32; SUPER_BLOCK_SIZE], use_mse: bool) -> Vec<u8> {
-    let config = GridConfig::hq5k();
+fn quantize_superblock(
+    block: &[f32],
+    config: &GridConfig,
+    use_mse: bool,
+    weights: Option<&[f32]>,
+) -> Vec<u8> {
+    let group_params = superblock_params_weighted(block, config, use_mse, weights);
     
-    let group_params = if use_mse {
-        optimize_superblock(block, &config)
-    } else {
-        fast_superblock(block)
-    };
+    let mut q_indices = vec![0u8; block.len()];
     
-    let mut q_indices = [0u8; SUPER_BLOCK_SIZE];
-    
-    for g in 0..NUM_GROUPS {
-        let gp = &group_params[g];
+    for (g, gp) in group_params.iter().enumerate() {
         let start = g * GROUP_SIZE;
         
         for i in 0..GROUP_SIZE {
@@ -31,13 +29,12 @@ fn quantize_superblock(block: &[f32; SUPER_BLOCK_SIZE], use_mse: bool) -> Vec<u8
         }
     }
     
-    let mut output = vec![0u8; HQ5K_BLOCK_SIZE];
-    
-    let header = encode_header(&group_params);
-    output[..HEADER_SIZE].copy_from_slice(&header);
+    let header_len = header_size(block.len());
+    let mut output = vec![0u8; superblock_bytes(5, block.len())];
+    output[..header_len].copy_from_slice(&encode_header(&group_params));
     
     // Pack 5-bit LSB-first: 8 valores = 40 bits = 5 bytes
-    for chunk_idx in 0..(SUPER_BLOCK_SIZE / 8) {
+    for chunk_idx in 0..(block.len() / 8) {
         let base = chunk_idx * 8;
         
         let mut bits: u64 = 0;
@@ -45,7 +42,7 @@ fn quantize_superblock(block: &[f32; SUPER_BLOCK_SIZE], use_mse: bool) -> Vec<u8
             bits |= (q_indices[base + k] as u64 & 0x1F) << (k * 5);
         }
         
-        let byte_idx = HEADER_SIZE + chunk_idx * 5;
+        let byte_idx = header_len + chunk_idx * 5;
         for k in 0..5 {
             output[byte_idx + k] = ((bits >> (k * 8)) & 0xFF) as u8;
         }
@@ -55,16 +52,32 @@ fn quantize_superblock(block: &[f32; SUPER_BLOCK_SIZE], use_mse: bool) -> Vec<u8
 }
 
 pub fn quantize_hq5k(data: &[f32]) -> Vec<u8> {
-    quantize_hq5k_internal(data, true)
+    quantize_hq5k_internal(data, true, GridConfig::hq5k(), None)
 }
 
 pub fn quantize_hq5k_fast(data: &[f32]) -> Vec<u8> {
-    quantize_hq5k_internal(data, false)
+    quantize_hq5k_internal(data, false, GridConfig::hq5k(), None)
+}
+
+/// Variante simétrica (sin zero-point). Mismo layout: min = -scale/2
+pub fn quantize_hq5k_symmetric(data: &[f32], use_mse: bool) -> Vec<u8> {
+    quantize_hq5k_internal(data, use_mse, GridConfig::hq5k().with_symmetric(true), None)
+}
+
+/// Error del grid search ponderado por importancia de canal (--calibration-data)
+pub fn quantize_hq5k_weighted(data: &[f32], use_mse: bool, symmetric: bool, weights: ChannelWeights) -> Vec<u8> {
+    quantize_hq5k_internal(data, use_mse, GridConfig::hq5k().with_symmetric(symmetric), Some(weights))
+}
+
+/// Simetría y tamaño de super-block de `grid` (los bits los fija el formato)
+pub fn quantize_hq5k_with(data: &[f32], use_mse: bool, grid: GridConfig, weights: Option<ChannelWeights>) -> Vec<u8> {
+    quantize_hq5k_internal(data, use_mse, grid.with_bits(5), weights)
 }
 
-fn quantize_hq5k_internal(data: &[f32], use_mse: bool) -> Vec<u8> {
-    let padded = pad_to_superblock(data);
-    let num_blocks = padded.len() / SUPER_BLOCK_SIZE;
+fn quantize_hq5k_internal(data: &[f32], use_mse: bool, config: GridConfig, weights: Option<ChannelWeights>) -> Vec<u8> {
+    let super_block = config.super_block;
+    let padded = pad_to_multiple(data, super_block);
+    let num_blocks = padded.len() / super_block;
     
     if num_blocks == 0 {
         return Vec::new();
@@ -73,17 +86,15 @@ fn quantize_hq5k_internal(data: &[f32], use_mse: bool) -> Vec<u8> {
     let results: Vec<Vec<u8>> = (0..num_blocks)
         .into_par_iter()
         .map(|b| {
-            let start = b * SUPER_BLOCK_SIZE;
-            let mut block = [0.0f32; SUPER_BLOCK_SIZE];
-            for i in 0..SUPER_BLOCK_SIZE {
-                let val = padded[start + i];
-                block[i] = if val.is_finite() { val } else { 0.0 };
-            }
-            quantize_superblock(&block, use_mse)
+            let block: Vec<f32> = padded[b * super_block..(b + 1) * super_block].iter()
+                .map(|&val| if val.is_finite() { val } else { 0.0 })
+                .collect();
+            let block_weights = weights.map(|w| w.superblock(b, super_block));
+            quantize_superblock(&block, &config, use_mse, block_weights.as_deref())
         })
         .collect();
     
-    let mut output = Vec::with_capacity(num_blocks * HQ5K_BLOCK_SIZE);
+    let mut output = Vec::with_capacity(num_blocks * superblock_bytes(5, super_block));
     for block_data in results {
         output.extend(block_data);
     }
@@ -92,25 +103,27 @@ fn quantize_hq5k_internal(data: &[f32], use_mse: bool) -> Vec<u8> {
 }
 
 pub fn dequantize_hq5k(data: &[u8], numel: usize) -> Vec<f32> {
+    dequantize_hq5k_with(data, numel, SUPER_BLOCK_SIZE)
+}
+
+/// dequantize_hq5k con super-blocks de `super_block` elementos (manifest)
+pub fn dequantize_hq5k_with(data: &[u8], numel: usize, super_block: usize) -> Vec<f32> {
     if data.is_empty() {
         return vec![0.0; numel];
     }
     
-    let num_blocks = data.len() / HQ5K_BLOCK_SIZE;
-    let mut output = Vec::with_capacity(num_blocks * SUPER_BLOCK_SIZE);
+    let block_size = superblock_bytes(5, super_block);
+    let header_len = header_size(super_block);
+    let num_blocks = data.len() / block_size;
+    let mut output = Vec::with_capacity(num_blocks * super_block);
+    let mut q_indices = vec![0u8; super_block];
     
     for b in 0..num_blocks {
-        let block_start = b * HQ5K_BLOCK_SIZE;
-        let header: [u8; HEADER_SIZE] = data[block_start..block_start + HEADER_SIZE]
-            .try_into()
-            .unwrap();
-        
-        let group_params = decode_header(&header);
+        let block_start = b * block_size;
+        let group_params = decode_header(&data[block_start..block_start + header_len]);
         
-        let payload_start = block_start + HEADER_SIZE;
-        let mut q_indices = [0u8; SUPER_BLOCK_SIZE];
-        
-        for chunk_idx in 0..(SUPER_BLOCK_SIZE / 8) {
+        let payload_start = block_start + header_len;
+        for chunk_idx in 0..(super_block / 8) {
             let byte_idx = payload_start + chunk_idx * 5;
             
             let mut bits: u64 = 0;
@@ -123,8 +136,7 @@ pub fn dequantize_hq5k(data: &[u8], numel: usize) -> Vec<f32> {
             }
         }
         
-        for g in 0..NUM_GROUPS {
-            let gp = &group_params[g];
+        for (g, gp) in group_params.iter().enumerate() {
             let start = g * GROUP_SIZE;
             
             for i in 0..GROUP_SIZE {
@@ -140,8 +152,11 @@ pub fn dequantize_hq5k(data: &[u8], numel: usize) -> Vec<f32> {
 }
 
 pub fn hq5k_size(numel: usize) -> usize {
-    let num_blocks = (numel + SUPER_BLOCK_SIZE - 1) / SUPER_BLOCK_SIZE;
-    num_blocks * HQ5K_BLOCK_SIZE
+    hq5k_size_with(numel, SUPER_BLOCK_SIZE)
+}
+
+pub fn hq5k_size_with(numel: usize, super_block: usize) -> usize {
+    numel.div_ceil(super_block) * superblock_bytes(5, super_block)
 }
 
 pub fn validate_hq5k(data: &[u8], numel: usize) -> Result<(), String> {
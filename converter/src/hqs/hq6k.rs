@@ -0,0 +1,208 @@
+// src/hqs/hq6k.rs
+// ============================================================================
+// HQ6K v6 - NUCLEAR: grupos de 8 elementos, 6 bits
+// ============================================================================
+//
+// Entre HQ5K y FP16: mismo header de 32 grupos, payload de 6 bits.
+// 128 header + 192 payload = 320 bytes por super-block (~0.63 vs FP16).
+// Pensado para embeddings y capas sensibles.
+//
+// ============================================================================
+
+use rayon::prelude::*;
+use crate::hqs::common::*;
+use crate::hqs::grid_search::*;
+
+const Q_MAX: f32 = 63.0;
+
+fn quantize_superblock(
+    block: &[f32],
+    config: &GridConfig,
+    use_mse: bool,
+    weights: Option<&[f32]>,
+) -> Vec<u8> {
+    let group_params = superblock_params_weighted(block, config, use_mse, weights);
+    
+    let mut q_indices = vec![0u8; block.len()];
+    
+    for (g, gp) in group_params.iter().enumerate() {
+        let start = g * GROUP_SIZE;
+        
+        for i in 0..GROUP_SIZE {
+            let val = block[start + i];
+            let q = ((val - gp.min) / gp.scale * Q_MAX).round().clamp(0.0, Q_MAX) as u8;
+            q_indices[start + i] = q;
+        }
+    }
+    
+    let header_len = header_size(block.len());
+    let mut output = vec![0u8; superblock_bytes(6, block.len())];
+    output[..header_len].copy_from_slice(&encode_header(&group_params));
+    
+    // Pack 6-bit LSB-first: 4 valores = 24 bits = 3 bytes
+    for chunk_idx in 0..(block.len() / 4) {
+        let base = chunk_idx * 4;
+        
+        let mut bits: u32 = 0;
+        for k in 0..4 {
+            bits |= (q_indices[base + k] as u32 & 0x3F) << (k * 6);
+        }
+        
+        let byte_idx = header_len + chunk_idx * 3;
+        for k in 0..3 {
+            output[byte_idx + k] = ((bits >> (k * 8)) & 0xFF) as u8;
+        }
+    }
+    
+    output
+}
+
+pub fn quantize_hq6k(data: &[f32]) -> Vec<u8> {
+    quantize_hq6k_internal(data, true, GridConfig::hq6k(), None)
+}
+
+pub fn quantize_hq6k_fast(data: &[f32]) -> Vec<u8> {
+    quantize_hq6k_internal(data, false, GridConfig::hq6k(), None)
+}
+
+/// Variante simétrica (sin zero-point). Mismo layout: min = -scale/2
+pub fn quantize_hq6k_symmetric(data: &[f32], use_mse: bool) -> Vec<u8> {
+    quantize_hq6k_internal(data, use_mse, GridConfig::hq6k().with_symmetric(true), None)
+}
+
+/// Error del grid search ponderado por importancia de canal (--calibration-data)
+pub fn quantize_hq6k_weighted(data: &[f32], use_mse: bool, symmetric: bool, weights: ChannelWeights) -> Vec<u8> {
+    quantize_hq6k_internal(data, use_mse, GridConfig::hq6k().with_symmetric(symmetric), Some(weights))
+}
+
+/// Simetría y tamaño de super-block de `grid` (los bits los fija el formato)
+pub fn quantize_hq6k_with(data: &[f32], use_mse: bool, grid: GridConfig, weights: Option<ChannelWeights>) -> Vec<u8> {
+    quantize_hq6k_internal(data, use_mse, grid.with_bits(6), weights)
+}
+
+fn quantize_hq6k_internal(data: &[f32], use_mse: bool, config: GridConfig, weights: Option<ChannelWeights>) -> Vec<u8> {
+    let super_block = config.super_block;
+    let padded = pad_to_multiple(data, super_block);
+    let num_blocks = padded.len() / super_block;
+    
+    if num_blocks == 0 {
+        return Vec::new();
+    }
+    
+    let results: Vec<Vec<u8>> = (0..num_blocks)
+        .into_par_iter()
+        .map(|b| {
+            let block: Vec<f32> = padded[b * super_block..(b + 1) * super_block].iter()
+                .map(|&val| if val.is_finite() { val } else { 0.0 })
+                .collect();
+            let block_weights = weights.map(|w| w.superblock(b, super_block));
+            quantize_superblock(&block, &config, use_mse, block_weights.as_deref())
+        })
+        .collect();
+    
+    let mut output = Vec::with_capacity(num_blocks * superblock_bytes(6, super_block));
+    for block_data in results {
+        output.extend(block_data);
+    }
+    
+    output
+}
+
+pub fn dequantize_hq6k(data: &[u8], numel: usize) -> Vec<f32> {
+    dequantize_hq6k_with(data, numel, SUPER_BLOCK_SIZE)
+}
+
+/// dequantize_hq6k con super-blocks de `super_block` elementos (manifest)
+pub fn dequantize_hq6k_with(data: &[u8], numel: usize, super_block: usize) -> Vec<f32> {
+    if data.is_empty() {
+        return vec![0.0; numel];
+    }
+    
+    let block_size = superblock_bytes(6, super_block);
+    let header_len = header_size(super_block);
+    let num_blocks = data.len() / block_size;
+    let mut output = Vec::with_capacity(num_blocks * super_block);
+    let mut q_indices = vec![0u8; super_block];
+    
+    for b in 0..num_blocks {
+        let block_start = b * block_size;
+        let group_params = decode_header(&data[block_start..block_start + header_len]);
+        
+        let payload_start = block_start + header_len;
+        for chunk_idx in 0..(super_block / 4) {
+            let byte_idx = payload_start + chunk_idx * 3;
+            
+            let mut bits: u32 = 0;
+            for k in 0..3 {
+                bits |= (data[byte_idx + k] as u32) << (k * 8);
+            }
+            
+            for k in 0..4 {
+                q_indices[chunk_idx * 4 + k] = ((bits >> (k * 6)) & 0x3F) as u8;
+            }
+        }
+        
+        for (g, gp) in group_params.iter().enumerate() {
+            let start = g * GROUP_SIZE;
+            
+            for i in 0..GROUP_SIZE {
+                let q = q_indices[start + i] as f32;
+                let val = gp.min + q / Q_MAX * gp.scale;
+                output.push(val);
+            }
+        }
+    }
+    
+    output.truncate(numel);
+    output
+}
+
+pub fn hq6k_size(numel: usize) -> usize {
+    hq6k_size_with(numel, SUPER_BLOCK_SIZE)
+}
+
+pub fn hq6k_size_with(numel: usize, super_block: usize) -> usize {
+    numel.div_ceil(super_block) * superblock_bytes(6, super_block)
+}
+
+pub fn validate_hq6k(data: &[u8], numel: usize) -> Result<(), String> {
+    let expected_size = hq6k_size(numel);
+    if data.len() != expected_size {
+        return Err(format!("Size mismatch: expected {}, got {}", expected_size, data.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hqs::{quantize_hq5k, dequantize_hq5k};
+    use rand::Rng;
+    
+    fn mse(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f32>() / a.len() as f32
+    }
+    
+    #[test]
+    fn test_mse_between_hq5k_and_fp16() {
+        let mut rng = rand::thread_rng();
+        // Tamaño no múltiplo de super-block: el último bloque va con padding
+        let original: Vec<f32> = (0..10_000).map(|_| rng.gen_range(-2.0..2.0)).collect();
+        
+        let quantized = quantize_hq6k(&original);
+        assert_eq!(quantized.len(), hq6k_size(original.len()));
+        assert!(validate_hq6k(&quantized, original.len()).is_ok());
+        let hq6k = dequantize_hq6k(&quantized, original.len());
+        assert_eq!(hq6k.len(), original.len());
+        
+        let hq5k = dequantize_hq5k(&quantize_hq5k(&original), original.len());
+        let fp16: Vec<f32> = original.iter().map(|&x| half::f16::from_f32(x).to_f32()).collect();
+        
+        let (mse_fp16, mse_hq6k, mse_hq5k) = (mse(&original, &fp16), mse(&original, &hq6k), mse(&original, &hq5k));
+        println!("MSE FP16 {:.3e} < HQ6K {:.3e} < HQ5K {:.3e}", mse_fp16, mse_hq6k, mse_hq5k);
+        assert!(mse_fp16 < mse_hq6k, "HQ6K beats FP16?");
+        assert!(mse_hq6k < mse_hq5k, "HQ6K {} not better than HQ5K {}", mse_hq6k, mse_hq5k);
+        
+        assert!(validate_hq6k(&quantized[1..], original.len()).is_err());
+    }
+}
@@ -7,12 +7,27 @@ pub mod common;
 pub mod grid_search;
 pub mod hq4k;
 pub mod hq5k;
+pub mod hq6k;
 
 // Re-exports
 pub use common::*;
-pub use grid_search::GridConfig;
-pub use hq4k::{quantize_hq4k, quantize_hq4k_fast, dequantize_hq4k, hq4k_size, validate_hq4k};
-pub use hq5k::{quantize_hq5k, quantize_hq5k_fast, dequantize_hq5k, hq5k_size, validate_hq5k};
+pub use grid_search::{ChannelWeights, GridConfig};
+pub use hq4k::{quantize_hq4k, quantize_hq4k_fast, quantize_hq4k_symmetric, quantize_hq4k_weighted, quantize_hq4k_with, dequantize_hq4k, dequantize_hq4k_with, hq4k_size, hq4k_size_with, validate_hq4k};
+pub use hq5k::{quantize_hq5k, quantize_hq5k_fast, quantize_hq5k_symmetric, quantize_hq5k_weighted, quantize_hq5k_with, dequantize_hq5k, dequantize_hq5k_with, hq5k_size, hq5k_size_with, validate_hq5k};
+pub use hq6k::{quantize_hq6k, quantize_hq6k_fast, quantize_hq6k_symmetric, quantize_hq6k_weighted, quantize_hq6k_with, dequantize_hq6k, dequantize_hq6k_with, hq6k_size, hq6k_size_with, validate_hq6k};
+
+/// Metadatos de un formato para menús de herramientas/GUIs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuantInfo {
+    pub name: &'static str,
+    pub bits: u8,
+    /// Bytes que ocupa un tensor de 1M de elementos
+    pub bytes_per_million: usize,
+    /// 1 = mejor calidad; mayor = más pérdida
+    pub quality_rank: u8,
+    pub implemented: bool,
+    pub description: &'static str,
+}
 
 /// Formato de cuantización
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,15 +36,51 @@ pub enum QuantFormat {
     HQ3K,
     HQ4K,
     HQ5K,
+    HQ6K,
 }
 
 impl QuantFormat {
+    /// Todos los formatos, de mayor a menor calidad
+    pub fn all() -> &'static [QuantFormat] {
+        &[Self::FP16, Self::HQ6K, Self::HQ5K, Self::HQ4K, Self::HQ3K]
+    }
+    
+    /// ¿Hay cuantizador para este formato?
+    pub fn is_implemented(&self) -> bool {
+        !matches!(self, Self::HQ3K)
+    }
+    
+    pub fn describe(&self) -> QuantInfo {
+        let (quality_rank, description) = match self {
+            Self::FP16 => (1, "Half precision, no quantization loss"),
+            Self::HQ6K => (2, "6-bit groups of 8 with FP16 min/scale, for embeddings and sensitive layers"),
+            Self::HQ5K => (3, "5-bit groups of 8 with FP16 min/scale, near-lossless"),
+            Self::HQ4K => (4, "4-bit groups of 8 with FP16 min/scale, 1:2 vs FP16"),
+            Self::HQ3K => (5, "3-bit groups (not implemented yet)"),
+        };
+        QuantInfo {
+            name: match self {
+                Self::FP16 => "FP16",
+                Self::HQ3K => "HQ3K",
+                Self::HQ4K => "HQ4K",
+                Self::HQ5K => "HQ5K",
+                Self::HQ6K => "HQ6K",
+            },
+            bits: self.bits(),
+            bytes_per_million: self.size_for(1_000_000),
+            quality_rank,
+            implemented: self.is_implemented(),
+            description,
+        }
+    }
+    
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_uppercase().as_str() {
             "FP16" | "FLOAT16" => Some(Self::FP16),
             "HQ3K" | "3BIT" => Some(Self::HQ3K),
             "HQ4K" | "4BIT" => Some(Self::HQ4K),
             "HQ5K" | "5BIT" => Some(Self::HQ5K),
+            "HQ6K" | "6BIT" => Some(Self::HQ6K),
             _ => None,
         }
     }
@@ -40,6 +91,7 @@ impl QuantFormat {
             Self::HQ3K => 0x01,
             Self::HQ4K => 0x02,
             Self::HQ5K => 0x03,
+            Self::HQ6K => 0x07,
         }
     }
     
@@ -49,6 +101,7 @@ impl QuantFormat {
             Self::HQ3K => 3,
             Self::HQ4K => 4,
             Self::HQ5K => 5,
+            Self::HQ6K => 6,
         }
     }
     
@@ -58,6 +111,20 @@ impl QuantFormat {
             Self::HQ3K => HQ3K_BLOCK_SIZE,
             Self::HQ4K => HQ4K_BLOCK_SIZE,
             Self::HQ5K => HQ5K_BLOCK_SIZE,
+            Self::HQ6K => HQ6K_BLOCK_SIZE,
+        }
+    }
+    
+    /// Mínimo de elementos para que el formato tenga sentido (un super-block)
+    pub fn min_elements(&self) -> usize {
+        self.min_elements_for(SUPER_BLOCK_SIZE)
+    }
+    
+    /// min_elements con super-blocks de `super_block` elementos
+    pub fn min_elements_for(&self, super_block: usize) -> usize {
+        match self {
+            Self::FP16 => 1,
+            Self::HQ3K | Self::HQ4K | Self::HQ5K | Self::HQ6K => super_block,
         }
     }
     
@@ -71,23 +138,50 @@ impl QuantFormat {
             }
             Self::HQ4K => hq4k_size(numel),
             Self::HQ5K => hq5k_size(numel),
+            Self::HQ6K => hq6k_size(numel),
+        }
+    }
+    
+    /// size_for con super-blocks de `super_block` elementos (--super-block)
+    pub fn size_for_super_block(&self, numel: usize, super_block: usize) -> usize {
+        match self {
+            Self::FP16 | Self::HQ3K => self.size_for(numel),
+            Self::HQ4K => hq4k_size_with(numel, super_block),
+            Self::HQ5K => hq5k_size_with(numel, super_block),
+            Self::HQ6K => hq6k_size_with(numel, super_block),
         }
     }
 }
 
 impl std::fmt::Display for QuantFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::FP16 => write!(f, "FP16"),
-            Self::HQ3K => write!(f, "HQ3K"),
-            Self::HQ4K => write!(f, "HQ4K"),
-            Self::HQ5K => write!(f, "HQ5K"),
-        }
+        write!(f, "{}", self.describe().name)
     }
 }
 
 /// Cuantiza datos según el formato especificado
-pub fn quantize(data: &[f32], format: QuantFormat, use_mse: bool) -> Vec<u8> {
+///
+/// `symmetric` solo afecta a HQ4K/HQ5K/HQ6K: sin zero-point (min = -scale/2).
+/// El layout no cambia, así que `dequantize` sirve para ambos modos.
+pub fn quantize(data: &[f32], format: QuantFormat, use_mse: bool, symmetric: bool) -> Vec<u8> {
+    quantize_weighted(data, format, use_mse, symmetric, None)
+}
+
+/// `quantize` con importancia por canal de entrada para el grid search
+/// (--calibration-data). FP16 la ignora; con `weights = None` es `quantize`.
+pub fn quantize_weighted(
+    data: &[f32],
+    format: QuantFormat,
+    use_mse: bool,
+    symmetric: bool,
+    weights: Option<ChannelWeights>,
+) -> Vec<u8> {
+    match (format, weights) {
+        (QuantFormat::HQ4K, Some(w)) => return quantize_hq4k_weighted(data, use_mse, symmetric, w),
+        (QuantFormat::HQ5K, Some(w)) => return quantize_hq5k_weighted(data, use_mse, symmetric, w),
+        (QuantFormat::HQ6K, Some(w)) => return quantize_hq6k_weighted(data, use_mse, symmetric, w),
+        _ => {}
+    }
     match format {
         QuantFormat::FP16 => {
             // Convertir a f16
@@ -100,19 +194,63 @@ pub fn quantize(data: &[f32], format: QuantFormat, use_mse: bool) -> Vec<u8> {
             unimplemented!("HQ3K not yet implemented")
         }
         QuantFormat::HQ4K => {
-            if use_mse {
+            if symmetric {
+                quantize_hq4k_symmetric(data, use_mse)
+            } else if use_mse {
                 quantize_hq4k(data)
             } else {
                 quantize_hq4k_fast(data)
             }
         }
         QuantFormat::HQ5K => {
-            if use_mse {
+            if symmetric {
+                quantize_hq5k_symmetric(data, use_mse)
+            } else if use_mse {
                 quantize_hq5k(data)
             } else {
                 quantize_hq5k_fast(data)
             }
         }
+        QuantFormat::HQ6K => {
+            if symmetric {
+                quantize_hq6k_symmetric(data, use_mse)
+            } else if use_mse {
+                quantize_hq6k(data)
+            } else {
+                quantize_hq6k_fast(data)
+            }
+        }
+    }
+}
+
+/// `quantize_weighted` con la simetría y el tamaño de super-block de `grid`
+/// (--super-block); los bits de `grid` se ignoran, los fija `format`
+pub fn quantize_with(
+    data: &[f32],
+    format: QuantFormat,
+    use_mse: bool,
+    grid: GridConfig,
+    weights: Option<ChannelWeights>,
+) -> Vec<u8> {
+    if grid.super_block == SUPER_BLOCK_SIZE {
+        return quantize_weighted(data, format, use_mse, grid.symmetric, weights);
+    }
+    match format {
+        QuantFormat::HQ4K => quantize_hq4k_with(data, use_mse, grid, weights),
+        QuantFormat::HQ5K => quantize_hq5k_with(data, use_mse, grid, weights),
+        QuantFormat::HQ6K => quantize_hq6k_with(data, use_mse, grid, weights),
+        QuantFormat::FP16 | QuantFormat::HQ3K => quantize_weighted(data, format, use_mse, grid.symmetric, weights),
+    }
+}
+
+/// `dequantize` para datos cuantizados con super-blocks de `super_block`
+/// elementos (manifest["quantization"]["super_block"])
+pub fn dequantize_with(data: &[u8], format: QuantFormat, numel: usize, super_block: usize) -> Vec<f32> {
+    match format {
+        QuantFormat::HQ4K => dequantize_hq4k_with(data, numel, super_block),
+        QuantFormat::HQ5K => dequantize_hq5k_with(data, numel, super_block),
+        QuantFormat::HQ6K => dequantize_hq6k_with(data, numel, super_block),
+        QuantFormat::FP16 | QuantFormat::HQ3K => dequantize(data, format, numel),
     }
 }
 
@@ -133,5 +271,67 @@ pub fn dequantize(data: &[u8], format: QuantFormat, numel: usize) -> Vec<f32> {
         }
         QuantFormat::HQ4K => dequantize_hq4k(data, numel),
         QuantFormat::HQ5K => dequantize_hq5k(data, numel),
+        QuantFormat::HQ6K => dequantize_hq6k(data, numel),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_describe_covers_all_formats() {
+        // Si se añade una variante, este match obliga a revisar all()
+        let covered = |f: QuantFormat| match f {
+            QuantFormat::FP16 | QuantFormat::HQ3K | QuantFormat::HQ4K | QuantFormat::HQ5K | QuantFormat::HQ6K => {
+                QuantFormat::all().contains(&f)
+            }
+        };
+        for f in [QuantFormat::FP16, QuantFormat::HQ3K, QuantFormat::HQ4K, QuantFormat::HQ5K, QuantFormat::HQ6K] {
+            assert!(covered(f), "{} missing from all()", f);
+        }
+        assert_eq!(QuantFormat::all().len(), 5);
+        
+        for f in QuantFormat::all() {
+            let info = f.describe();
+            assert!(!info.description.is_empty());
+            assert_eq!(QuantFormat::from_str(info.name), Some(*f));
+            assert_eq!(info.bits, f.bits());
+            assert!(info.bytes_per_million > 0);
+        }
+        
+        assert!(!QuantFormat::HQ3K.describe().implemented);
+        assert_eq!(QuantFormat::HQ4K.describe().bytes_per_million, hq4k_size(1_000_000));
+        
+        // quality_rank sigue el orden de all()
+        let ranks: Vec<u8> = QuantFormat::all().iter().map(|f| f.describe().quality_rank).collect();
+        assert!(ranks.windows(2).all(|w| w[0] < w[1]));
+    }
+    
+    #[test]
+    fn test_super_block_sizes_decode_with_recorded_size() {
+        let data: Vec<f32> = (0..1000).map(|i| ((i * 37 % 101) as f32 / 50.0 - 1.0) * 0.7).collect();
+        
+        for format in [QuantFormat::HQ4K, QuantFormat::HQ5K, QuantFormat::HQ6K] {
+            let small = quantize_with(&data, format, true, GridConfig::default().with_super_block(64), None);
+            let large = quantize_with(&data, format, true, GridConfig::default().with_super_block(512), None);
+            
+            // Mismos grupos de 8, distinto reparto header/payload y padding
+            assert_eq!(small.len(), format.size_for_super_block(data.len(), 64));
+            assert_eq!(large.len(), format.size_for_super_block(data.len(), 512));
+            assert_ne!(small, large, "{}", format);
+            
+            let from_small = dequantize_with(&small, format, data.len(), 64);
+            let from_large = dequantize_with(&large, format, data.len(), 512);
+            assert_eq!(from_small.len(), data.len());
+            // Grupos independientes: mismo resultado con cualquier super-block
+            assert_eq!(from_small, from_large, "{}", format);
+            let max_err = data.iter().zip(&from_small).map(|(a, b)| (a - b).abs()).fold(0.0f32, f32::max);
+            assert!(max_err < 0.1, "{}: max error {}", format, max_err);
+            
+            // El tamaño por defecto sigue siendo el layout de siempre
+            let default = quantize_with(&data, format, true, GridConfig::default(), None);
+            assert_eq!(default, quantize(&data, format, true, false));
+        }
     }
 }
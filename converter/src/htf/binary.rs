@@ -13,6 +13,8 @@
 use serde_json::Value;
 use std::collections::HashMap;
 
+use crate::hints::vision_num_image_tokens;
+
 // ============================================================================
 // CONSTANTS
 // ============================================================================
@@ -31,6 +33,39 @@ pub const FLAG_BYTE_LEVEL: u8 = 0x01;
 pub const FLAG_ADD_PREFIX_SPACE: u8 = 0x02;
 pub const FLAG_TRIM_OFFSETS: u8 = 0x04;
 pub const FLAG_LEGACY_BEHAVIOUR: u8 = 0x08;
+pub const FLAG_HAS_PIPELINE: u8 = 0x10;      // bytes [24:27] contienen normalizer/pre-tokenizer
+pub const FLAG_BYTE_FALLBACK: u8 = 0x20;     // SentencePiece byte_fallback: OOV → <0xNN> (aunque falten en vocab)
+pub const FLAG_RAW_BYTE_VOCAB: u8 = 0x40;    // tokens byte-level guardados como bytes crudos (TOKEN_FLAG_RAW_BYTES)
+
+// NormalizerFlags (§4.3.1) - byte [24] de TextDomainConfigBin
+pub const NORMALIZER_NFC: u8 = 0x01;
+pub const NORMALIZER_NFKC: u8 = 0x02;
+pub const NORMALIZER_NFD: u8 = 0x04;
+pub const NORMALIZER_NFKD: u8 = 0x08;
+pub const NORMALIZER_LOWERCASE: u8 = 0x10;
+pub const NORMALIZER_STRIP_ACCENTS: u8 = 0x20;
+pub const NORMALIZER_PREPEND_SPACE: u8 = 0x40;   // Prepend("▁")
+pub const NORMALIZER_REPLACE_SPACE: u8 = 0x80;   // Replace(" " → "▁")
+
+// PreTokenizerType (§4.3.2) - byte [25]
+pub const PRETOK_NONE: u8 = 0;
+pub const PRETOK_BYTE_LEVEL: u8 = 1;
+pub const PRETOK_WHITESPACE: u8 = 2;
+pub const PRETOK_WHITESPACE_SPLIT: u8 = 3;
+pub const PRETOK_METASPACE: u8 = 4;
+pub const PRETOK_BERT: u8 = 5;
+pub const PRETOK_SPLIT: u8 = 6;
+pub const PRETOK_OTHER: u8 = 0xFF;
+
+// PreTokenizerFlags (§4.3.2) - byte [26]
+pub const PRETOK_FLAG_USE_REGEX: u8 = 0x01;         // ByteLevel con regex GPT-2
+pub const PRETOK_FLAG_CUSTOM_SPLIT: u8 = 0x02;      // Split(regex) previo en la secuencia
+pub const PRETOK_FLAG_INDIVIDUAL_DIGITS: u8 = 0x04;
+
+// SpaceScheme (§4.3.3) - byte [27]: cómo se reconstruyen los espacios al decodificar
+pub const SPACE_NONE: u8 = 0;
+pub const SPACE_BYTE_LEVEL: u8 = 1;      // Ġ = espacio (alfabeto de 256 bytes)
+pub const SPACE_METASPACE: u8 = 2;       // carácter de reemplazo en [28:32] (▁ en SentencePiece)
 
 // AddedTokenFlags (§4.4)
 pub const ADDED_FLAG_SPECIAL: u8 = 0x01;
@@ -57,6 +92,9 @@ pub const AUDIO_ENCODEC: u32 = 1;
 pub const AUDIO_SEAMLESS: u32 = 2;
 pub const AUDIO_WAV2VEC2: u32 = 3;
 
+// AudioConfigFlags (§6) - AudioDomainConfigBin.flags
+pub const AUDIO_FLAG_MULTI_CODEBOOK: u16 = 0x0001;  // num_codebooks × CodebookEntryBin tras el config (RVQ: Mimi, SNAC)
+
 // ============================================================================
 // TEXT DOMAIN CONFIG (32 bytes)
 // ============================================================================
@@ -72,7 +110,11 @@ pub const AUDIO_WAV2VEC2: u32 = 3;
 ///   [20:22] num_added_tokens u16
 ///   [22]    encoding_type   u8
 ///   [23]    flags           u8
-///   [24:32] reserved        8 bytes (0x00)
+///   [24]    normalizer_flags    u8 (NORMALIZER_*)
+///   [25]    pretokenizer_type   u8 (PRETOK_*)
+///   [26]    pretokenizer_flags  u8 (PRETOK_FLAG_*)
+///   [27]    space_scheme        u8 (SPACE_*)
+///   [28:32] space_replacement   u32 (code point; 0 salvo SPACE_METASPACE)
 #[repr(C, align(8))]
 #[derive(Debug, Clone, Copy, Default)]
 pub struct TextDomainConfigBin {
@@ -84,7 +126,11 @@ pub struct TextDomainConfigBin {
     pub num_added_tokens: u16,
     pub encoding_type: u8,
     pub flags: u8,
-    pub reserved: [u8; 8],
+    pub normalizer_flags: u8,
+    pub pretokenizer_type: u8,
+    pub pretokenizer_flags: u8,
+    pub space_scheme: u8,
+    pub space_replacement: u32,
 }
 
 impl TextDomainConfigBin {
@@ -121,11 +167,23 @@ impl TextDomainConfigBin {
         
         let mut flags: u8 = 0;
         if config.get("byte_level").and_then(|v| v.as_bool()).unwrap_or(false) {
-            flags |= FLAG_BYTE_LEVEL;
+            // v1.3: el vocab byte-level se escribe siempre como bytes crudos
+            flags |= FLAG_BYTE_LEVEL | FLAG_RAW_BYTE_VOCAB;
         }
         if config.get("add_prefix_space").and_then(|v| v.as_bool()).unwrap_or(false) {
             flags |= FLAG_ADD_PREFIX_SPACE;
         }
+        if config.get("byte_fallback").and_then(|v| v.as_bool()).unwrap_or(false) {
+            flags |= FLAG_BYTE_FALLBACK;
+        }
+        
+        let space = SpaceScheme::from_config(config);
+        
+        let normalizer_flags = config.get("normalizer_flags").and_then(|v| v.as_u64());
+        let pretokenizer_type = config.get("pretokenizer_type").and_then(|v| v.as_u64());
+        if normalizer_flags.is_some() || pretokenizer_type.is_some() {
+            flags |= FLAG_HAS_PIPELINE;
+        }
         
         Self {
             bos_token_id: bos,
@@ -136,7 +194,11 @@ impl TextDomainConfigBin {
             num_added_tokens,
             encoding_type,
             flags,
-            reserved: [0; 8],
+            normalizer_flags: normalizer_flags.unwrap_or(0) as u8,
+            pretokenizer_type: pretokenizer_type.unwrap_or(PRETOK_NONE as u64) as u8,
+            pretokenizer_flags: config.get("pretokenizer_flags").and_then(|v| v.as_u64()).unwrap_or(0) as u8,
+            space_scheme: space.code(),
+            space_replacement: space.replacement(),
         }
     }
     
@@ -150,11 +212,250 @@ impl TextDomainConfigBin {
         buf[20..22].copy_from_slice(&self.num_added_tokens.to_le_bytes());
         buf[22] = self.encoding_type;
         buf[23] = self.flags;
-        // [24:32] already zeros
+        buf[24] = self.normalizer_flags;
+        buf[25] = self.pretokenizer_type;
+        buf[26] = self.pretokenizer_flags;
+        buf[27] = self.space_scheme;
+        buf[28..32].copy_from_slice(&self.space_replacement.to_le_bytes());
         buf
     }
 }
 
+// ============================================================================
+// SPACE SCHEME
+// ============================================================================
+
+/// Cómo marca el tokenizer los espacios, según su decoder (o, sin decoder,
+/// pre_tokenizer/normalizer). `byte_level` solo distingue Ġ; los derivados
+/// de SentencePiece usan ▁ (Metaspace) y se decodifican distinto.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpaceScheme {
+    #[default]
+    None,
+    ByteLevel,
+    /// Carácter que sustituye al espacio (▁ casi siempre)
+    Metaspace(char),
+}
+
+impl SpaceScheme {
+    pub fn from_tokenizer_json(tokenizer: &Value) -> Self {
+        [
+            tokenizer.get("decoder").map(Self::visit_decoder),
+            tokenizer.get("pre_tokenizer").map(Self::visit_decoder),
+            tokenizer.get("normalizer").map(Self::visit_normalizer),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|scheme| *scheme != Self::None)
+        .unwrap_or_default()
+    }
+    
+    /// Decoder y pre_tokenizer comparten forma: ByteLevel, Metaspace,
+    /// Replace("▁" → " ") y Sequence
+    fn visit_decoder(node: &Value) -> Self {
+        match node.get("type").and_then(|v| v.as_str()) {
+            Some("ByteLevel") => Self::ByteLevel,
+            Some("Metaspace") => Self::Metaspace(
+                node.get("replacement").and_then(|v| v.as_str()).and_then(|r| r.chars().next()).unwrap_or('▁'),
+            ),
+            Some("Replace") => {
+                let pattern = node.get("pattern").and_then(|p| p.get("String")).and_then(|v| v.as_str());
+                let content = node.get("content").and_then(|v| v.as_str());
+                match (pattern.and_then(single_char), content) {
+                    (Some(c), Some(" ")) => Self::Metaspace(c),
+                    _ => Self::None,
+                }
+            }
+            Some("Sequence") => ["decoders", "pretokenizers"].iter()
+                .filter_map(|key| node.get(*key).and_then(|v| v.as_array()))
+                .flatten()
+                .map(Self::visit_decoder)
+                .find(|scheme| *scheme != Self::None)
+                .unwrap_or_default(),
+            _ => Self::None,
+        }
+    }
+    
+    /// Normalizer Replace(" " → "▁") (Llama 2)
+    fn visit_normalizer(node: &Value) -> Self {
+        match node.get("type").and_then(|v| v.as_str()) {
+            Some("Replace") => {
+                let pattern = node.get("pattern").and_then(|p| p.get("String")).and_then(|v| v.as_str());
+                match (pattern, node.get("content").and_then(|v| v.as_str()).and_then(single_char)) {
+                    (Some(" "), Some(c)) => Self::Metaspace(c),
+                    _ => Self::None,
+                }
+            }
+            Some("Sequence") => node.get("normalizers").and_then(|v| v.as_array()).into_iter()
+                .flatten()
+                .map(Self::visit_normalizer)
+                .find(|scheme| *scheme != Self::None)
+                .unwrap_or_default(),
+            _ => Self::None,
+        }
+    }
+    
+    /// Desde el config del dominio ("space_scheme", "space_replacement")
+    pub fn from_config(config: &Value) -> Self {
+        match config.get("space_scheme").and_then(|v| v.as_str()) {
+            Some("byte_level") => Self::ByteLevel,
+            Some("metaspace") => Self::Metaspace(
+                config.get("space_replacement").and_then(|v| v.as_str()).and_then(single_char).unwrap_or('▁'),
+            ),
+            _ => Self::None,
+        }
+    }
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::ByteLevel => "byte_level",
+            Self::Metaspace(_) => "metaspace",
+        }
+    }
+    
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::None => SPACE_NONE,
+            Self::ByteLevel => SPACE_BYTE_LEVEL,
+            Self::Metaspace(_) => SPACE_METASPACE,
+        }
+    }
+    
+    pub fn replacement(&self) -> u32 {
+        match self {
+            Self::Metaspace(c) => *c as u32,
+            _ => 0,
+        }
+    }
+}
+
+/// El único carácter de `s` (None si está vacío o tiene más de uno)
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+// ============================================================================
+// NORMALIZER / PRE-TOKENIZER DESCRIPTOR
+// ============================================================================
+
+/// Descriptor compacto de tokenizer.json["normalizer"] y ["pre_tokenizer"]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenizerPipeline {
+    pub normalizer_flags: u8,
+    pub pretokenizer_type: u8,
+    pub pretokenizer_flags: u8,
+    pub byte_level: bool,
+    pub add_prefix_space: Option<bool>,
+}
+
+impl TokenizerPipeline {
+    /// Extrae el descriptor de un tokenizer.json completo.
+    /// Devuelve None si no hay normalizer ni pre_tokenizer.
+    pub fn from_tokenizer_json(tokenizer: &Value) -> Option<Self> {
+        let normalizer = tokenizer.get("normalizer").filter(|v| !v.is_null());
+        let pre_tokenizer = tokenizer.get("pre_tokenizer").filter(|v| !v.is_null());
+        if normalizer.is_none() && pre_tokenizer.is_none() {
+            return None;
+        }
+        
+        let mut pipeline = Self::default();
+        if let Some(n) = normalizer {
+            pipeline.visit_normalizer(n);
+        }
+        if let Some(p) = pre_tokenizer {
+            pipeline.visit_pretokenizer(p);
+        }
+        Some(pipeline)
+    }
+    
+    fn visit_normalizer(&mut self, node: &Value) {
+        match node.get("type").and_then(|v| v.as_str()) {
+            Some("Sequence") => {
+                for child in node.get("normalizers").and_then(|v| v.as_array()).into_iter().flatten() {
+                    self.visit_normalizer(child);
+                }
+            }
+            Some("NFC") => self.normalizer_flags |= NORMALIZER_NFC,
+            Some("NFKC") => self.normalizer_flags |= NORMALIZER_NFKC,
+            Some("NFD") => self.normalizer_flags |= NORMALIZER_NFD,
+            Some("NFKD") => self.normalizer_flags |= NORMALIZER_NFKD,
+            Some("Lowercase") => self.normalizer_flags |= NORMALIZER_LOWERCASE,
+            Some("StripAccents") => self.normalizer_flags |= NORMALIZER_STRIP_ACCENTS,
+            Some("Prepend") => self.normalizer_flags |= NORMALIZER_PREPEND_SPACE,
+            Some("Replace") => {
+                let pattern = node.get("pattern").and_then(|p| p.get("String")).and_then(|v| v.as_str());
+                if pattern == Some(" ") {
+                    self.normalizer_flags |= NORMALIZER_REPLACE_SPACE;
+                }
+            }
+            Some("BertNormalizer") => {
+                let lowercase = node.get("lowercase").and_then(|v| v.as_bool()).unwrap_or(true);
+                if lowercase {
+                    self.normalizer_flags |= NORMALIZER_LOWERCASE;
+                }
+                // strip_accents null = sigue a lowercase (semántica de HF)
+                let strip = node.get("strip_accents").and_then(|v| v.as_bool()).unwrap_or(lowercase);
+                if strip {
+                    self.normalizer_flags |= NORMALIZER_STRIP_ACCENTS;
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    fn visit_pretokenizer(&mut self, node: &Value) {
+        let kind = match node.get("type").and_then(|v| v.as_str()) {
+            Some("Sequence") => {
+                for child in node.get("pretokenizers").and_then(|v| v.as_array()).into_iter().flatten() {
+                    self.visit_pretokenizer(child);
+                }
+                return;
+            }
+            Some("ByteLevel") => {
+                self.byte_level = true;
+                self.add_prefix_space = node.get("add_prefix_space").and_then(|v| v.as_bool());
+                if node.get("use_regex").and_then(|v| v.as_bool()).unwrap_or(true) {
+                    self.pretokenizer_flags |= PRETOK_FLAG_USE_REGEX;
+                }
+                PRETOK_BYTE_LEVEL
+            }
+            Some("Metaspace") => {
+                // Versiones nuevas usan prepend_scheme en lugar de add_prefix_space
+                self.add_prefix_space = node.get("add_prefix_space").and_then(|v| v.as_bool())
+                    .or_else(|| node.get("prepend_scheme").and_then(|v| v.as_str()).map(|s| s != "never"));
+                PRETOK_METASPACE
+            }
+            Some("Split") => {
+                self.pretokenizer_flags |= PRETOK_FLAG_CUSTOM_SPLIT;
+                PRETOK_SPLIT
+            }
+            Some("Digits") => {
+                if node.get("individual_digits").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    self.pretokenizer_flags |= PRETOK_FLAG_INDIVIDUAL_DIGITS;
+                }
+                return;
+            }
+            Some("Whitespace") => PRETOK_WHITESPACE,
+            Some("WhitespaceSplit") => PRETOK_WHITESPACE_SPLIT,
+            Some("BertPreTokenizer") => PRETOK_BERT,
+            _ => PRETOK_OTHER,
+        };
+        
+        // En secuencias manda el paso "fuerte": ByteLevel/Metaspace sobre Split
+        let rank = |t: u8| match t {
+            PRETOK_BYTE_LEVEL | PRETOK_METASPACE => 3,
+            PRETOK_NONE => 0,
+            PRETOK_SPLIT => 1,
+            _ => 2,
+        };
+        if rank(kind) >= rank(self.pretokenizer_type) {
+            self.pretokenizer_type = kind;
+        }
+    }
+}
+
 // ============================================================================
 // ADDED TOKEN ENTRY (8 + content_len bytes, aligned to 4)
 // ============================================================================
@@ -236,6 +537,28 @@ pub fn extract_added_tokens(config: &Value) -> Vec<AddedTokenEntry> {
     entries
 }
 
+/// Valida que los IDs de added tokens caben en la tabla de embeddings.
+/// 
+/// `embedding_rows` es el vocab_size del modelo (config.json), que puede ser
+/// mayor que el vocab del tokenizer. Sin él, se permite que los added tokens
+/// extiendan el vocab como mucho en su propio número.
+/// Devuelve un mensaje por cada token fuera de rango.
+pub fn check_added_token_ids(
+    added: &[AddedTokenEntry],
+    vocab_len: usize,
+    embedding_rows: Option<usize>,
+) -> Vec<String> {
+    let limit = embedding_rows.unwrap_or(vocab_len + added.len());
+    
+    added.iter()
+        .filter(|t| t.token_id as usize >= limit)
+        .map(|t| format!(
+            "added token {:?} has id {} but the embedding only has {} rows",
+            t.content, t.token_id, limit
+        ))
+        .collect()
+}
+
 // ============================================================================
 // VISION DOMAIN CONFIG (64 bytes)
 // ============================================================================
@@ -313,7 +636,7 @@ impl VisionDomainConfigBin {
         let (mean_r, mean_g, mean_b) = extract_image_mean(config);
         let (std_r, std_g, std_b) = extract_image_std(config);
         
-        let num_image_tokens = config.get("num_image_tokens").and_then(|v| v.as_u64()).unwrap_or(196) as u32;
+        let num_image_tokens = vision_num_image_tokens(config);
         let image_token_id = config.get("image_token_id").and_then(|v| v.as_i64()).unwrap_or(-1) as i32;
         let projection_dim = config.get("projection_dim").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
         
@@ -374,26 +697,38 @@ impl VisionDomainConfigBin {
     }
 }
 
-fn extract_image_mean(config: &Value) -> (i16, i16, i16) {
-    if let Some(arr) = config.get("image_mean").and_then(|v| v.as_array()) {
-        let r = arr.get(0).and_then(|v| v.as_f64()).unwrap_or(0.5);
-        let g = arr.get(1).and_then(|v| v.as_f64()).unwrap_or(0.5);
-        let b = arr.get(2).and_then(|v| v.as_f64()).unwrap_or(0.5);
-        ((r * 1000.0) as i16, (g * 1000.0) as i16, (b * 1000.0) as i16)
-    } else {
-        (500, 500, 500) // Default 0.5
+/// Componentes por encima de esto indican escala de píxel (0-255), no 0-1
+const PIXEL_SCALE_THRESHOLD: f64 = 1.5;
+
+/// image_mean/image_std en punto fijo ×1000. Algunos preprocessor_config
+/// traen la escala de píxel (123.675 en vez de 0.485): ×1000 no cabe en i16,
+/// así que se detecta y se pasa a 0-1 antes de convertir.
+fn extract_image_norm(config: &Value, key: &str) -> (i16, i16, i16) {
+    let Some(arr) = config.get(key).and_then(|v| v.as_array()) else {
+        return (500, 500, 500); // Default 0.5
+    };
+    let mut rgb = [0.5f64; 3];
+    for (i, c) in rgb.iter_mut().enumerate() {
+        if let Some(v) = arr.get(i).and_then(|v| v.as_f64()) {
+            *c = v;
+        }
+    }
+    if rgb.iter().any(|&c| c > PIXEL_SCALE_THRESHOLD) {
+        eprintln!("[INFO] {} {:?} is in 0-255 scale: normalized to 0-1", key, rgb);
+        for c in &mut rgb {
+            *c /= 255.0;
+        }
     }
+    let fixed = |c: f64| (c * 1000.0).round().clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+    (fixed(rgb[0]), fixed(rgb[1]), fixed(rgb[2]))
+}
+
+fn extract_image_mean(config: &Value) -> (i16, i16, i16) {
+    extract_image_norm(config, "image_mean")
 }
 
 fn extract_image_std(config: &Value) -> (i16, i16, i16) {
-    if let Some(arr) = config.get("image_std").and_then(|v| v.as_array()) {
-        let r = arr.get(0).and_then(|v| v.as_f64()).unwrap_or(0.5);
-        let g = arr.get(1).and_then(|v| v.as_f64()).unwrap_or(0.5);
-        let b = arr.get(2).and_then(|v| v.as_f64()).unwrap_or(0.5);
-        ((r * 1000.0) as i16, (g * 1000.0) as i16, (b * 1000.0) as i16)
-    } else {
-        (500, 500, 500) // Default 0.5
-    }
+    extract_image_norm(config, "image_std")
 }
 
 // ============================================================================
@@ -436,7 +771,7 @@ impl AudioDomainConfigBin {
             _ => AUDIO_WHISPER,
         };
         
-        Self {
+        let mut base = Self {
             encoder_type,
             sample_rate: config.get("sample_rate").and_then(|v| v.as_u64()).unwrap_or(16000) as u32,
             n_mels: config.get("n_mels").and_then(|v| v.as_u64()).unwrap_or(128) as u32,
@@ -455,6 +790,25 @@ impl AudioDomainConfigBin {
             eot_token_id: config.get("eot_token_id").and_then(|v| v.as_i64()).unwrap_or(-1) as i32,
             flags: 0,
             reserved2: [0; 2],
+        };
+        
+        // RVQ: la lista de codebooks manda sobre el par genérico
+        let codebooks = codebooks_from_config(config);
+        if let Some(first) = codebooks.first() {
+            base.num_codebooks = codebooks.len() as u16;
+            base.codebook_size = first.size;
+            base.codebook_dim = first.dim;
+            base.flags |= AUDIO_FLAG_MULTI_CODEBOOK;
+        }
+        base
+    }
+    
+    /// Bytes que ocupa el config más la tabla de codebooks (si la hay)
+    pub fn data_size(&self) -> usize {
+        if self.flags & AUDIO_FLAG_MULTI_CODEBOOK != 0 {
+            Self::SIZE + self.num_codebooks as usize * CodebookEntryBin::SIZE
+        } else {
+            Self::SIZE
         }
     }
     
@@ -482,6 +836,67 @@ impl AudioDomainConfigBin {
     }
 }
 
+/// CodebookEntryBin - 16 bytes, uno por codebook RVQ (AUDIO_FLAG_MULTI_CODEBOOK)
+///
+/// Layout:
+///   [0:4]   size        u32 (entradas del codebook)
+///   [4:8]   dim         u32
+///   [8:12]  frame_rate  f32 (Hz; Mimi 12.5, SNAC varía por nivel)
+///   [12:16] reserved
+#[repr(C, align(8))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CodebookEntryBin {
+    pub size: u32,
+    pub dim: u32,
+    pub frame_rate: f32,
+    pub reserved: u32,
+}
+
+impl CodebookEntryBin {
+    pub const SIZE: usize = 16;
+    
+    pub fn new(size: u32, dim: u32, frame_rate: f32) -> Self {
+        Self { size, dim, frame_rate, reserved: 0 }
+    }
+    
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut buf = [0u8; Self::SIZE];
+        buf[0..4].copy_from_slice(&self.size.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.dim.to_le_bytes());
+        buf[8..12].copy_from_slice(&self.frame_rate.to_le_bytes());
+        // [12:16] already zeros
+        buf
+    }
+    
+    pub fn from_bytes(data: &[u8]) -> Self {
+        Self {
+            size: u32::from_le_bytes(data[0..4].try_into().unwrap()),
+            dim: u32::from_le_bytes(data[4..8].try_into().unwrap()),
+            frame_rate: f32::from_le_bytes(data[8..12].try_into().unwrap()),
+            reserved: u32::from_le_bytes(data[12..16].try_into().unwrap()),
+        }
+    }
+    
+    /// Forma JSON que lee codebooks_from_config
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({"size": self.size, "dim": self.dim, "frame_rate": self.frame_rate})
+    }
+}
+
+/// config["codebooks"]: [{"size", "dim", "frame_rate"}, ...]
+pub fn codebooks_from_config(config: &Value) -> Vec<CodebookEntryBin> {
+    config.get("codebooks")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .map(|cb| CodebookEntryBin::new(
+            cb.get("size").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+            cb.get("dim").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+            cb.get("frame_rate").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+        ))
+        .collect()
+}
+
 // ============================================================================
 // CODE DOMAIN CONFIG (32 bytes)
 // ============================================================================
@@ -544,6 +959,21 @@ impl CodeDomainConfigBin {
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_pixel_scale_image_mean_normalized() {
+        let config = serde_json::json!({
+            "image_mean": [123.675, 116.28, 103.53],
+            "image_std": [58.395, 57.12, 57.375],
+        });
+        assert_eq!(extract_image_mean(&config), (485, 456, 406));
+        assert_eq!(extract_image_std(&config), (229, 224, 225));
+        
+        // Escala 0-1: tal cual
+        let config = serde_json::json!({"image_mean": [0.485, 0.456, 0.406], "image_std": [0.5, 0.5, 0.5]});
+        assert_eq!(extract_image_mean(&config), (485, 456, 406));
+        assert_eq!(extract_image_std(&config), (500, 500, 500));
+    }
+    
     #[test]
     fn test_text_config_size() {
         assert_eq!(std::mem::size_of::<TextDomainConfigBin>(), 32);
@@ -588,4 +1018,84 @@ mod tests {
         // Verify flags
         assert_eq!(bytes[6], ADDED_FLAG_SPECIAL);
     }
+    
+    #[test]
+    fn test_pipeline_byte_level_no_prefix_space() {
+        let tokenizer = serde_json::json!({
+            "normalizer": {"type": "NFC"},
+            "pre_tokenizer": {
+                "type": "Sequence",
+                "pretokenizers": [
+                    {"type": "Split", "pattern": {"Regex": "\\p{N}"}, "behavior": "Isolated"},
+                    {"type": "ByteLevel", "add_prefix_space": false, "trim_offsets": false, "use_regex": false}
+                ]
+            }
+        });
+        let p = TokenizerPipeline::from_tokenizer_json(&tokenizer).unwrap();
+        assert_eq!(p.normalizer_flags, NORMALIZER_NFC);
+        assert_eq!(p.pretokenizer_type, PRETOK_BYTE_LEVEL);
+        assert_eq!(p.pretokenizer_flags, PRETOK_FLAG_CUSTOM_SPLIT);
+        assert!(p.byte_level);
+        assert_eq!(p.add_prefix_space, Some(false));
+        
+        let config = serde_json::json!({
+            "byte_level": p.byte_level,
+            "add_prefix_space": false,
+            "normalizer_flags": p.normalizer_flags,
+            "pretokenizer_type": p.pretokenizer_type,
+            "pretokenizer_flags": p.pretokenizer_flags,
+        });
+        let bytes = TextDomainConfigBin::from_config(&config, 100, 0).to_bytes();
+        assert_eq!(bytes[23], FLAG_BYTE_LEVEL | FLAG_RAW_BYTE_VOCAB | FLAG_HAS_PIPELINE);
+        assert_eq!(bytes[24], NORMALIZER_NFC);
+        assert_eq!(bytes[25], PRETOK_BYTE_LEVEL);
+        assert_eq!(bytes[26], PRETOK_FLAG_CUSTOM_SPLIT);
+        assert!(bytes[27..32].iter().all(|&b| b == 0));
+    }
+    
+    #[test]
+    fn test_metaspace_distinct_from_byte_level() {
+        // Llama/SentencePiece: decoder Sequence con Replace("▁" → " ")
+        let metaspace = serde_json::json!({
+            "normalizer": {"type": "Sequence", "normalizers": [
+                {"type": "Prepend", "prepend": "▁"},
+                {"type": "Replace", "pattern": {"String": " "}, "content": "▁"}
+            ]},
+            "decoder": {"type": "Sequence", "decoders": [
+                {"type": "Replace", "pattern": {"String": "▁"}, "content": " "},
+                {"type": "ByteFallback"},
+                {"type": "Fuse"}
+            ]}
+        });
+        let byte_level = serde_json::json!({
+            "pre_tokenizer": {"type": "ByteLevel", "add_prefix_space": false},
+            "decoder": {"type": "ByteLevel"}
+        });
+        assert_eq!(SpaceScheme::from_tokenizer_json(&metaspace), SpaceScheme::Metaspace('▁'));
+        assert_eq!(SpaceScheme::from_tokenizer_json(&byte_level), SpaceScheme::ByteLevel);
+        assert_eq!(SpaceScheme::from_tokenizer_json(&serde_json::json!({})), SpaceScheme::None);
+        
+        let config = |scheme: SpaceScheme| serde_json::json!({
+            "space_scheme": scheme.name(),
+            "space_replacement": scheme.replacement().ne(&0).then(|| '▁'.to_string()),
+        });
+        let bytes = TextDomainConfigBin::from_config(&config(SpaceScheme::Metaspace('▁')), 100, 0).to_bytes();
+        assert_eq!(bytes[27], SPACE_METASPACE);
+        assert_eq!(u32::from_le_bytes(bytes[28..32].try_into().unwrap()), 0x2581);
+        let bytes = TextDomainConfigBin::from_config(&config(SpaceScheme::ByteLevel), 100, 0).to_bytes();
+        assert_eq!(bytes[27], SPACE_BYTE_LEVEL);
+        assert_eq!(&bytes[28..32], &[0; 4]);
+    }
+    
+    #[test]
+    fn test_pipeline_bert_normalizer() {
+        let tokenizer = serde_json::json!({
+            "normalizer": {"type": "BertNormalizer", "lowercase": true, "strip_accents": null},
+            "pre_tokenizer": {"type": "BertPreTokenizer"}
+        });
+        let p = TokenizerPipeline::from_tokenizer_json(&tokenizer).unwrap();
+        assert_eq!(p.normalizer_flags, NORMALIZER_LOWERCASE | NORMALIZER_STRIP_ACCENTS);
+        assert_eq!(p.pretokenizer_type, PRETOK_BERT);
+        assert!(TokenizerPipeline::from_tokenizer_json(&serde_json::json!({"model": {}})).is_none());
+    }
 }
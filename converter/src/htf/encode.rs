@@ -0,0 +1,420 @@
+// src/htf/encode.rs
+// ============================================================================
+// HTF ENCODE - Codificación BPE leyendo solo el HTF v1.3 (helios-tokenize)
+// ============================================================================
+//
+// Reconstruye vocab + merges del dominio TEXT primario y tokeniza igual que
+// el engine: sin tokenizer.json, solo con lo que el HTF serializa.
+//
+// Pipeline:
+//   1. Added tokens: se cortan del texto tal cual (match más largo)
+//   2. Normalizer: NF*, lowercase, Prepend/Replace(" " → ▁)
+//   3. Pre-tokenizer: ByteLevel (regex GPT-2 opcional) o Metaspace
+//   4. BPE: fusiona el par adyacente de menor rank hasta que no quede ninguno
+//
+// Si el resultado difiere del tokenizer original (--verify-tokenizer), la
+// serialización de vocab/merges perdió información.
+//
+// ============================================================================
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
+
+use super::binary::*;
+use super::{
+    HTF_DOMAIN_TEXT, HTF_DOMAIN_ENTRY_SIZE, HTF_FLAG_IS_PRIMARY, HTF_HEADER_SIZE,
+    SCORE_TYPE_F32, TOKEN_FLAG_ADDED, TOKEN_FLAG_RAW_BYTES, TOKEN_FLAG_SPECIAL,
+};
+
+/// Regex GPT-2 sin el `\s+(?!\S)` (el crate regex no tiene lookahead):
+/// se emula en pretokenize_gpt2 recortando el último espacio de la racha
+static GPT2_SPLIT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+").unwrap()
+});
+
+/// Frases de prueba por defecto de --verify-tokenizer: espacios, dígitos,
+/// puntuación, contracciones y texto no ASCII
+pub const DEFAULT_PROBES: &[&str] = &[
+    "Hello world",
+    "The quick brown fox jumps over the lazy dog.",
+    "  leading and trailing spaces  ",
+    "line one\nline two\n\n  indented",
+    "It's 2024; we'll see 3.14159 and 1,000,000.",
+    "fn main() { println!(\"hi\"); }",
+    "naïve café — 東京 🚀",
+];
+
+/// Tokenizer BPE reconstruido desde el dominio TEXT de un HTF v1.3
+#[derive(Debug, Clone)]
+pub struct HtfEncoder {
+    config: TextDomainConfigBin,
+    /// Bytes del token (crudos si byte-level) → token_id
+    token_ids: HashMap<Vec<u8>, u32>,
+    /// (izquierdo, derecho) → (rank, token fusionado)
+    merges: HashMap<(u32, u32), (usize, u32)>,
+    /// Added tokens (contenido literal), del más largo al más corto
+    added: Vec<(String, u32)>,
+}
+
+/// Lector secuencial little-endian sobre los datos de un dominio
+struct DomainCursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DomainCursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let bytes = self.pos.checked_add(n)
+            .and_then(|end| self.data.get(self.pos..end))
+            .context("HTF text domain truncated")?;
+        self.pos += n;
+        Ok(bytes)
+    }
+    
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+    
+    fn align(&mut self, alignment: usize) {
+        self.pos = self.pos.div_ceil(alignment) * alignment;
+    }
+}
+
+impl HtfEncoder {
+    /// Dominio TEXT primario (o el primero TEXT) de un HTF v1.3
+    pub fn from_htf(data: &[u8]) -> Result<Self> {
+        if data.len() < HTF_HEADER_SIZE || &data[0..4] != HTF3_MAGIC {
+            anyhow::bail!("encoding needs an HTF v1.3 blob (magic HTF3)");
+        }
+        
+        let mut text_domain = None;
+        for idx in 0..data[8] as usize {
+            let start = HTF_HEADER_SIZE + idx * HTF_DOMAIN_ENTRY_SIZE;
+            let entry = data.get(start..start + HTF_DOMAIN_ENTRY_SIZE)
+                .context("HTF domain table truncated")?;
+            if entry[0] != HTF_DOMAIN_TEXT {
+                continue;
+            }
+            let offset = u64::from_le_bytes(entry[8..16].try_into().unwrap()) as usize;
+            let size = u64::from_le_bytes(entry[16..24].try_into().unwrap()) as usize;
+            let domain = offset.checked_add(size)
+                .and_then(|end| data.get(offset..end))
+                .with_context(|| format!("HTF domain {} exceeds blob", idx))?;
+            let primary = entry[1] & HTF_FLAG_IS_PRIMARY != 0;
+            if primary || text_domain.is_none() {
+                text_domain = Some(domain);
+            }
+            if primary {
+                break;
+            }
+        }
+        
+        Self::from_text_domain(text_domain.context("HTF has no TEXT domain")?)
+    }
+    
+    /// Datos de un dominio TEXT v1.3: config, added tokens, vocab, merges
+    pub fn from_text_domain(domain: &[u8]) -> Result<Self> {
+        let mut cursor = DomainCursor { data: domain, pos: 0 };
+        let config = parse_text_config(cursor.take(TextDomainConfigBin::SIZE)?);
+        if config.encoding_type == ENCODING_UNIGRAM || config.encoding_type == ENCODING_WORDPIECE {
+            anyhow::bail!("only BPE text domains can be encoded (encoding_type {})", config.encoding_type);
+        }
+        
+        let mut added = Vec::new();
+        for _ in 0..cursor.u32()? {
+            let token_id = cursor.u32()?;
+            let len = cursor.u16()? as usize;
+            cursor.take(2)?;
+            let content = String::from_utf8_lossy(cursor.take(len)?).into_owned();
+            cursor.align(4);
+            added.push((content, token_id));
+        }
+        cursor.align(8);
+        
+        let mut token_ids = HashMap::new();
+        let mut token_bytes: HashMap<u32, Vec<u8>> = HashMap::new();
+        if cursor.pos < domain.len() {
+            for _ in 0..cursor.u32()? {
+                let token_id = cursor.u32()?;
+                let len = cursor.u16()? as usize;
+                let flags = cursor.take(1)?[0];
+                if cursor.take(1)?[0] == SCORE_TYPE_F32 {
+                    cursor.take(4)?;
+                }
+                let bytes = cursor.take(len)?.to_vec();
+                cursor.align(4);
+                
+                // Added/special sin crudos: solo entran por el corte literal
+                let literal = flags & (TOKEN_FLAG_ADDED | TOKEN_FLAG_SPECIAL) != 0;
+                if literal && flags & TOKEN_FLAG_RAW_BYTES == 0 {
+                    if let Ok(content) = String::from_utf8(bytes.clone()) {
+                        if !added.iter().any(|(_, id)| *id == token_id) {
+                            added.push((content, token_id));
+                        }
+                    }
+                    if config.flags & FLAG_RAW_BYTE_VOCAB != 0 {
+                        continue;
+                    }
+                }
+                token_ids.insert(bytes.clone(), token_id);
+                token_bytes.insert(token_id, bytes);
+            }
+        }
+        
+        let mut merges = HashMap::new();
+        if cursor.pos < domain.len() {
+            for rank in 0..cursor.u32()? as usize {
+                let (a, b) = (cursor.u32()?, cursor.u32()?);
+                let (Some(left), Some(right)) = (token_bytes.get(&a), token_bytes.get(&b)) else {
+                    continue;
+                };
+                if let Some(&merged) = token_ids.get(&[left.as_slice(), right.as_slice()].concat()) {
+                    merges.entry((a, b)).or_insert((rank, merged));
+                }
+            }
+        }
+        
+        added.retain(|(content, _)| !content.is_empty());
+        added.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.1.cmp(&b.1)));
+        Ok(Self { config, token_ids, merges, added })
+    }
+    
+    pub fn vocab_size(&self) -> usize {
+        self.token_ids.len()
+    }
+    
+    /// Token IDs de `text` (sin BOS/EOS: solo lo que produce el modelo BPE)
+    pub fn encode(&self, text: &str) -> Vec<u32> {
+        let mut ids = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            // Primer added token del texto; en empate de posición, el más largo
+            let next = self.added.iter()
+                .filter_map(|(content, id)| rest.find(content.as_str()).map(|pos| (pos, content.len(), *id)))
+                .min_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+            match next {
+                Some((pos, len, id)) => {
+                    self.encode_segment(&rest[..pos], &mut ids);
+                    ids.push(id);
+                    rest = &rest[pos + len..];
+                }
+                None => {
+                    self.encode_segment(rest, &mut ids);
+                    break;
+                }
+            }
+        }
+        ids
+    }
+    
+    /// Texto sin added tokens: normalizer + pre-tokenizer + BPE por palabra
+    fn encode_segment(&self, text: &str, ids: &mut Vec<u32>) {
+        if text.is_empty() {
+            return;
+        }
+        let text = self.normalize(text);
+        let add_prefix_space = self.config.flags & FLAG_ADD_PREFIX_SPACE != 0;
+        
+        if self.config.flags & FLAG_BYTE_LEVEL != 0 {
+            let text = if add_prefix_space && !text.starts_with(char::is_whitespace) {
+                format!(" {}", text)
+            } else {
+                text
+            };
+            let words = if self.config.pretokenizer_flags & PRETOK_FLAG_USE_REGEX != 0 {
+                pretokenize_gpt2(&text)
+            } else {
+                vec![text.as_str()]
+            };
+            for word in words {
+                let symbols = word.bytes().map(|b| self.byte_symbol(b)).collect();
+                self.bpe(symbols, ids);
+            }
+            return;
+        }
+        
+        let replacement = char::from_u32(self.config.space_replacement).unwrap_or('▁');
+        if self.config.pretokenizer_type == PRETOK_METASPACE {
+            let mut text = text.replace(' ', &replacement.to_string());
+            if add_prefix_space && !text.starts_with(replacement) {
+                text.insert(0, replacement);
+            }
+            // Metaspace corta delante de cada ▁ (la palabra lo lleva como prefijo)
+            let mut start = 0;
+            for (pos, _) in text.match_indices(replacement).filter(|(pos, _)| *pos > 0) {
+                self.bpe_chars(&text[start..pos], ids);
+                start = pos;
+            }
+            self.bpe_chars(&text[start..], ids);
+        } else {
+            self.bpe_chars(&text, ids);
+        }
+    }
+    
+    fn normalize(&self, text: &str) -> String {
+        let flags = self.config.normalizer_flags;
+        let mut text: String = if flags & NORMALIZER_NFKC != 0 {
+            text.nfkc().collect()
+        } else if flags & NORMALIZER_NFC != 0 {
+            text.nfc().collect()
+        } else if flags & NORMALIZER_NFKD != 0 {
+            text.nfkd().collect()
+        } else if flags & NORMALIZER_NFD != 0 {
+            text.nfd().collect()
+        } else {
+            text.to_string()
+        };
+        if flags & NORMALIZER_LOWERCASE != 0 {
+            text = text.to_lowercase();
+        }
+        let replacement = char::from_u32(self.config.space_replacement).unwrap_or('▁');
+        if flags & NORMALIZER_PREPEND_SPACE != 0 {
+            text.insert(0, replacement);
+        }
+        if flags & NORMALIZER_REPLACE_SPACE != 0 {
+            text = text.replace(' ', &replacement.to_string());
+        }
+        text
+    }
+    
+    /// Símbolo inicial de un byte: el token de 1 byte (vocab byte-level crudo)
+    fn byte_symbol(&self, byte: u8) -> Option<u32> {
+        self.token_ids.get(&[byte][..]).copied()
+    }
+    
+    /// BPE sobre caracteres; OOV → <0xNN> con byte_fallback, si no unk
+    fn bpe_chars(&self, word: &str, ids: &mut Vec<u32>) {
+        let mut symbols = Vec::new();
+        let mut buf = [0u8; 4];
+        for c in word.chars() {
+            let encoded = c.encode_utf8(&mut buf);
+            match self.token_ids.get(encoded.as_bytes()) {
+                Some(&id) => symbols.push(Some(id)),
+                None if self.config.flags & FLAG_BYTE_FALLBACK != 0 => {
+                    symbols.extend(encoded.bytes().map(|b| {
+                        self.token_ids.get(format!("<0x{:02X}>", b).as_bytes()).copied()
+                    }));
+                }
+                None => symbols.push(None),
+            }
+        }
+        self.bpe(symbols, ids);
+    }
+    
+    /// Fusiona el par de menor rank (el más a la izquierda en empate) hasta
+    /// que ningún par adyacente tenga merge. Símbolos sin token → unk (o nada)
+    fn bpe(&self, symbols: Vec<Option<u32>>, ids: &mut Vec<u32>) {
+        let unk = u32::try_from(self.config.unk_token_id).ok();
+        let mut word: Vec<Option<u32>> = symbols;
+        
+        loop {
+            let best = word.windows(2)
+                .enumerate()
+                .filter_map(|(i, pair)| match (pair[0], pair[1]) {
+                    (Some(a), Some(b)) => self.merges.get(&(a, b)).map(|&(rank, merged)| (rank, i, merged)),
+                    _ => None,
+                })
+                .min_by_key(|&(rank, i, _)| (rank, i));
+            let Some((_, i, merged)) = best else { break };
+            word[i] = Some(merged);
+            word.remove(i + 1);
+        }
+        
+        ids.extend(word.into_iter().filter_map(|symbol| symbol.or(unk)));
+    }
+}
+
+fn parse_text_config(bytes: &[u8]) -> TextDomainConfigBin {
+    let i32_at = |i: usize| i32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+    TextDomainConfigBin {
+        bos_token_id: i32_at(0),
+        eos_token_id: i32_at(4),
+        pad_token_id: i32_at(8),
+        unk_token_id: i32_at(12),
+        vocab_size: u32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+        num_added_tokens: u16::from_le_bytes(bytes[20..22].try_into().unwrap()),
+        encoding_type: bytes[22],
+        flags: bytes[23],
+        normalizer_flags: bytes[24],
+        pretokenizer_type: bytes[25],
+        pretokenizer_flags: bytes[26],
+        space_scheme: bytes[27],
+        space_replacement: u32::from_le_bytes(bytes[28..32].try_into().unwrap()),
+    }
+}
+
+/// Frase en la que el HTF y el tokenizer original no coinciden
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenizerDivergence {
+    pub probe: String,
+    /// IDs del tokenizer original (crate tokenizers)
+    pub expected: Vec<u32>,
+    /// IDs de HtfEncoder
+    pub actual: Vec<u32>,
+}
+
+impl std::fmt::Display for TokenizerDivergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: tokenizer.json {:?} != HTF {:?}", self.probe, self.expected, self.actual)
+    }
+}
+
+/// Codifica `probes` con el tokenizer.json original y con el HTF; devuelve
+/// las frases cuyos IDs difieren (vacío = round-trip exacto)
+#[cfg(feature = "verify-tokenizer")]
+pub fn verify_tokenizer(tokenizer_json: &std::path::Path, htf: &[u8], probes: &[&str]) -> Result<Vec<TokenizerDivergence>> {
+    let reference = tokenizers::Tokenizer::from_file(tokenizer_json)
+        .map_err(|e| anyhow::anyhow!("Cannot load {}: {}", tokenizer_json.display(), e))?;
+    let encoder = HtfEncoder::from_htf(htf)?;
+    
+    let mut divergences = Vec::new();
+    for probe in probes {
+        let expected = reference.encode(*probe, false)
+            .map_err(|e| anyhow::anyhow!("tokenizers failed on {:?}: {}", probe, e))?
+            .get_ids()
+            .to_vec();
+        let actual = encoder.encode(probe);
+        if expected != actual {
+            divergences.push(TokenizerDivergence { probe: probe.to_string(), expected, actual });
+        }
+    }
+    Ok(divergences)
+}
+
+/// Palabras del pre-tokenizer ByteLevel de GPT-2. Una racha de espacios
+/// seguida de texto cede su último carácter a la palabra siguiente
+/// (`\s+(?!\S)` en la regex original)
+fn pretokenize_gpt2(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut pos = 0;
+    while let Some(m) = GPT2_SPLIT.find_at(text, pos) {
+        let word = m.as_str();
+        let mut end = m.end();
+        if end < text.len() && word.chars().all(char::is_whitespace) && word.chars().nth(1).is_some() {
+            end -= word.chars().last().map_or(0, char::len_utf8);
+        }
+        words.push(&text[m.start()..end]);
+        pos = end;
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_gpt2_split_keeps_space_with_next_word() {
+        assert_eq!(pretokenize_gpt2("Hello  world"), vec!["Hello", " ", " world"]);
+        assert_eq!(pretokenize_gpt2("it's 3.14\n"), vec!["it", "'s", " 3", ".", "14", "\n"]);
+        assert_eq!(pretokenize_gpt2("a\n\nb"), vec!["a", "\n", "\n", "b"]);
+    }
+}
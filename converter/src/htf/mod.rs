@@ -8,6 +8,18 @@
 //   - HTF v1.3.0 (magic "HTF3"): Config como estructuras binarias (nuevo)
 //
 // v1.3.0 CHANGES:
+//   - encode.rs: HtfEncoder tokeniza BPE solo con el HTF (--verify-tokenizer)
+//   - Conteos de vocab/merges comprobados contra u32; aviso si merges >> vocab
+//   - HtfOptions::max_vocab: vocab TEXT recortado a las filas del embedding
+//   - AUDIO: AUDIO_FLAG_MULTI_CODEBOOK + CodebookEntryBin por codebook RVQ (Mimi/SNAC)
+//   - IDs especiales en desacuerdo entre configs se avisan; --prefer-config
+//   - Merges como pares (MergePair): acepta ["a", "b"] además de "a b"
+//   - HTFWriter::build_to escribe en streaming (sin ensamblar el blob en memoria)
+//   - Unigram: vocab [[token, score]] con score f32 por entrada (SCORE_TYPE_F32)
+//   - TextConfigFlags 0x40 + TOKEN_FLAG_RAW_BYTES: vocab byte-level como bytes crudos
+//   - JSON con surrogates sueltos (\uD800) se recupera como U+FFFD con aviso
+//   - TextConfigFlags 0x20: byte_fallback (el engine sintetiza <0xNN> ausentes)
+//   - TextDomainConfigBin [24:27]: descriptor de normalizer/pre-tokenizer
 //   - config_json reemplazado por TextDomainConfigBin (32 bytes)
 //   - added_tokens_decoder ahora es array binario de AddedTokenEntry
 //   - Magic cambia de "HTF2" a "HTF3"
@@ -41,16 +53,20 @@
 // ============================================================================
 
 pub mod binary;
+pub mod encode;
 pub mod validate;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
 use std::path::Path;
+use std::sync::LazyLock;
 use anyhow::Result;
 use serde_json::Value;
 
 use binary::{
     TextDomainConfigBin, VisionDomainConfigBin, AudioDomainConfigBin, CodeDomainConfigBin,
-    AddedTokenEntry, extract_added_tokens,
+    CodebookEntryBin, codebooks_from_config, AUDIO_FLAG_MULTI_CODEBOOK,
+    AddedTokenEntry, TokenizerPipeline, SpaceScheme, extract_added_tokens, check_added_token_ids,
     HTF3_MAGIC, HTF3_VERSION,
 };
 
@@ -64,8 +80,14 @@ pub const HTF_VERSION: u16 = 0x0103;  // v1.2.1
 
 // HTF v1.3 (nuevo) - re-exportados de binary.rs
 pub use binary::{HTF3_MAGIC as HTF_MAGIC_V13, HTF3_VERSION as HTF_VERSION_V13};
+pub use encode::{HtfEncoder, TokenizerDivergence, DEFAULT_PROBES};
+#[cfg(feature = "verify-tokenizer")]
+pub use encode::verify_tokenizer;
 pub const HTF_HEADER_SIZE: usize = 32;
 pub const HTF_DOMAIN_ENTRY_SIZE: usize = 32;
+/// Campos reservados (deben ser 0x00): header [9:16], domain entry [2:4]
+pub const HTF_HEADER_RESERVED: std::ops::Range<usize> = 9..16;
+pub const HTF_ENTRY_RESERVED: std::ops::Range<usize> = 2..4;
 
 // Domain types (§5)
 pub const HTF_DOMAIN_TEXT: u8 = 0x00;
@@ -90,6 +112,64 @@ pub const TOKEN_FLAG_UNKNOWN: u8 = 0x02;  // bit 1: IS_UNKNOWN
 pub const TOKEN_FLAG_CONTROL: u8 = 0x04;  // bit 2: IS_CONTROL
 pub const TOKEN_FLAG_BYTE: u8 = 0x08;     // bit 3: IS_BYTE
 pub const TOKEN_FLAG_ADDED: u8 = 0x10;    // bit 4: IS_ADDED (de Python)
+pub const TOKEN_FLAG_RAW_BYTES: u8 = 0x20; // bit 5: bytes crudos (Ġ → 0x20), no UTF-8
+
+// Token score_type (v1.3). Con F32 el score va tras la cabecera de 8 bytes:
+//   [token_id u32][len u16][flags u8][score_type u8][score f32][bytes][pad 4]
+pub const SCORE_TYPE_NONE: u8 = 0;
+pub const SCORE_TYPE_F32: u8 = 1;  // log-prob Unigram
+
+/// Scores por token_id (solo modelos Unigram)
+pub type TokenScores = HashMap<u32, f32>;
+
+/// Merge BPE (token_a, token_b). Los tokens pueden contener espacios
+pub type MergePair = (String, String);
+
+/// Merge de tokenizer.json: "a b" (formato clásico) o ["a", "b"] (tokenizers
+/// recientes). Solo la forma de array admite tokens con espacios.
+pub fn parse_merge(value: &Value) -> Option<MergePair> {
+    match value {
+        Value::String(s) => parse_merge_line(s),
+        Value::Array(pair) => match pair.as_slice() {
+            [Value::String(a), Value::String(b)] => Some((a.clone(), b.clone())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// "a b" → (a, b). Más de un espacio es ambiguo y se descarta
+fn parse_merge_line(line: &str) -> Option<MergePair> {
+    let (a, b) = line.split_once(' ')?;
+    (!b.contains(' ')).then(|| (a.to_string(), b.to_string()))
+}
+
+/// Resuelve los merges a pares de ids (los que no están en el vocab se descartan)
+fn merge_id_pairs(vocab: &HashMap<String, u32>, merges: &[MergePair]) -> Vec<(u32, u32)> {
+    merges.iter()
+        .filter_map(|(a, b)| Some((*vocab.get(a)?, *vocab.get(b)?)))
+        .collect()
+}
+
+/// Merges por token a partir del cual el conteo huele a error de parsing
+/// (en BPE cada merge produce un token nuevo: merges <= vocab)
+const MERGE_VOCAB_RATIO_WARN: usize = 2;
+
+/// Los conteos de vocab/merges se serializan como u32
+fn check_domain_count(what: &str, count: usize) -> Result<()> {
+    if u32::try_from(count).is_err() {
+        anyhow::bail!("HTF domain has {} {} entries, more than a u32 count can hold", count, what);
+    }
+    Ok(())
+}
+
+/// Aviso si hay muchos más merges que tokens
+fn merge_count_warning(vocab_len: usize, merges_len: usize) -> Option<String> {
+    (merges_len > vocab_len.max(1) * MERGE_VOCAB_RATIO_WARN).then(|| format!(
+        "{} merges for a vocab of {} tokens (> {}x): merges.txt/tokenizer.json probably misparsed",
+        merges_len, vocab_len, MERGE_VOCAB_RATIO_WARN
+    ))
+}
 
 // ============================================================================
 // XXH3-64 hash (contractual)
@@ -134,6 +214,79 @@ fn is_byte_token(token: &str) -> bool {
     }
 }
 
+/// Inversa de bytes_to_unicode de GPT-2: carácter visible → byte original
+static BYTE_LEVEL_DECODER: LazyLock<HashMap<char, u8>> = LazyLock::new(|| {
+    let mut map = HashMap::with_capacity(256);
+    let mut extra = 0u32;
+    for b in 0..=255u8 {
+        let printable = matches!(b, b'!'..=b'~' | 0xA1..=0xAC | 0xAE..=0xFF);
+        let c = if printable {
+            b as u32
+        } else {
+            extra += 1;
+            255 + extra
+        };
+        map.insert(char::from_u32(c).unwrap(), b);
+    }
+    map
+});
+
+/// Bytes crudos de un token byte-level ("Ġhola" → b" hola").
+/// None si contiene caracteres fuera del alfabeto de 256 (p.ej. "ñ" sin escapar).
+fn decode_byte_level(token: &str) -> Option<Vec<u8>> {
+    token.chars().map(|c| BYTE_LEVEL_DECODER.get(&c).copied()).collect()
+}
+
+/// Parsea JSON recuperando escapes de surrogates sueltos (\uD800 sin pareja)
+/// como U+FFFD. serde_json los rechaza y algunos vocab.json byte-level los traen.
+fn parse_json_lossy(text: &str, what: &str) -> Result<Value> {
+    let text = &*crate::json::prepare(text, what);
+    // El mensaje de serde_json varía ("lone leading surrogate", "unexpected
+    // end of hex escape"); si no hay surrogates sueltos se devuelve el error original
+    let err = match serde_json::from_str(text) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+    
+    // Escapes \uXXXX en rango surrogate (no precedidos por otra '\' escapada)
+    let escapes: Vec<(usize, u16)> = text.match_indices("\\u")
+        .filter(|(i, _)| text[..*i].bytes().rev().take_while(|&b| b == b'\\').count() % 2 == 0)
+        .filter_map(|(i, _)| {
+            let unit = u16::from_str_radix(text.get(i + 2..i + 6)?, 16).ok()?;
+            (0xD800..0xE000).contains(&unit).then_some((i, unit))
+        })
+        .collect();
+    
+    let mut lone = Vec::new();
+    let mut k = 0;
+    while k < escapes.len() {
+        let (i, unit) = escapes[k];
+        let paired = unit < 0xDC00 && escapes.get(k + 1)
+            .is_some_and(|&(j, next)| j == i + 6 && next >= 0xDC00);
+        if paired {
+            k += 2;
+        } else {
+            lone.push(i);
+            k += 1;
+        }
+    }
+    if lone.is_empty() {
+        return Err(err.into());
+    }
+    
+    let mut fixed = String::with_capacity(text.len());
+    let mut last = 0;
+    for &i in &lone {
+        fixed.push_str(&text[last..i]);
+        fixed.push_str("\\uFFFD");
+        last = i + 6;
+    }
+    fixed.push_str(&text[last..]);
+    
+    eprintln!("[WARN] {}: {} lone UTF-16 surrogate escapes replaced with U+FFFD", what, lone.len());
+    Ok(serde_json::from_str(&fixed)?)
+}
+
 fn extract_control_ids(config: &Value) -> std::collections::HashSet<u32> {
     let mut ids = std::collections::HashSet::new();
     for key in &["bos_token_id", "eos_token_id", "pad_token_id", "unk_token_id"] {
@@ -211,37 +364,90 @@ impl HTFWriter {
         self.use_v13 = use_v13;
     }
     
+    /// Reabre un HTF ya serializado (dominios tal cual, sin re-tokenizar)
+    pub fn from_htf(data: &[u8]) -> Result<Self> {
+        if data.len() < HTF_HEADER_SIZE {
+            anyhow::bail!("HTF too small: {} bytes", data.len());
+        }
+        let use_v13 = match &data[0..4] {
+            m if m == HTF3_MAGIC => true,
+            m if m == HTF_MAGIC => false,
+            m => anyhow::bail!("Unsupported HTF magic {:?}", m),
+        };
+        
+        let num_domains = data[8] as usize;
+        let mut domains = Vec::with_capacity(num_domains);
+        for idx in 0..num_domains {
+            let start = HTF_HEADER_SIZE + idx * HTF_DOMAIN_ENTRY_SIZE;
+            let entry = data.get(start..start + HTF_DOMAIN_ENTRY_SIZE)
+                .ok_or_else(|| anyhow::anyhow!("HTF domain table truncated"))?;
+            let offset = u64::from_le_bytes(entry[8..16].try_into().unwrap()) as usize;
+            let size = u64::from_le_bytes(entry[16..24].try_into().unwrap()) as usize;
+            let domain_data = data.get(offset..offset + size)
+                .ok_or_else(|| anyhow::anyhow!("HTF domain {} exceeds blob", idx))?;
+            
+            domains.push(DomainEntry {
+                domain_type: entry[0],
+                domain_flags: entry[1],
+                vocab_size: u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+                data: domain_data.to_vec(),
+            });
+        }
+        
+        Ok(Self { domains, use_v13 })
+    }
+    
     /// Añade dominio TEXT con vocab y merges
     pub fn add_text_domain(
         &mut self,
         vocab: &HashMap<String, u32>,
-        merges: &[String],
+        merges: &[MergePair],
         config: &Value,
         is_primary: bool,
-    ) {
-        self.add_domain_internal(HTF_DOMAIN_TEXT, vocab, merges, config, is_primary);
+    ) -> Result<()> {
+        self.add_domain_internal(HTF_DOMAIN_TEXT, vocab, &TokenScores::new(), merges, config, is_primary)
     }
     
     /// Añade dominio CODE con vocab y merges
     pub fn add_code_domain(
         &mut self,
         vocab: &HashMap<String, u32>,
-        merges: &[String],
+        merges: &[MergePair],
         config: &Value,
         is_primary: bool,
-    ) {
-        self.add_domain_internal(HTF_DOMAIN_CODE, vocab, merges, config, is_primary);
+    ) -> Result<()> {
+        self.add_domain_internal(HTF_DOMAIN_CODE, vocab, &TokenScores::new(), merges, config, is_primary)
     }
     
-    /// Añade dominio AUDIO con vocab y merges
+    /// Añade dominio AUDIO con vocab y merges.
+    /// `codebooks` (RVQ: Mimi, SNAC) reemplaza config["codebooks"] si no está vacío.
     pub fn add_audio_domain(
         &mut self,
         vocab: &HashMap<String, u32>,
-        merges: &[String],
+        merges: &[MergePair],
         config: &Value,
+        codebooks: &[CodebookEntryBin],
         is_primary: bool,
-    ) {
-        self.add_domain_internal(HTF_DOMAIN_AUDIO, vocab, merges, config, is_primary);
+    ) -> Result<()> {
+        let mut config = config.clone();
+        if let (false, Some(obj)) = (codebooks.is_empty(), config.as_object_mut()) {
+            obj.insert("codebooks".to_string(), codebooks.iter().map(CodebookEntryBin::to_json).collect());
+        }
+        self.add_domain_internal(HTF_DOMAIN_AUDIO, vocab, &TokenScores::new(), merges, &config, is_primary)
+    }
+    
+    /// Añade un dominio con scores por token (Unigram). En v1.2 los scores
+    /// no tienen representación y se descartan.
+    pub fn add_scored_domain(
+        &mut self,
+        domain_type: DomainType,
+        vocab: &HashMap<String, u32>,
+        scores: &TokenScores,
+        merges: &[MergePair],
+        config: &Value,
+        is_primary: bool,
+    ) -> Result<()> {
+        self.add_domain_internal(domain_type.to_u8(), vocab, scores, merges, config, is_primary)
     }
     
     /// Añade dominio genérico (interno)
@@ -249,10 +455,18 @@ impl HTFWriter {
         &mut self,
         domain_type: u8,
         vocab: &HashMap<String, u32>,
-        merges: &[String],
+        scores: &TokenScores,
+        merges: &[MergePair],
         config: &Value,
         is_primary: bool,
-    ) {
+    ) -> Result<()> {
+        // Los builders escriben los conteos como u32: fallar antes que truncar
+        check_domain_count("vocab", vocab.len())?;
+        check_domain_count("merges", merges.len())?;
+        if let Some(msg) = merge_count_warning(vocab.len(), merges.len()) {
+            eprintln!("[WARN] {}", msg);
+        }
+        
         let mut flags: u8 = 0;
         if !vocab.is_empty() {
             flags |= HTF_FLAG_HAS_VOCAB;
@@ -266,7 +480,7 @@ impl HTFWriter {
         
         // Elegir formato según versión
         let data = if self.use_v13 {
-            Self::build_domain_data_v13(domain_type, vocab, merges, config)
+            Self::build_domain_data_v13(domain_type, vocab, scores, merges, config)
         } else {
             Self::build_domain_data_v12(vocab, merges, config)
         };
@@ -277,12 +491,13 @@ impl HTFWriter {
             vocab_size: vocab.len() as u32,
             data,
         });
+        Ok(())
     }
     
     /// Build domain data para HTF v1.2 (JSON config)
     fn build_domain_data_v12(
         vocab: &HashMap<String, u32>,
-        merges: &[String],
+        merges: &[MergePair],
         config: &Value,
     ) -> Vec<u8> {
         let mut buf = Vec::new();
@@ -334,7 +549,7 @@ impl HTFWriter {
                     token_flags |= TOKEN_FLAG_ADDED;    // 0x10
                 }
                 
-                let score_type: u8 = 0; // NONE
+                let score_type = SCORE_TYPE_NONE;  // v1.2 no lleva scores
                 
                 // TokenEntry: u32 token_id, u16 token_len, u8 flags, u8 score_type
                 buf.extend_from_slice(&token_id.to_le_bytes());
@@ -348,17 +563,7 @@ impl HTFWriter {
         
         // Merges (contractual: pares de IDs)
         if !merges.is_empty() && !vocab.is_empty() {
-            let mut merge_pairs: Vec<(u32, u32)> = Vec::new();
-            
-            for m in merges {
-                // Soportar formato "token_a token_b" y también arrays ["a", "b"]
-                let parts: Vec<&str> = m.split(' ').collect();
-                if parts.len() == 2 {
-                    if let (Some(&a), Some(&b)) = (vocab.get(parts[0]), vocab.get(parts[1])) {
-                        merge_pairs.push((a, b));
-                    }
-                }
-            }
+            let merge_pairs = merge_id_pairs(vocab, merges);
             
             buf.extend_from_slice(&(merge_pairs.len() as u32).to_le_bytes());
             for (a, b) in merge_pairs {
@@ -374,7 +579,8 @@ impl HTFWriter {
     fn build_domain_data_v13(
         domain_type: u8,
         vocab: &HashMap<String, u32>,
-        merges: &[String],
+        scores: &TokenScores,
+        merges: &[MergePair],
         config: &Value,
     ) -> Vec<u8> {
         let mut buf = Vec::new();
@@ -441,9 +647,16 @@ impl HTFWriter {
             HTF_DOMAIN_AUDIO => {
                 // AudioDomainConfigBin (64 bytes)
                 let audio_config = AudioDomainConfigBin::from_config(config);
+                buf.reserve(audio_config.data_size());
                 buf.extend_from_slice(&audio_config.to_bytes());
-                // Audio puede tener codebook, pero vocab normal no
-                // Por ahora retornamos solo config
+                // RVQ: num_codebooks × CodebookEntryBin (16 bytes)
+                if audio_config.flags & AUDIO_FLAG_MULTI_CODEBOOK != 0 {
+                    for codebook in codebooks_from_config(config) {
+                        buf.extend_from_slice(&codebook.to_bytes());
+                    }
+                }
+                debug_assert_eq!(buf.len(), audio_config.data_size());
+                // Audio no tiene vocab normal, retornar solo config
                 return buf;
             }
             _ => {
@@ -464,12 +677,28 @@ impl HTFWriter {
             let mut sorted: Vec<_> = vocab.iter().collect();
             sorted.sort_by_key(|(_, &id)| id);
             
+            // byte-level: guardar bytes crudos (Ġ → 0x20) salvo added/special,
+            // cuyo contenido es literal
+            let raw_bytes = config.get("byte_level").and_then(|v| v.as_bool()).unwrap_or(false);
+            let mut replacement_tokens = 0usize;
+            
             for (token, &token_id) in sorted {
-                let token_bytes = token.as_bytes();
+                let raw = if raw_bytes && !added_ids.contains(&token_id) && !special_ids.contains(&token_id) {
+                    decode_byte_level(token)
+                } else {
+                    None
+                };
+                let token_bytes = raw.as_deref().unwrap_or(token.as_bytes());
                 let token_len = token_bytes.len() as u16;
+                if token.contains('\u{FFFD}') {
+                    replacement_tokens += 1;
+                }
                 
                 // Token flags (§7 spec)
                 let mut token_flags: u8 = 0;
+                if raw.is_some() {
+                    token_flags |= TOKEN_FLAG_RAW_BYTES;
+                }
                 if special_ids.contains(&token_id) {
                     token_flags |= TOKEN_FLAG_SPECIAL;
                 }
@@ -486,29 +715,31 @@ impl HTFWriter {
                     token_flags |= TOKEN_FLAG_ADDED;
                 }
                 
-                let score_type: u8 = 0;
+                let score = scores.get(&token_id);
+                let score_type = if score.is_some() { SCORE_TYPE_F32 } else { SCORE_TYPE_NONE };
                 
                 buf.extend_from_slice(&token_id.to_le_bytes());
                 buf.extend_from_slice(&token_len.to_le_bytes());
                 buf.push(token_flags);
                 buf.push(score_type);
+                if let Some(score) = score {
+                    buf.extend_from_slice(&score.to_le_bytes());
+                }
                 buf.extend_from_slice(token_bytes);
                 pad_to(&mut buf, 4);
             }
+            
+            if replacement_tokens > 0 {
+                eprintln!(
+                    "[WARN] {} vocab tokens contain U+FFFD (invalid UTF-8 in source, stored as-is)",
+                    replacement_tokens
+                );
+            }
         }
         
         // 3. Merges (mismo formato que v1.2)
         if !merges.is_empty() && !vocab.is_empty() {
-            let mut merge_pairs: Vec<(u32, u32)> = Vec::new();
-            
-            for m in merges {
-                let parts: Vec<&str> = m.split(' ').collect();
-                if parts.len() == 2 {
-                    if let (Some(&a), Some(&b)) = (vocab.get(parts[0]), vocab.get(parts[1])) {
-                        merge_pairs.push((a, b));
-                    }
-                }
-            }
+            let merge_pairs = merge_id_pairs(vocab, merges);
             
             buf.extend_from_slice(&(merge_pairs.len() as u32).to_le_bytes());
             for (a, b) in merge_pairs {
@@ -522,10 +753,42 @@ impl HTFWriter {
     
     /// Construye el archivo HTF completo (contractual)
     pub fn build(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        self.build_to(&mut result).expect("writing to a Vec cannot fail");
+        result
+    }
+    
+    /// Escribe el HTF en `out` sin ensamblarlo en memoria: los datos de cada
+    /// dominio se emiten tal cual. Dos pasadas sobre los dominios: la primera
+    /// calcula el checksum (va en el header), la segunda escribe.
+    /// Devuelve (total_size, checksum).
+    pub fn build_to<W: Write + ?Sized>(&self, out: &mut W) -> io::Result<(u64, u64)> {
         if self.domains.is_empty() {
-            return self.build_empty();
+            let empty = self.build_empty();
+            out.write_all(&empty)?;
+            let checksum = u64::from_le_bytes(empty[24..32].try_into().unwrap());
+            return Ok((empty.len() as u64, checksum));
         }
         
+        let (mut head, offsets, total_size) = self.build_head();
+        
+        // Pasada 1: checksum (Regla 6) con el campo checksum a cero en `head`
+        let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+        self.emit_body(&head, &offsets, total_size, |chunk| {
+            hasher.update(chunk);
+            Ok(())
+        })?;
+        let checksum = hasher.digest();
+        head[24..32].copy_from_slice(&checksum.to_le_bytes());
+        
+        // Pasada 2: escribir
+        self.emit_body(&head, &offsets, total_size, |chunk| out.write_all(chunk))?;
+        Ok((total_size, checksum))
+    }
+    
+    /// Header + domain table (checksum a cero) y offsets de cada dominio.
+    /// Los tamaños se conocen de antemano, así que no hace falta backpatch.
+    fn build_head(&self) -> (Vec<u8>, Vec<u64>, u64) {
         let num_domains = self.domains.len() as u8;
         
         // HTF header flags (§4)
@@ -537,34 +800,23 @@ impl HTFWriter {
             htf_flags |= HTF_HEADER_HAS_CODEBOOK;  // 0x0001
         }
         
-        let mut result = Vec::new();
-        
-        // HEADER placeholder (32 bytes)
-        result.extend_from_slice(&[0u8; HTF_HEADER_SIZE]);
-        
-        // DOMAIN TABLE placeholder (N * 32)
-        let domain_table_offset = result.len();
-        result.extend(std::iter::repeat(0u8).take(num_domains as usize * HTF_DOMAIN_ENTRY_SIZE));
-        
-        // Alinear a 16 antes del primer dominio (Regla 4: data_offset alineado a 16)
-        pad_to(&mut result, 16);
+        let align = |pos: u64, alignment: u64| pos.div_ceil(alignment) * alignment;
         
+        // DOMAIN DATA, cada dominio empieza alineado a 16 (Regla 4)
+        let table_end = (HTF_HEADER_SIZE + num_domains as usize * HTF_DOMAIN_ENTRY_SIZE) as u64;
+        let mut pos = table_end;
         let mut domain_offsets: Vec<u64> = Vec::new();
-        let mut domain_sizes: Vec<u64> = Vec::new();
-        
-        // DOMAIN DATA, cada dominio empieza alineado a 16
         for domain in &self.domains {
-            pad_to(&mut result, 16);
-            domain_offsets.push(result.len() as u64);
-            domain_sizes.push(domain.data.len() as u64);
-            result.extend_from_slice(&domain.data);
+            pos = align(pos, 16);
+            domain_offsets.push(pos);
+            pos += domain.data.len() as u64;
         }
         
         // Añadir padding final para que el HTF sea múltiplo de 32 bytes
         // Esto evita que el HNF writer añada padding externo
-        pad_to(&mut result, 32);
+        let total_size = align(pos, 32);
         
-        let total_size = result.len() as u64;
+        let mut head = vec![0u8; table_end as usize];
         
         // Escribir domain table
         for (idx, domain) in self.domains.iter().enumerate() {
@@ -577,38 +829,53 @@ impl HTFWriter {
             };
             let name_hash = xxh3_64(name.as_bytes());
             
-            let start = domain_table_offset + idx * HTF_DOMAIN_ENTRY_SIZE;
+            let start = HTF_HEADER_SIZE + idx * HTF_DOMAIN_ENTRY_SIZE;
             
             // Domain entry: type(1) + flags(1) + reserved(2) + vocab_size(4) + offset(8) + size(8) + hash(8)
-            result[start] = domain.domain_type;
-            result[start + 1] = domain.domain_flags;
-            result[start + 2..start + 4].copy_from_slice(&0u16.to_le_bytes());
-            result[start + 4..start + 8].copy_from_slice(&domain.vocab_size.to_le_bytes());
-            result[start + 8..start + 16].copy_from_slice(&domain_offsets[idx].to_le_bytes());
-            result[start + 16..start + 24].copy_from_slice(&domain_sizes[idx].to_le_bytes());
-            result[start + 24..start + 32].copy_from_slice(&name_hash.to_le_bytes());
+            head[start] = domain.domain_type;
+            head[start + 1] = domain.domain_flags;
+            head[start + HTF_ENTRY_RESERVED.start..start + HTF_ENTRY_RESERVED.end].fill(0);
+            head[start + 4..start + 8].copy_from_slice(&domain.vocab_size.to_le_bytes());
+            head[start + 8..start + 16].copy_from_slice(&domain_offsets[idx].to_le_bytes());
+            head[start + 16..start + 24].copy_from_slice(&(domain.data.len() as u64).to_le_bytes());
+            head[start + 24..start + 32].copy_from_slice(&name_hash.to_le_bytes());
         }
         
-        // Escribir HEADER (sin checksum primero)
+        // Escribir HEADER (checksum se rellena en build_to)
         // Usar magic y version según versión configurada
         if self.use_v13 {
-            result[0..4].copy_from_slice(HTF3_MAGIC);
-            result[4..6].copy_from_slice(&HTF3_VERSION.to_le_bytes());  // 0x0130
+            head[0..4].copy_from_slice(HTF3_MAGIC);
+            head[4..6].copy_from_slice(&HTF3_VERSION.to_le_bytes());  // 0x0130
         } else {
-            result[0..4].copy_from_slice(HTF_MAGIC);
-            result[4..6].copy_from_slice(&HTF_VERSION.to_le_bytes());  // 0x0103
+            head[0..4].copy_from_slice(HTF_MAGIC);
+            head[4..6].copy_from_slice(&HTF_VERSION.to_le_bytes());  // 0x0103
         }
-        result[6..8].copy_from_slice(&htf_flags.to_le_bytes());
-        result[8] = num_domains;
-        // [9:16] ya son zeros (reserved)
-        result[16..24].copy_from_slice(&total_size.to_le_bytes());
-        // [24:32] checksum placeholder (zeros)
+        head[6..8].copy_from_slice(&htf_flags.to_le_bytes());
+        head[8] = num_domains;
+        head[HTF_HEADER_RESERVED].fill(0);
+        head[16..24].copy_from_slice(&total_size.to_le_bytes());
         
-        // Calcular checksum y parchear
-        let checksum = compute_htf_checksum(&result);
-        result[24..32].copy_from_slice(&checksum.to_le_bytes());
+        (head, domain_offsets, total_size)
+    }
+    
+    /// Emite head, padding y datos de dominio en orden de archivo
+    fn emit_body(
+        &self,
+        head: &[u8],
+        offsets: &[u64],
+        total_size: u64,
+        mut emit: impl FnMut(&[u8]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        const ZEROS: [u8; 32] = [0u8; 32];
         
-        result
+        emit(head)?;
+        let mut pos = head.len() as u64;
+        for (domain, &offset) in self.domains.iter().zip(offsets) {
+            emit(&ZEROS[..(offset - pos) as usize])?;
+            emit(&domain.data)?;
+            pos = offset + domain.data.len() as u64;
+        }
+        emit(&ZEROS[..(total_size - pos) as usize])
     }
     
     fn build_empty(&self) -> Vec<u8> {
@@ -655,12 +922,118 @@ impl Default for HTFWriter {
 // INTERNAL: Load tokenizer from model directory
 // ============================================================================
 
-fn load_tokenizer_from_dir(dir: &Path) -> Result<(HashMap<String, u32>, Vec<String>, serde_json::Map<String, Value>)> {
+/// (vocab, merges, config, scores) de un directorio de modelo
+type LoadedTokenizer = (HashMap<String, u32>, Vec<MergePair>, serde_json::Map<String, Value>, TokenScores);
+
+/// IDs de tokens especiales que pueden venir de varios archivos de config
+const SPECIAL_ID_KEYS: [&str; 4] = ["bos_token_id", "eos_token_id", "unk_token_id", "pad_token_id"];
+
+/// --prefer-config: qué archivo manda cuando los IDs especiales no coinciden
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigPreference {
+    /// tokenizer_config.json > config.json > generation_config.json
+    #[default]
+    Tokenizer,
+    /// generation_config.json > tokenizer_config.json > config.json
+    Generation,
+}
+
+impl ConfigPreference {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "tokenizer" => Some(Self::Tokenizer),
+            "generation" => Some(Self::Generation),
+            _ => None,
+        }
+    }
+    
+    /// Archivos de config en orden de precedencia
+    pub fn order(&self) -> [&'static str; 3] {
+        match self {
+            Self::Tokenizer => ["tokenizer_config.json", "config.json", "generation_config.json"],
+            Self::Generation => ["generation_config.json", "tokenizer_config.json", "config.json"],
+        }
+    }
+}
+
+/// Un token especial con valores distintos según el archivo de config
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecialIdConflict {
+    pub key: String,
+    /// (archivo, valor) en orden de precedencia
+    pub values: Vec<(&'static str, Value)>,
+    /// Archivo cuyo valor se aplicó
+    pub chosen: &'static str,
+}
+
+impl std::fmt::Display for SpecialIdConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values: Vec<String> = self.values.iter()
+            .map(|(file, v)| format!("{}={}", file, v))
+            .collect();
+        write!(f, "{} disagrees: {} -> using {}", self.key, values.join(", "), self.chosen)
+    }
+}
+
+/// IDs de un valor de config (escalar o lista, p.ej. eos_token_id de Qwen3)
+fn special_ids(v: &Value) -> Vec<u64> {
+    match v {
+        Value::Array(arr) => arr.iter().filter_map(|x| x.as_u64()).collect(),
+        _ => v.as_u64().into_iter().collect(),
+    }
+}
+
+/// Subconjunto de `config` con solo `keys`
+fn pick_keys(config: &Value, keys: &[&str]) -> Value {
+    Value::Object(keys.iter()
+        .filter_map(|key| config.get(*key).map(|v| (key.to_string(), v.clone())))
+        .collect())
+}
+
+/// Combina los IDs especiales de `sources` (archivo, config) según `prefer`.
+///
+/// Gana el primer archivo en orden de precedencia que define la clave (null
+/// no cuenta). Una lista se guarda por su primer ID y coincide con cualquier
+/// otro archivo cuyo ID contenga. Devuelve las claves resueltas y los desacuerdos.
+pub fn resolve_special_ids(
+    sources: &[(&'static str, &Value)],
+    prefer: ConfigPreference,
+) -> (serde_json::Map<String, Value>, Vec<SpecialIdConflict>) {
+    let mut resolved = serde_json::Map::new();
+    let mut conflicts = Vec::new();
+    
+    for key in SPECIAL_ID_KEYS {
+        let values: Vec<(&'static str, Value)> = prefer.order().iter()
+            .filter_map(|file| sources.iter().find(|(name, _)| name == file))
+            .filter_map(|(file, config)| config.get(key).filter(|v| !v.is_null()).map(|v| (*file, v.clone())))
+            .collect();
+        let Some((chosen, value)) = values.first() else { continue };
+        
+        let chosen_id = special_ids(value).first().copied();
+        resolved.insert(key.to_string(), match chosen_id {
+            Some(id) => Value::Number(id.into()),
+            None => value.clone(),
+        });
+        
+        if values.iter().any(|(_, v)| chosen_id.is_none_or(|id| !special_ids(v).contains(&id))) {
+            conflicts.push(SpecialIdConflict { key: key.to_string(), values: values.clone(), chosen });
+        }
+    }
+    
+    (resolved, conflicts)
+}
+
+fn load_tokenizer_from_dir(dir: &Path) -> Result<LoadedTokenizer> {
+    load_tokenizer_with(dir, ConfigPreference::default())
+}
+
+/// Como load_tokenizer_from_dir con precedencia explícita de IDs especiales
+fn load_tokenizer_with(dir: &Path, prefer: ConfigPreference) -> Result<LoadedTokenizer> {
     // Leer tokenizer.json (puede no existir en modelos legacy)
     let tokenizer_path = dir.join("tokenizer.json");
     let tokenizer: Value = if tokenizer_path.exists() {
         let data = std::fs::read_to_string(&tokenizer_path)?;
-        serde_json::from_str(&data)?
+        parse_json_lossy(&data, "tokenizer.json")?
     } else {
         Value::Null
     };
@@ -668,16 +1041,28 @@ fn load_tokenizer_from_dir(dir: &Path) -> Result<(HashMap<String, u32>, Vec<Stri
     // ════════════════════════════════════════════════════════════════════════
     // VOCAB: Intentar tokenizer.json["model"]["vocab"] primero
     // ════════════════════════════════════════════════════════════════════════
-    let mut vocab: HashMap<String, u32> = tokenizer
-        .get("model")
-        .and_then(|m| m.get("vocab"))
-        .and_then(|v| v.as_object())
-        .map(|obj| {
-            obj.iter()
-                .filter_map(|(k, v)| v.as_u64().map(|id| (k.clone(), id as u32)))
-                .collect()
-        })
-        .unwrap_or_default();
+    let is_unigram = tokenizer.get("model")
+        .and_then(|m| m.get("type"))
+        .and_then(|v| v.as_str()) == Some("Unigram");
+    let mut scores = TokenScores::new();
+    
+    let mut vocab: HashMap<String, u32> = if is_unigram {
+        // Unigram: [[token, score], ...], el índice es el token_id
+        let (vocab, unigram_scores) = parse_unigram_vocab(&tokenizer);
+        scores = unigram_scores;
+        vocab
+    } else {
+        tokenizer
+            .get("model")
+            .and_then(|m| m.get("vocab"))
+            .and_then(|v| v.as_object())
+            .map(|obj| {
+                obj.iter()
+                    .filter_map(|(k, v)| v.as_u64().map(|id| (k.clone(), id as u32)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
     
     // ════════════════════════════════════════════════════════════════════════
     // v1.2.2 FALLBACK: Si vocab vacío, leer vocab.json (Phi-4, GPT-2 format)
@@ -686,7 +1071,7 @@ fn load_tokenizer_from_dir(dir: &Path) -> Result<(HashMap<String, u32>, Vec<Stri
         let vocab_path = dir.join("vocab.json");
         if vocab_path.exists() {
             let vocab_data = std::fs::read_to_string(&vocab_path)?;
-            let vocab_json: Value = serde_json::from_str(&vocab_data)?;
+            let vocab_json = parse_json_lossy(&vocab_data, "vocab.json")?;
             if let Some(obj) = vocab_json.as_object() {
                 vocab = obj.iter()
                     .filter_map(|(k, v)| v.as_u64().map(|id| (k.clone(), id as u32)))
@@ -698,22 +1083,20 @@ fn load_tokenizer_from_dir(dir: &Path) -> Result<(HashMap<String, u32>, Vec<Stri
     
     // Si aún vacío y no hay tokenizer.json, devolver vacío
     if vocab.is_empty() && tokenizer.is_null() {
-        return Ok((HashMap::new(), Vec::new(), serde_json::Map::new()));
+        return Ok((HashMap::new(), Vec::new(), serde_json::Map::new(), TokenScores::new()));
     }
     
     // ════════════════════════════════════════════════════════════════════════
     // MERGES: Intentar tokenizer.json["model"]["merges"] primero
+    // ("a b" o ["a", "b"], ver parse_merge)
     // ════════════════════════════════════════════════════════════════════════
-    let mut merges: Vec<String> = tokenizer
-        .get("model")
-        .and_then(|m| m.get("merges"))
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                .collect()
-        })
-        .unwrap_or_default();
+    let mut merges: Vec<MergePair> = Vec::new();
+    if let Some(arr) = tokenizer.get("model").and_then(|m| m.get("merges")).and_then(|v| v.as_array()) {
+        merges = arr.iter().filter_map(parse_merge).collect();
+        if merges.len() < arr.len() {
+            eprintln!("[WARN] {} malformed merges skipped", arr.len() - merges.len());
+        }
+    }
     
     // ════════════════════════════════════════════════════════════════════════
     // v1.2.2 FALLBACK: Si merges vacío, leer merges.txt (Phi-4, GPT-2 format)
@@ -731,7 +1114,7 @@ fn load_tokenizer_from_dir(dir: &Path) -> Result<(HashMap<String, u32>, Vec<Stri
                     && !line.starts_with("# ")
                     && !line.starts_with("#")
                 })
-                .map(|s| s.to_string())
+                .filter_map(parse_merge_line)
                 .collect();
             println!("  [HTF] Loaded merges from merges.txt: {} merges", merges.len());
         }
@@ -740,67 +1123,64 @@ fn load_tokenizer_from_dir(dir: &Path) -> Result<(HashMap<String, u32>, Vec<Stri
     // Construir config
     let mut config = serde_json::Map::new();
     
+    // IDs especiales: (archivo, config) de cada fuente, ver resolve_special_ids
+    let mut special_sources: Vec<(&'static str, Value)> = Vec::new();
+    
     // Leer tokenizer_config.json (prioridad 1 según §17)
     let tok_config_path = dir.join("tokenizer_config.json");
     if tok_config_path.exists() {
         let data = std::fs::read_to_string(&tok_config_path)?;
-        let tok_config: Value = serde_json::from_str(&data)?;
+        let tok_config = crate::json::parse(&data, "tokenizer_config.json")?;
         
-        for key in &["bos_token_id", "eos_token_id", "unk_token_id", "pad_token_id", 
-                     "tokenizer_class", "added_tokens_decoder", "chat_template"] {
+        for key in &["tokenizer_class", "added_tokens_decoder", "chat_template"] {
             if let Some(v) = tok_config.get(*key) {
                 config.insert(key.to_string(), v.clone());
             }
         }
+        special_sources.push(("tokenizer_config.json", pick_keys(&tok_config, &SPECIAL_ID_KEYS)));
     }
     
     // Leer config.json para tokens especiales
     let model_config_path = dir.join("config.json");
     if model_config_path.exists() {
         let data = std::fs::read_to_string(&model_config_path)?;
-        let model_config: Value = serde_json::from_str(&data)?;
+        let model_config = crate::json::parse(&data, "config.json")?;
         
-        for key in &["bos_token_id", "eos_token_id", "vocab_size"] {
-            if !config.contains_key(*key) {
-                if let Some(v) = model_config.get(*key) {
-                    config.insert(key.to_string(), v.clone());
-                }
-            }
+        if let Some(v) = model_config.get("vocab_size") {
+            config.insert("vocab_size".to_string(), v.clone());
         }
+        special_sources.push(("config.json", pick_keys(&model_config, &["bos_token_id", "eos_token_id"])));
     }
     
     // Leer generation_config.json
     let gen_config_path = dir.join("generation_config.json");
     if gen_config_path.exists() {
         let data = std::fs::read_to_string(&gen_config_path)?;
-        let gen_config: Value = serde_json::from_str(&data)?;
-        
-        for key in &["bos_token_id", "eos_token_id", "pad_token_id"] {
-            if let Some(v) = gen_config.get(*key) {
-                // Manejar eos_token_id como array (Qwen3, etc.)
-                if *key == "eos_token_id" && v.is_array() {
-                    // Guardar el primero como eos_token_id
-                    if !config.contains_key(*key) {
-                        if let Some(first) = v.as_array().and_then(|a| a.first()) {
-                            config.insert(key.to_string(), first.clone());
-                        }
-                    }
-                    // TAMBIÉN guardar la lista completa como eos_token_ids
-                    if !config.contains_key("eos_token_ids") {
-                        config.insert("eos_token_ids".to_string(), v.clone());
-                    }
-                } else if !config.contains_key(*key) {
-                    config.insert(key.to_string(), v.clone());
-                }
-            }
+        let gen_config = crate::json::parse(&data, "generation_config.json")?;
+        
+        // Manejar eos_token_id como array (Qwen3, etc.): guardar la lista
+        // completa como eos_token_ids
+        if let Some(v) = gen_config.get("eos_token_id").filter(|v| v.is_array()) {
+            config.insert("eos_token_ids".to_string(), v.clone());
         }
+        special_sources.push((
+            "generation_config.json",
+            pick_keys(&gen_config, &["bos_token_id", "eos_token_id", "pad_token_id"]),
+        ));
     }
     
+    let sources: Vec<(&'static str, &Value)> = special_sources.iter().map(|(f, v)| (*f, v)).collect();
+    let (special, conflicts) = resolve_special_ids(&sources, prefer);
+    for conflict in &conflicts {
+        eprintln!("[WARN] Special token {}", conflict);
+    }
+    config.extend(special);
+    
     // Leer added_tokens.json (prioridad 2 según §17)
     let added_tokens_path = dir.join("added_tokens.json");
     if added_tokens_path.exists() {
         let data = std::fs::read_to_string(&added_tokens_path)?;
-        let added: Value = serde_json::from_str(&data)?;
+        let added = crate::json::parse(&data, "added_tokens.json")?;
         
         if let Some(obj) = added.as_object() {
             let mut decoder = config
@@ -861,7 +1241,9 @@ fn load_tokenizer_from_dir(dir: &Path) -> Result<(HashMap<String, u32>, Vec<Stri
     
     let tok_model_exists = dir.join("tokenizer.model").exists();
     
-    let encoding_type = if tok_model_exists {
+    let encoding_type = if is_unigram {
+        "unigram"
+    } else if tok_model_exists {
         "sentencepiece"
     } else if tokenizer_class.to_lowercase().contains("sentencepiece") 
         || tokenizer_class == "LlamaTokenizer" 
@@ -873,13 +1255,85 @@ fn load_tokenizer_from_dir(dir: &Path) -> Result<(HashMap<String, u32>, Vec<Stri
     };
     
     // Detectar byte_level (§17: presencia de Ġ, Ċ en vocab)
-    let byte_level = vocab.keys().any(|k| k.contains('Ġ') || k.contains('Ċ'));
+    let mut byte_level = vocab.keys().any(|k| k.contains('Ġ') || k.contains('Ċ'));
+    
+    // Normalizer / pre-tokenizer (§4.3.1): determinan la segmentación exacta
+    if let Some(pipeline) = TokenizerPipeline::from_tokenizer_json(&tokenizer) {
+        byte_level |= pipeline.byte_level;
+        config.insert("normalizer_flags".to_string(), Value::from(pipeline.normalizer_flags));
+        config.insert("pretokenizer_type".to_string(), Value::from(pipeline.pretokenizer_type));
+        config.insert("pretokenizer_flags".to_string(), Value::from(pipeline.pretokenizer_flags));
+        if let Some(add_prefix_space) = pipeline.add_prefix_space {
+            config.insert("add_prefix_space".to_string(), Value::Bool(add_prefix_space));
+        }
+    }
+    
+    // byte_fallback (SentencePiece): OOV se codifica byte a byte como <0xNN>.
+    // Muchos vocabs no enumeran los 256; el engine debe caer a bytes igualmente.
+    let byte_fallback = tokenizer.get("model")
+        .and_then(|m| m.get("byte_fallback"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if byte_fallback {
+        let present = vocab.keys().filter(|k| is_byte_token(k)).count();
+        if present < 256 {
+            eprintln!(
+                "[INFO] byte_fallback enabled but only {}/256 <0xNN> tokens in vocab; flagging domain for engine fallback",
+                present
+            );
+        }
+        config.insert("byte_fallback".to_string(), Value::Bool(true));
+    }
+    
+    // Esquema de espacios explícito (decoder > pre_tokenizer > normalizer);
+    // sin ninguno, el vocab: Ġ → ByteLevel, ▁ → Metaspace
+    let space = match SpaceScheme::from_tokenizer_json(&tokenizer) {
+        SpaceScheme::None if byte_level => SpaceScheme::ByteLevel,
+        SpaceScheme::None if vocab.keys().any(|k| k.starts_with('▁')) => SpaceScheme::Metaspace('▁'),
+        scheme => scheme,
+    };
+    config.insert("space_scheme".to_string(), Value::String(space.name().to_string()));
+    if let SpaceScheme::Metaspace(c) = space {
+        config.insert("space_replacement".to_string(), Value::String(c.to_string()));
+    }
     
     config.insert("encoding_type".to_string(), Value::String(encoding_type.to_string()));
     config.insert("byte_level".to_string(), Value::Bool(byte_level));
+    
+    // vocab_size de config.json = filas reales del embedding (puede incluir padding)
+    if let Some(rows) = config.get("vocab_size").cloned() {
+        config.insert("embedding_rows".to_string(), rows);
+    }
     config.insert("vocab_size".to_string(), Value::Number(vocab.len().into()));
     
-    Ok((vocab, merges, config))
+    // Unigram declara el unk en el modelo, no en tokenizer_config
+    if is_unigram && !config.contains_key("unk_token_id") {
+        if let Some(unk_id) = tokenizer.get("model").and_then(|m| m.get("unk_id")).filter(|v| v.is_u64()) {
+            config.insert("unk_token_id".to_string(), unk_id.clone());
+        }
+    }
+    
+    Ok((vocab, merges, config, scores))
+}
+
+/// Vocab Unigram de tokenizer.json: model.vocab = [[token, score], ...]
+fn parse_unigram_vocab(tokenizer: &Value) -> (HashMap<String, u32>, TokenScores) {
+    let mut vocab = HashMap::new();
+    let mut scores = TokenScores::new();
+    
+    let entries = tokenizer.get("model")
+        .and_then(|m| m.get("vocab"))
+        .and_then(|v| v.as_array());
+    for (id, entry) in entries.into_iter().flatten().enumerate() {
+        let Some(token) = entry.get(0).and_then(|v| v.as_str()) else { continue };
+        let id = id as u32;
+        vocab.insert(token.to_string(), id);
+        if let Some(score) = entry.get(1).and_then(|v| v.as_f64()) {
+            scores.insert(id, score as f32);
+        }
+    }
+    
+    (vocab, scores)
 }
 
 // ============================================================================
@@ -930,6 +1384,148 @@ impl DomainType {
 /// let htf_bytes = build_htf_multi_versioned(&sources, true)?;  // v1.3
 /// ```
 pub fn build_htf_multi_versioned(sources: &[(&Path, DomainType, bool)], use_v13: bool) -> Result<Vec<u8>> {
+    build_htf_multi_with(sources, &HtfOptions { use_v13, ..Default::default() })
+}
+
+/// Opciones de construcción del HTF
+#[derive(Debug, Clone)]
+pub struct HtfOptions {
+    /// true = HTF v1.3 (binario), false = v1.2 (JSON)
+    pub use_v13: bool,
+    /// Added tokens cuyo ID no cabe en el embedding: error en vez de warning
+    pub strict: bool,
+    /// --set-special: (clave de config, id), p.ej. ("eos_token_id", 151645).
+    /// Solo se aplican al dominio TEXT primario.
+    pub special_overrides: Vec<(String, u32)>,
+    /// --prefer-config: precedencia de IDs especiales entre archivos de config
+    pub prefer_config: ConfigPreference,
+    /// Filas del embedding de texto: el vocab del dominio TEXT primario se
+    /// recorta a los IDs < max_vocab (None = sin recorte)
+    pub max_vocab: Option<usize>,
+}
+
+impl Default for HtfOptions {
+    fn default() -> Self {
+        Self {
+            use_v13: true,
+            strict: false,
+            special_overrides: Vec::new(),
+            prefer_config: ConfigPreference::default(),
+            max_vocab: None,
+        }
+    }
+}
+
+/// Parsea "eos=151645,pad=151643" → [("eos_token_id", 151645), ("pad_token_id", 151643)]
+pub fn parse_special_overrides(spec: &str) -> Result<Vec<(String, u32)>> {
+    spec.split(',')
+        .filter(|part| !part.trim().is_empty())
+        .map(|part| {
+            let (key, id) = part.split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid special override '{}' (expected name=id)", part))?;
+            let key = match key.trim().to_lowercase().as_str() {
+                "bos" => "bos_token_id",
+                "eos" => "eos_token_id",
+                "pad" => "pad_token_id",
+                "unk" => "unk_token_id",
+                other => anyhow::bail!("Unknown special token '{}' (expected bos, eos, pad, unk)", other),
+            };
+            let id = id.trim().parse::<u32>()
+                .map_err(|_| anyhow::anyhow!("Invalid token id '{}' for {}", id.trim(), key))?;
+            Ok((key.to_string(), id))
+        })
+        .collect()
+}
+
+/// Aplica los overrides al config del dominio. Los IDs resultantes reciben
+/// TOKEN_FLAG_CONTROL en el vocab vía extract_control_ids.
+fn apply_special_overrides(config: &mut serde_json::Map<String, Value>, overrides: &[(String, u32)]) {
+    for (key, id) in overrides {
+        if let Some(old) = config.get(key).and_then(|v| v.as_u64()) {
+            if old != *id as u64 {
+                println!("  [HTF] Override {}: {} -> {}", key, old, id);
+            }
+        }
+        config.insert(key.clone(), Value::Number((*id).into()));
+        // La lista de EOS de generation_config queda obsoleta
+        if key == "eos_token_id" && config.contains_key("eos_token_ids") {
+            config.insert("eos_token_ids".to_string(), serde_json::json!([id]));
+        }
+    }
+}
+
+/// Recorta el vocab a los IDs < `cap` cuando el tokenizer exportado tiene más
+/// entradas que filas el embedding. Se descartan también los added tokens,
+/// scores y merges de esos IDs; los especiales por debajo del cap se
+/// conservan. `vocab_size` no se toca; el cap queda en `embedding_rows`.
+/// Devuelve los tokens descartados (ordenados por ID).
+pub fn truncate_vocab(
+    vocab: &mut HashMap<String, u32>,
+    merges: &mut Vec<MergePair>,
+    scores: &mut TokenScores,
+    config: &mut serde_json::Map<String, Value>,
+    cap: usize,
+) -> Vec<(u32, String)> {
+    let in_range = |id: u64| (id as usize) < cap;
+    
+    let mut dropped: Vec<(u32, String)> = vocab.iter()
+        .filter(|(_, &id)| !in_range(id as u64))
+        .map(|(token, &id)| (id, token.clone()))
+        .collect();
+    dropped.sort();
+    if dropped.is_empty() {
+        return dropped;
+    }
+    
+    vocab.retain(|_, id| in_range(*id as u64));
+    scores.retain(|id, _| in_range(*id as u64));
+    
+    // Merges con una parte o con el resultado fuera del vocab recortado
+    let dropped_tokens: HashSet<&str> = dropped.iter().map(|(_, t)| t.as_str()).collect();
+    merges.retain(|(a, b)| {
+        vocab.contains_key(a) && vocab.contains_key(b) && !dropped_tokens.contains(format!("{}{}", a, b).as_str())
+    });
+    
+    if let Some(decoder) = config.get_mut("added_tokens_decoder").and_then(|v| v.as_object_mut()) {
+        decoder.retain(|id, _| id.parse::<u64>().is_ok_and(in_range));
+    }
+    
+    // Un ID especial fuera del embedding no es utilizable: se quita del config
+    for key in SPECIAL_ID_KEYS {
+        if let Some(id) = config.get(key).and_then(|v| v.as_u64()).filter(|id| !in_range(*id)) {
+            eprintln!("[WARN] {} = {} is beyond the embedding ({} rows); dropped", key, id, cap);
+            config.remove(key);
+        }
+    }
+    if let Some(ids) = config.get_mut("eos_token_ids").and_then(|v| v.as_array_mut()) {
+        ids.retain(|id| id.as_u64().is_some_and(in_range));
+    }
+    
+    config.entry("embedding_rows").or_insert(Value::Number(cap.into()));
+    dropped
+}
+
+/// IDs que usa el tokenizer de `dir` (mayor ID de vocab o added tokens + 1),
+/// antes de cualquier recorte; None si no hay tokenizer
+pub fn tokenizer_id_count(dir: &Path) -> Result<Option<usize>> {
+    let (vocab, _, config, _) = load_tokenizer_from_dir(dir)?;
+    let added = config.get("added_tokens_decoder")
+        .and_then(|v| v.as_object())
+        .into_iter()
+        .flatten()
+        .filter_map(|(id, _)| id.parse::<usize>().ok());
+    Ok(vocab.values().map(|&id| id as usize).chain(added).max().map(|max| max + 1))
+}
+
+/// Construye HTF con MÚLTIPLES dominios/tokenizers según `options`
+pub fn build_htf_multi_with(sources: &[(&Path, DomainType, bool)], options: &HtfOptions) -> Result<Vec<u8>> {
+    Ok(htf_writer_multi_with(sources, options)?.build())
+}
+
+/// HTFWriter con los dominios de `sources` cargados, para volcarlo en
+/// streaming (HnfWriter::write_tokenizer_from) en vez de ensamblar el blob
+pub fn htf_writer_multi_with(sources: &[(&Path, DomainType, bool)], options: &HtfOptions) -> Result<HTFWriter> {
+    let HtfOptions { use_v13, strict, prefer_config, .. } = *options;
     let mut writer = if use_v13 {
         HTFWriter::new_v13()
     } else {
@@ -937,7 +1533,7 @@ pub fn build_htf_multi_versioned(sources: &[(&Path, DomainType, bool)], use_v13:
     };
     
     for (dir, domain_type, is_primary) in sources {
-        let (vocab, merges, mut config) = load_tokenizer_from_dir(dir)?;
+        let (mut vocab, mut merges, mut config, mut scores) = load_tokenizer_with(dir, prefer_config)?;
         
         if vocab.is_empty() {
             eprintln!("[HTF] Warning: No tokenizer found in {}, skipping", dir.display());
@@ -945,22 +1541,60 @@ pub fn build_htf_multi_versioned(sources: &[(&Path, DomainType, bool)], use_v13:
         }
         
         config.insert("is_primary".to_string(), Value::Bool(*is_primary));
+        let is_primary_text = *domain_type == DomainType::Text && *is_primary;
+        
+        // Sobre el vocab SIN recortar: el recorte descartaría en silencio los
+        // added tokens fuera del embedding y --strict-tokenizer no saltaría
+        let embedding_rows = config.get("embedding_rows")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize)
+            .or(options.max_vocab.filter(|_| is_primary_text));
+        let out_of_range = check_added_token_ids(
+            &extract_added_tokens(&Value::Object(config.clone())), vocab.len(), embedding_rows,
+        );
+        for msg in &out_of_range {
+            eprintln!("[WARN] {}: {}", dir.display(), msg);
+        }
+        if strict && !out_of_range.is_empty() {
+            anyhow::bail!(
+                "{}: {} added tokens out of embedding range (strict tokenizer mode)",
+                dir.display(), out_of_range.len()
+            );
+        }
+        
+        if is_primary_text {
+            apply_special_overrides(&mut config, &options.special_overrides);
+            
+            if let Some(cap) = options.max_vocab {
+                let dropped = truncate_vocab(&mut vocab, &mut merges, &mut scores, &mut config, cap);
+                if !dropped.is_empty() {
+                    let sample: Vec<String> = dropped.iter().take(5)
+                        .map(|(id, token)| format!("{}={:?}", id, token))
+                        .collect();
+                    eprintln!(
+                        "[WARN] {}: tokenizer vocab exceeds the embedding ({} rows); dropped {} tokens with id >= {} ({}{})",
+                        dir.display(), cap, dropped.len(), cap, sample.join(", "),
+                        if dropped.len() > sample.len() { ", ..." } else { "" }
+                    );
+                }
+            }
+        }
         
         let config_value = Value::Object(config);
         
         match domain_type {
             DomainType::Text => {
-                writer.add_text_domain(&vocab, &merges, &config_value, *is_primary);
+                writer.add_scored_domain(DomainType::Text, &vocab, &scores, &merges, &config_value, *is_primary)?;
                 let version = if use_v13 { "v1.3" } else { "v1.2" };
                 println!("  [HTF {}] Added TEXT domain: {} tokens", version, vocab.len());
             }
             DomainType::Code => {
-                writer.add_code_domain(&vocab, &merges, &config_value, *is_primary);
+                writer.add_scored_domain(DomainType::Code, &vocab, &scores, &merges, &config_value, *is_primary)?;
                 let version = if use_v13 { "v1.3" } else { "v1.2" };
                 println!("  [HTF {}] Added CODE domain: {} tokens", version, vocab.len());
             }
             DomainType::Audio => {
-                writer.add_audio_domain(&vocab, &merges, &config_value, *is_primary);
+                writer.add_scored_domain(DomainType::Audio, &vocab, &scores, &merges, &config_value, *is_primary)?;
                 let version = if use_v13 { "v1.3" } else { "v1.2" };
                 println!("  [HTF {}] Added AUDIO domain: {} tokens", version, vocab.len());
             }
@@ -971,7 +1605,7 @@ pub fn build_htf_multi_versioned(sources: &[(&Path, DomainType, bool)], use_v13:
         }
     }
     
-    Ok(writer.build())
+    Ok(writer)
 }
 
 /// Construye HTF con MÚLTIPLES dominios/tokenizers (usa v1.3 por defecto)
@@ -992,6 +1626,42 @@ pub fn build_htf_multi(sources: &[(&Path, DomainType, bool)]) -> Result<Vec<u8>>
     build_htf_multi_versioned(sources, true)
 }
 
+/// Une varios HTF en uno, en orden de prioridad.
+/// 
+/// Los dominios se copian sin re-tokenizar. Si dos HTF traen el mismo tipo de
+/// dominio gana el primero; solo el primer HTF conserva IS_PRIMARY.
+/// Devuelve el HTF y los dominios descartados.
+pub fn merge_htf(blobs: &[&[u8]]) -> Result<(Vec<u8>, Vec<String>)> {
+    let mut merged: Option<HTFWriter> = None;
+    let mut dropped = Vec::new();
+    
+    for (idx, blob) in blobs.iter().enumerate() {
+        let source = HTFWriter::from_htf(blob)?;
+        let target = merged.get_or_insert_with(|| HTFWriter { domains: Vec::new(), use_v13: source.use_v13 });
+        if source.use_v13 != target.use_v13 {
+            anyhow::bail!("Cannot merge HTF v1.2 and v1.3 tokenizers");
+        }
+        
+        for mut domain in source.domains {
+            // Dominio TEXT vacío de build_empty: nada que aportar
+            if domain.vocab_size == 0 && domain.data.is_empty() {
+                continue;
+            }
+            if target.domains.iter().any(|d| d.domain_type == domain.domain_type) {
+                dropped.push(format!("input {}: domain type {}", idx + 1, domain.domain_type));
+                continue;
+            }
+            if target.domains.iter().any(|d| d.domain_flags & HTF_FLAG_IS_PRIMARY != 0) {
+                domain.domain_flags &= !HTF_FLAG_IS_PRIMARY;
+            }
+            target.domains.push(domain);
+        }
+    }
+    
+    let merged = merged.ok_or_else(|| anyhow::anyhow!("No HTF to merge"))?;
+    Ok((merged.build(), dropped))
+}
+
 /// Construye HTF binario desde un directorio de modelo HuggingFace (single domain)
 /// Usa v1.3 por defecto
 pub fn build_htf(model_dir: impl AsRef<Path>) -> Result<Vec<u8>> {
@@ -1006,7 +1676,7 @@ pub fn build_htf(model_dir: impl AsRef<Path>) -> Result<Vec<u8>> {
 pub fn build_htf_versioned(model_dir: impl AsRef<Path>, use_v13: bool) -> Result<Vec<u8>> {
     let dir = model_dir.as_ref();
     
-    let (vocab, merges, mut config) = load_tokenizer_from_dir(dir)?;
+    let (vocab, merges, mut config, scores) = load_tokenizer_from_dir(dir)?;
     
     if vocab.is_empty() {
         // Sin tokenizer.json, crear HTF mínimo
@@ -1018,10 +1688,363 @@ pub fn build_htf_versioned(model_dir: impl AsRef<Path>, use_v13: bool) -> Result
     
     // Construir HTF con versión especificada
     let mut writer = if use_v13 { HTFWriter::new_v13() } else { HTFWriter::new() };
-    writer.add_text_domain(&vocab, &merges, &Value::Object(config), true);
+    writer.add_scored_domain(DomainType::Text, &vocab, &scores, &merges, &Value::Object(config), true)?;
     
     let version = if use_v13 { "v1.3" } else { "v1.2" };
     println!("  [HTF {}] Built single TEXT domain: {} tokens", version, vocab.len());
     
     Ok(writer.build())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binary::FLAG_BYTE_FALLBACK;
+    
+    #[test]
+    fn test_byte_fallback_sets_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        // Solo 2 de los 256 byte tokens presentes
+        let tokenizer = serde_json::json!({
+            "model": {
+                "type": "BPE",
+                "byte_fallback": true,
+                "vocab": {"<unk>": 0, "<0x00>": 1, "<0x0A>": 2, "▁hola": 3},
+                "merges": [],
+            }
+        });
+        std::fs::write(dir.path().join("tokenizer.json"), tokenizer.to_string()).unwrap();
+        
+        let (vocab, _, config, _) = load_tokenizer_from_dir(dir.path()).unwrap();
+        assert_eq!(config.get("byte_fallback"), Some(&Value::Bool(true)));
+        
+        let bin = TextDomainConfigBin::from_config(&Value::Object(config), vocab.len() as u32, 0);
+        assert_ne!(bin.flags & FLAG_BYTE_FALLBACK, 0);
+        assert_eq!(bin.to_bytes()[23] & FLAG_BYTE_FALLBACK, FLAG_BYTE_FALLBACK);
+        
+        // Sin byte_fallback no se marca
+        let plain = serde_json::json!({"model": {"type": "BPE", "vocab": {"a": 0}, "merges": []}});
+        std::fs::write(dir.path().join("tokenizer.json"), plain.to_string()).unwrap();
+        let (_, _, config, _) = load_tokenizer_from_dir(dir.path()).unwrap();
+        assert!(config.get("byte_fallback").is_none());
+    }
+    
+    #[test]
+    fn test_streamed_build_matches_in_memory() {
+        let vocab: HashMap<String, u32> = (0..5000u32).map(|i| (format!("tok{}", i), i)).collect();
+        let merges: Vec<MergePair> = (0..777).map(|i| ("t".to_string(), format!("ok{}", i))).collect();
+        let code_vocab: HashMap<String, u32> = [("fn".to_string(), 0), ("let".to_string(), 1)].into();
+        
+        for use_v13 in [true, false] {
+            let mut writer = HTFWriter::new();
+            writer.set_version(use_v13);
+            writer.add_text_domain(&vocab, &merges, &serde_json::json!({}), true).unwrap();
+            writer.add_code_domain(&code_vocab, &[], &serde_json::json!({}), false).unwrap();
+            
+            let in_memory = writer.build();
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("tokenizer.htf");
+            let mut file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+            let (size, checksum) = writer.build_to(&mut file).unwrap();
+            drop(file);
+            
+            let streamed = std::fs::read(&path).unwrap();
+            assert_eq!(streamed, in_memory);
+            assert_eq!(size, streamed.len() as u64);
+            assert_eq!(checksum, compute_htf_checksum(&streamed));
+            assert_eq!(streamed.len() % 32, 0);
+            if use_v13 {
+                let result = validate::validate_htf(&streamed);
+                assert!(result.valid, "{:?}", result.errors);
+            }
+        }
+        
+        // Sin dominios: HTF mínimo
+        let mut empty = Vec::new();
+        let (size, _) = HTFWriter::new_v13().build_to(&mut empty).unwrap();
+        assert_eq!((size, empty.len()), (64, 64));
+    }
+    
+    #[test]
+    fn test_array_merges_keep_tokens_with_spaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let tokenizer = serde_json::json!({
+            "model": {
+                "type": "BPE",
+                "vocab": {"a": 0, "b": 1, "ab": 2, "c d": 3, "abc d": 4, "x": 5, "y": 6},
+                "merges": [["a", "b"], ["ab", "c d"], "x y", ["only_one"]],
+            }
+        });
+        std::fs::write(dir.path().join("tokenizer.json"), tokenizer.to_string()).unwrap();
+        
+        let (vocab, merges, _, _) = load_tokenizer_from_dir(dir.path()).unwrap();
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(merges, [pair("a", "b"), pair("ab", "c d"), pair("x", "y")]);
+        assert_eq!(merge_id_pairs(&vocab, &merges), [(0, 1), (2, 3), (5, 6)]);
+        
+        // La forma de string no puede representar "ab c d" sin ambigüedad
+        assert_eq!(parse_merge(&serde_json::json!("ab c d")), None);
+        
+        let htf = build_htf(dir.path()).unwrap();
+        let result = validate::validate_htf(&htf);
+        assert!(result.valid, "{:?}", result.errors);
+    }
+    
+    #[test]
+    fn test_byte_level_tokens_stored_as_raw_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        // vocab.json con un surrogate suelto (se recupera como U+FFFD)
+        let vocab_json = r#"{"Ġ": 0, "Ġhola": 1, "Ċ": 2, "\ud800x": 3}"#;
+        std::fs::write(dir.path().join("vocab.json"), vocab_json).unwrap();
+        
+        let (vocab, merges, config, _) = load_tokenizer_from_dir(dir.path()).unwrap();
+        assert_eq!(vocab.get("\u{FFFD}x"), Some(&3));
+        assert_eq!(config.get("byte_level"), Some(&Value::Bool(true)));
+        
+        let config = Value::Object(config);
+        let data = HTFWriter::build_domain_data_v13(HTF_DOMAIN_TEXT, &vocab, &TokenScores::new(), &merges, &config);
+        assert_ne!(data[23] & binary::FLAG_RAW_BYTE_VOCAB, 0);
+        
+        // config(32) + num_added(4) → pad 8 → vocab_count(4) + entradas
+        let mut pos = 44;
+        let mut tokens = Vec::new();
+        for _ in 0..u32::from_le_bytes(data[40..44].try_into().unwrap()) {
+            let len = u16::from_le_bytes(data[pos + 4..pos + 6].try_into().unwrap()) as usize;
+            let flags = data[pos + 6];
+            tokens.push((data[pos + 8..pos + 8 + len].to_vec(), flags));
+            pos = (pos + 8 + len).div_ceil(4) * 4;
+        }
+        
+        assert_eq!(tokens[0], (b" ".to_vec(), TOKEN_FLAG_RAW_BYTES));
+        assert_eq!(tokens[1], (b" hola".to_vec(), TOKEN_FLAG_RAW_BYTES));
+        assert_eq!(tokens[2], (b"\n".to_vec(), TOKEN_FLAG_RAW_BYTES));
+        // Fuera del alfabeto byte-level: UTF-8 tal cual, sin flag
+        assert_eq!(tokens[3], ("\u{FFFD}x".as_bytes().to_vec(), 0));
+    }
+    
+    #[test]
+    fn test_added_token_id_out_of_embedding_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let tokenizer = serde_json::json!({
+            "model": {"type": "BPE", "vocab": {"a": 0, "b": 1, "c": 2}, "merges": []}
+        });
+        std::fs::write(dir.path().join("tokenizer.json"), tokenizer.to_string()).unwrap();
+        // 3 extiende el vocab dentro del embedding (8 filas); 80000 es un typo
+        let tok_config = serde_json::json!({
+            "added_tokens_decoder": {
+                "3": {"content": "<|im_end|>", "special": true},
+                "80000": {"content": "<|typo|>", "special": true},
+            }
+        });
+        std::fs::write(dir.path().join("tokenizer_config.json"), tok_config.to_string()).unwrap();
+        std::fs::write(dir.path().join("config.json"), r#"{"vocab_size": 8}"#).unwrap();
+        
+        let (vocab, _, config, _) = load_tokenizer_from_dir(dir.path()).unwrap();
+        let config = Value::Object(config);
+        let added = extract_added_tokens(&config);
+        
+        let warnings = check_added_token_ids(&added, vocab.len(), Some(8));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("80000"));
+        
+        // Sin vocab_size del modelo: se permite extender el vocab en len(added)
+        assert_eq!(check_added_token_ids(&added[..1], vocab.len(), None).len(), 0);
+        
+        let sources = [(dir.path(), DomainType::Text, true)];
+        assert!(build_htf_multi_with(&sources, &HtfOptions::default()).is_ok());
+        assert!(build_htf_multi_with(&sources, &HtfOptions { strict: true, ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_set_special_overrides_eos() {
+        let dir = tempfile::tempdir().unwrap();
+        let tokenizer = serde_json::json!({
+            "model": {"type": "BPE", "vocab": {"a": 0, "<|endoftext|>": 1, "<|im_end|>": 2}, "merges": []}
+        });
+        std::fs::write(dir.path().join("tokenizer.json"), tokenizer.to_string()).unwrap();
+        std::fs::write(dir.path().join("config.json"), r#"{"eos_token_id": 1}"#).unwrap();
+        
+        let overrides = parse_special_overrides("eos=2, pad=1").unwrap();
+        assert_eq!(overrides[0], ("eos_token_id".to_string(), 2));
+        assert!(parse_special_overrides("cls=3").is_err());
+        
+        for use_v13 in [true, false] {
+            let options = HtfOptions { use_v13, special_overrides: overrides.clone(), ..Default::default() };
+            let htf = build_htf_multi_with(&[(dir.path(), DomainType::Text, true)], &options).unwrap();
+            let offset = u64::from_le_bytes(htf[HTF_HEADER_SIZE + 8..HTF_HEADER_SIZE + 16].try_into().unwrap()) as usize;
+            
+            if use_v13 {
+                let eos = i32::from_le_bytes(htf[offset + 4..offset + 8].try_into().unwrap());
+                let pad = i32::from_le_bytes(htf[offset + 8..offset + 12].try_into().unwrap());
+                assert_eq!((eos, pad), (2, 1));
+            } else {
+                let len = u32::from_le_bytes(htf[offset..offset + 4].try_into().unwrap()) as usize;
+                let config: Value = serde_json::from_slice(&htf[offset + 4..offset + 4 + len]).unwrap();
+                assert_eq!(config["eos_token_id"], 2);
+            }
+        }
+        
+        // El id sobrescrito queda marcado como control en el vocab escrito
+        let control = |special_overrides: Vec<(String, u32)>| {
+            let options = HtfOptions { special_overrides, ..Default::default() };
+            let htf = build_htf_multi_with(&[(dir.path(), DomainType::Text, true)], &options).unwrap();
+            let offset = u64::from_le_bytes(htf[HTF_HEADER_SIZE + 8..HTF_HEADER_SIZE + 16].try_into().unwrap()) as usize;
+            let data = &htf[offset..];
+            assert_eq!(u32::from_le_bytes(data[32..36].try_into().unwrap()), 0, "fixture has no added tokens");
+            let mut flags = HashMap::new();
+            let mut pos = 44;
+            for _ in 0..u32::from_le_bytes(data[40..44].try_into().unwrap()) {
+                let id = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+                let len = u16::from_le_bytes(data[pos + 4..pos + 6].try_into().unwrap()) as usize;
+                flags.insert(id, data[pos + 6] & TOKEN_FLAG_CONTROL != 0);
+                pos = (pos + 8 + len).div_ceil(4) * 4;
+            }
+            assert_eq!(flags.len(), 3);
+            flags
+        };
+        assert_eq!(control(Vec::new()), HashMap::from([(0, false), (1, true), (2, false)]));
+        assert_eq!(control(overrides), HashMap::from([(0, false), (1, true), (2, true)]));
+    }
+    
+    #[test]
+    fn test_max_vocab_truncates_above_embedding() {
+        let dir = tempfile::tempdir().unwrap();
+        let tokenizer = serde_json::json!({
+            "model": {
+                "type": "BPE",
+                "vocab": {"a": 0, "b": 1, "ab": 2, "<s>": 3, "c": 4, "bc": 5, "</s>": 6},
+                "merges": ["a b", "b c"]
+            },
+            "added_tokens": [
+                {"id": 3, "content": "<s>", "special": true},
+                {"id": 6, "content": "</s>", "special": true}
+            ]
+        });
+        std::fs::write(dir.path().join("tokenizer.json"), tokenizer.to_string()).unwrap();
+        std::fs::write(dir.path().join("config.json"), r#"{"bos_token_id": 3, "eos_token_id": 6}"#).unwrap();
+        
+        let (mut vocab, mut merges, mut config, mut scores) = load_tokenizer_from_dir(dir.path()).unwrap();
+        let dropped = truncate_vocab(&mut vocab, &mut merges, &mut scores, &mut config, 4);
+        assert_eq!(dropped.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![4, 5, 6]);
+        assert_eq!(vocab.len(), 4);
+        assert_eq!(vocab["<s>"], 3);
+        assert_eq!(merges, vec![("a".to_string(), "b".to_string())]);
+        assert_eq!(config["vocab_size"], 7);
+        assert_eq!(config["embedding_rows"], 4);
+        
+        // Los especiales bajo el cap sobreviven; los de encima se descartan
+        let added = extract_added_tokens(&Value::Object(config.clone()));
+        assert_eq!(added.iter().map(|t| t.token_id).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(config["bos_token_id"], 3);
+        assert!(!config.contains_key("eos_token_id"));
+        
+        // Con cap >= vocab no se toca nada
+        let (mut vocab, mut merges, mut config, mut scores) = load_tokenizer_from_dir(dir.path()).unwrap();
+        assert!(truncate_vocab(&mut vocab, &mut merges, &mut scores, &mut config, 8).is_empty());
+        assert_eq!(vocab.len(), 7);
+        
+        let sources = [(dir.path(), DomainType::Text, true)];
+        let full = build_htf_multi_with(&sources, &HtfOptions::default()).unwrap();
+        let capped = build_htf_multi_with(&sources, &HtfOptions { max_vocab: Some(4), ..Default::default() }).unwrap();
+        assert!(capped.len() < full.len());
+        
+        // En modo estricto, el </s> (id 6) fuera del embedding es un error, no un recorte
+        let strict = HtfOptions { max_vocab: Some(4), strict: true, ..Default::default() };
+        assert!(build_htf_multi_with(&sources, &strict).is_err());
+    }
+    
+    #[test]
+    fn test_merge_count_guards() {
+        // Un merges.txt mal parseado: 10 tokens, 100 merges
+        let vocab: HashMap<String, u32> = (0..10).map(|i| (format!("t{}", i), i)).collect();
+        let merges: Vec<MergePair> = (0..100).map(|i| (format!("m{}", i), "x".to_string())).collect();
+        let warning = merge_count_warning(vocab.len(), merges.len()).unwrap();
+        assert!(warning.contains("100 merges for a vocab of 10"), "{}", warning);
+        assert!(merge_count_warning(10, 9).is_none());
+        assert!(merge_count_warning(0, 0).is_none());
+        
+        // El aviso no impide construir el dominio
+        let mut writer = HTFWriter::new_v13();
+        assert!(writer.add_text_domain(&vocab, &merges, &serde_json::json!({}), true).is_ok());
+        
+        assert!(check_domain_count("merges", u32::MAX as usize).is_ok());
+        let err = check_domain_count("merges", u32::MAX as usize + 1).unwrap_err();
+        assert!(err.to_string().contains("more than a u32"), "{}", err);
+    }
+    
+    #[test]
+    fn test_conflicting_eos_ids_across_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        let tokenizer = serde_json::json!({
+            "model": {"type": "BPE", "vocab": {"a": 0, "</s>": 1, "<eot>": 2}, "merges": []}
+        });
+        std::fs::write(dir.path().join("tokenizer.json"), tokenizer.to_string()).unwrap();
+        std::fs::write(dir.path().join("tokenizer_config.json"), r#"{"eos_token_id": 1, "bos_token_id": 0}"#).unwrap();
+        std::fs::write(dir.path().join("config.json"), r#"{"eos_token_id": 1, "bos_token_id": 0}"#).unwrap();
+        std::fs::write(dir.path().join("generation_config.json"), r#"{"eos_token_id": [2, 3], "bos_token_id": 0}"#).unwrap();
+        
+        // Por defecto manda tokenizer_config.json
+        let (_, _, config, _) = load_tokenizer_from_dir(dir.path()).unwrap();
+        assert_eq!(config["eos_token_id"], 1);
+        assert_eq!(config["eos_token_ids"], serde_json::json!([2, 3]));
+        
+        let (_, _, config, _) = load_tokenizer_with(dir.path(), ConfigPreference::Generation).unwrap();
+        assert_eq!(config["eos_token_id"], 2);
+        assert_eq!(config["bos_token_id"], 0);
+        
+        // Solo eos entra en conflicto; el aviso lista el valor de cada archivo
+        let tok = serde_json::json!({"eos_token_id": 1, "bos_token_id": 0});
+        let gen = serde_json::json!({"eos_token_id": [2, 3], "bos_token_id": 0});
+        let sources = [("tokenizer_config.json", &tok), ("generation_config.json", &gen)];
+        let (resolved, conflicts) = resolve_special_ids(&sources, ConfigPreference::Tokenizer);
+        assert_eq!(resolved["eos_token_id"], 1);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "eos_token_id disagrees: tokenizer_config.json=1, generation_config.json=[2,3] -> using tokenizer_config.json"
+        );
+        
+        // Una lista que contiene el ID elegido no es conflicto
+        let gen = serde_json::json!({"eos_token_id": [3, 1]});
+        let sources = [("tokenizer_config.json", &tok), ("generation_config.json", &gen)];
+        assert!(resolve_special_ids(&sources, ConfigPreference::Tokenizer).1.is_empty());
+        assert_eq!(ConfigPreference::parse("Generation"), Some(ConfigPreference::Generation));
+        assert_eq!(ConfigPreference::parse("model"), None);
+    }
+    
+    #[test]
+    fn test_unigram_vocab_with_scores() {
+        let dir = tempfile::tempdir().unwrap();
+        let tokenizer = serde_json::json!({
+            "model": {
+                "type": "Unigram",
+                "unk_id": 0,
+                "vocab": [["<unk>", 0.0], ["▁the", -3.5], ["▁hola", -9.25], ["s", -4.0]],
+            }
+        });
+        std::fs::write(dir.path().join("tokenizer.json"), tokenizer.to_string()).unwrap();
+        
+        let (vocab, merges, config, scores) = load_tokenizer_from_dir(dir.path()).unwrap();
+        assert_eq!(vocab.len(), 4);
+        assert_eq!(vocab["▁hola"], 2);
+        assert_eq!(vocab["s"], 3);
+        assert_eq!(scores[&1], -3.5);
+        assert_eq!(scores[&2], -9.25);
+        assert!(merges.is_empty());
+        assert_eq!(config["encoding_type"], "unigram");
+        assert_eq!(config["unk_token_id"], 0);
+        
+        let config = Value::Object(config);
+        assert_eq!(TextDomainConfigBin::from_config(&config, 4, 0).encoding_type, binary::ENCODING_UNIGRAM);
+        
+        // Cada entrada lleva score_type F32 y el score tras la cabecera
+        let data = HTFWriter::build_domain_data_v13(HTF_DOMAIN_TEXT, &vocab, &scores, &merges, &config);
+        let mut pos = 44;
+        for expected in [0.0f32, -3.5, -9.25, -4.0] {
+            let len = u16::from_le_bytes(data[pos + 4..pos + 6].try_into().unwrap()) as usize;
+            assert_eq!(data[pos + 7], SCORE_TYPE_F32);
+            assert_eq!(f32::from_le_bytes(data[pos + 8..pos + 12].try_into().unwrap()), expected);
+            pos = (pos + 12 + len).div_ceil(4) * 4;
+        }
+    }
+}
@@ -194,7 +194,7 @@ pub fn validate_htf(data: &[u8]) -> HTFValidationResult {
         }
         
         // Verificar que data_offset y data_size son válidos
-        if data_offset as usize + data_size as usize > data.len() {
+        if data_offset.checked_add(data_size).is_none_or(|end| end > data.len() as u64) {
             result.errors.push(format!(
                 "Domain {} data exceeds file bounds: offset {} + size {} > {}",
                 i, data_offset, data_size, data.len()
@@ -221,6 +221,29 @@ pub fn validate_htf(data: &[u8]) -> HTFValidationResult {
         });
     }
     
+    // 8b. Los datos de un dominio no pueden pisar los del siguiente
+    //     (un tokenizer leería bytes de otro)
+    let mut spans: Vec<(usize, &DomainInfo)> = result.info.domains.iter()
+        .enumerate()
+        .filter(|(_, d)| d.data_size > 0)
+        .collect();
+    spans.sort_by_key(|(_, d)| d.data_offset);
+    let mut overlaps = Vec::new();
+    for pair in spans.windows(2) {
+        let ((i, a), (j, b)) = (pair[0], pair[1]);
+        let a_end = a.data_offset.saturating_add(a.data_size);
+        if a_end > b.data_offset {
+            overlaps.push(format!(
+                "Domain {} data [{}..{}) overlaps domain {} data starting at {}",
+                i, a.data_offset, a_end, j, b.data_offset
+            ));
+        }
+    }
+    if !overlaps.is_empty() {
+        result.errors.extend(overlaps);
+        result.valid = false;
+    }
+    
     if !has_primary {
         result.errors.push("No domain marked as PRIMARY".to_string());
         result.valid = false;
@@ -240,7 +263,13 @@ fn validate_v13_domains(data: &[u8], result: &mut HTFValidationResult) {
             continue;
         }
         
-        let offset = domain.data_offset as usize;
+        // Fuera de límites ya se reportó al leer la domain table
+        let Some(bytes) = domain.data_offset.checked_add(domain.data_size)
+            .filter(|&end| end <= data.len() as u64)
+            .map(|end| &data[domain.data_offset as usize..end as usize])
+        else {
+            continue;
+        };
         
         match domain.domain_type.as_str() {
             "TEXT" => {
@@ -256,12 +285,12 @@ fn validate_v13_domains(data: &[u8], result: &mut HTFValidationResult) {
                 
                 // Leer y validar config
                 let vocab_size = u32::from_le_bytes(
-                    data[offset + 16..offset + 20].try_into().unwrap()
+                    bytes[16..20].try_into().unwrap()
                 );
                 let num_added = u16::from_le_bytes(
-                    data[offset + 20..offset + 22].try_into().unwrap()
+                    bytes[20..22].try_into().unwrap()
                 );
-                let encoding_type = data[offset + 22];
+                let encoding_type = bytes[22];
                 
                 if encoding_type > 3 {
                     result.errors.push(format!(
@@ -278,14 +307,44 @@ fn validate_v13_domains(data: &[u8], result: &mut HTFValidationResult) {
                     ));
                 }
                 
-                // Verificar reserved bytes
-                for j in 0..8 {
-                    if data[offset + 24 + j] != 0 {
+                // Verificar descriptor normalizer/pre-tokenizer
+                let flags = bytes[23];
+                let pretokenizer_type = bytes[25];
+                if flags & FLAG_HAS_PIPELINE == 0 {
+                    if bytes[24..27].iter().any(|&b| b != 0) {
                         result.warnings.push(format!(
-                            "TEXT domain {}: reserved byte {} is non-zero",
-                            i, j
+                            "TEXT domain {}: pipeline bytes set without FLAG_HAS_PIPELINE",
+                            i
                         ));
                     }
+                } else if pretokenizer_type > PRETOK_SPLIT && pretokenizer_type != PRETOK_OTHER {
+                    result.errors.push(format!(
+                        "TEXT domain {}: invalid pretokenizer_type {}",
+                        i, pretokenizer_type
+                    ));
+                    result.valid = false;
+                }
+                
+                // Esquema de espacios: la sustitución solo tiene sentido con Metaspace
+                let space_scheme = bytes[27];
+                let space_replacement = u32::from_le_bytes([bytes[28], bytes[29], bytes[30], bytes[31]]);
+                if space_scheme > SPACE_METASPACE {
+                    result.errors.push(format!(
+                        "TEXT domain {}: invalid space_scheme {}",
+                        i, space_scheme
+                    ));
+                    result.valid = false;
+                } else if space_scheme == SPACE_METASPACE && char::from_u32(space_replacement).is_none_or(|c| c == '\0') {
+                    result.errors.push(format!(
+                        "TEXT domain {}: metaspace without a valid replacement char ({:#x})",
+                        i, space_replacement
+                    ));
+                    result.valid = false;
+                } else if space_scheme != SPACE_METASPACE && space_replacement != 0 {
+                    result.warnings.push(format!(
+                        "TEXT domain {}: space_replacement set without metaspace scheme",
+                        i
+                    ));
                 }
             }
             "VISION" => {
@@ -304,6 +363,40 @@ fn validate_v13_domains(data: &[u8], result: &mut HTFValidationResult) {
                         i, domain.data_size, AudioDomainConfigBin::SIZE
                     ));
                     result.valid = false;
+                    continue;
+                }
+                // RVQ: config + num_codebooks × CodebookEntryBin, sin bytes sueltos
+                let flags = u16::from_le_bytes(bytes[60..62].try_into().unwrap());
+                if flags & AUDIO_FLAG_MULTI_CODEBOOK == 0 {
+                    continue;
+                }
+                let num_codebooks = u16::from_le_bytes(bytes[44..46].try_into().unwrap()) as usize;
+                let expected = AudioDomainConfigBin::SIZE + num_codebooks * CodebookEntryBin::SIZE;
+                if num_codebooks == 0 || domain.data_size != expected as u64 {
+                    result.errors.push(format!(
+                        "AUDIO domain {}: {} codebooks need {} bytes, domain has {}",
+                        i, num_codebooks, expected, domain.data_size
+                    ));
+                    result.valid = false;
+                    continue;
+                }
+                
+                let table = &bytes[AudioDomainConfigBin::SIZE..expected];
+                for (j, entry) in table.chunks_exact(CodebookEntryBin::SIZE).enumerate() {
+                    let codebook = CodebookEntryBin::from_bytes(entry);
+                    if codebook.size == 0 || codebook.dim == 0 || codebook.frame_rate.is_nan() || codebook.frame_rate <= 0.0 {
+                        result.errors.push(format!(
+                            "AUDIO domain {}: codebook {} invalid (size {}, dim {}, frame_rate {})",
+                            i, j, codebook.size, codebook.dim, codebook.frame_rate
+                        ));
+                        result.valid = false;
+                    }
+                    if codebook.reserved != 0 {
+                        result.warnings.push(format!(
+                            "AUDIO domain {}: codebook {} reserved bytes are non-zero",
+                            i, j
+                        ));
+                    }
                 }
             }
             "CODE" => {
@@ -322,6 +415,13 @@ fn validate_v13_domains(data: &[u8], result: &mut HTFValidationResult) {
     }
 }
 
+/// Checksum interno de un blob HTF: (declarado en header[24..32], recalculado).
+/// None si no llega al tamaño del header.
+pub fn inner_checksum(data: &[u8]) -> Option<(u64, u64)> {
+    let stored = u64::from_le_bytes(data.get(24..32)?.try_into().ok()?);
+    (data.len() >= HTF_HEADER_SIZE).then(|| (stored, compute_checksum_for_validation(data)))
+}
+
 fn compute_checksum_for_validation(data: &[u8]) -> u64 {
     use xxhash_rust::xxh3::Xxh3;
     
@@ -338,46 +438,46 @@ fn compute_checksum_for_validation(data: &[u8]) -> u64 {
 
 /// Imprime un resumen de validación
 pub fn print_validation_result(result: &HTFValidationResult) {
-    println!("╔══════════════════════════════════════════════════════════════════╗");
-    println!("║                    HTF VALIDATION RESULT                         ║");
-    println!("╠══════════════════════════════════════════════════════════════════╣");
+    crate::outln!("╔══════════════════════════════════════════════════════════════════╗");
+    crate::outln!("║                    HTF VALIDATION RESULT                         ║");
+    crate::outln!("╠══════════════════════════════════════════════════════════════════╣");
     
     if result.valid {
-        println!("║  Status: ✓ VALID                                                 ║");
+        crate::outln!("║  Status: ✓ VALID                                                 ║");
     } else {
-        println!("║  Status: ✗ INVALID                                               ║");
+        crate::outln!("║  Status: ✗ INVALID                                               ║");
     }
     
-    println!("║  Magic: {}                                                       ║", result.info.magic);
-    println!("║  Version: {}                                                     ║", result.version);
-    println!("║  Domains: {}                                                        ║", result.info.num_domains);
-    println!("║  Total Size: {} bytes                                       ║", result.info.total_size);
-    println!("╠══════════════════════════════════════════════════════════════════╣");
+    crate::outln!("║  Magic: {}                                                       ║", result.info.magic);
+    crate::outln!("║  Version: {}                                                     ║", result.version);
+    crate::outln!("║  Domains: {}                                                        ║", result.info.num_domains);
+    crate::outln!("║  Total Size: {} bytes                                       ║", result.info.total_size);
+    crate::outln!("╠══════════════════════════════════════════════════════════════════╣");
     
-    println!("║  DOMAINS:                                                         ║");
+    crate::outln!("║  DOMAINS:                                                         ║");
     for (i, domain) in result.info.domains.iter().enumerate() {
         let primary = if domain.is_primary { " [PRIMARY]" } else { "" };
-        println!("║    [{}] {} - vocab: {}, size: {}{}",
+        crate::outln!("║    [{}] {} - vocab: {}, size: {}{}",
             i, domain.domain_type, domain.vocab_size, domain.data_size, primary);
     }
     
     if !result.errors.is_empty() {
-        println!("╠══════════════════════════════════════════════════════════════════╣");
-        println!("║  ERRORS:                                                         ║");
+        crate::outln!("╠══════════════════════════════════════════════════════════════════╣");
+        crate::outln!("║  ERRORS:                                                         ║");
         for err in &result.errors {
-            println!("║    ✗ {}", err);
+            crate::outln!("║    ✗ {}", err);
         }
     }
     
     if !result.warnings.is_empty() {
-        println!("╠══════════════════════════════════════════════════════════════════╣");
-        println!("║  WARNINGS:                                                       ║");
+        crate::outln!("╠══════════════════════════════════════════════════════════════════╣");
+        crate::outln!("║  WARNINGS:                                                       ║");
         for warn in &result.warnings {
-            println!("║    ⚠ {}", warn);
+            crate::outln!("║    ⚠ {}", warn);
         }
     }
     
-    println!("╚══════════════════════════════════════════════════════════════════╝");
+    crate::outln!("╚══════════════════════════════════════════════════════════════════╝");
 }
 
 #[cfg(test)]
@@ -398,4 +498,103 @@ mod tests {
         assert!(!result.valid);
         assert!(result.errors.iter().any(|e| e.contains("Invalid magic")));
     }
+    
+    #[test]
+    fn test_validate_accepts_pipeline_descriptor() {
+        let vocab: HashMap<String, u32> = [("a", 0), ("b", 1), ("ab", 2)]
+            .iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        let config = serde_json::json!({
+            "byte_level": true,
+            "normalizer_flags": NORMALIZER_NFC,
+            "pretokenizer_type": PRETOK_BYTE_LEVEL,
+            "pretokenizer_flags": PRETOK_FLAG_USE_REGEX,
+        });
+        let mut writer = HTFWriter::new_v13();
+        writer.add_text_domain(&vocab, &[("a".to_string(), "b".to_string())], &config, true).unwrap();
+        
+        let result = validate_htf(&writer.build());
+        assert!(result.valid, "{:?}", result.errors);
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+    }
+    
+    #[test]
+    fn test_overlapping_domains_rejected() {
+        let vocab: HashMap<String, u32> = [("a", 0), ("b", 1)]
+            .iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        let mut writer = HTFWriter::new_v13();
+        writer.add_text_domain(&vocab, &[], &serde_json::json!({}), true).unwrap();
+        writer.add_code_domain(&vocab, &[], &serde_json::json!({}), false).unwrap();
+        let mut htf = writer.build();
+        assert!(validate_htf(&htf).valid);
+        
+        // Dominio 1 empieza 16 bytes antes de que acabe el 0
+        let entry = |i: usize| HTF_HEADER_SIZE + i * HTF_DOMAIN_ENTRY_SIZE;
+        let read_u64 = |data: &[u8], at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        let end0 = read_u64(&htf, entry(0) + 8) + read_u64(&htf, entry(0) + 16);
+        htf[entry(1) + 8..entry(1) + 16].copy_from_slice(&(end0 - 16).to_le_bytes());
+        let checksum = compute_checksum_for_validation(&htf);
+        htf[24..32].copy_from_slice(&checksum.to_le_bytes());
+        
+        let result = validate_htf(&htf);
+        assert!(!result.valid);
+        assert!(result.errors.iter().any(|e| e.contains("Domain 0 data") && e.contains("overlaps domain 1")), "{:?}", result.errors);
+    }
+    
+    #[test]
+    fn test_multi_codebook_audio_domain() {
+        let vocab: HashMap<String, u32> = [("a".to_string(), 0)].into_iter().collect();
+        let codebooks = [
+            CodebookEntryBin::new(4096, 8, 12.0),
+            CodebookEntryBin::new(2048, 8, 23.0),
+            CodebookEntryBin::new(1024, 16, 47.0),
+        ];
+        let mut writer = HTFWriter::new_v13();
+        writer.add_text_domain(&vocab, &[], &serde_json::json!({}), true).unwrap();
+        writer.add_audio_domain(&HashMap::new(), &[], &serde_json::json!({"encoder_type": "encodec"}), &codebooks, false).unwrap();
+        let htf = writer.build();
+        
+        let result = validate_htf(&htf);
+        assert!(result.valid, "{:?}", result.errors);
+        let audio = &result.info.domains[1];
+        assert_eq!(audio.data_size as usize, AudioDomainConfigBin::SIZE + 3 * CodebookEntryBin::SIZE);
+        
+        // Config base: flag, num_codebooks y el primer codebook como par genérico
+        let offset = audio.data_offset as usize;
+        let config = &htf[offset..offset + AudioDomainConfigBin::SIZE];
+        assert_eq!(u16::from_le_bytes(config[60..62].try_into().unwrap()), AUDIO_FLAG_MULTI_CODEBOOK);
+        assert_eq!(u16::from_le_bytes(config[44..46].try_into().unwrap()), 3);
+        assert_eq!(u32::from_le_bytes(config[36..40].try_into().unwrap()), 4096);
+        let table = &htf[offset + AudioDomainConfigBin::SIZE..offset + audio.data_size as usize];
+        let parsed: Vec<_> = table.chunks_exact(CodebookEntryBin::SIZE).map(CodebookEntryBin::from_bytes).collect();
+        assert_eq!(parsed, codebooks);
+        
+        // Un codebook de menos en la cabecera ya no cuadra con data_size
+        let mut broken = htf.clone();
+        broken[offset + 44..offset + 46].copy_from_slice(&2u16.to_le_bytes());
+        let result = validate_htf(&broken);
+        assert!(result.errors.iter().any(|e| e.contains("2 codebooks need")), "{:?}", result.errors);
+    }
+    
+    #[test]
+    fn test_malformed_domain_table_never_panics() {
+        let vocab: HashMap<String, u32> = [("a".to_string(), 0)].into_iter().collect();
+        let mut writer = HTFWriter::new_v13();
+        writer.add_text_domain(&vocab, &[], &serde_json::json!({}), true).unwrap();
+        writer.add_audio_domain(&HashMap::new(), &[], &serde_json::json!({}), &[CodebookEntryBin::new(1024, 8, 50.0)], false).unwrap();
+        let htf = writer.build();
+        
+        let mut inputs: Vec<Vec<u8>> = (0..htf.len()).map(|len| htf[..len].to_vec()).collect();
+        for field in (8..HTF_HEADER_SIZE + 2 * HTF_DOMAIN_ENTRY_SIZE).step_by(8) {
+            for value in [u64::MAX, u64::MAX - 15, htf.len() as u64, 1 << 40] {
+                let mut data = htf.clone();
+                data[field..field + 8].copy_from_slice(&value.to_le_bytes());
+                inputs.push(data);
+            }
+        }
+        
+        for data in inputs {
+            let result = std::panic::catch_unwind(|| validate_htf(&data));
+            assert!(result.is_ok(), "panic con {} bytes", data.len());
+        }
+    }
 }
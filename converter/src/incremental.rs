@@ -0,0 +1,246 @@
+// src/incremental.rs
+// ============================================================================
+// INCREMENTAL - Qué bloques reconstruir según los archivos fuente
+// ============================================================================
+//
+// Cada conversión guarda en el manifest ("source_files") tamaño y mtime de
+// los archivos de cada modelo. Con --incremental se comparan con los
+// actuales y solo se reconstruye lo afectado:
+// - pesos (safetensors, LoRA,  → conversión completa
+//   --calibration-data)
+// - config (config.json, ...)  → execution hints (+ revisar tokenizer, que
+//                                lee bos/eos de config.json)
+// - tokenizer                  → HTF
+//
+// Los flags que cambian el contenido (quant, super-block, --select, ...) se
+// guardan también ("conversion_settings"): si no coinciden, conversión
+// completa aunque las fuentes no hayan cambiado.
+//
+// ============================================================================
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Qué bloque depende de un archivo fuente
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceRole {
+    Weights,
+    Config,
+    Tokenizer,
+}
+
+impl SourceRole {
+    /// Rol de un archivo del directorio del modelo (None = no lo lee el conversor)
+    pub fn of(file_name: &str) -> Option<Self> {
+        match file_name {
+            "config.json" | "preprocessor_config.json" => Some(Self::Config),
+            "tokenizer.json" | "tokenizer_config.json" | "tokenizer.model" | "vocab.json"
+            | "merges.txt" | "added_tokens.json" | "special_tokens_map.json"
+            | "generation_config.json" => Some(Self::Tokenizer),
+            _ if file_name.ends_with(".safetensors") || file_name.ends_with(".safetensors.index.json") => {
+                Some(Self::Weights)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Archivo fuente tal como se registra en el manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFile {
+    pub modality: String,
+    pub file: String,
+    pub role: SourceRole,
+    pub size: u64,
+    /// 0 si el sistema de archivos no da mtime
+    pub mtime_ns: u64,
+}
+
+/// Archivos fuente de `dir` (solo el primer nivel), ordenados por nombre
+pub fn scan_sources(dir: &Path, modality: &str) -> Result<Vec<SourceFile>> {
+    let mut files = Vec::new();
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Cannot list {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(role) = SourceRole::of(&name) else { continue };
+        let meta = entry.metadata()?;
+        if !meta.is_file() {
+            continue;
+        }
+        files.push(source_file(modality, name, role, &meta));
+    }
+    files.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(files)
+}
+
+/// Archivo fuente fuera del directorio del modelo (adapter LoRA, datos de
+/// calibración): cualquier cambio obliga a reconvertir como `role`
+pub fn scan_file(path: &Path, modality: &str, role: SourceRole) -> Result<SourceFile> {
+    let meta = std::fs::metadata(path)
+        .with_context(|| format!("Cannot stat {}", path.display()))?;
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    Ok(source_file(modality, name, role, &meta))
+}
+
+fn source_file(modality: &str, file: String, role: SourceRole, meta: &std::fs::Metadata) -> SourceFile {
+    let mtime_ns = meta.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64);
+    SourceFile {
+        modality: modality.to_string(),
+        file,
+        role,
+        size: meta.len(),
+        mtime_ns,
+    }
+}
+
+/// source_files del manifest (None si el HNF es anterior a --incremental)
+pub fn recorded_sources(manifest: &Value) -> Option<Vec<SourceFile>> {
+    serde_json::from_value(manifest.get("source_files")?.clone()).ok()
+}
+
+/// Flags de conversión que cambian los bytes del HNF
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversionSettings {
+    pub quant: String,
+    pub use_mse: bool,
+    pub super_block: usize,
+    pub symmetric: bool,
+    pub anneal_quant: Option<usize>,
+    pub target_size: Option<usize>,
+    pub quant_min_bytes: usize,
+    pub select: Option<Vec<String>>,
+    pub flat_names: bool,
+    pub keep_unmapped: bool,
+    pub tied_lm_head: String,
+    pub lora_alpha: Option<f32>,
+    pub max_position: Option<usize>,
+    pub metadata_first: bool,
+    pub hnf_version: Option<u16>,
+    pub checksum: String,
+}
+
+/// conversion_settings del manifest (None si el HNF no los registra)
+pub fn recorded_settings(manifest: &Value) -> Option<ConversionSettings> {
+    serde_json::from_value(manifest.get("conversion_settings")?.clone()).ok()
+}
+
+/// Bloques a reconstruir
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RebuildPlan {
+    /// Pesos distintos: no hay nada que reutilizar
+    pub full: bool,
+    pub hints: bool,
+    pub tokenizer: bool,
+}
+
+impl RebuildPlan {
+    pub fn is_empty(&self) -> bool {
+        !self.full && !self.hints && !self.tokenizer
+    }
+}
+
+/// Compara lo registrado con lo actual: un archivo nuevo, borrado o con
+/// otro tamaño/mtime marca su rol
+pub fn plan_rebuild(recorded: &[SourceFile], current: &[SourceFile]) -> RebuildPlan {
+    let key = |s: &SourceFile| (s.modality.clone(), s.file.clone());
+    let before: BTreeMap<_, _> = recorded.iter().map(|s| (key(s), s)).collect();
+    let after: BTreeMap<_, _> = current.iter().map(|s| (key(s), s)).collect();
+    
+    let mut plan = RebuildPlan::default();
+    for k in before.keys().chain(after.keys()) {
+        let (old, new) = (before.get(k), after.get(k));
+        let changed = match (old, new) {
+            (Some(o), Some(n)) => o.size != n.size || o.mtime_ns != n.mtime_ns || o.role != n.role,
+            _ => true,
+        };
+        if !changed {
+            continue;
+        }
+        match old.or(new).map(|s| s.role) {
+            Some(SourceRole::Weights) => plan.full = true,
+            Some(SourceRole::Config) => {
+                plan.hints = true;
+                plan.tokenizer = true;
+            }
+            Some(SourceRole::Tokenizer) => plan.tokenizer = true,
+            None => {}
+        }
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn source(file: &str, size: u64, mtime_ns: u64) -> SourceFile {
+        SourceFile {
+            modality: "text_model".to_string(),
+            file: file.to_string(),
+            role: SourceRole::of(file).unwrap(),
+            size,
+            mtime_ns,
+        }
+    }
+    
+    #[test]
+    fn test_recorded_settings_round_trip() {
+        let settings = ConversionSettings {
+            quant: "HQ5K".to_string(),
+            use_mse: true,
+            super_block: 256,
+            symmetric: false,
+            anneal_quant: None,
+            target_size: Some(1 << 30),
+            quant_min_bytes: 0,
+            select: Some(vec!["vision".to_string()]),
+            flat_names: false,
+            keep_unmapped: false,
+            tied_lm_head: "omit".to_string(),
+            lora_alpha: None,
+            max_position: None,
+            metadata_first: false,
+            hnf_version: None,
+            checksum: "xxh3".to_string(),
+        };
+        let manifest = serde_json::json!({"conversion_settings": settings});
+        assert_eq!(recorded_settings(&manifest), Some(settings));
+        // HNF anterior a conversion_settings
+        assert_eq!(recorded_settings(&serde_json::json!({"source_files": []})), None);
+    }
+    
+    #[test]
+    fn test_plan_rebuild_by_role() {
+        let recorded = vec![
+            source("config.json", 100, 1),
+            source("model.safetensors", 4096, 1),
+            source("tokenizer.json", 500, 1),
+        ];
+        assert!(plan_rebuild(&recorded, &recorded).is_empty());
+        
+        let mut current = recorded.clone();
+        current[0].mtime_ns = 2;
+        assert_eq!(plan_rebuild(&recorded, &current), RebuildPlan { full: false, hints: true, tokenizer: true });
+        
+        let mut current = recorded.clone();
+        current[2].size = 501;
+        assert_eq!(plan_rebuild(&recorded, &current), RebuildPlan { full: false, hints: false, tokenizer: true });
+        
+        let mut current = recorded.clone();
+        current.push(source("model-00002-of-00002.safetensors", 10, 1));
+        assert!(plan_rebuild(&recorded, &current).full);
+    }
+}
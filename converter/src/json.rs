@@ -0,0 +1,106 @@
+// src/json.rs
+// ============================================================================
+// JSON - Lectura tolerante de configs de HuggingFace
+// ============================================================================
+//
+// Los config.json de HF suelen estar limpios, pero los editados a mano traen
+// a veces un BOM UTF-8 o comas finales (`{"a": 1,}`) que serde_json rechaza.
+//
+//   - El BOM inicial se quita siempre.
+//   - Las comas finales solo con --lenient-json (set_lenient), avisando.
+//
+// ============================================================================
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+static LENIENT: AtomicBool = AtomicBool::new(false);
+
+/// Activa la eliminación de comas finales (--lenient-json)
+pub fn set_lenient(lenient: bool) {
+    LENIENT.store(lenient, Ordering::Relaxed);
+}
+
+pub fn lenient() -> bool {
+    LENIENT.load(Ordering::Relaxed)
+}
+
+/// Quita comas seguidas (salvo espacios) de `}` o `]`, fuera de strings.
+/// Devuelve el texto y cuántas se quitaron.
+fn strip_trailing_commas(text: &str) -> (String, usize) {
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    let mut removed = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut last = 0;
+    
+    for (i, &b) in bytes.iter().enumerate() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b',' => {
+                let next = bytes[i + 1..].iter().find(|c| !c.is_ascii_whitespace());
+                if matches!(next, Some(b'}') | Some(b']')) {
+                    out.push_str(&text[last..i]);
+                    last = i + 1;
+                    removed += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    out.push_str(&text[last..]);
+    (out, removed)
+}
+
+/// Texto listo para serde_json: sin BOM y, con --lenient-json, sin comas finales
+pub fn prepare<'a>(text: &'a str, what: &str) -> Cow<'a, str> {
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    if !lenient() {
+        return Cow::Borrowed(text);
+    }
+    let (fixed, removed) = strip_trailing_commas(text);
+    if removed == 0 {
+        return Cow::Borrowed(text);
+    }
+    eprintln!("[WARN] {}: {} trailing commas removed (--lenient-json)", what, removed);
+    Cow::Owned(fixed)
+}
+
+/// Parsea un JSON de configuración (`what` = nombre del archivo para mensajes)
+pub fn parse(text: &str, what: &str) -> Result<Value> {
+    let text = prepare(text, what);
+    serde_json::from_str(&text).with_context(|| {
+        if lenient() {
+            format!("Invalid JSON in {}", what)
+        } else {
+            format!("Invalid JSON in {} (try --lenient-json for trailing commas)", what)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_trailing_commas_outside_strings() {
+        let (fixed, removed) = strip_trailing_commas("{\"a\": [1, 2,], \"b\": \"x,}\",\n}");
+        assert_eq!(removed, 2);
+        assert_eq!(fixed, "{\"a\": [1, 2], \"b\": \"x,}\"\n}");
+        let value: Value = serde_json::from_str(&fixed).unwrap();
+        assert_eq!(value["b"], "x,}");
+    }
+}
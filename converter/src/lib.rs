@@ -12,11 +12,16 @@ pub mod mapping;
 pub mod safetensor;
 pub mod hints;
 pub mod builder;
+pub mod incremental;
+pub mod calibration;
+pub mod lora;
 pub mod dictionary;
+pub mod term;
+pub mod json;
 
 // Re-exports principales
 pub use hnf::HnfWriter;
-pub use hqs::{QuantFormat, quantize, dequantize};
+pub use hqs::{QuantFormat, QuantInfo, quantize, dequantize};
 pub use safetensor::SafetensorReader;
 pub use mapping::{ModelMapper, BlockType, QuantHint, TensorMapping, create_mapper};
-pub use builder::{process_model, write_combined_hints, BuildStats};
+pub use builder::{process_model, process_model_with_mapper, process_model_observed, process_vlm, map_and_quantize, write_combined_hints, BuildOptions, BuildStats, ConversionObserver, ConvertError, HintOverrides, Progress, VlmTower};
@@ -0,0 +1,202 @@
+// src/lora.rs
+// ============================================================================
+// LORA - Fusión de adaptadores LoRA (PEFT) en los pesos base
+// ============================================================================
+//
+// Con --lora adapter/ cada peso base con un par lora_A/lora_B se fusiona en
+// f32 antes de cuantizar:
+//
+//   W' = W + (alpha / r) · B @ A       A: [r, in]   B: [out, r]   W: [out, in]
+//
+// El rango r sale de las shapes de cada par (admite rank_pattern). alpha:
+// --lora-alpha, si no adapter_config.json["lora_alpha"], si no r (escala 1).
+//
+// Nombres PEFT:
+//   base_model.model.model.layers.0.self_attn.q_proj.lora_A.weight
+//   base_model.model.model.layers.0.self_attn.q_proj.lora_A.default.weight
+//   → model.layers.0.self_attn.q_proj.weight
+//
+// ============================================================================
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::safetensor::SafetensorFile;
+
+/// Prefijo que PEFT antepone a los nombres del modelo base
+const PEFT_PREFIX: &str = "base_model.model.";
+
+/// Par A/B de un peso base, ya en f32
+#[derive(Debug, Clone)]
+pub struct LoraPair {
+    /// [rank, in_features]
+    pub a: Vec<f32>,
+    /// [out_features, rank]
+    pub b: Vec<f32>,
+    pub rank: usize,
+    pub in_features: usize,
+    pub out_features: usize,
+}
+
+/// Adaptador cargado: pares indexados por nombre del peso base
+#[derive(Debug, Clone)]
+pub struct LoraAdapter {
+    pub path: PathBuf,
+    pub alpha: f32,
+    pub pairs: BTreeMap<String, LoraPair>,
+    /// Tensores del adaptador que no son lora_A/lora_B (modules_to_save,
+    /// lora_embedding_*...): no se fusionan
+    pub unsupported: Vec<String>,
+}
+
+/// Lado de un tensor LoRA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    A,
+    B,
+}
+
+/// (peso base, lado) de un nombre PEFT; None si no es lora_A/lora_B
+fn parse_lora_name(name: &str) -> Option<(String, Side)> {
+    let name = name.strip_prefix(PEFT_PREFIX).unwrap_or(name);
+    for (marker, side) in [(".lora_A.", Side::A), (".lora_B.", Side::B)] {
+        if let Some((module, rest)) = name.split_once(marker) {
+            if rest == "weight" || rest.ends_with(".weight") {
+                return Some((format!("{}.weight", module), side));
+            }
+        }
+    }
+    None
+}
+
+impl LoraAdapter {
+    /// Abre un adaptador: directorio con adapter_model.safetensors o el
+    /// archivo directamente (adapter_config.json se busca al lado)
+    pub fn open(path: &Path, alpha: Option<f32>) -> Result<Self> {
+        let (dir, file) = if path.is_dir() {
+            (path.to_path_buf(), path.join("adapter_model.safetensors"))
+        } else {
+            (path.parent().map(Path::to_path_buf).unwrap_or_default(), path.to_path_buf())
+        };
+        let adapter = SafetensorFile::open(&file)
+            .with_context(|| format!("Failed to open LoRA adapter {}", file.display()))?;
+        
+        let config_alpha = match std::fs::read_to_string(dir.join("adapter_config.json")) {
+            Ok(text) => crate::json::parse(&text, "adapter_config.json")?
+                .get("lora_alpha")
+                .and_then(|v| v.as_f64())
+                .map(|a| a as f32),
+            Err(_) => None,
+        };
+        
+        let mut halves: BTreeMap<String, [Option<String>; 2]> = BTreeMap::new();
+        let mut unsupported = Vec::new();
+        let mut names: Vec<&str> = adapter.tensor_names().collect();
+        names.sort_unstable();
+        for name in names {
+            match parse_lora_name(name) {
+                Some((base, side)) => halves.entry(base).or_default()[side as usize] = Some(name.to_string()),
+                None => unsupported.push(name.to_string()),
+            }
+        }
+        
+        let mut pairs = BTreeMap::new();
+        for (base, [a_name, b_name]) in halves {
+            let (Some(a_name), Some(b_name)) = (a_name, b_name) else {
+                bail!("LoRA adapter {}: '{}' has lora_A or lora_B but not both", file.display(), base);
+            };
+            let shape = |name: &str| adapter.tensor_info(name).map(|info| info.shape.clone()).unwrap_or_default();
+            let (&[rank, in_features], &[out_features, b_rank]) = (shape(&a_name).as_slice(), shape(&b_name).as_slice()) else {
+                bail!("LoRA adapter {}: '{}' lora_A/lora_B are not 2D", file.display(), base);
+            };
+            if rank != b_rank || rank == 0 {
+                bail!("LoRA adapter {}: '{}' rank mismatch (lora_A {}, lora_B {})", file.display(), base, rank, b_rank);
+            }
+            pairs.insert(base, LoraPair {
+                a: adapter.read_f32(&a_name)?,
+                b: adapter.read_f32(&b_name)?,
+                rank,
+                in_features,
+                out_features,
+            });
+        }
+        if pairs.is_empty() {
+            bail!("LoRA adapter {} has no lora_A/lora_B pairs", file.display());
+        }
+        
+        // Sin alpha en ningún lado: alpha = r (escala 1) con el rango mayor
+        let alpha = match alpha.or(config_alpha) {
+            Some(alpha) => alpha,
+            None => {
+                let rank = pairs.values().map(|p| p.rank).max().unwrap_or(1);
+                eprintln!("[WARN] LoRA: no --lora-alpha nor lora_alpha in adapter_config.json, using alpha = r = {}", rank);
+                rank as f32
+            }
+        };
+        
+        Ok(Self { path: file, alpha, pairs, unsupported })
+    }
+    
+    /// ¿Hay par LoRA para este peso base (nombre fuente)?
+    pub fn has(&self, base_name: &str) -> bool {
+        self.pairs.contains_key(base_name)
+    }
+    
+    /// Suma (alpha / r) · B @ A a `weight` [out, in] si hay par para `base_name`.
+    /// Devuelve si se fusionó.
+    pub fn merge_into(&self, base_name: &str, weight: &mut [f32], shape: &[usize]) -> Result<bool> {
+        let Some(pair) = self.pairs.get(base_name) else {
+            return Ok(false);
+        };
+        if shape != [pair.out_features, pair.in_features] {
+            bail!(
+                "LoRA '{}': B @ A is [{}, {}] but the base weight is {:?}",
+                base_name, pair.out_features, pair.in_features, shape
+            );
+        }
+        
+        let scale = self.alpha / pair.rank as f32;
+        let (rank, cols) = (pair.rank, pair.in_features);
+        for (row, out) in weight.chunks_exact_mut(cols).enumerate() {
+            for k in 0..rank {
+                let b = pair.b[row * rank + k] * scale;
+                if b == 0.0 {
+                    continue;
+                }
+                for (w, a) in out.iter_mut().zip(&pair.a[k * cols..(k + 1) * cols]) {
+                    *w += b * a;
+                }
+            }
+        }
+        Ok(true)
+    }
+    
+    /// Pesos base del adaptador que no están entre `base_names`
+    pub fn unmatched<'a>(&self, base_names: impl IntoIterator<Item = &'a str>) -> Vec<&str> {
+        let present: std::collections::HashSet<&str> = base_names.into_iter().collect();
+        self.pairs.keys()
+            .map(String::as_str)
+            .filter(|name| !present.contains(name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_peft_names() {
+        assert_eq!(
+            parse_lora_name("base_model.model.model.layers.0.self_attn.q_proj.lora_A.weight"),
+            Some(("model.layers.0.self_attn.q_proj.weight".to_string(), Side::A))
+        );
+        assert_eq!(
+            parse_lora_name("base_model.model.model.layers.3.mlp.down_proj.lora_B.default.weight"),
+            Some(("model.layers.3.mlp.down_proj.weight".to_string(), Side::B))
+        );
+        assert_eq!(parse_lora_name("base_model.model.model.embed_tokens.lora_embedding_A"), None);
+    }
+}
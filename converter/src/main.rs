@@ -14,22 +14,69 @@
 //       --cortex ./Phi-4-mini \
 //       -o helios_core.hnf
 //
+// Extraer solo algunas torres de un VLM combinado:
+//   helios-convert ./llava-1.5-7b --select vision -o vision.hnf
+//   helios-convert ./llava-1.5-7b --select text,vision -o llava.hnf   (una sola pasada)
+//
+// Cambiar tokenizer de un HNF ya construido:
+//   helios-convert --set-tokenizer ./Qwen2-7B-new model.hnf -o out.hnf
+//
+// Hints + tokenizer al principio (clientes que leen solo la cabecera):
+//   helios-convert ./Qwen2-7B --metadata-first -o qwen.hnf
+//
+// Reparar block_id/block_type desordenados (offsets intactos):
+//   helios-convert --repair-block-table model.hnf -o fixed.hnf
+//
+// Quitar flags de bloques vacíos y huecos entre bloques:
+//   helios-convert --prune model.hnf -o pruned.hnf
+//
+// Unir HNFs construidos por separado (texto + visión):
+//   helios-convert --merge text.hnf vision.hnf -o combined.hnf
+//
+// Reconvertir solo lo que cambió (config → hints, tokenizer → HTF):
+//   helios-convert ./Qwen2-7B --incremental -o qwen.hnf
+//
+// Salida ASCII para logs de CI (automática si stdout no es un TTY):
+//   helios-convert ./Qwen2-7B --ascii -o qwen.hnf
+//
+// Fusionar un adaptador LoRA (PEFT) antes de cuantizar:
+//   helios-convert ./Llama-3-8B --lora ./my-adapter --lora-alpha 16 -o merged.hnf
+//
+// Configs editados a mano con comas finales:
+//   helios-convert ./Qwen2-7B --lenient-json -o qwen.hnf
+//
+// Ver por qué un tensor se mapea o se descarta:
+//   helios-convert --explain model.layers.0.self_attn.q_proj.weight ./Qwen2-7B
+//
+// Elegir HQ5K/HQ4K por tensor para que los pesos quepan en un tamaño:
+//   helios-convert ./Qwen2-7B --target-size 5GB -o qwen.hnf
+//
+// Códigos de salida: 0 completo, 1 error, 2 archivo escrito pero parcial
+// (--select deja fuera una torre, faltan capas o el embedding de entrada)
+//
 // ============================================================================
 
 use std::path::PathBuf;
 use std::time::Instant;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
 
 use helios_convert::{
-    hqs::QuantFormat,
-    hnf::HnfWriter,
-    mapping::{BlockType, create_mapper, ModelMapper},
-    builder::{process_model, write_combined_hints, BuildStats},
-    htf::{self, DomainType},
+    hqs::{check_super_block, QuantFormat, SUPER_BLOCK_SIZE},
+    hnf::{HnfWriter, HnfReader, HeaderFlags, ChecksumAlgo, merge_hnf, parse_hnf_version, prune_blocks, repair_block_table, reorder_blocks, METADATA_BLOCKS, VERSION_MINOR, BLOCK_MODEL_CARD, MODEL_CARD_MAX_SIZE},
+    mapping::{BlockType, create_mapper, create_tower_mapper, load_config, ModelMapper},
+    builder::{process_model_with_mapper, process_vlm, write_combined_hints, check_skip_ratio, missing_layers, set_tokenizer, write_canonical_report, quant_report, rebuild_incremental, embedding_rows, estimate_model, fit_quant_plan, vocab_sources, BuildOptions, BuildStats, DEFAULT_CHUNK_BYTES, ReportBucket, HintOverrides, TensorEstimate, TiedLmHead},
+    htf::{self, ConfigPreference, DomainType, parse_special_overrides},
+    calibration::load_calibration_tokens,
+    lora::LoraAdapter,
+    incremental::{plan_rebuild, recorded_settings, recorded_sources, scan_file, scan_sources, ConversionSettings, SourceFile, SourceRole},
+    term, outln,
 };
 
+/// Exit code de una conversión que escribió el HNF pero le faltan partes
+const EXIT_PARTIAL: i32 = 2;
+
 #[derive(Parser, Debug)]
 #[command(name = "helios-convert")]
 #[command(about = "Convert HuggingFace models to HNFv9 format")]
@@ -60,10 +107,10 @@ struct Args {
     code: Option<PathBuf>,
     
     /// Output HNF file
-    #[arg(short, long, required = true)]
-    output: PathBuf,
+    #[arg(short, long, required_unless_present = "explain")]
+    output: Option<PathBuf>,
     
-    /// Default quantization format
+    /// Default quantization format (FP16, HQ6K, HQ5K, HQ4K)
     #[arg(short, long, default_value = "HQ5K")]
     quant: String,
     
@@ -71,6 +118,155 @@ struct Args {
     #[arg(long)]
     fast: bool,
     
+    /// Symmetric HQ4K/HQ5K (no zero-point, min = -scale/2)
+    #[arg(long)]
+    symmetric: bool,
+    
+    /// Elements per HQ4K/HQ5K/HQ6K super-block (multiple of 8; recorded in the manifest)
+    #[arg(long, value_name = "N", default_value_t = SUPER_BLOCK_SIZE, value_parser = parse_super_block)]
+    super_block: usize,
+    
+    /// Token sequences (JSON [[ids]]) to weight quantization error by input
+    /// channel importance (AWQ-style, text block only; needs MSE search)
+    #[arg(long, value_name = "FILE", visible_alias = "sample-calibration")]
+    calibration_data: Option<PathBuf>,
+    
+    /// PEFT LoRA adapter (dir with adapter_model.safetensors, or the file) to
+    /// merge into the text weights before quantizing
+    #[arg(long, value_name = "PATH")]
+    lora: Option<PathBuf>,
+    
+    /// LoRA alpha (scale = alpha / r); defaults to adapter_config.json lora_alpha
+    #[arg(long, value_name = "A", requires = "lora")]
+    lora_alpha: Option<f32>,
+    
+    /// Accept trailing commas in config/tokenizer JSON (logged when applied)
+    #[arg(long)]
+    lenient_json: bool,
+    
+    /// HQ5K for the first/last K layers and HQ4K in between (K defaults to 2)
+    #[arg(long, value_name = "K", num_args = 0..=1, default_missing_value = "2")]
+    anneal_quant: Option<usize>,
+    
+    /// Target an older HNF minor version for old readers (e.g. 9.0)
+    #[arg(long, value_name = "VERSION", value_parser = parse_hnf_version)]
+    hnf_version: Option<u16>,
+    
+    /// Block checksum algorithm: xxh3 (default) or blake3 (cryptographic, truncated to 64 bits)
+    #[arg(long, value_name = "ALGO", default_value = "xxh3")]
+    checksum: String,
+    
+    /// Place hints + tokenizer blocks before the weights (extra copy pass)
+    #[arg(long)]
+    metadata_first: bool,
+    
+    /// Override max_position_embeddings in the text hints (extended context)
+    #[arg(long, value_name = "N")]
+    max_position: Option<usize>,
+    
+    /// Only extract these towers from a combined MODEL dir (e.g. text,vision)
+    #[arg(long, value_delimiter = ',', value_name = "TOWERS")]
+    select: Option<Vec<String>>,
+    
+    /// Rebuild the tokenizer (block 0x9) of MODEL (an existing .hnf) from this dir
+    #[arg(long, value_name = "DIR")]
+    set_tokenizer: Option<PathBuf>,
+    
+    /// Rewrite block_id/block_type of MODEL (an existing .hnf) to their index
+    #[arg(long)]
+    repair_block_table: bool,
+    
+    /// Copy MODEL (an existing .hnf) without flags of empty blocks or gaps
+    /// between blocks; offsets, checksums and manifest are recomputed
+    #[arg(long, visible_alias = "prune-optional-blocks")]
+    prune: bool,
+    
+    /// Merge these existing .hnf files into one (e.g. text.hnf vision.hnf)
+    #[arg(long, num_args = 2.., value_name = "HNF")]
+    merge: Option<Vec<PathBuf>>,
+    
+    /// With --merge: the input that wins conflicting blocks and hints
+    #[arg(long, value_name = "HNF", requires = "merge")]
+    prefer: Option<PathBuf>,
+    
+    /// Tied lm_head missing from the checkpoint: omit (default) or alias (manifest alias to the embedding)
+    #[arg(long, value_name = "MODE", default_value = "omit")]
+    tied_lm_head: String,
+    
+    /// Store canonical tensor names without the text./code./vision. prefixes
+    #[arg(long)]
+    flat_names: bool,
+    
+    /// Bundle this model card (markdown or plain text, max 1MB) in reserved block 0xE
+    #[arg(long, value_name = "FILE")]
+    model_card: Option<PathBuf>,
+    
+    /// Store unmapped tensors as FP16 under their original names (non-canonical)
+    #[arg(long, alias = "keep-non-canonical")]
+    keep_unmapped: bool,
+    
+    /// Fail if unmapped/total tensors exceeds this ratio (e.g. 0.05)
+    #[arg(long, value_name = "RATIO")]
+    max_skip_ratio: Option<f64>,
+    
+    /// Store tensors smaller than this many source bytes as FP16
+    #[arg(long, value_name = "BYTES", default_value_t = 0)]
+    quant_min_bytes: usize,
+    
+    /// Quantize tensors in parallel, capping decoded f32 bytes in flight (e.g. 8GB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_byte_size)]
+    max_memory: Option<usize>,
+    
+    /// Pick HQ6K/HQ5K/HQ4K per tensor so the weights fit this size (e.g. 8GB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_byte_size, conflicts_with = "anneal_quant")]
+    target_size: Option<usize>,
+    
+    /// Write every source→canonical decision to this file (.csv or .json)
+    #[arg(long, value_name = "FILE")]
+    canonical_report: Option<PathBuf>,
+    
+    /// Print stored bytes and compression ratio vs FP16 per layer and tensor category
+    #[arg(long)]
+    quant_report: bool,
+    
+    /// Record XXH3-64 of every source shard in the manifest (reads shards fully)
+    #[arg(long)]
+    hash_sources: bool,
+    
+    /// Reuse an existing --output: rebuild only the blocks whose sources changed
+    /// (size/mtime). Different conversion flags force a full conversion
+    #[arg(long)]
+    incremental: bool,
+    
+    /// Override special token ids of the text tokenizer (e.g. eos=151645,pad=151643)
+    #[arg(long, value_name = "SPEC")]
+    set_special: Option<String>,
+    
+    /// Config file that wins when special token ids disagree: tokenizer (default) or generation
+    #[arg(long, value_name = "FILE", default_value = "tokenizer")]
+    prefer_config: String,
+    
+    /// Explain how the model's mapper treats this tensor name and exit (repeatable)
+    #[arg(long, value_name = "TENSOR")]
+    explain: Vec<String>,
+    
+    /// Plain ASCII output (no box drawing or ✓/✗); automatic when stdout is not a TTY
+    #[arg(long, visible_alias = "ascii")]
+    no_color: bool,
+    
+    /// Fail if an added token ID does not fit in the embedding
+    #[arg(long)]
+    strict_tokenizer: bool,
+    
+    /// Fail if config.json vocab_size or the tokenizer go beyond the embedding rows
+    #[arg(long)]
+    strict_vocab: bool,
+    
+    /// Re-encode probe strings with tokenizer.json and with the written HTF; fail if the IDs differ
+    /// (needs --features verify-tokenizer)
+    #[arg(long)]
+    verify_tokenizer: bool,
+    
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
@@ -78,6 +274,8 @@ struct Args {
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    term::init(args.no_color);
+    helios_convert::json::set_lenient(args.lenient_json);
     let start = Instant::now();
     
     // Parse quant format
@@ -85,203 +283,753 @@ fn main() -> Result<()> {
         .ok_or_else(|| anyhow::anyhow!("Invalid quant format: {}", args.quant))?;
     
     let use_mse = !args.fast;
+    let calibration = args.calibration_data.as_deref()
+        .map(load_calibration_tokens)
+        .transpose()?;
+    if calibration.is_some() && !use_mse {
+        eprintln!("[WARN] --calibration-data only affects the MSE search: ignored with --fast");
+    }
+    
+    let lora = args.lora.as_deref()
+        .map(|path| LoraAdapter::open(path, args.lora_alpha))
+        .transpose()?
+        .map(std::sync::Arc::new);
+    
+    let checksum_algo = ChecksumAlgo::parse(&args.checksum)
+        .ok_or_else(|| anyhow::anyhow!("Invalid checksum algorithm: {} (expected xxh3, blake3)", args.checksum))?;
+    
+    let prefer_config = ConfigPreference::parse(&args.prefer_config)
+        .ok_or_else(|| anyhow::anyhow!("Invalid --prefer-config: {} (expected tokenizer, generation)", args.prefer_config))?;
+    let tied_lm_head = TiedLmHead::parse(&args.tied_lm_head)
+        .ok_or_else(|| anyhow::anyhow!("Invalid --tied-lm-head: {} (expected omit, alias)", args.tied_lm_head))?;
+    let special_overrides = args.set_special.as_deref()
+        .map(parse_special_overrides)
+        .transpose()?
+        .unwrap_or_default();
+    
+    // Modo consulta: decisión map/skip de tensores concretos, sin convertir
+    if !args.explain.is_empty() {
+        let path = [&args.model, &args.text, &args.vision, &args.audio, &args.cortex, &args.code]
+            .into_iter()
+            .find_map(|p| p.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("--explain requires a model dir"))?;
+        let mapper = create_mapper(path)
+            .with_context(|| format!("Failed to create mapper for {}", path.display()))?;
+        outln!("[EXPLAIN] {} (mapper: {})", path.display(), mapper.name());
+        for name in &args.explain {
+            outln!("\n{}", mapper.explain(name));
+        }
+        return Ok(());
+    }
+    
+    let output = args.output.clone().expect("clap: --output is required without --explain");
     
-    // Resolver modelo de texto (positional o --text)
-    let text_model = args.text.or(args.model);
+    // Modo post-build: solo reemplazar tokenizer
+    if let Some(tok_dir) = &args.set_tokenizer {
+        let input = args.model.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("--set-tokenizer requires the input .hnf as positional argument"))?;
+        outln!("[TOKENIZER] {} → {} (block 0x9)", tok_dir.display(), output.display());
+        let options = htf::HtfOptions {
+            strict: args.strict_tokenizer,
+            special_overrides: special_overrides.clone(),
+            prefer_config,
+            ..Default::default()
+        };
+        let warnings = set_tokenizer(input, tok_dir, &output, &options)?;
+        outln!("  ✓ Done ({} warnings) in {:.1}s", warnings.len(), start.elapsed().as_secs_f64());
+        return Ok(());
+    }
+    
+    // Modo post-build: reparar ids/types de la block table
+    if args.repair_block_table {
+        let input = args.model.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("--repair-block-table requires the input .hnf as positional argument"))?;
+        outln!("[REPAIR] {} → {}", input.display(), output.display());
+        let fixes = repair_block_table(input, &output)?;
+        for fix in &fixes {
+            outln!("  ✓ {}", fix);
+        }
+        if fixes.is_empty() {
+            outln!("  Block table already consistent");
+        }
+        return Ok(());
+    }
+    
+    // Modo post-build: compactar un HNF existente
+    if args.prune {
+        let input = args.model.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("--prune requires the input .hnf as positional argument"))?;
+        outln!("[PRUNE] {} → {}", input.display(), output.display());
+        let report = prune_blocks(input, &output)?;
+        for block in &report.cleared_blocks {
+            outln!("  ✓ Cleared flag of empty block {}", block);
+        }
+        if report.gap_bytes > 0 {
+            outln!("  ✓ Removed {} bytes of gaps between blocks", report.gap_bytes);
+        }
+        outln!("  {} → {} bytes", report.input_size, report.output_size);
+        return Ok(());
+    }
+    
+    // Modo post-build: unir HNFs existentes
+    if let Some(inputs) = &args.merge {
+        let prefer = match &args.prefer {
+            Some(p) => Some(inputs.iter().position(|i| i == p)
+                .ok_or_else(|| anyhow::anyhow!("--prefer {} is not one of the --merge inputs", p.display()))?),
+            None => None,
+        };
+        outln!("[MERGE] {} inputs → {}", inputs.len(), output.display());
+        let paths: Vec<&std::path::Path> = inputs.iter().map(|p| p.as_path()).collect();
+        let warnings = merge_hnf(&paths, &output, prefer)?;
+        outln!("  ✓ Done ({} warnings) in {:.1}s", warnings.len(), start.elapsed().as_secs_f64());
+        return Ok(());
+    }
+    
+    // --select: las torres elegidas salen todas del mismo directorio combinado
+    let mut vision_model = args.vision.clone();
+    // Motivos por los que el archivo queda parcial (manifest "partial", exit 2)
+    let mut partial: Vec<String> = Vec::new();
+    let text_model = match &args.select {
+        Some(towers) => {
+            let combined = args.model.clone()
+                .ok_or_else(|| anyhow::anyhow!("--select requires the combined model dir as positional argument"))?;
+            let mut text = None;
+            for tower in towers {
+                match tower.trim().to_lowercase().as_str() {
+                    "text" => text = Some(combined.clone()),
+                    "vision" => vision_model = Some(combined.clone()),
+                    other => anyhow::bail!("Unknown tower '{}' in --select (expected text, vision)", other),
+                }
+            }
+            if load_config(&combined)?.get("vision_config").is_some() {
+                if text.is_none() {
+                    partial.push("--select: text tower omitted".to_string());
+                }
+                if vision_model.is_none() {
+                    partial.push("--select: vision tower omitted".to_string());
+                }
+            }
+            text
+        }
+        // Resolver modelo de texto (positional o --text)
+        None => args.text.clone().or(args.model.clone()),
+    };
     
     // Validar que hay al menos un modelo
     if text_model.is_none() 
-        && args.vision.is_none() 
+        && vision_model.is_none() 
         && args.audio.is_none() 
         && args.cortex.is_none() 
         && args.code.is_none() 
     {
         anyhow::bail!("No model specified. Use positional argument or --text/--vision/--audio/--cortex/--code");
     }
+    // Sin prefijo, text/code/cortex mapean al mismo layer0.attn.q_proj.weight...
+    let text_like = [text_model.is_some(), args.code.is_some(), args.cortex.is_some()];
+    if args.flat_names && text_like.iter().filter(|&&present| present).count() > 1 {
+        anyhow::bail!("--flat-names needs a single text-like tower: text, --code and --cortex would share tensor names");
+    }
+    if args.verify_tokenizer && !cfg!(feature = "verify-tokenizer") {
+        anyhow::bail!("--verify-tokenizer needs a build with --features verify-tokenizer");
+    }
+    
+    // Model card: leer antes de convertir para fallar pronto si no cabe
+    let model_card = match &args.model_card {
+        Some(path) => {
+            let card = std::fs::read(path)
+                .with_context(|| format!("Cannot read --model-card {}", path.display()))?;
+            if card.len() > MODEL_CARD_MAX_SIZE {
+                anyhow::bail!(
+                    "--model-card {} is {} bytes, the limit is {} bytes (1 MB)",
+                    path.display(), card.len(), MODEL_CARD_MAX_SIZE
+                );
+            }
+            Some(card)
+        }
+        None => None,
+    };
+    
+    let towers: [(&str, Option<&PathBuf>, BlockType); 5] = [
+        ("TEXT", text_model.as_ref(), BlockType::TextModel),
+        ("VISION", vision_model.as_ref(), BlockType::Vision),
+        ("AUDIO", args.audio.as_ref(), BlockType::Audio),
+        ("CORTEX", args.cortex.as_ref(), BlockType::Cortex),
+        ("CODE", args.code.as_ref(), BlockType::CodeExec),
+    ];
+    
+    let make_mapper = |path: &PathBuf, block: BlockType| {
+        if args.select.is_some() {
+            create_tower_mapper(path, block)
+        } else {
+            create_mapper(path)
+        }
+    };
+    
+    // Construir lista de fuentes de tokenizer
+    let mut tok_sources: Vec<(&std::path::Path, DomainType, bool)> = Vec::new();
+    
+    // TEXT es siempre primario si existe
+    if let Some(path) = text_model.as_ref() {
+        tok_sources.push((path.as_path(), DomainType::Text, true));
+    }
+    
+    // CODE como dominio secundario
+    if let Some(path) = args.code.as_ref() {
+        tok_sources.push((path.as_path(), DomainType::Code, false));
+    }
+    
+    // CORTEX como dominio secundario (usa TEXT domain type ya que es LLM)
+    if let Some(path) = args.cortex.as_ref() {
+        // Cortex es otro LLM, podría compartir tokenizer con text o tener el suyo
+        // Por ahora lo añadimos como TEXT secundario
+        tok_sources.push((path.as_path(), DomainType::Text, false));
+    }
+    
+    // AUDIO si tiene tokenizer
+    if let Some(path) = args.audio.as_ref() {
+        tok_sources.push((path.as_path(), DomainType::Audio, false));
+    }
+    
+    let htf_options = htf::HtfOptions {
+        strict: args.strict_tokenizer,
+        special_overrides: special_overrides.clone(),
+        prefer_config,
+        ..Default::default()
+    };
+    let overrides = HintOverrides { max_position: args.max_position };
+    
+    // Tamaño y mtime de los archivos fuente: --incremental los compara
+    let mut source_files: Vec<SourceFile> = Vec::new();
+    for (_, path, block) in towers {
+        let Some(path) = path else { continue };
+        source_files.extend(scan_sources(path, block.name())?);
+    }
+    // El adapter LoRA y la calibración cambian los pesos cuantizados igual que los safetensors
+    if let Some(lora) = &lora {
+        source_files.push(scan_file(&lora.path, "lora", SourceRole::Weights)?);
+        let adapter_config = lora.path.with_file_name("adapter_config.json");
+        if adapter_config.is_file() {
+            source_files.push(scan_file(&adapter_config, "lora", SourceRole::Weights)?);
+        }
+    }
+    if let Some(path) = &args.calibration_data {
+        source_files.push(scan_file(path, "calibration", SourceRole::Weights)?);
+    }
+    // La model card va en su propio bloque, pero no hay reescritura parcial para él
+    if let Some(path) = &args.model_card {
+        source_files.push(scan_file(path, "model_card", SourceRole::Weights)?);
+    }
+    let settings = ConversionSettings {
+        quant: default_quant.to_string(),
+        use_mse,
+        super_block: args.super_block,
+        symmetric: args.symmetric,
+        anneal_quant: args.anneal_quant,
+        target_size: args.target_size,
+        quant_min_bytes: args.quant_min_bytes,
+        select: args.select.clone(),
+        flat_names: args.flat_names,
+        keep_unmapped: args.keep_unmapped,
+        tied_lm_head: args.tied_lm_head.clone(),
+        lora_alpha: args.lora_alpha,
+        max_position: args.max_position,
+        metadata_first: args.metadata_first,
+        hnf_version: args.hnf_version,
+        checksum: checksum_algo.name().to_string(),
+    };
+    
+    // --incremental: reutilizar el HNF existente si los pesos no cambiaron
+    if args.incremental && output.exists() {
+        let recorded = HnfReader::open(&output)
+            .ok()
+            .and_then(|hnf| Some((recorded_sources(hnf.manifest())?, recorded_settings(hnf.manifest()))));
+        match recorded.map(|(recorded, flags)| (plan_rebuild(&recorded, &source_files), flags.as_ref() == Some(&settings))) {
+            None => outln!("[INCREMENTAL] {} has no source metadata: full conversion", output.display()),
+            Some((_, false)) => outln!("[INCREMENTAL] Conversion flags changed: full conversion"),
+            Some((plan, _)) if plan.full => outln!("[INCREMENTAL] Weights changed: full conversion"),
+            Some((plan, _)) if plan.is_empty() => {
+                outln!("[INCREMENTAL] Sources unchanged: {} is up to date", output.display());
+                return Ok(());
+            }
+            Some((plan, _)) => {
+                let mut mappers: Vec<(Box<dyn ModelMapper>, BlockType)> = Vec::new();
+                for (_, path, block) in towers {
+                    let Some(path) = path else { continue };
+                    mappers.push((make_mapper(path, block)?, block));
+                }
+                let mapper_refs: Vec<(&dyn ModelMapper, BlockType)> = mappers
+                    .iter()
+                    .map(|(m, b)| (m.as_ref(), *b))
+                    .collect();
+                let outcome = rebuild_incremental(
+                    &output, &plan, &mapper_refs, &overrides, &tok_sources, &htf_options, &source_files,
+                )?;
+                outln!("[INCREMENTAL] {}", output.display());
+                outln!("  Rebuilt: {}", if outcome.rebuilt.is_empty() { "-".to_string() } else { outcome.rebuilt.join(", ") });
+                outln!("  Reused:  {}", outcome.reused.join(", "));
+                outln!("  ✓ Done in {:.1}s", start.elapsed().as_secs_f64());
+                return Ok(());
+            }
+        }
+    }
     
-    println!("═══════════════════════════════════════════════════════════════");
-    println!("  HELIOS CONVERTER v0.2.1 - HQS v6 Nuclear + Multi-Tokenizer");
-    println!("═══════════════════════════════════════════════════════════════");
-    println!("  Default quant: {}", default_quant);
-    println!("  MSE search:    {}", if use_mse { "ON" } else { "OFF (fast)" });
-    println!("  Output:        {}", args.output.display());
-    println!("═══════════════════════════════════════════════════════════════");
+    outln!("═══════════════════════════════════════════════════════════════");
+    outln!("  HELIOS CONVERTER v0.2.1 - HQS v6 Nuclear + Multi-Tokenizer");
+    outln!("═══════════════════════════════════════════════════════════════");
+    outln!("  Default quant: {}", default_quant);
+    outln!("  MSE search:    {}", if use_mse { "ON" } else { "OFF (fast)" });
+    if args.symmetric {
+        outln!("  Symmetric:     ON (no zero-point)");
+    }
+    if args.super_block != SUPER_BLOCK_SIZE {
+        outln!("  Super-block:   {} elements", args.super_block);
+    }
+    if let Some(sequences) = calibration.as_ref().filter(|_| use_mse) {
+        outln!("  Calibration:   {} sequences, {} tokens", sequences.len(), sequences.iter().map(Vec::len).sum::<usize>());
+    }
+    if let Some(lora) = &lora {
+        outln!("  LoRA:          {} pairs, alpha {} ({})", lora.pairs.len(), lora.alpha, lora.path.display());
+    }
+    if let Some(edge) = args.anneal_quant {
+        outln!("  Anneal:        HQ5K first/last {} layers, HQ4K middle", edge);
+    }
+    if checksum_algo != ChecksumAlgo::Xxh3 {
+        outln!("  Checksum:      {}", checksum_algo.name());
+    }
+    if let Some(minor) = args.hnf_version.filter(|&m| m != VERSION_MINOR) {
+        outln!("  HNF version:   9.{} (compat)", minor);
+    }
+    outln!("  Output:        {}", output.display());
+    outln!("═══════════════════════════════════════════════════════════════");
     
     // Crear writer
-    let mut writer = HnfWriter::create(&args.output)?;
+    // --metadata-first: se construye en un temporal y luego se reordena
+    let build_path = if args.metadata_first {
+        let mut partial = output.clone().into_os_string();
+        partial.push(".partial");
+        PathBuf::from(partial)
+    } else {
+        output.clone()
+    };
+    let mut writer = HnfWriter::create(&build_path)?;
+    writer.set_version_minor(args.hnf_version.unwrap_or(VERSION_MINOR))?;
+    writer.set_checksum_algo(checksum_algo)?;
     
     // Recolectar mappers para hints combinados
     let mut mappers: Vec<(Box<dyn ModelMapper>, BlockType)> = Vec::new();
     let mut total_stats = BuildStats::default();
+    let mut sources: Vec<serde_json::Value> = Vec::new();
+    let mut options = BuildOptions {
+        default_quant,
+        use_mse,
+        symmetric: args.symmetric,
+        super_block: args.super_block,
+        verbose: args.verbose,
+        hash_sources: args.hash_sources,
+        quant_min_bytes: args.quant_min_bytes,
+        canonical_report: args.canonical_report.is_some() || args.quant_report,
+        max_memory: args.max_memory,
+        anneal_layers: args.anneal_quant,
+        keep_unmapped: args.keep_unmapped,
+        quant_plan: None,
+        tied_lm_head,
+        flat_names: args.flat_names,
+        chunk_bytes: DEFAULT_CHUNK_BYTES,
+        calibration: calibration.filter(|_| use_mse),
+        lora: lora.clone(),
+    };
     
     // ══════════════════════════════════════════════════════════════════════
     // PROCESAR CADA MODELO
     // ══════════════════════════════════════════════════════════════════════
     
-    if let Some(path) = &text_model {
-        println!("\n[TEXT] {} → block 0x0", path.display());
-        let stats = process_model(path, BlockType::TextModel, &mut writer, default_quant, use_mse, args.verbose)?;
-        println!("  ✓ {} tensors (FP16:{}, HQ5K:{}, HQ4K:{})", 
-            stats.total_tensors(), stats.fp16_count, stats.hq5k_count, stats.hq4k_count);
+    // --target-size: planificar todo con HQ5K (HQ6K si el mapper lo pide) y bajar hasta que quepa
+    let mut predicted_bytes = None;
+    if let Some(budget) = args.target_size {
+        outln!("\n[TARGET] Fitting tensors into {:.1} MB...", budget as f64 / 1024.0 / 1024.0);
+        let estimate_options = BuildOptions { default_quant: QuantFormat::HQ5K, ..options.clone() };
+        let mut estimates: Vec<TensorEstimate> = Vec::new();
+        for (_, path, block) in towers {
+            let Some(path) = path else { continue };
+            let mapper = make_mapper(path, block)?;
+            estimates.extend(estimate_model(path, block, mapper.as_ref(), &estimate_options)?);
+        }
+        let plan = fit_quant_plan(&mut estimates, budget)?;
         
-        let mapper = create_mapper(path)?;
-        mappers.push((mapper, BlockType::TextModel));
-        merge_stats(&mut total_stats, &stats);
+        let count = |q: QuantFormat| estimates.iter().filter(|e| e.quant == q).count();
+        let predicted: usize = estimates.iter().map(TensorEstimate::bytes).sum();
+        outln!("  Plan:      FP16:{}, HQ6K:{}, HQ5K:{}, HQ4K:{}",
+            count(QuantFormat::FP16), count(QuantFormat::HQ6K), count(QuantFormat::HQ5K), count(QuantFormat::HQ4K));
+        if args.verbose {
+            for e in estimates.iter().filter(|e| e.quant == QuantFormat::HQ4K) {
+                outln!("    [HQ4K] {} ({} elements)", e.final_name, e.numel);
+            }
+        }
+        outln!("  Predicted: {:.1} MB of tensors", predicted as f64 / 1024.0 / 1024.0);
+        predicted_bytes = Some(predicted);
+        options.quant_plan = Some(plan);
     }
     
-    if let Some(path) = &args.vision {
-        println!("\n[VISION] {} → block 0x1", path.display());
-        let stats = process_model(path, BlockType::Vision, &mut writer, default_quant, use_mse, args.verbose)?;
-        println!("  ✓ {} tensors (FP16:{}, HQ5K:{}, HQ4K:{})", 
-            stats.total_tensors(), stats.fp16_count, stats.hq5k_count, stats.hq4k_count);
-        
-        let mapper = create_mapper(path)?;
-        mappers.push((mapper, BlockType::Vision));
-        merge_stats(&mut total_stats, &stats);
-    }
+    // --select text,vision: las dos torres en una pasada sobre el mismo reader
+    let mut vlm_towers = match (&text_model, &vision_model) {
+        (Some(text), Some(vision)) if args.select.is_some() && text == vision => {
+            outln!("\n[VLM] {} → blocks 0x{:X} + 0x{:X} (single pass)",
+                text.display(), BlockType::TextModel.as_usize(), BlockType::Vision.as_usize());
+            process_vlm(text, &mut writer, &options)?
+        }
+        _ => Vec::new(),
+    };
     
-    if let Some(path) = &args.audio {
-        println!("\n[AUDIO] {} → block 0x2", path.display());
-        let stats = process_model(path, BlockType::Audio, &mut writer, default_quant, use_mse, args.verbose)?;
-        println!("  ✓ {} tensors (FP16:{}, HQ5K:{}, HQ4K:{})", 
-            stats.total_tensors(), stats.fp16_count, stats.hq5k_count, stats.hq4k_count);
+    for (label, path, block) in towers {
+        let Some(path) = path else { continue };
         
-        let mapper = create_mapper(path)?;
-        mappers.push((mapper, BlockType::Audio));
-        merge_stats(&mut total_stats, &stats);
-    }
-    
-    if let Some(path) = &args.cortex {
-        println!("\n[CORTEX] {} → block 0x7", path.display());
-        let stats = process_model(path, BlockType::Cortex, &mut writer, default_quant, use_mse, args.verbose)?;
-        println!("  ✓ {} tensors (FP16:{}, HQ5K:{}, HQ4K:{})", 
-            stats.total_tensors(), stats.fp16_count, stats.hq5k_count, stats.hq4k_count);
+        outln!("\n[{}] {} → block 0x{:X}", label, path.display(), block.as_usize());
+        let (mapper, stats) = match vlm_towers.iter().position(|t| t.block == block) {
+            Some(i) => {
+                let tower = vlm_towers.remove(i);
+                (tower.mapper, tower.stats)
+            }
+            None => {
+                let mapper = make_mapper(path, block)?;
+                let stats = process_model_with_mapper(path, block, &mut writer, mapper.as_ref(), &options)?;
+                (mapper, stats)
+            }
+        };
+        outln!("  ✓ {} tensors (FP16:{}, HQ6K:{}, HQ5K:{}, HQ4K:{})", 
+            stats.total_tensors(), stats.fp16_count, stats.hq6k_count, stats.hq5k_count, stats.hq4k_count);
+        if stats.skipped_count > 0 || stats.ignored_count > 0 {
+            outln!("    skipped: {} unmapped ({:.2}%), {} ignored",
+                stats.skipped_count, stats.skip_ratio() * 100.0, stats.ignored_count);
+        }
+        if stats.below_super_block_count > 0 {
+            outln!("    {} tensors smaller than a super-block kept as FP16", stats.below_super_block_count);
+        }
+        if stats.below_min_bytes_count > 0 {
+            outln!("    {} tensors below --quant-min-bytes kept as FP16", stats.below_min_bytes_count);
+        }
+        if stats.aliased_count > 0 {
+            outln!("    {} aliased tensors share storage (stored once)", stats.aliased_count);
+        }
+        if stats.extras_count > 0 {
+            outln!("    {} unmapped tensors kept verbatim as FP16 (non-canonical)", stats.extras_count);
+        }
+        if stats.lora_merged > 0 {
+            outln!("    {} weights merged with LoRA", stats.lora_merged);
+        }
+        
+        if let Some(max_ratio) = args.max_skip_ratio {
+            check_skip_ratio(&stats, max_ratio)
+                .with_context(|| format!("[{}] {}", label, path.display()))?;
+        }
+        
+        for src in &stats.sources {
+            sources.push(serde_json::json!({
+                "modality": block.name(),
+                "file": src.file,
+                "size": src.size,
+                "xxh3": src.xxh3,
+            }));
+        }
         
-        let mapper = create_mapper(path)?;
-        mappers.push((mapper, BlockType::Cortex));
+        let missing = missing_layers(&writer.tensor_manifests()[block.as_usize()], mapper.num_layers());
+        if !missing.is_empty() {
+            eprintln!("[WARN] [{}] {} of {} layers have no tensors: {:?}",
+                label, missing.len(), mapper.num_layers(), missing);
+            partial.push(format!("{}: {} of {} layers missing", block.name(), missing.len(), mapper.num_layers()));
+        }
+        
+        mappers.push((mapper, block));
         merge_stats(&mut total_stats, &stats);
+        total_stats.mapping.extend(stats.mapping);
     }
     
-    if let Some(path) = &args.code {
-        println!("\n[CODE] {} → block 0x8", path.display());
-        let stats = process_model(path, BlockType::CodeExec, &mut writer, default_quant, use_mse, args.verbose)?;
-        println!("  ✓ {} tensors (FP16:{}, HQ5K:{}, HQ4K:{})", 
-            stats.total_tensors(), stats.fp16_count, stats.hq5k_count, stats.hq4k_count);
-        
-        let mapper = create_mapper(path)?;
-        mappers.push((mapper, BlockType::CodeExec));
-        merge_stats(&mut total_stats, &stats);
+    // IS_MOE no sale de los bloques: lo declara un mapper o hay expertos escritos
+    let is_moe = mappers.iter()
+        .any(|(m, _)| m.execution_hints().get("moe_enabled").and_then(|v| v.as_bool()).unwrap_or(false))
+        || writer.tensor_manifests().iter().flatten().any(|t| t.name.contains("moe.experts."));
+    if is_moe {
+        writer.set_flags(HeaderFlags::IS_MOE);
     }
     
     // ══════════════════════════════════════════════════════════════════════
     // EXECUTION HINTS
     // ══════════════════════════════════════════════════════════════════════
     
-    println!("\n[HINTS] Writing execution hints...");
+    outln!("\n[HINTS] Writing execution hints...");
     let mapper_refs: Vec<(&dyn ModelMapper, BlockType)> = mappers
         .iter()
         .map(|(m, b)| (m.as_ref(), *b))
         .collect();
-    write_combined_hints(&mut writer, &mapper_refs)?;
-    println!("  ✓ Done");
+    write_combined_hints(&mut writer, &mapper_refs, &overrides)?;
+    outln!("  ✓ Done");
+    
+    // config.json, embedding y tokenizer deben cubrir los mismos IDs: es la
+    // causa más común de inferencia rota (token → embedding fuera de rango)
+    if let Some((mapper, _)) = mapper_refs.iter().find(|(_, b)| *b == BlockType::TextModel) {
+        let tensors = &writer.tensor_manifests()[BlockType::TextModel.as_usize()];
+        let vocab = vocab_sources(*mapper, tensors, text_model.as_deref())?;
+        if let Some(message) = vocab.disagreement() {
+            if args.strict_vocab {
+                anyhow::bail!("{} (--strict-vocab)", message);
+            }
+            eprintln!("[WARN] {}", message);
+        }
+        if embedding_rows(tensors).is_none() {
+            partial.push("text_model: token_embedding.weight missing".to_string());
+        }
+    }
     
     // ══════════════════════════════════════════════════════════════════════
     // TOKENIZER (MULTI-DOMAIN)
     // ══════════════════════════════════════════════════════════════════════
     
-    println!("\n[TOKENIZER] Writing tokenizers (multi-domain)...");
-    
-    // Construir lista de fuentes de tokenizer
-    let mut tok_sources: Vec<(&std::path::Path, DomainType, bool)> = Vec::new();
-    
-    // TEXT es siempre primario si existe
-    if let Some(path) = text_model.as_ref() {
-        tok_sources.push((path.as_path(), DomainType::Text, true));
-    }
-    
-    // CODE como dominio secundario
-    if let Some(path) = args.code.as_ref() {
-        tok_sources.push((path.as_path(), DomainType::Code, false));
-    }
-    
-    // CORTEX como dominio secundario (usa TEXT domain type ya que es LLM)
-    if let Some(path) = args.cortex.as_ref() {
-        // Cortex es otro LLM, podría compartir tokenizer con text o tener el suyo
-        // Por ahora lo añadimos como TEXT secundario
-        tok_sources.push((path.as_path(), DomainType::Text, false));
-    }
-    
-    // AUDIO si tiene tokenizer
-    if let Some(path) = args.audio.as_ref() {
-        tok_sources.push((path.as_path(), DomainType::Audio, false));
-    }
+    outln!("\n[TOKENIZER] Writing tokenizers (multi-domain)...");
     
     // Construir HTF multi-domain
     if !tok_sources.is_empty() {
-        let htf_bytes = htf::build_htf_multi(&tok_sources)?;
-        writer.write_tokenizer(&htf_bytes)?;
-        println!("  ✓ {} bytes ({} domains)", htf_bytes.len(), tok_sources.len());
+        let htf_options = htf::HtfOptions {
+            // El vocab del tokenizer no puede pasar de las filas del embedding de texto
+            max_vocab: embedding_rows(&writer.tensor_manifests()[BlockType::TextModel.as_usize()]),
+            ..htf_options.clone()
+        };
+        // Directo al archivo: con vocabs de 256k tokens el blob no se ensambla en memoria
+        let htf_writer = htf::htf_writer_multi_with(&tok_sources, &htf_options)?;
+        let htf_size = writer.write_tokenizer_from(&htf_writer)?;
+        outln!("  ✓ {} bytes ({} domains)", htf_size, tok_sources.len());
+        
+        #[cfg(feature = "verify-tokenizer")]
+        if args.verify_tokenizer {
+            let text_source = tok_sources.iter().find(|(_, domain, _)| *domain == DomainType::Text);
+            verify_htf_tokenizer(text_source.map(|(path, _, _)| *path), &htf_writer.build())?;
+        }
     } else {
-        println!("  ⚠ No tokenizers found");
+        outln!("  ⚠ No tokenizers found");
     }
     
     // ══════════════════════════════════════════════════════════════════════
     // FINALIZE
     // ══════════════════════════════════════════════════════════════════════
     
-    println!("\n[FINALIZE] Writing manifest...");
-    let manifest = serde_json::json!({
+    if let Some(card) = &model_card {
+        writer.write_model_card(card)?;
+        outln!("\n[MODEL CARD] ✓ {} bytes in block 0x{:X}", card.len(), BLOCK_MODEL_CARD);
+    }
+    
+    outln!("\n[FINALIZE] Writing manifest...");
+    let mut manifest = serde_json::json!({
         "format": "HNFv9",
         "version": "9.0.1",
         "generator": "helios-convert 0.2.1",
+        // Tensores con prefijo de modalidad ("text.layer0...") o canónicos pelados
+        "prefixed": !args.flat_names,
         "quantization": {
             "default": args.quant,
             "hqs_version": "v6-nuclear",
             "mse_search": use_mse,
+            "symmetric": args.symmetric,
+            // Elementos por super-block HQ*K: el dequantizador lo necesita
+            "super_block": args.super_block,
+            "anneal": args.anneal_quant.map(|edge| serde_json::json!({
+                "edge_layers": edge,
+                "edge_format": QuantFormat::HQ5K.to_string(),
+                "middle_format": QuantFormat::HQ4K.to_string(),
+            })),
+            "target_size": args.target_size,
+            // Secuencias de --calibration-data (None = MSE plano)
+            "calibration_sequences": options.calibration.as_ref().map(Vec::len),
         },
         "stats": {
             "total_tensors": total_stats.total_tensors(),
             "fp16": total_stats.fp16_count,
+            "hq6k": total_stats.hq6k_count,
             "hq5k": total_stats.hq5k_count,
             "hq4k": total_stats.hq4k_count,
             "skipped": total_stats.skipped_count,
+            "ignored": total_stats.ignored_count,
+            "aliased": total_stats.aliased_count,
+            "non_canonical": total_stats.extras_count,
+            "lora_merged": total_stats.lora_merged,
         },
         "tokenizer": {
             "multi_domain": true,
             "domains": tok_sources.len(),
         }
     });
+    if let Some(lora) = &lora {
+        manifest["lora"] = serde_json::json!({
+            "adapter": lora.path.file_name().map(|n| n.to_string_lossy().into_owned()),
+            "alpha": lora.alpha,
+            "pairs": lora.pairs.len(),
+        });
+    }
+    if args.hash_sources {
+        manifest["sources"] = serde_json::Value::Array(sources);
+    }
+    manifest["source_files"] = serde_json::to_value(&source_files)?;
+    manifest["conversion_settings"] = serde_json::to_value(&settings)?;
+    manifest["partial"] = serde_json::json!(!partial.is_empty());
+    if !partial.is_empty() {
+        manifest["partial_reasons"] = serde_json::json!(partial);
+    }
+    manifest["has_model_card"] = serde_json::json!(model_card.is_some());
+    if let Some(card) = &model_card {
+        manifest["model_card"] = serde_json::json!({"block": BLOCK_MODEL_CARD, "size": card.len()});
+    }
     writer.finalize(manifest)?;
     
+    if args.metadata_first {
+        outln!("[FINALIZE] Moving hints + tokenizer to file head...");
+        reorder_blocks(&build_path, &output, &METADATA_BLOCKS)?;
+        std::fs::remove_file(&build_path)?;
+    }
+    
+    if let Some(report) = &args.canonical_report {
+        write_canonical_report(report, &total_stats.mapping)?;
+        outln!("  ✓ Canonical report: {} ({} rows)", report.display(), total_stats.mapping.len());
+    }
+    
+    if args.quant_report {
+        print_quant_report(&total_stats);
+    }
+    
     // ══════════════════════════════════════════════════════════════════════
     // SUMMARY
     // ══════════════════════════════════════════════════════════════════════
     
     let elapsed = start.elapsed();
-    let file_size = std::fs::metadata(&args.output)?.len();
-    
-    println!("\n═══════════════════════════════════════════════════════════════");
-    println!("  CONVERSION COMPLETE");
-    println!("═══════════════════════════════════════════════════════════════");
-    println!("  Time:       {:.1}s", elapsed.as_secs_f64());
-    println!("  Size:       {:.1} MB", file_size as f64 / 1024.0 / 1024.0);
-    println!("  Tensors:    {} (FP16:{}, HQ5K:{}, HQ4K:{})", 
+    let file_size = std::fs::metadata(&output)?.len();
+    
+    outln!("\n═══════════════════════════════════════════════════════════════");
+    outln!("  CONVERSION COMPLETE");
+    outln!("═══════════════════════════════════════════════════════════════");
+    outln!("  Time:       {:.1}s", elapsed.as_secs_f64());
+    outln!("  Size:       {:.1} MB", file_size as f64 / 1024.0 / 1024.0);
+    outln!("  Tensors:    {} (FP16:{}, HQ6K:{}, HQ5K:{}, HQ4K:{})", 
         total_stats.total_tensors(),
         total_stats.fp16_count,
+        total_stats.hq6k_count,
         total_stats.hq5k_count,
         total_stats.hq4k_count);
-    println!("  Skipped:    {}", total_stats.skipped_count);
-    println!("  Tokenizers: {} domains", tok_sources.len());
-    println!("  Output:     {}", args.output.display());
-    println!("═══════════════════════════════════════════════════════════════");
+    outln!("  Skipped:    {} ({} ignored)", total_stats.skipped_count, total_stats.ignored_count);
+    if let (Some(budget), Some(predicted)) = (args.target_size, predicted_bytes) {
+        outln!("  Target:     {:.1} MB (tensors predicted {:.1} MB, actual {:.1} MB; file {:.1} MB)",
+            budget as f64 / 1024.0 / 1024.0,
+            predicted as f64 / 1024.0 / 1024.0,
+            total_stats.total_bytes as f64 / 1024.0 / 1024.0,
+            file_size as f64 / 1024.0 / 1024.0);
+    }
+    outln!("  Tokenizers: {} domains", tok_sources.len());
+    outln!("  Output:     {}", output.display());
+    outln!("═══════════════════════════════════════════════════════════════");
+    
+    // 0 = completo, 1 = error (Err de main), 2 = archivo escrito pero parcial:
+    // los scripts no pueden confundir un modelo recortado con uno completo
+    if !partial.is_empty() {
+        outln!("\n═══════════════════════════════════════════════════════════════");
+        outln!("  PARTIAL: output is missing parts of the model");
+        outln!("═══════════════════════════════════════════════════════════════");
+        for reason in &partial {
+            outln!("  - {}", reason);
+        }
+        std::process::exit(EXIT_PARTIAL);
+    }
     
     Ok(())
 }
 
+/// --verify-tokenizer: IDs de tokenizer.json (crate tokenizers) contra los
+/// del HTF recién construido; cualquier diferencia aborta la conversión
+#[cfg(feature = "verify-tokenizer")]
+fn verify_htf_tokenizer(model_dir: Option<&std::path::Path>, htf_bytes: &[u8]) -> Result<()> {
+    let tokenizer_json = model_dir.map(|dir| dir.join("tokenizer.json"))
+        .filter(|path| path.exists())
+        .context("--verify-tokenizer needs a tokenizer.json in the text model dir")?;
+    let divergences = htf::verify_tokenizer(&tokenizer_json, htf_bytes, htf::DEFAULT_PROBES)?;
+    if !divergences.is_empty() {
+        for divergence in &divergences {
+            eprintln!("[WARN] {}", divergence);
+        }
+        anyhow::bail!(
+            "HTF tokenizer diverges from {} on {} of {} probes",
+            tokenizer_json.display(), divergences.len(), htf::DEFAULT_PROBES.len()
+        );
+    }
+    outln!("  ✓ Round-trip: {} probes encode identically", htf::DEFAULT_PROBES.len());
+    Ok(())
+}
+
+/// Histograma de bytes guardados por capa y por categoría (--quant-report)
+fn print_quant_report(stats: &BuildStats) {
+    const BAR_WIDTH: usize = 30;
+    let report = quant_report(&stats.mapping);
+    let max = report.layers.values().chain(report.categories.values())
+        .map(|b| b.stored)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let line = |label: &str, bucket: &ReportBucket| {
+        let bar = (bucket.stored as f64 / max as f64 * BAR_WIDTH as f64).round() as usize;
+        outln!("  {:<16} {:>10.2} MB  {:>5.2}x  {}",
+            label,
+            bucket.stored as f64 / 1024.0 / 1024.0,
+            bucket.ratio(),
+            "█".repeat(bar));
+    };
+    
+    outln!("\n[QUANT REPORT] Stored bytes (ratio vs FP16)");
+    for (layer, bucket) in &report.layers {
+        match layer {
+            Some(idx) => line(&format!("layer {}", idx), bucket),
+            None => line("(no layer)", bucket),
+        }
+    }
+    outln!("  ─────");
+    for (category, bucket) in &report.categories {
+        line(category, bucket);
+    }
+    outln!("  ─────");
+    line("total", &report.total);
+}
+
+/// --super-block: múltiplo de GROUP_SIZE hasta MAX_SUPER_BLOCK_SIZE
+fn parse_super_block(s: &str) -> Result<usize, String> {
+    let size: usize = s.trim().parse().map_err(|_| format!("invalid super-block size '{}'", s))?;
+    check_super_block(size)?;
+    Ok(size)
+}
+
+/// "8GB", "512MiB", "1048576" → bytes (sufijos en base 1024)
+fn parse_byte_size(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid size '{}'", s))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        other => return Err(format!("unknown size unit '{}'", other)),
+    };
+    let bytes = (number * multiplier as f64) as usize;
+    if bytes == 0 {
+        return Err("size must be greater than zero".to_string());
+    }
+    Ok(bytes)
+}
+
 fn merge_stats(total: &mut BuildStats, part: &BuildStats) {
     total.fp16_count += part.fp16_count;
+    total.hq6k_count += part.hq6k_count;
     total.hq5k_count += part.hq5k_count;
     total.hq4k_count += part.hq4k_count;
     total.skipped_count += part.skipped_count;
+    total.ignored_count += part.ignored_count;
+    total.below_min_bytes_count += part.below_min_bytes_count;
+    total.below_super_block_count += part.below_super_block_count;
+    total.aliased_count += part.aliased_count;
+    total.extras_count += part.extras_count;
+    total.lora_merged += part.lora_merged;
     total.total_bytes += part.total_bytes;
 }
@@ -6,6 +6,7 @@
 // Soporta: CLIP, OpenCLIP, SigLIP, ViT
 // Vision encoders con arquitectura transformer.
 //
+// v9.0.7: num_image_tokens con compute_num_image_tokens (SigLIP sin CLS)
 // v9.0.5: Mejora encoder_variant dinámico
 //
 // Nombres canónicos según HELIOS_DICTIONARY v9.0.2:
@@ -19,14 +20,30 @@
 //   vision.layer{N}.ln2.{weight,bias}
 //   vision.pre_layernorm.{weight,bias}
 //   vision.post_layernorm.{weight,bias}
+//   projector.vision.linear{N}.{weight,bias}   (multi_modal_projector de un VLM)
 //
 // ============================================================================
 
+use anyhow::Result;
 use regex::Regex;
 use serde_json::{json, Value};
 
+use crate::hints::{compute_num_image_tokens, resolve_head_dim};
 use super::traits::ModelMapper;
-use super::types::{TensorMapping, QuantHint, TensorCategory};
+use super::types::{MapExplanation, TensorMapping, QuantHint, TensorCategory};
+
+/// SigLIP (raíz o vision_config): model_type o architectures lo nombran
+fn is_siglip(config: &Value) -> bool {
+    let model_types = [config, config.get("vision_config").unwrap_or(&Value::Null)]
+        .into_iter()
+        .filter_map(|c| c.get("model_type").and_then(|v| v.as_str()));
+    let archs = config.get("architectures")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str());
+    model_types.chain(archs).any(|name| name.to_lowercase().contains("siglip"))
+}
 
 #[derive(Debug, Clone)]
 pub struct ClipConfig {
@@ -34,11 +51,14 @@ pub struct ClipConfig {
     pub hidden_size: usize,
     pub intermediate_size: usize,
     pub num_attention_heads: usize,
+    pub head_dim: Option<usize>,  // explícito en config.json; si no, hidden_size / heads
     pub image_size: usize,
     pub patch_size: usize,
     pub num_channels: usize,
     pub layer_norm_eps: f64,
     pub projection_dim: Option<usize>,  // v9.0.5: Para detectar variante
+    /// SigLIP no tiene CLS token: sus tokens de imagen son solo los patches
+    pub has_cls: bool,
 }
 
 impl ClipConfig {
@@ -51,11 +71,13 @@ impl ClipConfig {
             hidden_size: vision_config["hidden_size"].as_u64().unwrap_or(1024) as usize,
             intermediate_size: vision_config["intermediate_size"].as_u64().unwrap_or(4096) as usize,
             num_attention_heads: vision_config["num_attention_heads"].as_u64().unwrap_or(16) as usize,
+            head_dim: vision_config["head_dim"].as_u64().map(|v| v as usize),
             image_size: vision_config["image_size"].as_u64().unwrap_or(224) as usize,
             patch_size: vision_config["patch_size"].as_u64().unwrap_or(14) as usize,
             num_channels: vision_config["num_channels"].as_u64().unwrap_or(3) as usize,
             layer_norm_eps: vision_config["layer_norm_eps"].as_f64().unwrap_or(1e-5),
             projection_dim: vision_config["projection_dim"].as_u64().map(|x| x as usize),
+            has_cls: !is_siglip(config),
         }
     }
     
@@ -101,6 +123,8 @@ pub struct ClipMapper {
     re_post_norm: Regex,
     // Projection head
     re_projection: Regex,
+    // Projector VLM (LLaVA): linear_1, linear_2...
+    re_mm_projector: Regex,
 }
 
 impl ClipMapper {
@@ -126,12 +150,24 @@ impl ClipMapper {
             re_post_norm: Regex::new(r"^vision_model\.post_layernorm\.(weight|bias)$").unwrap(),
             // Projection
             re_projection: Regex::new(r"^visual_projection\.weight$").unwrap(),
+            // Projector VLM
+            re_mm_projector: Regex::new(r"^(?:model\.)?multi_modal_projector\.linear_(\d+)\.(weight|bias)$").unwrap(),
         }
     }
     
     pub fn from_json(config: &Value) -> Self {
         Self::new(ClipConfig::from_json(config))
     }
+    
+    /// Regexes en el orden en que las prueba map_tensor
+    fn rules(&self) -> [&Regex; 13] {
+        [
+            &self.re_patch_embed, &self.re_pos_embed, &self.re_class_embed,
+            &self.re_attn_qkv, &self.re_attn_out, &self.re_mlp_fc1, &self.re_mlp_fc2,
+            &self.re_ln1, &self.re_ln2, &self.re_pre_norm, &self.re_post_norm,
+            &self.re_projection, &self.re_mm_projector,
+        ]
+    }
 }
 
 impl ModelMapper for ClipMapper {
@@ -297,13 +333,43 @@ impl ModelMapper for ClipMapper {
             ));
         }
         
+        // ══════════════════════════════════════════════════════════════
+        // PROJECTOR VLM (weight HQ5K, bias FP16) - la profundidad real la
+        // fija el builder según cuántos linearN se escriben
+        // ══════════════════════════════════════════════════════════════
+        
+        if let Some(caps) = self.re_mm_projector.captures(name) {
+            let (idx, kind) = (&caps[1], &caps[2]);
+            let quant = if kind == "weight" { QuantHint::HQ5K } else { QuantHint::FP16 };
+            return Some(TensorMapping::new(
+                format!("projector.vision.linear{}.{}", idx, kind),
+                quant,
+                TensorCategory::VisionProjector,
+            ));
+        }
+        
         None
     }
     
+    fn explain(&self, name: &str) -> MapExplanation {
+        if self.should_ignore(name) {
+            return MapExplanation::ignored(name, "should_ignore (rotary_emb, inv_freq, ...)");
+        }
+        if name.starts_with("text_model.") || name.starts_with("text_projection") {
+            return MapExplanation::unmatched(name, "text tower of CLIP (vision only)");
+        }
+        let rule = self.rules().into_iter().find(|re| re.is_match(name)).map(|re| re.as_str().to_string());
+        MapExplanation::new(name, self.map_tensor(name), rule)
+    }
+    
+    fn check_config(&self) -> Result<()> {
+        let c = &self.config;
+        resolve_head_dim(c.hidden_size, c.num_attention_heads, c.head_dim).map(|_| ())
+    }
+    
     fn execution_hints(&self) -> Value {
         let c = &self.config;
-        let head_dim = c.hidden_size / c.num_attention_heads;
-        let num_patches = (c.image_size / c.patch_size).pow(2);
+        let head_dim = c.head_dim.unwrap_or(c.hidden_size / c.num_attention_heads);
         
         // v9.0.5: Detectar variante automáticamente
         let variant = c.detect_variant();
@@ -312,6 +378,7 @@ impl ModelMapper for ClipMapper {
         json!({
             "encoder_arch": "clip",
             "encoder_variant": variant,
+            "encoder_type": if c.has_cls { "clip" } else { "siglip" },
             "image_size": c.image_size,
             "patch_size": c.patch_size,
             "num_channels": c.num_channels,
@@ -325,7 +392,8 @@ impl ModelMapper for ClipMapper {
             "mlp_activation": "quick_gelu",
             "norm_type": "layernorm",
             "layer_norm_eps": c.layer_norm_eps,
-            "num_image_tokens": num_patches + 1,  // +1 for CLS token
+            "has_cls_token": c.has_cls,
+            "num_image_tokens": compute_num_image_tokens(c.image_size, c.patch_size, c.has_cls),
             "projector": {
                 "type": "mlp",
                 "input_dim": c.hidden_size,
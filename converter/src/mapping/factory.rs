@@ -12,12 +12,70 @@ use super::qwen2::Qwen2Mapper;
 use super::llama::LlamaMapper;
 use super::clip::ClipMapper;
 use super::phi::PhiMapper;  // AÑADIDO
+use super::persimmon::PersimmonMapper;
+use super::gemma::GemmaMapper;
+use super::layers::LayerPathMapper;
+use crate::safetensor::SafetensorReader;
+
+/// Sufijos de model_type/architectures que no cambian de familia
+/// (qwen2_moe, llama4_text, Qwen2ForCausalLM)
+const ARCH_SUFFIXES: [&str; 5] = ["forconditionalgeneration", "forcausallm", "model", "_text", "_moe"];
+
+/// (alias, familia canónica). Las familias se listan a sí mismas para
+/// normalizar mayúsculas.
+const ARCH_ALIASES: [(&str, &str); 20] = [
+    ("llama", "llama"),
+    ("llama2", "llama2"),
+    ("llama3", "llama3"),
+    ("llama4", "llama"),
+    ("codellama", "llama"),
+    ("qwen", "qwen"),
+    ("qwen1", "qwen"),
+    ("qwen2", "qwen2"),
+    ("phi", "phi"),
+    ("phi3", "phi3"),
+    ("phi4", "phi4"),
+    ("gemma", "gemma"),
+    ("gemma2", "gemma2"),
+    ("mistral", "mistral"),
+    ("mixtral", "mixtral"),
+    ("deepseek", "deepseek"),
+    ("deepseek_v2", "deepseek2"),
+    ("deepseek2", "deepseek2"),
+    ("persimmon", "persimmon"),
+    ("fuyu", "fuyu"),
+];
+
+/// strip_suffix sin distinguir mayúsculas (ASCII)
+fn strip_suffix_ignore_case<'a>(s: &'a str, suffix: &str) -> Option<&'a str> {
+    let cut = s.len().checked_sub(suffix.len())?;
+    (cut > 0 && s.is_char_boundary(cut) && s[cut..].eq_ignore_ascii_case(suffix)).then(|| &s[..cut])
+}
+
+/// Nombre de familia para un model_type de HF: quita sufijos de variante
+/// (_text, _moe, ForCausalLM...) y la versión decimal (qwen2.5 → qwen2,
+/// llama3.1 → llama3), y resuelve alias conocidos. Lo desconocido se
+/// devuelve recortado pero sin cambiar.
+pub fn normalize_arch(model_type: &str) -> &str {
+    let mut arch = model_type.trim();
+    while let Some(stripped) = ARCH_SUFFIXES.iter().find_map(|suffix| strip_suffix_ignore_case(arch, suffix)) {
+        arch = stripped;
+    }
+    if let Some((base, minor)) = arch.split_once('.') {
+        if base.ends_with(|c: char| c.is_ascii_digit()) && !minor.is_empty() && minor.chars().all(|c| c.is_ascii_digit()) {
+            arch = base;
+        }
+    }
+    ARCH_ALIASES.iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(arch))
+        .map_or(arch, |(_, family)| family)
+}
 
 /// Detecta la arquitectura de un modelo desde config.json
 pub fn detect_architecture(config: &Value) -> String {
     // Por model_type
     if let Some(model_type) = config.get("model_type").and_then(|v| v.as_str()) {
-        let mt = model_type.to_lowercase();
+        let mt = normalize_arch(&model_type.to_lowercase()).to_string();
         
         // Vision encoders
         if mt.contains("clip") || mt.contains("siglip") {
@@ -43,6 +101,10 @@ pub fn detect_architecture(config: &Value) -> String {
         if mt.contains("gemma") {
             return "gemma".to_string();
         }
+        // Fuyu = backbone Persimmon + projector de patches
+        if mt == "persimmon" || mt == "fuyu" {
+            return mt;
+        }
         
         return mt;
     }
@@ -50,7 +112,7 @@ pub fn detect_architecture(config: &Value) -> String {
     // Por architectures
     if let Some(archs) = config.get("architectures").and_then(|v| v.as_array()) {
         if let Some(arch) = archs.first().and_then(|v| v.as_str()) {
-            let arch_lower = arch.to_lowercase();
+            let arch_lower = normalize_arch(&arch.to_lowercase()).to_string();
             
             // Vision
             if arch_lower.contains("clip") || arch_lower.contains("siglip") {
@@ -78,6 +140,14 @@ pub fn detect_architecture(config: &Value) -> String {
             if arch_lower.contains("gemma") {
                 return "gemma".to_string();
             }
+            
+            // Persimmon / Fuyu
+            if arch_lower.contains("fuyu") {
+                return "fuyu".to_string();
+            }
+            if arch_lower.contains("persimmon") {
+                return "persimmon".to_string();
+            }
         }
     }
     
@@ -95,44 +165,84 @@ pub fn load_config(model_path: &Path) -> Result<Value> {
     let data = std::fs::read_to_string(&config_path)
         .with_context(|| format!("Failed to read {}", config_path.display()))?;
     
-    let config: Value = serde_json::from_str(&data)
-        .with_context(|| "Invalid JSON in config.json")?;
+    let config = crate::json::parse(&data, "config.json")?;
     
     Ok(config)
 }
 
-/// Crea el mapper correcto para un modelo
+/// Crea el mapper correcto para un modelo.
+/// Si las capas no son `model.layers.N` desde 0 (transformer.h.N, numeración
+/// desde 1...) lo envuelve en un LayerPathMapper.
 pub fn create_mapper(model_path: &Path) -> Result<Box<dyn ModelMapper>> {
     let config = load_config(model_path)?;
-    let arch = detect_architecture(&config);
+    let mapper = create_mapper_from_config(&config)?;
+    
+    let Ok(reader) = SafetensorReader::from_folder(model_path) else {
+        return Ok(mapper);
+    };
+    Ok(LayerPathMapper::wrap_if_needed(mapper, reader.iter_tensors().map(|(name, _)| name)))
+}
+
+/// Crea el mapper a partir de un config ya cargado (o un sub-config de torre)
+pub fn create_mapper_from_config(config: &Value) -> Result<Box<dyn ModelMapper>> {
+    let arch = detect_architecture(config);
     
     println!("[INFO] Detected architecture: {}", arch);
     
     match arch.as_str() {
         "qwen2" | "qwen" => {
-            Ok(Box::new(Qwen2Mapper::from_json(&config)))
+            Ok(Box::new(Qwen2Mapper::from_json(config)))
         }
         
         "llama" | "mistral" | "deepseek" | "codellama" => {
-            Ok(Box::new(LlamaMapper::from_json(&config)))
+            Ok(Box::new(LlamaMapper::from_json(config)))
         }
         
         "clip" | "siglip" | "vit" => {
-            Ok(Box::new(ClipMapper::from_json(&config)))
+            Ok(Box::new(ClipMapper::from_json(config)))
         }
         
         // AÑADIDO: Phi family
         "phi" | "phi3" | "phi4" => {
-            Ok(Box::new(PhiMapper::from_json(&config)))
+            Ok(Box::new(PhiMapper::from_json(config)))
+        }
+        
+        "persimmon" | "fuyu" => {
+            Ok(Box::new(PersimmonMapper::from_json(config)))
+        }
+        
+        "gemma" | "gemma2" => {
+            Ok(Box::new(GemmaMapper::from_json(config)))
         }
         
         // TODO: Añadir más arquitecturas
-        // "gemma" | "gemma2" => Ok(Box::new(GemmaMapper::from_json(&config))),
-        // "whisper" => Ok(Box::new(WhisperMapper::from_json(&config))),
+        // "whisper" => Ok(Box::new(WhisperMapper::from_json(config))),
         
         _ => {
             eprintln!("[WARN] Unknown architecture '{}', trying llama mapper", arch);
-            Ok(Box::new(LlamaMapper::from_json(&config)))
+            Ok(Box::new(LlamaMapper::from_json(config)))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_normalize_arch_qwen2_family() {
+        for model_type in ["qwen2", "qwen2_moe", "qwen2.5", "Qwen2ForCausalLM", "QWEN2"] {
+            assert_eq!(normalize_arch(model_type), "qwen2", "{}", model_type);
+        }
+        assert_eq!(normalize_arch("llama4_text"), "llama");
+        assert_eq!(normalize_arch("llama3.1"), "llama3");
+        assert_eq!(normalize_arch("gemma3_text"), "gemma3");
+        assert_eq!(normalize_arch("deepseek_v2"), "deepseek2");
+        assert_eq!(normalize_arch("mystery"), "mystery");
+        
+        let config = serde_json::json!({"architectures": ["Qwen2MoeForCausalLM"]});
+        assert_eq!(detect_architecture(&config), "qwen2");
+        let hints = serde_json::json!({"arch": "qwen2_moe"});
+        assert_eq!(crate::hints::TextModelConfigBin::from_json(&hints).arch, crate::hints::binary::ARCH_QWEN2);
+    }
+}
@@ -0,0 +1,152 @@
+// src/mapping/gemma.rs
+// ============================================================================
+// GEMMA MAPPER - Mapea tensores Gemma/Gemma2 a nombres canónicos
+// ============================================================================
+//
+// Soporta: Gemma, Gemma2
+//
+// Mismos nombres de tensor que Llama (embed_tokens, self_attn.*_proj,
+// mlp.gate/up/down_proj); lo que cambia es la ejecución:
+// - Embeddings multiplicados por sqrt(hidden_size) antes de la capa 0
+// - GeGLU con gelu tanh (hidden_activation = gelu_pytorch_tanh)
+// - Tied embeddings por defecto (sin lm_head separado)
+// - Gemma2: norms extra antes y después del MLP
+//
+// ============================================================================
+
+use anyhow::Result;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::hints::config_mlp_activation;
+use super::factory::normalize_arch;
+use super::llama::{LlamaConfig, LlamaMapper};
+use super::traits::ModelMapper;
+use super::types::{TensorMapping, QuantHint, TensorCategory};
+
+pub struct GemmaMapper {
+    inner: LlamaMapper,
+    /// "gemma" o "gemma2"
+    arch: String,
+    hidden_size: usize,
+    /// hidden_activation de config.json (hidden_act en los más viejos)
+    activation: String,
+    re_ffn_norm: Regex,
+}
+
+impl GemmaMapper {
+    pub fn from_json(config: &Value) -> Self {
+        let mut llama = LlamaConfig::from_json(config);
+        // GemmaConfig de HF: tie_word_embeddings = true si no viene
+        llama.tie_word_embeddings = config.get("tie_word_embeddings")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        let hidden_size = llama.hidden_size;
+        
+        let arch = match config.get("model_type").and_then(|v| v.as_str()).map(normalize_arch) {
+            Some("gemma2") => "gemma2",
+            _ => "gemma",
+        };
+        
+        let activation = config_mlp_activation(config).unwrap_or("gelu_new");
+        
+        Self {
+            inner: LlamaMapper::new(llama),
+            arch: arch.to_string(),
+            hidden_size,
+            activation: activation.to_string(),
+            re_ffn_norm: Regex::new(r"^model\.layers\.(\d+)\.(pre|post)_feedforward_layernorm\.weight$").unwrap(),
+        }
+    }
+    
+    /// Factor que el runtime aplica a los embeddings: sqrt(hidden_size)
+    pub fn embedding_scale(&self) -> f64 {
+        (self.hidden_size as f64).sqrt()
+    }
+}
+
+impl ModelMapper for GemmaMapper {
+    fn name(&self) -> &str {
+        &self.arch
+    }
+    
+    fn map_tensor(&self, name: &str) -> Option<TensorMapping> {
+        if let Some(caps) = self.re_ffn_norm.captures(name) {
+            let layer: usize = caps[1].parse().ok()?;
+            return Some(TensorMapping::new(
+                format!("layer{}.ln_{}_ffn.weight", layer, &caps[2]),
+                QuantHint::FP16,
+                TensorCategory::Norm,
+            ).with_layer(layer));
+        }
+        
+        self.inner.map_tensor(name)
+    }
+    
+    fn check_config(&self) -> Result<()> {
+        self.inner.check_config()
+    }
+    
+    fn execution_hints(&self) -> Value {
+        let mut hints = self.inner.execution_hints();
+        hints["arch"] = self.arch.clone().into();
+        hints["mlp_type"] = "geglu".into();
+        hints["mlp_activation"] = self.activation.clone().into();
+        
+        // El engine multiplica la salida de token_embedding por este factor
+        hints["scale_embeddings"] = true.into();
+        hints["embedding_scale"] = self.embedding_scale().into();
+        
+        hints
+    }
+    
+    fn num_layers(&self) -> usize {
+        self.inner.num_layers()
+    }
+    
+    fn vocab_size(&self) -> usize {
+        self.inner.vocab_size()
+    }
+    
+    fn hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::hints::binary::{TextModelConfigBin, ARCH_GEMMA, FLAG_SCALE_EMBEDDINGS};
+    
+    #[test]
+    fn test_gemma_embedding_scale() {
+        let config = json!({
+            "model_type": "gemma",
+            "num_hidden_layers": 18,
+            "hidden_size": 2048,
+            "intermediate_size": 16384,
+            "num_attention_heads": 8,
+            "num_key_value_heads": 1,
+            "head_dim": 256,
+            "vocab_size": 256000,
+        });
+        let mapper = GemmaMapper::from_json(&config);
+        let hints = mapper.execution_hints();
+        
+        assert_eq!(hints["arch"], "gemma");
+        assert_eq!(hints["scale_embeddings"], true);
+        assert!((hints["embedding_scale"].as_f64().unwrap() - 2048f64.sqrt()).abs() < 1e-9);
+        assert_eq!(hints["mlp_type"], "geglu");
+        assert_eq!(hints["mlp_activation"], "gelu_new");
+        assert_eq!(hints["tie_word_embeddings"], true);
+        
+        let bin = TextModelConfigBin::from_json(&hints);
+        assert_eq!(bin.embedding_scale, 2048f32.sqrt());
+        assert_ne!(bin.flags & FLAG_SCALE_EMBEDDINGS, 0);
+        assert_eq!(bin.arch, ARCH_GEMMA);
+        
+        let norm = mapper.map_tensor("model.layers.3.pre_feedforward_layernorm.weight").unwrap();
+        assert_eq!(norm.canonical_name, "layer3.ln_pre_ffn.weight");
+    }
+}
@@ -0,0 +1,209 @@
+// src/mapping/layers.rs
+// ============================================================================
+// LAYER PATH MAPPER - Contenedores de capas no estándar y numeración desde 1
+// ============================================================================
+//
+// Los mappers esperan las capas como `model.layers.N.` con N desde 0. Otros
+// checkpoints usan otro contenedor:
+//
+//   transformer.h.N.              (GPT-2, Falcon)
+//   gpt_neox.layers.N.            (GPT-NeoX, Pythia)
+//   transformer.layers.N.         (ChatGLM, ...)
+//   model.decoder.layers.N.       (OPT)
+//
+// o numeran desde 1. LayerLayout detecta el contenedor y el índice mínimo a
+// partir de los nombres de tensor; LayerPathMapper reescribe cada nombre a
+// `model.layers.{N - base}.` antes de pasarlo al mapper de la arquitectura,
+// así los `layer{N}` canónicos siempre empiezan en 0.
+//
+// ============================================================================
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::traits::ModelMapper;
+use super::types::{MapExplanation, TensorMapping};
+
+/// Prefijo de capas que esperan los mappers
+pub const STANDARD_LAYER_PREFIX: &str = "model.layers.";
+
+/// Contenedores de capas conocidos (el nombre empieza por `{prefijo}N.`)
+const LAYER_PREFIXES: &[&str] = &[
+    STANDARD_LAYER_PREFIX,
+    "transformer.h.",
+    "gpt_neox.layers.",
+    "transformer.layers.",
+    "transformer.blocks.",
+    "model.decoder.layers.",
+    "layers.",
+];
+
+/// Contenedor e índice de la primera capa de un checkpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerLayout {
+    pub prefix: String,
+    /// Índice de la primera capa (0 normalmente, 1 en algunos checkpoints)
+    pub base: usize,
+}
+
+impl LayerLayout {
+    /// Contenedor con más tensores entre los conocidos; None si no hay capas
+    pub fn detect<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut found: Vec<(&str, usize, usize)> = Vec::new();  // (prefijo, tensores, índice mínimo)
+        for name in names {
+            let Some((prefix, index)) = LAYER_PREFIXES.iter().find_map(|p| Some((*p, layer_index(name, p)?))) else {
+                continue;
+            };
+            match found.iter_mut().find(|(p, _, _)| *p == prefix) {
+                Some(entry) => {
+                    entry.1 += 1;
+                    entry.2 = entry.2.min(index);
+                }
+                None => found.push((prefix, 1, index)),
+            }
+        }
+        found.into_iter()
+            .max_by_key(|(_, count, _)| *count)
+            .map(|(prefix, _, base)| Self { prefix: prefix.to_string(), base })
+    }
+    
+    /// Ya es `model.layers.` desde 0: no hace falta reescribir
+    pub fn is_standard(&self) -> bool {
+        self.prefix == STANDARD_LAYER_PREFIX && self.base == 0
+    }
+}
+
+/// N de `{prefix}N.resto`
+fn layer_index(name: &str, prefix: &str) -> Option<usize> {
+    let rest = name.strip_prefix(prefix)?;
+    let (index, _) = rest.split_once('.')?;
+    index.parse().ok()
+}
+
+/// Mapper que normaliza el contenedor de capas antes de mapear
+pub struct LayerPathMapper {
+    inner: Box<dyn ModelMapper>,
+    layout: LayerLayout,
+    re_layer: Regex,
+}
+
+impl LayerPathMapper {
+    pub fn new(inner: Box<dyn ModelMapper>, layout: LayerLayout) -> Self {
+        let re_layer = Regex::new(&format!(r"^{}(\d+)\.", regex::escape(&layout.prefix))).unwrap();
+        Self { inner, layout, re_layer }
+    }
+    
+    /// Envuelve `inner` si los nombres no siguen `model.layers.N` desde 0
+    pub fn wrap_if_needed<'a>(inner: Box<dyn ModelMapper>, names: impl IntoIterator<Item = &'a str>) -> Box<dyn ModelMapper> {
+        match LayerLayout::detect(names) {
+            Some(layout) if !layout.is_standard() => {
+                if layout.prefix != STANDARD_LAYER_PREFIX {
+                    println!("[INFO] Layer container '{}N' mapped to '{}N'", layout.prefix, STANDARD_LAYER_PREFIX);
+                }
+                if layout.base > 0 {
+                    println!("[INFO] Layers numbered from {}: rebasing to layer0", layout.base);
+                }
+                Box::new(Self::new(inner, layout))
+            }
+            _ => inner,
+        }
+    }
+    
+    /// Nombre con el contenedor estándar y el índice rebasado
+    fn rewrite(&self, name: &str) -> String {
+        let Some(caps) = self.re_layer.captures(name) else {
+            return name.to_string();
+        };
+        let index: usize = caps[1].parse().unwrap_or(0);
+        let Some(index) = index.checked_sub(self.layout.base) else {
+            return name.to_string();
+        };
+        format!("{}{}.{}", STANDARD_LAYER_PREFIX, index, &name[caps[0].len()..])
+    }
+}
+
+impl ModelMapper for LayerPathMapper {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+    
+    fn map_tensor(&self, original_name: &str) -> Option<TensorMapping> {
+        self.inner.map_tensor(&self.rewrite(original_name))
+    }
+    
+    fn explain(&self, name: &str) -> MapExplanation {
+        MapExplanation { name: name.to_string(), ..self.inner.explain(&self.rewrite(name)) }
+    }
+    
+    fn check_config(&self) -> anyhow::Result<()> {
+        self.inner.check_config()
+    }
+    
+    fn execution_hints(&self) -> Value {
+        self.inner.execution_hints()
+    }
+    
+    fn should_ignore(&self, name: &str) -> bool {
+        self.inner.should_ignore(&self.rewrite(name))
+    }
+    
+    fn num_layers(&self) -> usize {
+        self.inner.num_layers()
+    }
+    
+    fn vocab_size(&self) -> usize {
+        self.inner.vocab_size()
+    }
+    
+    fn hidden_size(&self) -> usize {
+        self.inner.hidden_size()
+    }
+    
+    fn is_moe(&self) -> bool {
+        self.inner.is_moe()
+    }
+    
+    fn num_experts(&self) -> Option<usize> {
+        self.inner.num_experts()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::llama::LlamaMapper;
+    
+    #[test]
+    fn test_gpt2_container_and_one_based_layers() {
+        let names: Vec<String> = (0..4)
+            .flat_map(|i| [
+                format!("transformer.h.{}.self_attn.q_proj.weight", i),
+                format!("transformer.h.{}.input_layernorm.weight", i),
+            ])
+            .chain(["model.norm.weight".to_string()])
+            .collect();
+        let layout = LayerLayout::detect(names.iter().map(|s| s.as_str())).unwrap();
+        assert_eq!(layout, LayerLayout { prefix: "transformer.h.".to_string(), base: 0 });
+        
+        let inner = Box::new(LlamaMapper::from_json(&serde_json::json!({"num_hidden_layers": 4})));
+        let mapper = LayerPathMapper::wrap_if_needed(inner, names.iter().map(|s| s.as_str()));
+        for i in 0..4 {
+            let q = mapper.map_tensor(&format!("transformer.h.{}.self_attn.q_proj.weight", i)).unwrap();
+            assert_eq!(q.canonical_name, format!("layer{}.attn.q_proj.weight", i));
+            assert_eq!(q.layer_idx, Some(i));
+        }
+        assert!(mapper.map_tensor("model.norm.weight").is_some());
+        
+        // Numeración desde 1: layers.1..4 → layer0..3
+        let one_based: Vec<String> = (1..=4).map(|i| format!("model.layers.{}.mlp.down_proj.weight", i)).collect();
+        let layout = LayerLayout::detect(one_based.iter().map(|s| s.as_str())).unwrap();
+        assert_eq!(layout.base, 1);
+        let inner = Box::new(LlamaMapper::from_json(&serde_json::json!({"num_hidden_layers": 4})));
+        let mapper = LayerPathMapper::new(inner, layout);
+        assert_eq!(mapper.map_tensor("model.layers.1.mlp.down_proj.weight").unwrap().canonical_name, "layer0.mlp.down.weight");
+        assert_eq!(mapper.map_tensor("model.layers.4.mlp.down_proj.weight").unwrap().canonical_name, "layer3.mlp.down.weight");
+        
+        let standard = ["model.layers.0.mlp.down_proj.weight", "model.layers.1.mlp.down_proj.weight"];
+        assert!(LayerLayout::detect(standard).unwrap().is_standard());
+    }
+}
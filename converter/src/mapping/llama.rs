@@ -6,13 +6,17 @@
 // Soporta: Llama, Llama2, Llama3, DeepSeek, DeepSeek-Coder, Mistral, etc.
 // Todos usan arquitectura similar.
 //
+// v9.0.6: Tipo de rope_scaling en cualquier grafía (rope_scaling.rope_type,
+//         rope_scaling.type o rope_type); conserva el resto de parámetros (low/high_freq_factor)
 // v9.0.5: Añade soporte para rope_scaling (linear, dynamic)
 //
 // ============================================================================
 
+use anyhow::Result;
 use regex::Regex;
 use serde_json::{json, Value};
 
+use crate::hints::{config_mlp_activation, resolve_head_dim, rope_scaling_params, rope_scaling_type};
 use super::traits::ModelMapper;
 use super::types::{TensorMapping, QuantHint, TensorCategory};
 
@@ -22,12 +26,15 @@ pub struct LlamaConfig {
     pub hidden_size: usize,
     pub intermediate_size: usize,
     pub num_attention_heads: usize,
+    pub head_dim: Option<usize>,  // explícito en config.json; si no, hidden_size / heads
     pub num_key_value_heads: usize,
     pub vocab_size: usize,
     pub max_position_embeddings: usize,
     pub rope_theta: f64,
     pub rms_norm_eps: f64,
     pub tie_word_embeddings: bool,
+    /// hidden_act de config.json normalizada (silu si falta)
+    pub mlp_activation: String,
     // v9.0.5: rope_scaling support
     pub rope_scaling: Option<RopeScaling>,
 }
@@ -36,6 +43,8 @@ pub struct LlamaConfig {
 pub struct RopeScaling {
     pub scaling_type: String,  // "linear", "dynamic", "yarn", etc.
     pub factor: f64,
+    /// Resto de rope_scaling tal cual (llama3: low/high_freq_factor, ...)
+    pub params: serde_json::Map<String, Value>,
 }
 
 impl LlamaConfig {
@@ -46,16 +55,14 @@ impl LlamaConfig {
                 if rs.is_null() {
                     return None;
                 }
-                let scaling_type = rs.get("type")
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("linear")
-                    .to_string();
+                let scaling_type = rope_scaling_type(config)
+                    .unwrap_or_else(|| "linear".to_string());
                 let factor = rs.get("factor")
                     .and_then(|f| f.as_f64())
                     .unwrap_or(1.0);
                 
                 if factor != 1.0 {
-                    Some(RopeScaling { scaling_type, factor })
+                    Some(RopeScaling { scaling_type, factor, params: rope_scaling_params(rs) })
                 } else {
                     None
                 }
@@ -66,6 +73,7 @@ impl LlamaConfig {
             hidden_size: config["hidden_size"].as_u64().unwrap_or(4096) as usize,
             intermediate_size: config["intermediate_size"].as_u64().unwrap_or(11008) as usize,
             num_attention_heads: config["num_attention_heads"].as_u64().unwrap_or(32) as usize,
+            head_dim: config["head_dim"].as_u64().map(|v| v as usize),
             num_key_value_heads: config["num_key_value_heads"]
                 .as_u64()
                 .or(config["num_attention_heads"].as_u64())
@@ -75,6 +83,7 @@ impl LlamaConfig {
             rope_theta: config["rope_theta"].as_f64().unwrap_or(10000.0),
             rms_norm_eps: config["rms_norm_eps"].as_f64().unwrap_or(1e-6),
             tie_word_embeddings: config["tie_word_embeddings"].as_bool().unwrap_or(false),
+            mlp_activation: config_mlp_activation(config).unwrap_or("silu").to_string(),
             rope_scaling,
         }
     }
@@ -214,6 +223,11 @@ impl ModelMapper for LlamaMapper {
         None
     }
     
+    fn check_config(&self) -> Result<()> {
+        let c = &self.config;
+        resolve_head_dim(c.hidden_size, c.num_attention_heads, c.head_dim).map(|_| ())
+    }
+    
     fn execution_hints(&self) -> Value {
         let c = &self.config;
         
@@ -225,7 +239,7 @@ impl ModelMapper for LlamaMapper {
             "gqa"
         };
         
-        let head_dim = c.hidden_size / c.num_attention_heads;
+        let head_dim = c.head_dim.unwrap_or(c.hidden_size / c.num_attention_heads);
         
         // Determinar rope_type basado en rope_scaling
         let rope_type = match &c.rope_scaling {
@@ -257,7 +271,7 @@ impl ModelMapper for LlamaMapper {
             
             // MLP (OBLIGATORIO)
             "mlp_type": "swiglu",
-            "mlp_activation": "silu",
+            "mlp_activation": c.mlp_activation,
             "mlp_bias": false,
             
             // NORMALIZATION (OBLIGATORIO)
@@ -290,10 +304,12 @@ impl ModelMapper for LlamaMapper {
         
         // v9.0.5: Añadir rope_scaling si está presente
         if let Some(rs) = &c.rope_scaling {
-            hints["rope_scaling"] = json!({
+            let mut rope_scaling = json!({
                 "type": rs.scaling_type,
                 "factor": rs.factor
             });
+            rope_scaling.as_object_mut().unwrap().extend(rs.params.clone());
+            hints["rope_scaling"] = rope_scaling;
         }
         
         hints
@@ -174,9 +174,7 @@ pub fn detect_architecture(model_dir: impl AsRef<Path>) -> Result<String> {
         anyhow::bail!("No config.json in {}", model_dir.as_ref().display());
     }
     
-    let config: serde_json::Value = serde_json::from_str(
-        &std::fs::read_to_string(&config_path)?
-    )?;
+    let config = crate::json::parse(&std::fs::read_to_string(&config_path)?, "config.json")?;
     
     // Detectar por model_type o architectures
     if let Some(model_type) = config.get("model_type").and_then(|v| v.as_str()) {
@@ -10,8 +10,14 @@ pub mod qwen2;
 pub mod llama;
 pub mod clip;
 pub mod phi;  // AÑADIDO
+pub mod persimmon;
+pub mod gemma;
+pub mod tower;
+pub mod layers;
 
 // Re-exports
-pub use types::{BlockType, QuantHint, TensorCategory, TensorMapping};
+pub use types::{BlockType, MapExplanation, MapOutcome, QuantHint, TensorCategory, TensorMapping};
 pub use traits::ModelMapper;
-pub use factory::{create_mapper, detect_architecture, load_config};
+pub use factory::{create_mapper, create_mapper_from_config, detect_architecture, load_config, normalize_arch};
+pub use tower::{create_tower_mapper, TowerMapper};
+pub use layers::{LayerLayout, LayerPathMapper};
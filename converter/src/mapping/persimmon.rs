@@ -0,0 +1,388 @@
+// src/mapping/persimmon.rs
+// ============================================================================
+// PERSIMMON MAPPER - Mapea tensores Persimmon/Fuyu a nombres canónicos
+// ============================================================================
+//
+// Soporta: Persimmon-8B, Fuyu-8B
+//
+// Características especiales:
+// - QKV fusionado POR CABEZA: query_key_value es [heads * 3 * head_dim, hidden]
+//   con layout [head][q|k|v][head_dim] (no [q|k|v] como Phi)
+// - LayerNorm con bias en todas partes, incluida la qk-norm
+//   (q_layernorm/k_layernorm sobre head_dim)
+// - MLP no gated: dense_h_to_4h → relu² → dense_4h_to_h
+// - Partial RoPE (partial_rotary_factor, típicamente 0.5)
+//
+// Fuyu = Persimmon + vision_embed_tokens: los patches de imagen (patch_size²
+// × canales) se proyectan linealmente al hidden del modelo de texto. No hay
+// encoder de visión: el projector vive en el bloque de texto.
+//
+// ============================================================================
+
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::hints::{normalize_mlp_activation, resolve_head_dim};
+use super::traits::ModelMapper;
+use super::types::{TensorMapping, QuantHint, TensorCategory};
+
+/// (prefijo original, reemplazo) de los checkpoints Fuyu
+const FUYU_PREFIXES: &[(&str, &str)] = &[
+    ("model.language_model.", "model."),
+    ("language_model.", ""),
+    ("model.vision_embed_tokens.", "vision_embed_tokens."),
+];
+
+#[derive(Debug, Clone)]
+pub struct PersimmonConfig {
+    pub num_hidden_layers: usize,
+    pub hidden_size: usize,
+    pub intermediate_size: usize,
+    pub num_attention_heads: usize,
+    pub head_dim: Option<usize>,  // explícito en config.json; si no, hidden_size / heads
+    pub vocab_size: usize,
+    pub max_position_embeddings: usize,
+    pub rope_theta: f64,
+    pub layer_norm_eps: f64,
+    pub tie_word_embeddings: bool,
+    pub partial_rotary_factor: f64,
+    pub qk_layernorm: bool,
+    pub hidden_act: String,
+    // Fuyu: proyección lineal de patches (None = Persimmon solo texto)
+    pub patch_size: Option<usize>,
+    pub num_channels: usize,
+}
+
+impl PersimmonConfig {
+    pub fn from_json(config: &Value) -> Self {
+        // Fuyu anida el backbone en text_config; los campos raíz tienen prioridad
+        let text = config.get("text_config").unwrap_or(&Value::Null);
+        let get = |key: &str| config.get(key).filter(|v| !v.is_null()).or_else(|| text.get(key));
+        
+        let is_fuyu = config.get("model_type").and_then(|v| v.as_str()) == Some("fuyu");
+        
+        Self {
+            num_hidden_layers: get("num_hidden_layers").and_then(|v| v.as_u64()).unwrap_or(36) as usize,
+            hidden_size: get("hidden_size").and_then(|v| v.as_u64()).unwrap_or(4096) as usize,
+            intermediate_size: get("intermediate_size").and_then(|v| v.as_u64()).unwrap_or(16384) as usize,
+            num_attention_heads: get("num_attention_heads").and_then(|v| v.as_u64()).unwrap_or(64) as usize,
+            head_dim: get("head_dim").and_then(|v| v.as_u64()).map(|v| v as usize),
+            vocab_size: get("vocab_size").and_then(|v| v.as_u64()).unwrap_or(262144) as usize,
+            max_position_embeddings: get("max_position_embeddings").and_then(|v| v.as_u64()).unwrap_or(16384) as usize,
+            rope_theta: get("rope_theta").and_then(|v| v.as_f64()).unwrap_or(25000.0),
+            layer_norm_eps: get("layer_norm_eps").and_then(|v| v.as_f64()).unwrap_or(1e-5),
+            tie_word_embeddings: get("tie_word_embeddings").and_then(|v| v.as_bool()).unwrap_or(false),
+            partial_rotary_factor: get("partial_rotary_factor").and_then(|v| v.as_f64()).unwrap_or(0.5),
+            qk_layernorm: get("qk_layernorm").and_then(|v| v.as_bool()).unwrap_or(true),
+            hidden_act: get("hidden_act").and_then(|v| v.as_str()).and_then(normalize_mlp_activation).unwrap_or("relu2").to_string(),
+            patch_size: is_fuyu.then(|| get("patch_size").and_then(|v| v.as_u64()).unwrap_or(30) as usize),
+            num_channels: get("num_channels").and_then(|v| v.as_u64()).unwrap_or(3) as usize,
+        }
+    }
+}
+
+pub struct PersimmonMapper {
+    config: PersimmonConfig,
+    re_embed: Regex,
+    re_lm_head: Regex,
+    re_final_norm: Regex,
+    // QKV fusionado por cabeza
+    re_attn_qkv: Regex,
+    re_attn_dense: Regex,
+    re_q_norm: Regex,
+    re_k_norm: Regex,
+    re_mlp_up: Regex,
+    re_mlp_down: Regex,
+    re_input_norm: Regex,
+    re_post_attn_norm: Regex,
+    // Fuyu: patches → hidden
+    re_patch_linear: Regex,
+}
+
+impl PersimmonMapper {
+    pub fn new(config: PersimmonConfig) -> Self {
+        Self {
+            config,
+            re_embed: Regex::new(r"^model\.embed_tokens\.weight$").unwrap(),
+            re_lm_head: Regex::new(r"^lm_head\.weight$").unwrap(),
+            re_final_norm: Regex::new(r"^model\.final_layernorm\.(weight|bias)$").unwrap(),
+            re_attn_qkv: Regex::new(r"^model\.layers\.(\d+)\.self_attn\.query_key_value\.(weight|bias)$").unwrap(),
+            re_attn_dense: Regex::new(r"^model\.layers\.(\d+)\.self_attn\.dense\.(weight|bias)$").unwrap(),
+            re_q_norm: Regex::new(r"^model\.layers\.(\d+)\.self_attn\.q_layernorm\.(weight|bias)$").unwrap(),
+            re_k_norm: Regex::new(r"^model\.layers\.(\d+)\.self_attn\.k_layernorm\.(weight|bias)$").unwrap(),
+            re_mlp_up: Regex::new(r"^model\.layers\.(\d+)\.mlp\.dense_h_to_4h\.(weight|bias)$").unwrap(),
+            re_mlp_down: Regex::new(r"^model\.layers\.(\d+)\.mlp\.dense_4h_to_h\.(weight|bias)$").unwrap(),
+            re_input_norm: Regex::new(r"^model\.layers\.(\d+)\.input_layernorm\.(weight|bias)$").unwrap(),
+            re_post_attn_norm: Regex::new(r"^model\.layers\.(\d+)\.post_attention_layernorm\.(weight|bias)$").unwrap(),
+            re_patch_linear: Regex::new(r"^vision_embed_tokens\.(weight|bias)$").unwrap(),
+        }
+    }
+    
+    pub fn from_json(config: &Value) -> Self {
+        Self::new(PersimmonConfig::from_json(config))
+    }
+    
+    /// Quita el prefijo language_model. de los checkpoints Fuyu
+    fn local_name(name: &str) -> String {
+        FUYU_PREFIXES.iter()
+            .find(|(p, _)| name.starts_with(p))
+            .map(|(p, r)| format!("{}{}", r, &name[p.len()..]))
+            .unwrap_or_else(|| name.to_string())
+    }
+    
+    /// Mapea un tensor de capa: (regex, sufijo canónico, quant de weight, categoría)
+    fn map_layer(
+        re: &Regex,
+        name: &str,
+        canonical: &str,
+        weight_quant: QuantHint,
+        category: TensorCategory,
+    ) -> Option<TensorMapping> {
+        let caps = re.captures(name)?;
+        let layer: usize = caps[1].parse().ok()?;
+        let kind = &caps[2];
+        // Los bias siempre en FP16
+        let quant = if kind == "weight" { weight_quant } else { QuantHint::FP16 };
+        Some(TensorMapping::new(
+            format!("layer{}.{}.{}", layer, canonical, kind),
+            quant,
+            category,
+        ).with_layer(layer))
+    }
+}
+
+impl ModelMapper for PersimmonMapper {
+    fn name(&self) -> &str {
+        if self.config.patch_size.is_some() { "fuyu" } else { "persimmon" }
+    }
+    
+    fn map_tensor(&self, original_name: &str) -> Option<TensorMapping> {
+        if self.should_ignore(original_name) {
+            return None;
+        }
+        let name = Self::local_name(original_name);
+        let name = name.as_str();
+        
+        // ═══════════════════════════════════════════════════════════════
+        // EMBEDDINGS + PROJECTOR (FP16)
+        // ═══════════════════════════════════════════════════════════════
+        
+        if self.re_embed.is_match(name) {
+            return Some(TensorMapping::new(
+                "token_embedding.weight",
+                QuantHint::FP16,
+                TensorCategory::Embedding,
+            ));
+        }
+        
+        if self.re_lm_head.is_match(name) {
+            return Some(TensorMapping::new(
+                "lm_head.weight",
+                QuantHint::FP16,
+                TensorCategory::LMHead,
+            ));
+        }
+        
+        if let Some(caps) = self.re_final_norm.captures(name) {
+            return Some(TensorMapping::new(
+                format!("final_norm.{}", &caps[1]),
+                QuantHint::FP16,
+                TensorCategory::Norm,
+            ));
+        }
+        
+        // vision_embed_tokens: [hidden, patch_size² × canales], entra directo al stream de texto
+        if let Some(caps) = self.re_patch_linear.captures(name) {
+            return Some(TensorMapping::new(
+                format!("projector.vision.patch_linear.{}", &caps[1]),
+                QuantHint::FP16,
+                TensorCategory::VisionProjector,
+            ));
+        }
+        
+        // ═══════════════════════════════════════════════════════════════
+        // ATTENTION (HQ5K) - QKV fusionado por cabeza
+        // ═══════════════════════════════════════════════════════════════
+        
+        Self::map_layer(&self.re_attn_qkv, name, "attn.qkv_proj", QuantHint::HQ5K, TensorCategory::Attention)
+            .or_else(|| Self::map_layer(&self.re_attn_dense, name, "attn.o_proj", QuantHint::HQ5K, TensorCategory::Attention))
+            .or_else(|| Self::map_layer(&self.re_q_norm, name, "attn.q_norm", QuantHint::FP16, TensorCategory::Norm))
+            .or_else(|| Self::map_layer(&self.re_k_norm, name, "attn.k_norm", QuantHint::FP16, TensorCategory::Norm))
+            // ═══════════════════════════════════════════════════════════
+            // MLP (HQ4K) - sin gate
+            // ═══════════════════════════════════════════════════════════
+            .or_else(|| Self::map_layer(&self.re_mlp_up, name, "mlp.up", QuantHint::HQ4K, TensorCategory::MLP))
+            .or_else(|| Self::map_layer(&self.re_mlp_down, name, "mlp.down", QuantHint::HQ4K, TensorCategory::MLP))
+            // ═══════════════════════════════════════════════════════════
+            // NORMS (FP16)
+            // ═══════════════════════════════════════════════════════════
+            .or_else(|| Self::map_layer(&self.re_input_norm, name, "ln_attn_in", QuantHint::FP16, TensorCategory::Norm))
+            .or_else(|| Self::map_layer(&self.re_post_attn_norm, name, "ln_attn_out", QuantHint::FP16, TensorCategory::Norm))
+    }
+    
+    fn check_config(&self) -> Result<()> {
+        let c = &self.config;
+        resolve_head_dim(c.hidden_size, c.num_attention_heads, c.head_dim).map(|_| ())
+    }
+    
+    fn execution_hints(&self) -> Value {
+        let c = &self.config;
+        
+        let head_dim = c.head_dim.unwrap_or(c.hidden_size / c.num_attention_heads);
+        let rope_dim = ((head_dim as f64) * c.partial_rotary_factor) as usize;
+        
+        let mut hints = json!({
+            // IDENTIFICACIÓN (OBLIGATORIO)
+            "arch": self.name(),
+            "dtype": "bf16",
+            
+            // DIMENSIONES (OBLIGATORIO)
+            "num_hidden_layers": c.num_hidden_layers,
+            "hidden_size": c.hidden_size,
+            "intermediate_size": c.intermediate_size,
+            "vocab_size": c.vocab_size,
+            
+            // ATTENTION (OBLIGATORIO) - QKV FUSIONADO POR CABEZA
+            "num_attention_heads": c.num_attention_heads,
+            "num_key_value_heads": c.num_attention_heads,
+            "head_dim": head_dim,
+            "attention_type": "mha",
+            "attention_bias": true,
+            "qkv_layout": "fused_per_head",  // CRÍTICO: [head][q|k|v][head_dim]
+            "use_qk_norm": c.qk_layernorm,
+            "qk_norm_type": "layernorm",
+            "parallel_attention": false,
+            "kv_layout": "BHSD",
+            
+            // MLP (OBLIGATORIO) - SIN GATE
+            "mlp_type": "standard",
+            "mlp_activation": c.hidden_act,
+            "mlp_bias": true,
+            
+            // NORMALIZATION (OBLIGATORIO)
+            "norm_type": "layernorm",
+            "norm_bias": true,
+            "layer_norm_eps": c.layer_norm_eps,
+            "pre_norm": true,
+            "final_norm": true,
+            
+            // RoPE (OBLIGATORIO) - PARTIAL
+            "rope_type": "default",
+            "rope_theta": c.rope_theta,
+            "rope_dim": rope_dim,
+            "rope_partial": true,
+            "partial_rotary_factor": c.partial_rotary_factor,
+            "rope_interleaved": false,
+            
+            // EMBEDDINGS (OBLIGATORIO)
+            "tie_word_embeddings": c.tie_word_embeddings,
+            "embedding_bias": false,
+            "lm_head_bias": false,
+            
+            // CONTEXT
+            "max_position_embeddings": c.max_position_embeddings,
+            
+            // INFERENCE CAPABILITIES
+            "supports_flash_attention": true,
+            "supports_paged_attention": true,
+            "supports_sdpa": true
+        });
+        
+        // Fuyu: proyección lineal de patches (sin encoder de visión)
+        if let Some(patch_size) = c.patch_size {
+            hints["vision_projector"] = json!({
+                "type": "patch_linear",
+                "patch_size": patch_size,
+                "num_channels": c.num_channels,
+                "input_dim": patch_size * patch_size * c.num_channels,
+                "output_dim": c.hidden_size,
+            });
+        }
+        
+        hints
+    }
+    
+    fn num_layers(&self) -> usize {
+        self.config.num_hidden_layers
+    }
+    
+    fn vocab_size(&self) -> usize {
+        self.config.vocab_size
+    }
+    
+    fn hidden_size(&self) -> usize {
+        self.config.hidden_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn fuyu_mapper() -> PersimmonMapper {
+        PersimmonMapper::from_json(&json!({
+            "model_type": "fuyu",
+            "patch_size": 30,
+            "num_channels": 3,
+            "text_config": {
+                "model_type": "persimmon",
+                "hidden_size": 4096,
+                "num_attention_heads": 64,
+                "num_hidden_layers": 36,
+            },
+        }))
+    }
+    
+    #[test]
+    fn test_fused_qkv_and_qk_layernorm() {
+        let mapper = PersimmonMapper::from_json(&json!({"model_type": "persimmon"}));
+        
+        let qkv = mapper.map_tensor("model.layers.3.self_attn.query_key_value.weight").unwrap();
+        assert_eq!(qkv.canonical_name, "layer3.attn.qkv_proj.weight");
+        assert_eq!(qkv.quant_hint, QuantHint::HQ5K);
+        assert_eq!(qkv.layer_idx, Some(3));
+        
+        let bias = mapper.map_tensor("model.layers.3.self_attn.query_key_value.bias").unwrap();
+        assert_eq!(bias.canonical_name, "layer3.attn.qkv_proj.bias");
+        assert_eq!(bias.quant_hint, QuantHint::FP16);
+        
+        let q_norm = mapper.map_tensor("model.layers.0.self_attn.q_layernorm.bias").unwrap();
+        assert_eq!(q_norm.canonical_name, "layer0.attn.q_norm.bias");
+        let k_norm = mapper.map_tensor("model.layers.0.self_attn.k_layernorm.weight").unwrap();
+        assert_eq!(k_norm.canonical_name, "layer0.attn.k_norm.weight");
+        
+        let hints = mapper.execution_hints();
+        assert_eq!(hints["arch"], "persimmon");
+        assert_eq!(hints["qkv_layout"], "fused_per_head");
+        assert!(hints.get("vision_projector").is_none());
+    }
+    
+    #[test]
+    fn test_fuyu_patch_projector() {
+        let mapper = fuyu_mapper();
+        
+        let proj = mapper.map_tensor("vision_embed_tokens.weight").unwrap();
+        assert_eq!(proj.canonical_name, "projector.vision.patch_linear.weight");
+        assert_eq!(proj.category, TensorCategory::VisionProjector);
+        assert_eq!(
+            mapper.map_tensor("model.vision_embed_tokens.bias").unwrap().canonical_name,
+            "projector.vision.patch_linear.bias"
+        );
+        
+        // Backbone con prefijo language_model.
+        let qkv = mapper.map_tensor("language_model.model.layers.0.self_attn.query_key_value.weight").unwrap();
+        assert_eq!(qkv.canonical_name, "layer0.attn.qkv_proj.weight");
+        assert_eq!(mapper.map_tensor("language_model.lm_head.weight").unwrap().canonical_name, "lm_head.weight");
+        
+        let hints = mapper.execution_hints();
+        assert_eq!(hints["arch"], "fuyu");
+        assert_eq!(hints["head_dim"], 64);
+        assert_eq!(hints["vision_projector"]["input_dim"], 30 * 30 * 3);
+        assert_eq!(hints["vision_projector"]["output_dim"], 4096);
+        
+        for name in ["projector.vision.patch_linear.weight", "layer0.attn.q_norm.bias"] {
+            assert!(crate::dictionary::validate_tensor_name(name), "{}", name);
+        }
+    }
+}
@@ -13,13 +13,17 @@
 // - Tied embeddings (sin lm_head separado)
 // - GQA (num_key_value_heads < num_attention_heads)
 //
+// v9.0.6: Tipo de rope_scaling en cualquier grafía (rope_scaling.rope_type,
+//         rope_scaling.type o rope_type)
 // v9.0.5: Soporte completo para Phi-4-mini-instruct
 //
 // ============================================================================
 
+use anyhow::Result;
 use regex::Regex;
 use serde_json::{json, Value};
 
+use crate::hints::{config_mlp_activation, resolve_head_dim, rope_scaling_type};
 use super::traits::ModelMapper;
 use super::types::{TensorMapping, QuantHint, TensorCategory};
 
@@ -29,12 +33,15 @@ pub struct PhiConfig {
     pub hidden_size: usize,
     pub intermediate_size: usize,
     pub num_attention_heads: usize,
+    pub head_dim: Option<usize>,  // explícito en config.json; si no, hidden_size / heads
     pub num_key_value_heads: usize,
     pub vocab_size: usize,
     pub max_position_embeddings: usize,
     pub rope_theta: f64,
     pub rms_norm_eps: f64,
     pub tie_word_embeddings: bool,
+    /// hidden_act de config.json normalizada (silu si falta)
+    pub mlp_activation: String,
     // Phi-specific
     pub partial_rotary_factor: f64,
     pub original_max_position_embeddings: usize,
@@ -58,10 +65,8 @@ impl PhiConfig {
                     return None;
                 }
                 
-                let scaling_type = rs.get("type")
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("longrope")
-                    .to_string();
+                let scaling_type = rope_scaling_type(config)
+                    .unwrap_or_else(|| "longrope".to_string());
                 
                 let long_factor = rs.get("long_factor")
                     .and_then(|f| f.as_array())
@@ -93,6 +98,7 @@ impl PhiConfig {
             hidden_size: config["hidden_size"].as_u64().unwrap_or(3072) as usize,
             intermediate_size: config["intermediate_size"].as_u64().unwrap_or(8192) as usize,
             num_attention_heads: config["num_attention_heads"].as_u64().unwrap_or(24) as usize,
+            head_dim: config["head_dim"].as_u64().map(|v| v as usize),
             num_key_value_heads: config["num_key_value_heads"]
                 .as_u64()
                 .or(config["num_attention_heads"].as_u64())
@@ -102,6 +108,7 @@ impl PhiConfig {
             rope_theta: config["rope_theta"].as_f64().unwrap_or(10000.0),
             rms_norm_eps: config["rms_norm_eps"].as_f64().unwrap_or(1e-5),
             tie_word_embeddings: config["tie_word_embeddings"].as_bool().unwrap_or(true),
+            mlp_activation: config_mlp_activation(config).unwrap_or("silu").to_string(),
             partial_rotary_factor: config["partial_rotary_factor"].as_f64().unwrap_or(0.75),
             original_max_position_embeddings: config["original_max_position_embeddings"]
                 .as_u64().unwrap_or(4096) as usize,
@@ -260,6 +267,11 @@ impl ModelMapper for PhiMapper {
         None
     }
     
+    fn check_config(&self) -> Result<()> {
+        let c = &self.config;
+        resolve_head_dim(c.hidden_size, c.num_attention_heads, c.head_dim).map(|_| ())
+    }
+    
     fn execution_hints(&self) -> Value {
         let c = &self.config;
         
@@ -271,7 +283,7 @@ impl ModelMapper for PhiMapper {
             "gqa"
         };
         
-        let head_dim = c.hidden_size / c.num_attention_heads;
+        let head_dim = c.head_dim.unwrap_or(c.hidden_size / c.num_attention_heads);
         let rope_dim = ((head_dim as f64) * c.partial_rotary_factor) as usize;
         
         // Determinar rope_type
@@ -304,7 +316,7 @@ impl ModelMapper for PhiMapper {
             
             // MLP (OBLIGATORIO) - GATE+UP FUSIONADO
             "mlp_type": "swiglu_fused",  // CRÍTICO: gate+up fusionado
-            "mlp_activation": "silu",
+            "mlp_activation": c.mlp_activation,
             "mlp_bias": false,
             
             // NORMALIZATION (OBLIGATORIO)
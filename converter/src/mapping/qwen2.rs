@@ -6,15 +6,21 @@
 // Soporta: Qwen2, Qwen2.5, Qwen2-Instruct, Qwen2.5-Coder, etc.
 // Todos usan la misma arquitectura de tensores.
 //
+// v9.0.7: Bias solo en q/k/v (attention_bias); o_proj.bias, si aparece,
+//         se mapea aparte y no cuenta para el flag
+// v9.0.6: Tipo de rope_scaling en cualquier grafía (rope_scaling.rope_type,
+//         rope_scaling.type o rope_type); conserva el resto de parámetros (low/high_freq_factor)
 // v9.0.5: Añade soporte para rope_scaling (linear, dynamic, yarn)
 //
 // ============================================================================
 
+use anyhow::Result;
 use regex::Regex;
 use serde_json::{json, Value};
 
+use crate::hints::{config_mlp_activation, resolve_head_dim, rope_scaling_params, rope_scaling_type};
 use super::traits::ModelMapper;
-use super::types::{TensorMapping, QuantHint, TensorCategory};
+use super::types::{MapExplanation, TensorMapping, QuantHint, TensorCategory};
 
 // ============================================================================
 // CONFIG
@@ -24,6 +30,8 @@ use super::types::{TensorMapping, QuantHint, TensorCategory};
 pub struct RopeScaling {
     pub scaling_type: String,  // "linear", "dynamic", "yarn", etc.
     pub factor: f64,
+    /// Resto de rope_scaling tal cual (llama3: low/high_freq_factor, ...)
+    pub params: serde_json::Map<String, Value>,
 }
 
 #[derive(Debug, Clone)]
@@ -32,12 +40,15 @@ pub struct Qwen2Config {
     pub hidden_size: usize,
     pub intermediate_size: usize,
     pub num_attention_heads: usize,
+    pub head_dim: Option<usize>,  // explícito en config.json; si no, hidden_size / heads
     pub num_key_value_heads: usize,
     pub vocab_size: usize,
     pub max_position_embeddings: usize,
     pub rope_theta: f64,
     pub rms_norm_eps: f64,
     pub tie_word_embeddings: bool,
+    /// hidden_act de config.json normalizada (silu si falta)
+    pub mlp_activation: String,
     pub attention_bias: bool,
     // v9.0.5: rope_scaling support
     pub rope_scaling: Option<RopeScaling>,
@@ -51,16 +62,14 @@ impl Qwen2Config {
                 if rs.is_null() {
                     return None;
                 }
-                let scaling_type = rs.get("type")
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("linear")
-                    .to_string();
+                let scaling_type = rope_scaling_type(config)
+                    .unwrap_or_else(|| "linear".to_string());
                 let factor = rs.get("factor")
                     .and_then(|f| f.as_f64())
                     .unwrap_or(1.0);
                 
                 if factor != 1.0 {
-                    Some(RopeScaling { scaling_type, factor })
+                    Some(RopeScaling { scaling_type, factor, params: rope_scaling_params(rs) })
                 } else {
                     None
                 }
@@ -71,12 +80,14 @@ impl Qwen2Config {
             hidden_size: config["hidden_size"].as_u64().unwrap_or(4096) as usize,
             intermediate_size: config["intermediate_size"].as_u64().unwrap_or(11008) as usize,
             num_attention_heads: config["num_attention_heads"].as_u64().unwrap_or(32) as usize,
+            head_dim: config["head_dim"].as_u64().map(|v| v as usize),
             num_key_value_heads: config["num_key_value_heads"].as_u64().unwrap_or(32) as usize,
             vocab_size: config["vocab_size"].as_u64().unwrap_or(151936) as usize,
             max_position_embeddings: config["max_position_embeddings"].as_u64().unwrap_or(32768) as usize,
             rope_theta: config["rope_theta"].as_f64().unwrap_or(10000.0),
             rms_norm_eps: config["rms_norm_eps"].as_f64().unwrap_or(1e-6),
             tie_word_embeddings: config["tie_word_embeddings"].as_bool().unwrap_or(false),
+            mlp_activation: config_mlp_activation(config).unwrap_or("silu").to_string(),
             attention_bias: config["attention_bias"].as_bool().unwrap_or(true),
             rope_scaling,
         }
@@ -95,6 +106,7 @@ pub struct Qwen2Mapper {
     re_final_norm: Regex,
     re_attn_weight: Regex,
     re_attn_bias: Regex,
+    re_o_bias: Regex,
     re_mlp_weight: Regex,
     re_input_norm: Regex,
     re_post_attn_norm: Regex,
@@ -108,7 +120,9 @@ impl Qwen2Mapper {
             re_lm_head: Regex::new(r"^lm_head\.weight$").unwrap(),
             re_final_norm: Regex::new(r"^model\.norm\.weight$").unwrap(),
             re_attn_weight: Regex::new(r"^model\.layers\.(\d+)\.self_attn\.(q|k|v|o)_proj\.weight$").unwrap(),
-            re_attn_bias: Regex::new(r"^model\.layers\.(\d+)\.self_attn\.(q|k|v|o)_proj\.bias$").unwrap(),
+            // Qwen2 solo lleva bias en q/k/v
+            re_attn_bias: Regex::new(r"^model\.layers\.(\d+)\.self_attn\.(q|k|v)_proj\.bias$").unwrap(),
+            re_o_bias: Regex::new(r"^model\.layers\.(\d+)\.self_attn\.o_proj\.bias$").unwrap(),
             re_mlp_weight: Regex::new(r"^model\.layers\.(\d+)\.mlp\.(gate|up|down)_proj\.weight$").unwrap(),
             re_input_norm: Regex::new(r"^model\.layers\.(\d+)\.input_layernorm\.weight$").unwrap(),
             re_post_attn_norm: Regex::new(r"^model\.layers\.(\d+)\.post_attention_layernorm\.weight$").unwrap(),
@@ -118,6 +132,15 @@ impl Qwen2Mapper {
     pub fn from_json(config: &Value) -> Self {
         Self::new(Qwen2Config::from_json(config))
     }
+    
+    /// Regexes en el orden en que las prueba map_tensor
+    fn rules(&self) -> [&Regex; 9] {
+        [
+            &self.re_embed, &self.re_lm_head, &self.re_final_norm,
+            &self.re_attn_weight, &self.re_attn_bias, &self.re_o_bias, &self.re_mlp_weight,
+            &self.re_input_norm, &self.re_post_attn_norm,
+        ]
+    }
 }
 
 impl ModelMapper for Qwen2Mapper {
@@ -187,6 +210,16 @@ impl ModelMapper for Qwen2Mapper {
             ).with_layer(layer));
         }
         
+        // Variantes con bias en la salida: se conserva, no es attention_bias
+        if let Some(caps) = self.re_o_bias.captures(name) {
+            let layer: usize = caps[1].parse().ok()?;
+            return Some(TensorMapping::new(
+                format!("layer{}.attn.o_proj.bias", layer),
+                QuantHint::FP16,
+                TensorCategory::Attention,
+            ).with_layer(layer));
+        }
+        
         // ═══════════════════════════════════════════════════════════════
         // MLP WEIGHTS (HQ4K - buena compresión)
         // ═══════════════════════════════════════════════════════════════
@@ -227,6 +260,19 @@ impl ModelMapper for Qwen2Mapper {
         None
     }
     
+    fn explain(&self, name: &str) -> MapExplanation {
+        if self.should_ignore(name) {
+            return MapExplanation::ignored(name, "should_ignore (rotary_emb, inv_freq, ...)");
+        }
+        let rule = self.rules().into_iter().find(|re| re.is_match(name)).map(|re| re.as_str().to_string());
+        MapExplanation::new(name, self.map_tensor(name), rule)
+    }
+    
+    fn check_config(&self) -> Result<()> {
+        let c = &self.config;
+        resolve_head_dim(c.hidden_size, c.num_attention_heads, c.head_dim).map(|_| ())
+    }
+    
     fn execution_hints(&self) -> Value {
         let c = &self.config;
         
@@ -238,7 +284,7 @@ impl ModelMapper for Qwen2Mapper {
             "gqa"
         };
         
-        let head_dim = c.hidden_size / c.num_attention_heads;
+        let head_dim = c.head_dim.unwrap_or(c.hidden_size / c.num_attention_heads);
         
         // Determinar rope_type basado en rope_scaling
         let rope_type = match &c.rope_scaling {
@@ -270,7 +316,7 @@ impl ModelMapper for Qwen2Mapper {
             
             // MLP (OBLIGATORIO)
             "mlp_type": "swiglu",
-            "mlp_activation": "silu",
+            "mlp_activation": c.mlp_activation,
             "mlp_bias": false,
             
             // NORMALIZATION (OBLIGATORIO)
@@ -306,10 +352,12 @@ impl ModelMapper for Qwen2Mapper {
         
         // v9.0.5: Añadir rope_scaling si está presente
         if let Some(rs) = &c.rope_scaling {
-            hints["rope_scaling"] = json!({
+            let mut rope_scaling = json!({
                 "type": rs.scaling_type,
                 "factor": rs.factor
             });
+            rope_scaling.as_object_mut().unwrap().extend(rs.params.clone());
+            hints["rope_scaling"] = rope_scaling;
         }
         
         hints
@@ -327,3 +375,49 @@ impl ModelMapper for Qwen2Mapper {
         self.config.hidden_size
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hints::detect_attention_bias;
+    use crate::mapping::types::MapOutcome;
+    
+    #[test]
+    fn test_explain_matched_unmatched_and_ignored() {
+        let mapper = Qwen2Mapper::from_json(&json!({}));
+        
+        let q = mapper.explain("model.layers.0.self_attn.q_proj.weight");
+        let mapping = q.mapping().unwrap();
+        assert_eq!(mapping.canonical_name, "layer0.attn.q_proj.weight");
+        assert_eq!(mapping.quant_hint, QuantHint::HQ5K);
+        assert_eq!(q.rule.as_deref(), Some(mapper.re_attn_weight.as_str()));
+        
+        // Parecido pero sin regla: unmatched, sin regex que lo reconozca
+        let typo = mapper.explain("model.layers.0.self_attn.qkv_proj.weight");
+        assert!(matches!(typo.outcome, MapOutcome::Unmatched(None)));
+        assert!(typo.rule.is_none());
+        
+        let rotary = mapper.explain("model.layers.0.self_attn.rotary_emb.inv_freq");
+        assert!(matches!(rotary.outcome, MapOutcome::Ignored(_)));
+        assert!(rotary.to_string().contains("should_ignore"));
+    }
+    
+    #[test]
+    fn test_attention_bias_only_from_qkv() {
+        let mapper = Qwen2Mapper::from_json(&json!({}));
+        let o_bias = mapper.map_tensor("model.layers.0.self_attn.o_proj.bias").unwrap();
+        assert_eq!(o_bias.canonical_name, "layer0.attn.o_proj.bias");
+        
+        // Layout real de Qwen2: q/k/v con bias, o_proj sin él
+        let qwen2: Vec<String> = ["q", "k", "v", "o"].iter()
+            .map(|p| format!("text.layer0.attn.{}_proj.weight", p))
+            .chain(["q", "k", "v"].iter().map(|p| format!("text.layer0.attn.{}_proj.bias", p)))
+            .collect();
+        assert_eq!(detect_attention_bias(qwen2.iter().map(|s| s.as_str())), Some(true));
+        
+        // o_proj.bias solo no activa attention_bias
+        let o_only = ["text.layer0.attn.q_proj.weight", "text.layer0.attn.o_proj.bias"];
+        assert_eq!(detect_attention_bias(o_only), Some(false));
+        assert_eq!(detect_attention_bias(["text.final_norm.weight"]), None);
+    }
+}
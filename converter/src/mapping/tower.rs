@@ -0,0 +1,194 @@
+// src/mapping/tower.rs
+// ============================================================================
+// TOWER MAPPER - Extrae una sola torre de un checkpoint multimodal combinado
+// ============================================================================
+//
+// Los VLM completos (LLaVA, etc.) guardan texto y visión en el mismo set de
+// safetensors con prefijos por torre:
+//
+//   language_model.model.layers.N...         → torre TEXT
+//   vision_tower.vision_model.encoder...     → torre VISION
+//   multi_modal_projector.linear_1...        → projector (ninguna de las dos)
+//
+// TowerMapper envuelve al mapper de la arquitectura de la torre, reescribe el
+// prefijo al formato que ese mapper espera y marca como ignorados los tensores
+// de las otras torres (no cuentan como skip).
+//
+// ============================================================================
+
+use std::path::Path;
+use anyhow::Result;
+use serde_json::Value;
+
+use super::factory::{create_mapper_from_config, load_config};
+use super::traits::ModelMapper;
+use super::types::{BlockType, MapExplanation, TensorMapping};
+
+/// (prefijo original, reemplazo) para tensores de la torre de texto
+const TEXT_PREFIXES: &[(&str, &str)] = &[
+    ("model.language_model.", "model."),
+    ("language_model.", ""),
+];
+
+/// (prefijo original, reemplazo) para tensores de la torre de visión
+const VISION_PREFIXES: &[(&str, &str)] = &[
+    ("model.vision_tower.", ""),
+    ("vision_tower.", ""),
+    ("vision_model.", "vision_model."),
+];
+
+/// Projector del VLM: viaja con la torre de visión (lo mapea ClipMapper)
+const PROJECTOR_PREFIXES: &[&str] = &[
+    "multi_modal_projector.",
+    "model.multi_modal_projector.",
+];
+
+/// Mapper que solo acepta tensores de una torre
+pub struct TowerMapper {
+    inner: Box<dyn ModelMapper>,
+    tower: BlockType,
+}
+
+impl TowerMapper {
+    pub fn new(inner: Box<dyn ModelMapper>, tower: BlockType) -> Self {
+        Self { inner, tower }
+    }
+    
+    /// Nombre con el prefijo de torre reescrito, o None si es de otra torre
+    fn rewrite(&self, name: &str) -> Option<String> {
+        if PROJECTOR_PREFIXES.iter().any(|p| name.starts_with(p)) {
+            return (self.tower == BlockType::Vision).then(|| name.to_string());
+        }
+        
+        let vision = VISION_PREFIXES.iter().find(|(p, _)| name.starts_with(p));
+        match self.tower {
+            BlockType::Vision => vision.map(|(p, r)| format!("{}{}", r, &name[p.len()..])),
+            _ => {
+                if vision.is_some() {
+                    return None;
+                }
+                let rewritten = TEXT_PREFIXES.iter()
+                    .find(|(p, _)| name.starts_with(p))
+                    .map(|(p, r)| format!("{}{}", r, &name[p.len()..]));
+                Some(rewritten.unwrap_or_else(|| name.to_string()))
+            }
+        }
+    }
+}
+
+impl ModelMapper for TowerMapper {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+    
+    fn map_tensor(&self, original_name: &str) -> Option<TensorMapping> {
+        self.inner.map_tensor(&self.rewrite(original_name)?)
+    }
+    
+    fn explain(&self, name: &str) -> MapExplanation {
+        match self.rewrite(name) {
+            Some(local) => MapExplanation { name: name.to_string(), ..self.inner.explain(&local) },
+            None => MapExplanation::ignored(name, format!("belongs to another tower (selected: {})", self.tower.name())),
+        }
+    }
+    
+    fn check_config(&self) -> anyhow::Result<()> {
+        self.inner.check_config()
+    }
+    
+    fn execution_hints(&self) -> Value {
+        self.inner.execution_hints()
+    }
+    
+    fn should_ignore(&self, name: &str) -> bool {
+        match self.rewrite(name) {
+            Some(local) => self.inner.should_ignore(&local),
+            None => true,  // Otra torre: descartado a propósito
+        }
+    }
+    
+    fn num_layers(&self) -> usize {
+        self.inner.num_layers()
+    }
+    
+    fn vocab_size(&self) -> usize {
+        self.inner.vocab_size()
+    }
+    
+    fn hidden_size(&self) -> usize {
+        self.inner.hidden_size()
+    }
+    
+    fn is_moe(&self) -> bool {
+        self.inner.is_moe()
+    }
+    
+    fn num_experts(&self) -> Option<usize> {
+        self.inner.num_experts()
+    }
+}
+
+/// Crea el mapper de una torre concreta de un checkpoint combinado.
+///
+/// Usa `text_config` / `vision_config` del config.json si existen; si no,
+/// el config raíz (checkpoint de una sola torre).
+pub fn create_tower_mapper(model_path: &Path, tower: BlockType) -> Result<Box<dyn ModelMapper>> {
+    let config = load_config(model_path)?;
+    
+    let inner = match tower {
+        BlockType::TextModel => {
+            let text_config = config.get("text_config").unwrap_or(&config);
+            create_mapper_from_config(text_config)?
+        }
+        BlockType::Vision => {
+            // ClipMapper ya lee vision_config por su cuenta
+            let mut vision_config = config.get("vision_config").cloned().unwrap_or(config);
+            if vision_config.get("model_type").is_none() {
+                vision_config["model_type"] = Value::from("clip_vision_model");
+            }
+            create_mapper_from_config(&vision_config)?
+        }
+        other => anyhow::bail!("Tower selection not supported for block {}", other.name()),
+    };
+    
+    Ok(Box::new(TowerMapper::new(inner, tower)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::clip::ClipMapper;
+    use crate::mapping::llama::LlamaMapper;
+    use crate::mapping::types::MapOutcome;
+    
+    #[test]
+    fn test_text_tower_rewrites_and_rejects_vision() {
+        let inner = Box::new(LlamaMapper::from_json(&serde_json::json!({})));
+        let mapper = TowerMapper::new(inner, BlockType::TextModel);
+        
+        assert_eq!(mapper.rewrite("language_model.model.norm.weight").as_deref(), Some("model.norm.weight"));
+        assert_eq!(mapper.rewrite("model.language_model.norm.weight").as_deref(), Some("model.norm.weight"));
+        assert_eq!(mapper.rewrite("model.norm.weight").as_deref(), Some("model.norm.weight"));
+        assert!(mapper.rewrite("vision_tower.vision_model.post_layernorm.weight").is_none());
+        assert!(mapper.should_ignore("multi_modal_projector.linear_1.weight"));
+        assert!(mapper.map_tensor("language_model.model.norm.weight").is_some());
+    }
+    
+    #[test]
+    fn test_vision_tower_explain() {
+        let inner = Box::new(ClipMapper::from_json(&serde_json::json!({})));
+        let mapper = TowerMapper::new(inner, BlockType::Vision);
+        
+        let fc1 = mapper.explain("vision_tower.vision_model.encoder.layers.3.mlp.fc1.weight");
+        assert_eq!(fc1.name, "vision_tower.vision_model.encoder.layers.3.mlp.fc1.weight");
+        assert_eq!(fc1.mapping().unwrap().canonical_name, "vision.layer3.mlp.fc1.weight");
+        assert!(fc1.rule.unwrap().contains(r"mlp\.fc1"));
+        
+        let text = mapper.explain("language_model.model.norm.weight");
+        assert!(matches!(&text.outcome, MapOutcome::Ignored(reason) if reason.contains("another tower")));
+        assert!(text.rule.is_none());
+        
+        let unknown = mapper.explain("vision_tower.vision_model.encoder.layers.3.mlp.fc3.weight");
+        assert!(matches!(unknown.outcome, MapOutcome::Unmatched(None)));
+    }
+}
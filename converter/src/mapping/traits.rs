@@ -3,7 +3,8 @@
 // MAPPER TRAIT - Interfaz para mappers de arquitecturas
 // ============================================================================
 
-use super::types::TensorMapping;
+use super::types::{MapExplanation, TensorMapping};
+use anyhow::Result;
 use serde_json::Value;
 
 /// Trait para mappers de diferentes arquitecturas.
@@ -20,6 +21,20 @@ pub trait ModelMapper: Send + Sync {
     /// Retorna None si el tensor debe ignorarse (rotary_emb, inv_freq, etc.)
     fn map_tensor(&self, original_name: &str) -> Option<TensorMapping>;
     
+    /// Explica la decisión map/skip de un tensor (--explain).
+    /// El default no conoce las reglas; los mappers con regex las reportan.
+    fn explain(&self, name: &str) -> MapExplanation {
+        if self.should_ignore(name) {
+            return MapExplanation::ignored(name, "should_ignore");
+        }
+        MapExplanation::new(name, self.map_tensor(name), None)
+    }
+    
+    /// Valida el config antes de convertir (dimensiones incoherentes, etc.)
+    fn check_config(&self) -> Result<()> {
+        Ok(())
+    }
+    
     /// Genera execution_hints para el runtime.
     /// Contiene info de arquitectura: attention_type, mlp_type, rope, etc.
     fn execution_hints(&self) -> Value;
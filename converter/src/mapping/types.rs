@@ -18,8 +18,9 @@ pub enum BlockType {
     CodeExec = 0x8,
     Tokenizer = 0x9,      // HTF tokenizer
     ExecutionHints = 0xA,
-    ExpertRouter = 0xB,
-    Tools = 0xC,          // Movido aquí
+    ExecHintsBin = 0xB,   // Mismos hints en binario (hnf::BLOCK_EXEC_HINTS_BIN)
+    Tools = 0xC,
+    ExpertRouter = 0xD,
 }
 
 impl BlockType {
@@ -40,8 +41,9 @@ impl BlockType {
             Self::CodeExec => "code_exec",
             Self::Tokenizer => "tokenizer",
             Self::ExecutionHints => "execution_hints",
-            Self::ExpertRouter => "expert_router",
+            Self::ExecHintsBin => "exec_hints_bin",
             Self::Tools => "tools",
+            Self::ExpertRouter => "expert_router",
         }
     }
 }
@@ -51,6 +53,8 @@ impl BlockType {
 pub enum QuantHint {
     /// FP16 - embeddings, norms, biases (sin pérdida)
     FP16,
+    /// HQ6K - embeddings y capas sensibles (casi sin pérdida)
+    HQ6K,
     /// HQ5K - atención, routers (alta precisión)
     HQ5K,
     /// HQ4K - MLP, expertos (buena compresión)
@@ -63,6 +67,7 @@ impl QuantHint {
     pub fn resolve(&self, default: crate::hqs::QuantFormat) -> crate::hqs::QuantFormat {
         match self {
             Self::FP16 => crate::hqs::QuantFormat::FP16,
+            Self::HQ6K => crate::hqs::QuantFormat::HQ6K,
             Self::HQ5K => crate::hqs::QuantFormat::HQ5K,
             Self::HQ4K => crate::hqs::QuantFormat::HQ4K,
             Self::Default => default,
@@ -71,7 +76,7 @@ impl QuantHint {
 }
 
 /// Categoría del tensor (para hints y estadísticas)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TensorCategory {
     Embedding,
     Attention,
@@ -83,9 +88,28 @@ pub enum TensorCategory {
     VisionPatch,
     VisionProjector,
     AudioMel,
+    #[default]
     Other,
 }
 
+impl TensorCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Embedding => "embedding",
+            Self::Attention => "attention",
+            Self::MLP => "mlp",
+            Self::Norm => "norm",
+            Self::LMHead => "lm_head",
+            Self::MoERouter => "moe_router",
+            Self::MoEExpert => "moe_expert",
+            Self::VisionPatch => "vision_patch",
+            Self::VisionProjector => "vision_projector",
+            Self::AudioMel => "audio_mel",
+            Self::Other => "other",
+        }
+    }
+}
+
 /// Resultado del mapeo de un tensor
 #[derive(Debug, Clone)]
 pub struct TensorMapping {
@@ -122,3 +146,66 @@ impl TensorMapping {
         self
     }
 }
+
+/// Qué hizo el mapper con un tensor (ver ModelMapper::explain)
+#[derive(Debug, Clone)]
+pub enum MapOutcome {
+    Mapped(TensorMapping),
+    /// Descartado a propósito (should_ignore), con el motivo: no cuenta como skip
+    Ignored(String),
+    /// Ninguna regla lo acepta: el builder lo cuenta como skip (motivo opcional)
+    Unmatched(Option<String>),
+}
+
+/// Decisión map/skip de un tensor, con la regla que la tomó
+#[derive(Debug, Clone)]
+pub struct MapExplanation {
+    /// Nombre original del tensor
+    pub name: String,
+    pub outcome: MapOutcome,
+    /// Patrón regex que reconoció el nombre
+    pub rule: Option<String>,
+}
+
+impl MapExplanation {
+    /// Mapped si hay mapping, Unmatched si no
+    pub fn new(name: &str, mapping: Option<TensorMapping>, rule: Option<String>) -> Self {
+        let outcome = mapping.map_or(MapOutcome::Unmatched(None), MapOutcome::Mapped);
+        Self { name: name.to_string(), outcome, rule }
+    }
+    
+    pub fn ignored(name: &str, reason: impl Into<String>) -> Self {
+        Self { name: name.to_string(), outcome: MapOutcome::Ignored(reason.into()), rule: None }
+    }
+    
+    /// Sin mapping, explicando por qué ninguna regla aplica
+    pub fn unmatched(name: &str, reason: impl Into<String>) -> Self {
+        Self { name: name.to_string(), outcome: MapOutcome::Unmatched(Some(reason.into())), rule: None }
+    }
+    
+    pub fn mapping(&self) -> Option<&TensorMapping> {
+        match &self.outcome {
+            MapOutcome::Mapped(m) => Some(m),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for MapExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.name)?;
+        match &self.outcome {
+            MapOutcome::Mapped(m) => {
+                write!(f, "  mapped    → {} ({:?}, {:?}", m.canonical_name, m.quant_hint, m.category)?;
+                if let Some(layer) = m.layer_idx {
+                    write!(f, ", layer {}", layer)?;
+                }
+                writeln!(f, ")")?;
+            }
+            MapOutcome::Ignored(reason) => writeln!(f, "  ignored   {} (allowlist, not counted as skipped)", reason)?,
+            MapOutcome::Unmatched(Some(reason)) => writeln!(f, "  unmatched {} (counted as skipped)", reason)?,
+            MapOutcome::Unmatched(None) => writeln!(f, "  unmatched (counted as skipped)")?,
+        }
+        write!(f, "  rule      {}", self.rule.as_deref().unwrap_or("-"))
+    }
+}
@@ -12,6 +12,76 @@ use anyhow::{anyhow, Context, Result};
 use memmap2::Mmap;
 use serde::Deserialize;
 
+/// Sufijos de los tensores empaquetados de checkpoints ya cuantizados
+/// (GPTQ/AWQ): qweight/qzeros en I32, scales y g_idx aparte
+const PACKED_QUANT_SUFFIXES: [&str; 4] = [".qweight", ".qzeros", ".scales", ".g_idx"];
+
+/// ¿Nombre de tensor de un checkpoint GPTQ/AWQ?
+pub fn is_packed_quant_name(name: &str) -> bool {
+    PACKED_QUANT_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+fn prequantized_error(name: &str, dtype: &str) -> anyhow::Error {
+    anyhow!(
+        "Tensor '{}' ({}) belongs to a pre-quantized GPTQ/AWQ checkpoint (qweight/qzeros/scales), \
+         which is not supported: convert from the original FP16/BF16/F32 weights instead",
+        name, dtype
+    )
+}
+
+/// Bytes por elemento de los dtypes que read_f32 sabe decodificar
+fn dtype_width(dtype: &str) -> Option<usize> {
+    match dtype {
+        "F32" => Some(4),
+        "F16" | "BF16" => Some(2),
+        "F64" => Some(8),
+        _ => None,
+    }
+}
+
+/// Decodifica bytes de `dtype` a f32 (`name` solo para los mensajes)
+fn decode_f32(name: &str, dtype: &str, data: &[u8]) -> Result<Vec<f32>> {
+    match dtype {
+        "F32" => {
+            Ok(data.chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect())
+        }
+        "F16" => {
+            Ok(data.chunks_exact(2)
+                .map(|b| half::f16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect())
+        }
+        "BF16" => {
+            Ok(data.chunks_exact(2)
+                .map(|b| half::bf16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect())
+        }
+        "F64" => {
+            // Se estrecha a f32: pierde precisión y lo diminuto queda en
+            // subnormal / 0. Un finito fuera de rango f32 sería ±inf y
+            // rompería la escala de su super-block: error
+            data.chunks_exact(8)
+                .map(|b| {
+                    let value = f64::from_le_bytes(b.try_into().unwrap());
+                    let narrowed = value as f32;
+                    if narrowed.is_infinite() && value.is_finite() {
+                        return Err(anyhow!("Tensor '{}': F64 value {:e} is outside the F32 range", name, value));
+                    }
+                    Ok(narrowed)
+                })
+                .collect()
+        }
+        dtype if is_packed_quant_name(name) => Err(prequantized_error(name, dtype)),
+        dtype @ ("I8" | "U8" | "I16" | "I32" | "U32" | "I64") => Err(anyhow!(
+            "Unsupported integer dtype {} for tensor '{}': pre-quantized checkpoints are not supported, \
+             convert from the original FP16/BF16/F32 weights",
+            dtype, name
+        )),
+        dtype => Err(anyhow!("Unsupported dtype: {}", dtype)),
+    }
+}
+
 /// Información de un tensor en el archivo safetensor
 #[derive(Debug, Clone, Deserialize)]
 pub struct TensorInfo {
@@ -29,12 +99,30 @@ pub struct SafetensorHeader {
     pub metadata: Option<HashMap<String, String>>,
 }
 
+/// Bytes de un safetensor: mmap de disco o buffer propio
+enum Storage {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl std::ops::Deref for Storage {
+    type Target = [u8];
+    
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(mmap) => mmap,
+            Self::Owned(data) => data,
+        }
+    }
+}
+
 /// Archivo safetensor abierto
 pub struct SafetensorFile {
+    /// Ruta de origen (vacía si viene de from_bytes)
     pub path: PathBuf,
     pub header: SafetensorHeader,
     pub header_size: usize,
-    mmap: Mmap,
+    data: Storage,
 }
 
 impl SafetensorFile {
@@ -64,7 +152,28 @@ impl SafetensorFile {
             path,
             header,
             header_size: 8 + header_size,
-            mmap,
+            data: Storage::Mapped(mmap),
+        })
+    }
+    
+    /// Safetensor completo ya en memoria (descargado, object storage...):
+    /// read_raw sirve slices del buffer, sin tocar disco
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        let size_bytes = data.get(..8)
+            .ok_or_else(|| anyhow!("Safetensor buffer too small: {} bytes", data.len()))?;
+        let header_size = u64::from_le_bytes(size_bytes.try_into().unwrap()) as usize;
+        let header_bytes = 8usize.checked_add(header_size)
+            .and_then(|end| data.get(8..end))
+            .ok_or_else(|| anyhow!("Safetensor header ({} bytes) exceeds buffer ({} bytes)", header_size, data.len()))?;
+        
+        let header: SafetensorHeader = serde_json::from_slice(header_bytes)
+            .with_context(|| "Invalid safetensor header JSON")?;
+        
+        Ok(Self {
+            path: PathBuf::new(),
+            header,
+            header_size: 8 + header_size,
+            data: Storage::Owned(data),
         })
     }
     
@@ -86,7 +195,8 @@ impl SafetensorFile {
         let start = self.header_size + info.data_offsets[0];
         let end = self.header_size + info.data_offsets[1];
         
-        Ok(&self.mmap[start..end])
+        self.data.get(start..end)
+            .ok_or_else(|| anyhow!("Tensor '{}' data [{}..{}) exceeds file size {}", name, start, end, self.data.len()))
     }
     
     /// Lee un tensor como f32 (convierte desde dtype original)
@@ -94,25 +204,26 @@ impl SafetensorFile {
         let info = self.tensor_info(name)
             .ok_or_else(|| anyhow!("Tensor '{}' not found", name))?;
         let data = self.read_raw(name)?;
+        decode_f32(name, &info.dtype, data)
+    }
+    
+    /// Elementos [start, start + count) de un tensor como f32, decodificando
+    /// solo esos bytes (lectura por trozos de tensores gigantes)
+    pub fn read_f32_range(&self, name: &str, start: usize, count: usize) -> Result<Vec<f32>> {
+        let info = self.tensor_info(name)
+            .ok_or_else(|| anyhow!("Tensor '{}' not found", name))?;
+        let Some(width) = dtype_width(&info.dtype) else {
+            // Mismo error que read_f32 (dtype no soportado, checkpoint GPTQ...)
+            return decode_f32(name, &info.dtype, &[]);
+        };
         
-        match info.dtype.as_str() {
-            "F32" => {
-                Ok(data.chunks_exact(4)
-                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
-                    .collect())
-            }
-            "F16" => {
-                Ok(data.chunks_exact(2)
-                    .map(|b| half::f16::from_le_bytes([b[0], b[1]]).to_f32())
-                    .collect())
-            }
-            "BF16" => {
-                Ok(data.chunks_exact(2)
-                    .map(|b| half::bf16::from_le_bytes([b[0], b[1]]).to_f32())
-                    .collect())
-            }
-            dtype => Err(anyhow!("Unsupported dtype: {}", dtype)),
+        let numel: usize = info.shape.iter().product();
+        if start + count > numel {
+            return Err(anyhow!("Tensor '{}': range {}..{} exceeds {} elements", name, start, start + count, numel));
         }
+        let data = self.read_raw(name)?.get(start * width..(start + count) * width)
+            .ok_or_else(|| anyhow!("Tensor '{}': data shorter than its shape", name))?;
+        decode_f32(name, &info.dtype, data)
     }
     
     /// Número de elementos de un tensor
@@ -120,6 +231,50 @@ impl SafetensorFile {
         self.tensor_info(name)
             .map(|info| info.shape.iter().product())
     }
+    
+    /// Tamaño del archivo en bytes
+    pub fn file_size(&self) -> u64 {
+        self.data.len() as u64
+    }
+    
+    /// XXH3-64 del archivo completo (lee todo el shard, caro en modelos grandes)
+    pub fn hash_xxh3(&self) -> u64 {
+        xxhash_rust::xxh3::xxh3_64(&self.data)
+    }
+}
+
+/// Trozo de un nombre de tensor para la ordenación natural
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyPart {
+    Num(u64),
+    Text(String),
+}
+
+/// Clave de orden natural: embeddings, luego capas, luego el resto
+/// (final_norm, lm_head). Los enteros embebidos se comparan como números,
+/// así que layer2 < layer10 y final_norm va detrás de layer39.
+pub fn natural_tensor_key(name: &str) -> (u8, Vec<KeyPart>) {
+    let mut parts = Vec::new();
+    let mut rest = name;
+    while let Some(c) = rest.chars().next() {
+        let is_digit = c.is_ascii_digit();
+        let end = rest.find(|ch: char| ch.is_ascii_digit() != is_digit).unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        parts.push(match chunk.parse() {
+            Ok(n) if is_digit => KeyPart::Num(n),
+            _ => KeyPart::Text(chunk.to_string()),
+        });
+        rest = tail;
+    }
+    
+    let group = if name.contains("embed") {
+        0
+    } else if parts.iter().any(|p| matches!(p, KeyPart::Num(_))) {
+        1
+    } else {
+        2
+    };
+    (group, parts)
 }
 
 /// Reader para múltiples archivos safetensor (modelos sharded)
@@ -148,20 +303,47 @@ impl SafetensorReader {
         paths.sort();
         
         // Abrir todos
-        let mut files = Vec::with_capacity(paths.len());
-        let mut tensor_to_file = HashMap::new();
+        let files = paths.iter()
+            .map(SafetensorFile::open)
+            .collect::<Result<Vec<_>>>()?;
         
-        for (idx, path) in paths.iter().enumerate() {
-            let file = SafetensorFile::open(path)?;
-            
+        Ok(Self::from_files(files))
+    }
+    
+    /// Reader sobre shards ya abiertos (p.ej. SafetensorFile::from_bytes).
+    /// Si un nombre se repite, gana el último shard.
+    pub fn from_files(files: Vec<SafetensorFile>) -> Self {
+        let mut tensor_to_file = HashMap::new();
+        for (idx, file) in files.iter().enumerate() {
             for name in file.tensor_names() {
                 tensor_to_file.insert(name.to_string(), idx);
             }
-            
-            files.push(file);
         }
+        Self { files, tensor_to_file }
+    }
+    
+    /// Falla con un error claro si el modelo ya viene cuantizado (GPTQ/AWQ):
+    /// mejor antes de planificar que en el primer read_f32
+    pub fn check_not_prequantized(&self) -> Result<()> {
+        let mut packed: Vec<&str> = self.tensor_to_file.keys()
+            .map(|name| name.as_str())
+            .filter(|name| name.ends_with(".qweight"))
+            .collect();
+        packed.sort();
         
-        Ok(Self { files, tensor_to_file })
+        match packed.first() {
+            Some(name) => {
+                let dtype = self.dtype(name).unwrap_or("?");
+                Err(prequantized_error(name, dtype)
+                    .context(format!("{} packed qweight tensors found", packed.len())))
+            }
+            None => Ok(()),
+        }
+    }
+    
+    /// Shards abiertos (ordenados por nombre)
+    pub fn files(&self) -> &[SafetensorFile] {
+        &self.files
     }
     
     /// Número total de tensores
@@ -182,6 +364,15 @@ impl SafetensorReader {
         })
     }
     
+    /// Como iter_tensors pero en orden estable (ver natural_tensor_key)
+    pub fn iter_tensors_sorted(&self) -> impl Iterator<Item = (&str, &TensorInfo)> {
+        let mut tensors: Vec<_> = self.iter_tensors()
+            .map(|(name, info)| (natural_tensor_key(name), name, info))
+            .collect();
+        tensors.sort_by(|a, b| a.0.cmp(&b.0));
+        tensors.into_iter().map(|(_, name, info)| (name, info))
+    }
+    
     /// Lee un tensor como f32
     pub fn read(&self, name: &str) -> Result<Vec<f32>> {
         let file_idx = self.tensor_to_file.get(name)
@@ -189,6 +380,13 @@ impl SafetensorReader {
         self.files[*file_idx].read_f32(name)
     }
     
+    /// Elementos [start, start + count) de un tensor como f32
+    pub fn read_range(&self, name: &str, start: usize, count: usize) -> Result<Vec<f32>> {
+        let file_idx = self.tensor_to_file.get(name)
+            .ok_or_else(|| anyhow!("Tensor '{}' not found", name))?;
+        self.files[*file_idx].read_f32_range(name, start, count)
+    }
+    
     /// Lee un tensor como bytes raw
     pub fn read_raw(&self, name: &str) -> Result<&[u8]> {
         let file_idx = self.tensor_to_file.get(name)
@@ -202,6 +400,14 @@ impl SafetensorReader {
         self.files[*file_idx].tensor_info(name)
     }
     
+    /// Región de almacenamiento (shard, data_offsets): nombres con la misma
+    /// clave son alias de los mismos bytes
+    pub fn storage_key(&self, name: &str) -> Option<(usize, [usize; 2])> {
+        let file_idx = *self.tensor_to_file.get(name)?;
+        let info = self.files[file_idx].tensor_info(name)?;
+        Some((file_idx, info.data_offsets))
+    }
+    
     /// Shape de un tensor
     pub fn shape(&self, name: &str) -> Option<&[usize]> {
         self.tensor_info(name).map(|info| info.shape.as_slice())
@@ -212,3 +418,177 @@ impl SafetensorReader {
         self.tensor_info(name).map(|info| info.dtype.as_str())
     }
 }
+
+/// Escribe un safetensors F32 mínimo (solo para tests)
+#[cfg(test)]
+pub(crate) fn write_test_safetensors(path: &Path, tensors: &[(&str, Vec<usize>, Vec<f32>)]) -> Result<()> {
+    write_test_safetensors_aliased(path, tensors, &[])
+}
+
+/// Como write_test_safetensors, más `(alias, original)` que comparten data_offsets
+#[cfg(test)]
+pub(crate) fn write_test_safetensors_aliased(
+    path: &Path,
+    tensors: &[(&str, Vec<usize>, Vec<f32>)],
+    aliases: &[(&str, &str)],
+) -> Result<()> {
+    use std::io::Write;
+    
+    let mut header = serde_json::Map::new();
+    let mut payload: Vec<u8> = Vec::new();
+    
+    for (name, shape, data) in tensors {
+        let start = payload.len();
+        for v in data {
+            payload.extend_from_slice(&v.to_le_bytes());
+        }
+        header.insert(name.to_string(), serde_json::json!({
+            "dtype": "F32",
+            "shape": shape,
+            "data_offsets": [start, payload.len()],
+        }));
+    }
+    for (alias, original) in aliases {
+        let entry = header.get(*original).cloned()
+            .ok_or_else(|| anyhow!("alias target {} missing", original))?;
+        header.insert(alias.to_string(), entry);
+    }
+    
+    let header_bytes = serde_json::to_vec(&serde_json::Value::Object(header))?;
+    let mut file = File::create(path)?;
+    file.write_all(&(header_bytes.len() as u64).to_le_bytes())?;
+    file.write_all(&header_bytes)?;
+    file.write_all(&payload)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    
+    #[test]
+    fn test_natural_tensor_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let names = [
+            "model.norm.weight",
+            "model.layers.10.mlp.down_proj.weight",
+            "lm_head.weight",
+            "model.layers.2.mlp.down_proj.weight",
+            "model.embed_tokens.weight",
+            "model.layers.2.input_layernorm.weight",
+        ];
+        let tensors: Vec<_> = names.iter().map(|n| (*n, vec![2], vec![0.0; 2])).collect();
+        write_test_safetensors(&dir.path().join("model.safetensors"), &tensors).unwrap();
+        
+        let reader = SafetensorReader::from_folder(dir.path()).unwrap();
+        let sorted: Vec<&str> = reader.iter_tensors_sorted().map(|(name, _)| name).collect();
+        assert_eq!(sorted, [
+            "model.embed_tokens.weight",
+            "model.layers.2.input_layernorm.weight",
+            "model.layers.2.mlp.down_proj.weight",
+            "model.layers.10.mlp.down_proj.weight",
+            "lm_head.weight",
+            "model.norm.weight",
+        ]);
+        
+        // Nombres canónicos: "final_norm" < "layer..." alfabéticamente, pero va al final
+        assert!(natural_tensor_key("layer2.attn.q_proj.weight") < natural_tensor_key("layer10.attn.q_proj.weight"));
+        assert!(natural_tensor_key("layer39.mlp.down_proj.weight") < natural_tensor_key("final_norm.weight"));
+        assert!(natural_tensor_key("token_embedding.weight") < natural_tensor_key("layer0.attn.q_proj.weight"));
+    }
+    
+    #[test]
+    fn test_from_bytes_matches_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.safetensors");
+        write_test_safetensors(&path, &[
+            ("model.embed_tokens.weight", vec![4, 2], (0..8).map(|i| i as f32).collect()),
+            ("model.norm.weight", vec![2], vec![1.0, -2.5]),
+        ]).unwrap();
+        
+        let on_disk = SafetensorFile::open(&path).unwrap();
+        let in_memory = SafetensorFile::from_bytes(std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(in_memory.file_size(), on_disk.file_size());
+        assert_eq!(in_memory.hash_xxh3(), on_disk.hash_xxh3());
+        
+        let mut names: Vec<&str> = on_disk.tensor_names().collect();
+        names.sort();
+        let mut memory_names: Vec<&str> = in_memory.tensor_names().collect();
+        memory_names.sort();
+        assert_eq!(names, memory_names);
+        for name in names {
+            let (a, b) = (on_disk.tensor_info(name).unwrap(), in_memory.tensor_info(name).unwrap());
+            assert_eq!((&a.dtype, &a.shape, a.data_offsets), (&b.dtype, &b.shape, b.data_offsets));
+            assert_eq!(on_disk.read_raw(name).unwrap(), in_memory.read_raw(name).unwrap());
+            assert_eq!(on_disk.read_f32(name).unwrap(), in_memory.read_f32(name).unwrap());
+        }
+        
+        // El reader sobre buffers sirve los mismos tensores que from_folder
+        let reader = SafetensorReader::from_files(vec![in_memory]);
+        assert_eq!(reader.read("model.norm.weight").unwrap(), vec![1.0, -2.5]);
+        
+        // Header que declara más bytes de los que hay
+        let mut truncated = std::fs::read(&path).unwrap();
+        truncated.truncate(20);
+        assert!(SafetensorFile::from_bytes(truncated).is_err());
+        assert!(SafetensorFile::from_bytes(vec![1, 2, 3]).is_err());
+    }
+    
+    #[test]
+    fn test_read_f64_narrows_to_f32() {
+        let values: [f64; 4] = [1.5, -0.1, 1e-50, 3.4e39];
+        let payload: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let header = serde_json::json!({
+            "norm.weight": {"dtype": "F64", "shape": [3], "data_offsets": [0, 24]},
+            "huge.weight": {"dtype": "F64", "shape": [4], "data_offsets": [0, payload.len()]},
+        });
+        let header_bytes = serde_json::to_vec(&header).unwrap();
+        
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.safetensors");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(&(header_bytes.len() as u64).to_le_bytes()).unwrap();
+        file.write_all(&header_bytes).unwrap();
+        file.write_all(&payload).unwrap();
+        drop(file);
+        
+        let st = SafetensorFile::open(&path).unwrap();
+        let data = st.read_f32("norm.weight").unwrap();
+        assert_eq!(data, [1.5f32, -0.1, 0.0]);
+        // Overflow: error con el nombre del tensor en vez de +inf
+        let err = st.read_f32("huge.weight").unwrap_err().to_string();
+        assert!(err.contains("huge.weight") && err.contains("outside the F32 range"), "{}", err);
+    }
+    
+    #[test]
+    fn test_gptq_checkpoint_gives_specific_error() {
+        let dir = tempfile::tempdir().unwrap();
+        write_test_safetensors(&dir.path().join("model.safetensors"), &[
+            ("model.norm.weight", vec![2], vec![1.0; 2]),
+            ("model.layers.0.self_attn.q_proj.qweight", vec![2], vec![0.0; 2]),
+            ("model.layers.0.self_attn.q_proj.scales", vec![2], vec![0.0; 2]),
+        ]).unwrap();
+        let reader = SafetensorReader::from_folder(dir.path()).unwrap();
+        let err = format!("{:#}", reader.check_not_prequantized().unwrap_err());
+        assert!(err.contains("1 packed qweight"), "{}", err);
+        assert!(err.contains("q_proj.qweight") && err.contains("GPTQ"), "{}", err);
+        
+        // read_f32 sobre el I32 empaquetado: mismo diagnóstico, no "Unsupported dtype"
+        let payload = [0u8; 8];
+        let header = serde_json::json!({
+            "layer.qweight": {"dtype": "I32", "shape": [2], "data_offsets": [0, 8]},
+            "layer.weight": {"dtype": "I8", "shape": [8], "data_offsets": [0, 8]},
+        });
+        let header_bytes = serde_json::to_vec(&header).unwrap();
+        let mut bytes = (header_bytes.len() as u64).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&header_bytes);
+        bytes.extend_from_slice(&payload);
+        let st = SafetensorFile::from_bytes(bytes).unwrap();
+        assert!(st.read_f32("layer.qweight").unwrap_err().to_string().contains("pre-quantized GPTQ/AWQ"));
+        assert!(st.read_f32("layer.weight").unwrap_err().to_string().contains("Unsupported integer dtype I8"));
+        
+        assert!(is_packed_quant_name("x.qzeros"));
+        assert!(!is_packed_quant_name("x.weight"));
+    }
+}
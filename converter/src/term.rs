@@ -0,0 +1,121 @@
+// src/term.rs
+// ============================================================================
+// TERM - Salida de consola UTF-8 o ASCII plano
+// ============================================================================
+//
+// Los CLIs dibujan cajas (═ │ ┌) y marcas (✓ ✗ ⚠) que se rompen en logs de
+// CI y terminales no UTF-8. Con --no-color/--ascii, o si stdout no es un TTY,
+// outln!/eoutln! pasan cada línea por to_ascii antes de escribirla.
+//
+// ============================================================================
+
+use std::borrow::Cow;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use unicode_normalization::UnicodeNormalization;
+
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Fija el modo ASCII para outln!/eoutln!
+pub fn set_ascii(ascii: bool) {
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+pub fn ascii_mode() -> bool {
+    ASCII.load(Ordering::Relaxed)
+}
+
+/// --no-color/--ascii fuerzan ASCII; sin flag, ASCII si stdout no es un TTY
+pub fn init(force_ascii: bool) {
+    set_ascii(force_ascii || !std::io::stdout().is_terminal());
+}
+
+/// Reemplazo ASCII de un carácter (None = sin equivalente directo)
+fn ascii_glyph(c: char) -> Option<&'static str> {
+    Some(match c {
+        '✓' | '✔' => "[OK]",
+        '✗' | '✘' => "[FAIL]",
+        '⚠' => "[WARN]",
+        '→' => "->",
+        '←' => "<-",
+        '≤' => "<=",
+        '≥' => ">=",
+        '×' => "x",
+        '•' | '·' => "*",
+        '…' => "...",
+        '²' => "^2",
+        '═' | '━' => "=",
+        '─' => "-",
+        '║' | '│' | '┃' => "|",
+        '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼'
+        | '╔' | '╗' | '╚' | '╝' | '╠' | '╣' | '╦' | '╩' | '╬' => "+",
+        '█' => "#",
+        '░' => ".",
+        '▁' => "_",
+        _ => return None,
+    })
+}
+
+/// Versión ASCII de `s`: marcas y cajas a su equivalente, acentos fuera
+/// (á → a, Ñ → N) y cualquier otro carácter como '?'
+pub fn to_ascii(s: &str) -> Cow<'_, str> {
+    if s.is_ascii() {
+        return Cow::Borrowed(s);
+    }
+    
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else if let Some(glyph) = ascii_glyph(c) {
+            out.push_str(glyph);
+        } else {
+            // Descomposición canónica: la letra base queda, el acento se descarta
+            let base: String = c.nfd().filter(char::is_ascii).collect();
+            if base.is_empty() {
+                out.push('?');
+            } else {
+                out.push_str(&base);
+            }
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Línea tal como se escribe según el modo actual
+pub fn render(line: &str) -> Cow<'_, str> {
+    if ascii_mode() {
+        to_ascii(line)
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+/// println! que respeta el modo ASCII
+#[macro_export]
+macro_rules! outln {
+    () => { println!() };
+    ($($arg:tt)*) => { println!("{}", $crate::term::render(&format!($($arg)*))) };
+}
+
+/// eprintln! que respeta el modo ASCII
+#[macro_export]
+macro_rules! eoutln {
+    () => { eprintln!() };
+    ($($arg:tt)*) => { eprintln!("{}", $crate::term::render(&format!($($arg)*))) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_to_ascii_replaces_glyphs_and_accents() {
+        assert_eq!(to_ascii("  ✓ VÁLIDO"), "  [OK] VALIDO");
+        assert_eq!(to_ascii("✗ Tamaño: a → b ⚠"), "[FAIL] Tamano: a -> b [WARN]");
+        assert_eq!(to_ascii("┌──┐│║═"), "+--+||=");
+        assert_eq!(to_ascii("▁hola 你"), "_hola ?");
+        assert!(matches!(to_ascii("plain"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,48 @@
+// tests/ascii_output.rs
+// ============================================================================
+// SALIDA ASCII - --ascii no deja bytes no ASCII en stdout/stderr
+// ============================================================================
+//
+// Ejecuta los tres binarios sobre el fixture compartido con --ascii y
+// comprueba que ni las cajas, ni las marcas ✓/✗, ni los acentos de los
+// mensajes llegan a la salida.
+//
+// ============================================================================
+
+mod common;
+
+use std::process::{Command, Output};
+
+use common::{run_convert, run_inspect, Fixture};
+
+/// (éxito, stdout + stderr)
+fn combined(output: Output) -> (bool, Vec<u8>) {
+    (output.status.success(), [output.stdout, output.stderr].concat())
+}
+
+fn assert_ascii(bin: &str, output: &[u8]) {
+    let text = String::from_utf8_lossy(output);
+    let bad: Vec<&str> = text.lines().filter(|line| !line.is_ascii()).collect();
+    assert!(bad.is_empty(), "{} printed non-ASCII lines: {:#?}", bin, bad);
+    assert!(!output.is_empty());
+}
+
+#[test]
+fn test_ascii_flag_output_is_plain_ascii() {
+    let fixture = Fixture::new();
+    let hnf = &fixture.hnf;
+    
+    let (ok, output) = combined(run_convert(fixture.model(), hnf, &[]));
+    assert!(ok, "{}", String::from_utf8_lossy(&output));
+    assert_ascii("helios-convert", &output);
+    
+    // Válido o no, las marcas salen como [OK]/[FAIL]
+    let validate = Command::new(env!("CARGO_BIN_EXE_validate")).arg(hnf).args(["-v", "--ascii"]).output().unwrap();
+    let (_, output) = combined(validate);
+    assert_ascii("validate", &output);
+    assert!(String::from_utf8_lossy(&output).contains("[OK]"));
+    
+    let (ok, output) = combined(run_inspect(hnf, &["--hints", "--no-color"]));
+    assert!(ok, "{}", String::from_utf8_lossy(&output));
+    assert_ascii("inspect", &output);
+}
@@ -0,0 +1,153 @@
+// tests/common/mod.rs
+// ============================================================================
+// Fixture compartido por los tests de integración
+// ============================================================================
+
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use helios_convert::builder::{process_model, write_combined_hints, BuildOptions, HintOverrides};
+use helios_convert::hnf::HnfWriter;
+use helios_convert::mapping::{create_mapper, BlockType};
+use tempfile::TempDir;
+
+/// Fixture escrito en un directorio temporal y otro para las salidas;
+/// `hnf` es la ruta de salida por defecto (aún sin crear)
+pub struct Fixture {
+    pub model_dir: TempDir,
+    pub out_dir: TempDir,
+    pub hnf: PathBuf,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let model_dir = tempfile::tempdir().unwrap();
+        write_fixture(model_dir.path());
+        let out_dir = tempfile::tempdir().unwrap();
+        let hnf = out_dir.path().join("model.hnf");
+        Self { model_dir, out_dir, hnf }
+    }
+    
+    /// Con `hnf` ya convertido (flujo de librería, ver `convert`)
+    pub fn converted() -> Self {
+        let fixture = Self::new();
+        convert(fixture.model(), &fixture.hnf, None);
+        fixture
+    }
+    
+    pub fn model(&self) -> &Path {
+        self.model_dir.path()
+    }
+    
+    /// Ruta dentro del directorio de salida
+    pub fn out(&self, name: &str) -> PathBuf {
+        self.out_dir.path().join(name)
+    }
+    
+    pub fn config_path(&self) -> PathBuf {
+        self.model().join("config.json")
+    }
+    
+    /// Edita config.json del fixture
+    pub fn edit_config(&self, edit: impl FnOnce(&mut serde_json::Value)) {
+        let mut config: serde_json::Value = serde_json::from_slice(&std::fs::read(self.config_path()).unwrap()).unwrap();
+        edit(&mut config);
+        std::fs::write(self.config_path(), config.to_string()).unwrap();
+    }
+}
+
+/// Llama mínimo de 2 capas con pesos F32 distintos por tensor
+pub fn write_fixture(dir: &Path) {
+    let config = serde_json::json!({
+        "model_type": "llama",
+        "num_hidden_layers": 2,
+        "hidden_size": 16,
+        "intermediate_size": 32,
+        "num_attention_heads": 2,
+        "num_key_value_heads": 2,
+        "vocab_size": 8,
+    });
+    std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+    
+    let mut tensors: Vec<(String, Vec<usize>)> = vec![
+        ("model.embed_tokens.weight".into(), vec![8, 16]),
+        ("model.norm.weight".into(), vec![16]),
+        ("lm_head.weight".into(), vec![8, 16]),
+    ];
+    for layer in 0..2 {
+        for (proj, shape) in [
+            ("self_attn.q_proj", vec![16, 16]),
+            ("self_attn.k_proj", vec![16, 16]),
+            ("self_attn.v_proj", vec![16, 16]),
+            ("self_attn.o_proj", vec![16, 16]),
+            ("mlp.gate_proj", vec![32, 16]),
+            ("mlp.up_proj", vec![32, 16]),
+            ("mlp.down_proj", vec![16, 32]),
+        ] {
+            tensors.push((format!("model.layers.{}.{}.weight", layer, proj), shape));
+        }
+        tensors.push((format!("model.layers.{}.input_layernorm.weight", layer), vec![16]));
+        tensors.push((format!("model.layers.{}.post_attention_layernorm.weight", layer), vec![16]));
+    }
+    
+    let mut header = serde_json::Map::new();
+    let mut payload: Vec<u8> = Vec::new();
+    for (seed, (name, shape)) in tensors.iter().enumerate() {
+        let start = payload.len();
+        let numel: usize = shape.iter().product();
+        for i in 0..numel {
+            let v = ((i * 7 + seed * 13) % 23) as f32 * 0.05 - 0.5;
+            payload.extend_from_slice(&v.to_le_bytes());
+        }
+        header.insert(name.clone(), serde_json::json!({
+            "dtype": "F32",
+            "shape": shape,
+            "data_offsets": [start, payload.len()],
+        }));
+    }
+    
+    let header_bytes = serde_json::to_vec(&serde_json::Value::Object(header)).unwrap();
+    let mut file = std::fs::File::create(dir.join("model.safetensors")).unwrap();
+    file.write_all(&(header_bytes.len() as u64).to_le_bytes()).unwrap();
+    file.write_all(&header_bytes).unwrap();
+    file.write_all(&payload).unwrap();
+}
+
+/// Convierte el fixture a `out` con el flujo del CLI (tensores + hints + finalize)
+pub fn convert(model_dir: &Path, out: &Path, max_memory: Option<usize>) {
+    let options = BuildOptions { use_mse: false, max_memory, ..Default::default() };
+    let mapper = create_mapper(model_dir).unwrap();
+    
+    let mut writer = HnfWriter::create(out).unwrap();
+    process_model(model_dir, BlockType::TextModel, &mut writer, &options).unwrap();
+    write_combined_hints(
+        &mut writer,
+        &[(mapper.as_ref(), BlockType::TextModel)],
+        &HintOverrides::default(),
+    ).unwrap();
+    writer.finalize(serde_json::json!({"source": "determinism"})).unwrap();
+}
+
+/// helios-convert <model> --fast --ascii [extra] -o <hnf>
+pub fn run_convert(model_dir: &Path, hnf: &Path, extra: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_helios-convert"))
+        .arg(model_dir)
+        .args(["--fast", "--ascii"])
+        .args(extra)
+        .arg("-o")
+        .arg(hnf)
+        .output()
+        .unwrap()
+}
+
+/// inspect <hnf> [args]
+pub fn run_inspect(hnf: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_inspect"))
+        .arg(hnf)
+        .args(args)
+        .output()
+        .unwrap()
+}
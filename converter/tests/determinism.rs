@@ -0,0 +1,71 @@
+// tests/determinism.rs
+// ============================================================================
+// DETERMINISMO - La misma entrada produce el mismo HNF byte a byte
+// ============================================================================
+//
+// El header safetensors se lee en un HashMap (orden distinto en cada
+// instancia): el builder debe fijar el orden antes de escribir. Se convierte
+// el mismo fixture dos veces y se comparan archivo, checksums de bloque y
+// manifest, también con la cuantización en paralelo (--max-memory).
+//
+// ============================================================================
+
+mod common;
+
+use std::path::Path;
+
+use common::{convert, write_fixture};
+use helios_convert::hnf::HnfReader;
+
+/// (block_id, offset, size, checksum) de cada bloque presente
+fn block_checksums(path: &Path) -> Vec<(u32, u64, u64, u64)> {
+    let source = HnfReader::open(path).unwrap();
+    source.block_table.entries.iter()
+        .filter(|e| e.size > 0)
+        .map(|e| (e.block_id, e.offset, e.size, e.checksum))
+        .collect()
+}
+
+/// Manifest JSON tal cual está escrito al final del archivo
+fn manifest_json(path: &Path) -> String {
+    let source = HnfReader::open(path).unwrap();
+    let data = std::fs::read(path).unwrap();
+    let start = source.header.manifest_offset as usize;
+    String::from_utf8(data[start..start + source.header.manifest_size as usize].to_vec()).unwrap()
+}
+
+#[test]
+fn test_same_input_same_bytes() {
+    let model_dir = tempfile::tempdir().unwrap();
+    write_fixture(model_dir.path());
+    
+    let first = tempfile::NamedTempFile::new().unwrap();
+    let second = tempfile::NamedTempFile::new().unwrap();
+    convert(model_dir.path(), first.path(), None);
+    convert(model_dir.path(), second.path(), None);
+    
+    assert_eq!(manifest_json(first.path()), manifest_json(second.path()));
+    assert_eq!(block_checksums(first.path()), block_checksums(second.path()));
+    assert!(
+        std::fs::read(first.path()).unwrap() == std::fs::read(second.path()).unwrap(),
+        "two conversions of the same fixture differ"
+    );
+}
+
+#[test]
+fn test_parallel_quantization_same_checksums() {
+    let model_dir = tempfile::tempdir().unwrap();
+    write_fixture(model_dir.path());
+    
+    let sequential = tempfile::NamedTempFile::new().unwrap();
+    convert(model_dir.path(), sequential.path(), None);
+    let expected = block_checksums(sequential.path());
+    
+    // Lotes pequeños (varios tensores en vuelo) y uno que lo abarca todo
+    for max_memory in [4 * 1024, 64 * 1024 * 1024] {
+        let parallel = tempfile::NamedTempFile::new().unwrap();
+        convert(model_dir.path(), parallel.path(), Some(max_memory));
+        assert_eq!(block_checksums(parallel.path()), expected, "max_memory = {}", max_memory);
+        assert_eq!(manifest_json(parallel.path()), manifest_json(sequential.path()));
+    }
+}
@@ -0,0 +1,32 @@
+// tests/hints_binary.rs
+// ============================================================================
+// HINTS BINARY - inspect --dump-hints-bin extrae el bloque 0xB parseable
+// ============================================================================
+
+mod common;
+
+use common::{run_inspect, Fixture};
+use helios_convert::hints::binary::{ExecutionHintsBin, TextModelConfigBin, HINTS_MAGIC};
+use helios_convert::hnf::HnfReader;
+
+#[test]
+fn test_dump_hints_bin_parses_back() {
+    let fixture = Fixture::converted();
+    
+    let dump = fixture.out("hints.bin");
+    let inspect = run_inspect(&fixture.hnf, &["--dump-hints-bin", dump.to_str().unwrap()]);
+    assert!(inspect.status.success(), "{}", String::from_utf8_lossy(&inspect.stderr));
+    
+    let blob = std::fs::read(&dump).unwrap();
+    assert_eq!(&blob[..4], &HINTS_MAGIC.to_le_bytes());
+    let header = ExecutionHintsBin::from_bytes(&blob).unwrap();
+    assert_eq!(header.num_text_models, 1);
+    
+    // El config binario sale del mismo JSON que el bloque 0xA
+    let hints = HnfReader::open(&fixture.hnf).unwrap().execution_hints_json().unwrap().unwrap();
+    let text = TextModelConfigBin::from_bytes(&blob[header.text_offset as usize..]).unwrap();
+    assert_eq!(text, TextModelConfigBin::from_json(&hints["text"]));
+    assert_eq!(text.num_hidden_layers, 2);
+    assert_eq!(text.hidden_size, 16);
+    assert_eq!(text.vocab_size, 8);
+}
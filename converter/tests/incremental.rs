@@ -0,0 +1,107 @@
+// tests/incremental.rs
+// ============================================================================
+// INCREMENTAL - Tocar config.json solo reconstruye los execution hints
+// ============================================================================
+
+mod common;
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use common::{run_convert, Fixture};
+use helios_convert::hnf::{HnfReader, BLOCK_EXEC_HINTS, BLOCK_TEXT_MODEL};
+
+/// helios-convert <model> --incremental [extra] -o <hnf>: stdout
+fn convert_incremental(model_dir: &Path, hnf: &Path, extra: &[&str]) -> String {
+    let output = run_convert(model_dir, hnf, &[extra, &["--incremental"]].concat());
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Adelanta el mtime (resolución gruesa en algunos FS)
+fn touch(path: &Path) {
+    std::fs::File::options().write(true).open(path).unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+}
+
+/// (checksum del bloque de pesos de texto, bytes de los hints)
+fn blocks(hnf: &Path) -> (u64, Vec<u8>) {
+    let reader = HnfReader::open(hnf).unwrap();
+    (reader.blocks()[BLOCK_TEXT_MODEL].checksum, reader.block_bytes(BLOCK_EXEC_HINTS).to_vec())
+}
+
+#[test]
+fn test_config_change_rebuilds_only_hints() {
+    let fixture = Fixture::new();
+    let (model_dir, hnf) = (fixture.model(), &fixture.hnf);
+    
+    // Primera pasada: no hay salida previa, conversión completa
+    let stdout = convert_incremental(model_dir, hnf, &[]);
+    assert!(stdout.contains("CONVERSION COMPLETE"), "{}", stdout);
+    let (weights_before, hints_before) = blocks(hnf);
+    
+    // Sin cambios: nada que hacer
+    let stdout = convert_incremental(model_dir, hnf, &[]);
+    assert!(stdout.contains("up to date"), "{}", stdout);
+    
+    // Cambiar rope_theta y adelantar el mtime
+    fixture.edit_config(|config| config["rope_theta"] = serde_json::json!(500000.0));
+    touch(&fixture.config_path());
+    
+    let stdout = convert_incremental(model_dir, hnf, &[]);
+    assert!(stdout.contains("Rebuilt: execution_hints"), "{}", stdout);
+    assert!(!stdout.contains("CONVERSION COMPLETE"), "{}", stdout);
+    
+    let (weights_after, hints_after) = blocks(hnf);
+    assert_eq!(weights_after, weights_before);
+    assert_ne!(hints_after, hints_before);
+    let hints: serde_json::Value = serde_json::from_slice(&hints_after).unwrap();
+    assert_eq!(hints["text"]["rope_theta"], 500000.0);
+    
+    // El manifest registra el mtime nuevo: la siguiente pasada no hace nada
+    let stdout = convert_incremental(model_dir, hnf, &[]);
+    assert!(stdout.contains("up to date"), "{}", stdout);
+}
+
+#[test]
+fn test_calibration_change_forces_full_conversion() {
+    let fixture = Fixture::new();
+    let (model_dir, hnf) = (fixture.model(), &fixture.hnf);
+    let calibration = fixture.out("calibration.json");
+    std::fs::write(&calibration, "[[1, 2, 3]]").unwrap();
+    let extra = ["--calibration-data", calibration.to_str().unwrap()];
+    
+    convert_incremental(model_dir, hnf, &extra);
+    let stdout = convert_incremental(model_dir, hnf, &extra);
+    assert!(stdout.contains("up to date"), "{}", stdout);
+    
+    // Los datos de calibración son una fuente de pesos más
+    std::fs::write(&calibration, "[[3, 2, 1], [0, 1]]").unwrap();
+    touch(&calibration);
+    let stdout = convert_incremental(model_dir, hnf, &extra);
+    assert!(stdout.contains("Weights changed"), "{}", stdout);
+    assert!(stdout.contains("CONVERSION COMPLETE"), "{}", stdout);
+}
+
+#[test]
+fn test_quant_change_forces_full_conversion() {
+    let fixture = Fixture::new();
+    let (model_dir, hnf) = (fixture.model(), &fixture.hnf);
+    
+    convert_incremental(model_dir, hnf, &["-q", "HQ5K"]);
+    let before = std::fs::read(hnf).unwrap();
+    let stdout = convert_incremental(model_dir, hnf, &["-q", "HQ5K"]);
+    assert!(stdout.contains("up to date"), "{}", stdout);
+    
+    // Mismas fuentes, otro formato: el HNF existente no sirve
+    let stdout = convert_incremental(model_dir, hnf, &["-q", "HQ4K"]);
+    assert!(stdout.contains("Conversion flags changed"), "{}", stdout);
+    assert!(stdout.contains("CONVERSION COMPLETE"), "{}", stdout);
+    assert_ne!(std::fs::read(hnf).unwrap(), before);
+    let reader = HnfReader::open(hnf).unwrap();
+    assert_eq!(reader.manifest()["conversion_settings"]["quant"], "HQ4K");
+    
+    let stdout = convert_incremental(model_dir, hnf, &["-q", "HQ4K"]);
+    assert!(stdout.contains("up to date"), "{}", stdout);
+}
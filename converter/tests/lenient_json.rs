@@ -0,0 +1,37 @@
+// tests/lenient_json.rs
+// ============================================================================
+// LENIENT JSON - BOM siempre tolerado, comas finales solo con --lenient-json
+// ============================================================================
+
+mod common;
+
+use common::{run_convert, Fixture};
+use helios_convert::hnf::HnfReader;
+
+#[test]
+fn test_bom_and_trailing_comma_config_loads_with_lenient_json() {
+    let fixture = Fixture::new();
+    let (model_dir, hnf) = (fixture.model(), &fixture.hnf);
+    let config: serde_json::Value = serde_json::from_slice(&std::fs::read(fixture.config_path()).unwrap()).unwrap();
+    let clean = serde_json::to_string_pretty(&config).unwrap();
+    let trailing = format!("{},\n}}", clean.strip_suffix("\n}").unwrap());
+    
+    // Solo BOM: carga sin flag
+    std::fs::write(fixture.config_path(), format!("\u{FEFF}{}", clean)).unwrap();
+    let output = run_convert(model_dir, hnf, &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    
+    // BOM + coma final: error claro sin flag
+    std::fs::write(fixture.config_path(), format!("\u{FEFF}{}", trailing)).unwrap();
+    let output = run_convert(model_dir, hnf, &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--lenient-json"));
+    
+    let output = run_convert(model_dir, hnf, &["--lenient-json"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("config.json: 1 trailing commas removed (--lenient-json)"), "{}", stderr);
+    
+    let reader = HnfReader::open(hnf).unwrap();
+    assert!(!reader.block_tensors(0).is_empty());
+}
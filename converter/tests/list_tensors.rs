@@ -0,0 +1,55 @@
+// tests/list_tensors.rs
+// ============================================================================
+// LIST TENSORS - inspect --list-tensors lista el manifest, filtrable por bloque
+// ============================================================================
+
+mod common;
+
+use std::path::Path;
+
+use common::{run_inspect, Fixture};
+use helios_convert::hnf::HnfReader;
+
+/// Filas de la tabla (sin cabecera ni total) y línea de total
+fn list(hnf: &Path, block: Option<&str>) -> (Vec<String>, String) {
+    let output = match block {
+        Some(block) => run_inspect(hnf, &["--list-tensors", "--block", block]),
+        None => run_inspect(hnf, &["--list-tensors"]),
+    };
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines: Vec<String> = stdout.lines().map(str::to_string).collect();
+    let total = lines.pop().unwrap();
+    assert!(lines.remove(0).starts_with("NAME"));
+    (lines, total)
+}
+
+#[test]
+fn test_list_tensors_matches_manifest() {
+    let fixture = Fixture::converted();
+    let hnf = &fixture.hnf;
+    
+    let reader = HnfReader::open(hnf).unwrap();
+    let tensors = reader.manifest()["tensors"].as_array().unwrap();
+    
+    let (rows, total) = list(hnf, None);
+    assert_eq!(rows.len(), tensors.len());
+    assert!(total.starts_with(&format!("Total: {} tensors", tensors.len())), "{}", total);
+    
+    // Ordenado por nombre
+    let names: Vec<&str> = rows.iter().map(|r| r.split_whitespace().next().unwrap()).collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+    
+    // "text" resuelve a text_model; el fixture no tiene visión
+    let (text_rows, _) = list(hnf, Some("text"));
+    assert_eq!(text_rows.len(), tensors.len());
+    let (vision_rows, total) = list(hnf, Some("vision"));
+    assert!(vision_rows.is_empty());
+    assert!(total.starts_with("Total: 0 tensors"), "{}", total);
+    
+    let unknown = run_inspect(hnf, &["--list-tensors", "--block", "nope"]);
+    assert!(!unknown.status.success());
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("Unknown block 'nope'"));
+}
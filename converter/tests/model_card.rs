@@ -0,0 +1,37 @@
+// tests/model_card.rs
+// ============================================================================
+// MODEL CARD - --model-card se guarda en el HNF y inspect --card lo extrae
+// ============================================================================
+
+mod common;
+
+use common::{run_convert, run_inspect, Fixture};
+use helios_convert::hnf::{HnfReader, BLOCK_MODEL_CARD, MODEL_CARD_MAX_SIZE};
+
+#[test]
+fn test_model_card_round_trip() {
+    let fixture = Fixture::new();
+    let card_path = fixture.out("README.md");
+    let card = "# Tiny Llama\n\nLicencia: Apache-2.0 — uso libre.\n";
+    std::fs::write(&card_path, card).unwrap();
+    let card_arg = ["--model-card", card_path.to_str().unwrap()];
+    
+    let convert = run_convert(fixture.model(), &fixture.hnf, &card_arg);
+    assert!(convert.status.success(), "{}", String::from_utf8_lossy(&convert.stderr));
+    
+    let reader = HnfReader::open(&fixture.hnf).unwrap();
+    assert_eq!(reader.model_card(), Some(card));
+    assert_eq!(reader.manifest()["has_model_card"], true);
+    assert_eq!(reader.manifest()["model_card"]["block"], BLOCK_MODEL_CARD);
+    assert_eq!(reader.manifest()["model_card"]["size"], card.len());
+    
+    let inspect = run_inspect(&fixture.hnf, &["--card"]);
+    assert!(inspect.status.success());
+    assert_eq!(String::from_utf8(inspect.stdout).unwrap(), card);
+    
+    // Por encima de 1 MB: error antes de convertir
+    std::fs::write(&card_path, vec![b'x'; MODEL_CARD_MAX_SIZE + 1]).unwrap();
+    let convert = run_convert(fixture.model(), &fixture.out("big.hnf"), &card_arg);
+    assert!(!convert.status.success());
+    assert!(String::from_utf8_lossy(&convert.stderr).contains("limit is 1048576 bytes"));
+}
@@ -0,0 +1,41 @@
+// tests/padding.rs
+// ============================================================================
+// PADDING - inspect informa del relleno entre secciones y avisa si sobra
+// ============================================================================
+
+mod common;
+
+use std::path::Path;
+
+use common::{run_inspect, Fixture};
+use helios_convert::hnf::HnfReader;
+
+fn inspect(hnf: &Path) -> String {
+    let output = run_inspect(hnf, &["--ascii"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_well_formed_file_padding_within_bound() {
+    let fixture = Fixture::converted();
+    let hnf = &fixture.hnf;
+    
+    let reader = HnfReader::open(hnf).unwrap();
+    let padding = reader.padding_bytes();
+    assert!(padding <= reader.padding_bound(), "{} > {}", padding, reader.padding_bound());
+    
+    let stdout = inspect(hnf);
+    let line = stdout.lines().find(|l| l.contains("Padding:")).unwrap();
+    assert!(line.contains(&format!("{} bytes", padding)), "{}", line);
+    assert!(line.contains("[OK]"), "{}", line);
+    
+    // Espacio perdido tras el manifest: por encima de la cota
+    let mut data = std::fs::read(hnf).unwrap();
+    data.extend(std::iter::repeat_n(0u8, 4096));
+    let wasteful = fixture.out("wasteful.hnf");
+    std::fs::write(&wasteful, &data).unwrap();
+    let stdout = inspect(&wasteful);
+    let line = stdout.lines().find(|l| l.contains("Padding:")).unwrap();
+    assert!(line.contains("[WARN] excede la alineacion"), "{}", line);
+}
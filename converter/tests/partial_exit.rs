@@ -0,0 +1,31 @@
+// tests/partial_exit.rs
+// ============================================================================
+// PARTIAL - exit 2 y manifest "partial" cuando el HNF no cubre todo el modelo
+// ============================================================================
+
+mod common;
+
+use common::{run_convert, Fixture};
+use helios_convert::hnf::HnfReader;
+
+#[test]
+fn test_truncated_layers_exit_partial() {
+    let fixture = Fixture::new();
+    
+    let full = fixture.out("full.hnf");
+    let output = run_convert(fixture.model(), &full, &[]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(HnfReader::open(&full).unwrap().manifest()["partial"], false);
+    
+    // config declara 3 capas pero el checkpoint solo trae 2 (recortado)
+    fixture.edit_config(|config| config["num_hidden_layers"] = serde_json::json!(3));
+    
+    let truncated = fixture.out("truncated.hnf");
+    let output = run_convert(fixture.model(), &truncated, &[]);
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("PARTIAL"));
+    
+    let reader = HnfReader::open(&truncated).unwrap();
+    assert_eq!(reader.manifest()["partial"], true);
+    assert_eq!(reader.manifest()["partial_reasons"][0], "text_model: 1 of 3 layers missing");
+}
@@ -0,0 +1,53 @@
+// tests/prune.rs
+// ============================================================================
+// PRUNE - --prune quita flags de bloques vacíos sin tocar el contenido
+// ============================================================================
+
+mod common;
+
+use std::process::Command;
+
+use common::{run_convert, Fixture};
+use helios_convert::hnf::{HeaderFlags, HnfReader, BLOCK_TEXT_MODEL, BLOCK_VISION};
+
+/// Offset de flags en el header (magic 8 + major 2 + minor 2)
+const FLAGS_OFFSET: usize = 12;
+
+#[test]
+fn test_prune_clears_flag_of_empty_vision_block() {
+    let fixture = Fixture::converted();
+    
+    // HAS_VISION activo con el bloque de visión vacío
+    let mut data = std::fs::read(&fixture.hnf).unwrap();
+    let flags = u32::from_le_bytes(data[FLAGS_OFFSET..FLAGS_OFFSET + 4].try_into().unwrap());
+    let flagged = flags | HeaderFlags::HAS_VISION | HeaderFlags::IS_MULTIMODAL;
+    data[FLAGS_OFFSET..FLAGS_OFFSET + 4].copy_from_slice(&flagged.to_le_bytes());
+    let stale = fixture.out("stale.hnf");
+    std::fs::write(&stale, &data).unwrap();
+    
+    let pruned = fixture.out("pruned.hnf");
+    let prune = run_convert(&stale, &pruned, &["--prune"]);
+    let stdout = String::from_utf8_lossy(&prune.stdout);
+    assert!(prune.status.success(), "{}", String::from_utf8_lossy(&prune.stderr));
+    assert!(stdout.contains("Cleared flag of empty block vision"), "{}", stdout);
+    
+    let before = HnfReader::open(&stale).unwrap();
+    let after = HnfReader::open(&pruned).unwrap();
+    assert!(!after.header.flags.has(HeaderFlags::HAS_VISION));
+    assert!(!after.header.flags.has(HeaderFlags::IS_MULTIMODAL));
+    assert!(after.block_table.entries[BLOCK_VISION].is_empty());
+    
+    // Texto intacto: mismos tensores con los mismos bytes
+    assert_eq!(after.block_bytes(BLOCK_TEXT_MODEL), before.block_bytes(BLOCK_TEXT_MODEL));
+    let names = |r: &HnfReader| r.block_tensors(BLOCK_TEXT_MODEL).iter().map(|t| t.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(&after), names(&before));
+    assert_eq!(after.header.file_size, std::fs::metadata(&pruned).unwrap().len());
+    
+    // El validador avisaba del flag huérfano; tras --prune ya no
+    let validate = |path: &std::path::Path| {
+        let output = Command::new(env!("CARGO_BIN_EXE_validate")).arg(path).output().unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    assert!(validate(&stale).contains("Flag vision activo pero bloque vacio"));
+    assert!(!validate(&pruned).contains("Flag vision activo"));
+}
@@ -0,0 +1,51 @@
+// tests/standalone_htf.rs
+// ============================================================================
+// HTF SUELTO - El validador acepta tokenizers fuera de un HNF
+// ============================================================================
+//
+// Un .htf construido aparte (build_htf) empieza por HTF3/HTF2 en vez de
+// HNFv9: el binario validate lo pasa a validate_htf y el código de salida
+// refleja el resultado.
+//
+// ============================================================================
+
+use std::path::Path;
+use std::process::Command;
+
+use helios_convert::htf::HTFWriter;
+
+/// Ejecuta validate sobre `path`: (éxito, stdout + stderr)
+fn validate(path: &Path) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_validate"))
+        .arg(path)
+        .arg("--ascii")
+        .output()
+        .unwrap();
+    (output.status.success(), String::from_utf8_lossy(&[output.stdout, output.stderr].concat()).into_owned())
+}
+
+#[test]
+fn test_standalone_htf3_validates() {
+    let mut tokenizer = HTFWriter::new_v13();
+    let vocab = [("a".to_string(), 0), ("b".to_string(), 1), ("ab".to_string(), 2)].into();
+    tokenizer.add_text_domain(&vocab, &[("a".to_string(), "b".to_string())], &serde_json::json!({}), true).unwrap();
+    let htf = tokenizer.build();
+    assert_eq!(&htf[..4], b"HTF3");
+    
+    let dir = tempfile::tempdir().unwrap();
+    let good = dir.path().join("tokenizer.htf");
+    std::fs::write(&good, &htf).unwrap();
+    let (ok, output) = validate(&good);
+    assert!(ok, "{}", output);
+    assert!(output.contains("VALID"), "{}", output);
+    
+    // Un byte del cuerpo cambiado: el checksum del header ya no cuadra
+    let mut corrupt = htf.clone();
+    let last = corrupt.len() - 1;
+    corrupt[last] ^= 0xFF;
+    let bad = dir.path().join("corrupt.htf");
+    std::fs::write(&bad, &corrupt).unwrap();
+    let (ok, output) = validate(&bad);
+    assert!(!ok, "{}", output);
+    assert!(output.contains("INVALID"), "{}", output);
+}
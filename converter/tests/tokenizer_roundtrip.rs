@@ -0,0 +1,155 @@
+// tests/tokenizer_roundtrip.rs
+// ============================================================================
+// TOKENIZER ROUND-TRIP - El HTF tokeniza igual que el tokenizer.json original
+// ============================================================================
+//
+// build_htf serializa vocab + merges; HtfEncoder los relee y codifica.
+// El crate tokenizers (HF) sobre el mismo tokenizer.json es la referencia:
+// cualquier ID distinto es información perdida en la serialización.
+// Solo con --features verify-tokenizer (que trae el crate tokenizers).
+//
+// ============================================================================
+
+#![cfg(feature = "verify-tokenizer")]
+
+use std::path::Path;
+
+use helios_convert::htf::{build_htf, HtfEncoder};
+use serde_json::{json, Value};
+
+/// Alfabeto byte-level de GPT-2: byte → carácter imprimible (Ġ = espacio)
+fn byte_chars() -> Vec<char> {
+    let mut printable: Vec<u32> = (0x21..=0x7E).chain(0xA1..=0xAC).chain(0xAE..=0xFF).collect();
+    let mut chars: Vec<u32> = printable.clone();
+    let mut extra = 0;
+    for b in 0..256u32 {
+        if !printable.contains(&b) {
+            printable.push(b);
+            chars.push(256 + extra);
+            extra += 1;
+        }
+    }
+    let mut table = vec!['\0'; 256];
+    for (b, c) in printable.into_iter().zip(chars) {
+        table[b as usize] = char::from_u32(c).unwrap();
+    }
+    table
+}
+
+/// tokenizer.json BPE: vocab = `base` + un token por merge, added tokens al final
+fn write_tokenizer(dir: &Path, base: Vec<String>, merges: &[(&str, &str)], added: &[&str], extra: Value) {
+    let mut vocab = serde_json::Map::new();
+    let mut push = |token: String| {
+        let id = vocab.len();
+        vocab.entry(token).or_insert(json!(id)).clone()
+    };
+    base.into_iter().for_each(|token| { push(token); });
+    for (a, b) in merges {
+        push(format!("{}{}", a, b));
+    }
+    let added_tokens: Vec<Value> = added.iter()
+        .map(|content| json!({
+            "id": push(content.to_string()), "content": content, "single_word": false,
+            "lstrip": false, "rstrip": false, "normalized": false, "special": true,
+        }))
+        .collect();
+    
+    let mut tokenizer = json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": added_tokens,
+        "normalizer": null,
+        "post_processor": null,
+        "model": {
+            "type": "BPE", "dropout": null, "unk_token": null,
+            "continuing_subword_prefix": null, "end_of_word_suffix": null,
+            "fuse_unk": false, "byte_fallback": false, "ignore_merges": false,
+            "vocab": vocab,
+            "merges": merges.iter().map(|(a, b)| format!("{} {}", a, b)).collect::<Vec<_>>(),
+        },
+    });
+    json_merge(&mut tokenizer, extra);
+    std::fs::write(dir.join("tokenizer.json"), tokenizer.to_string()).unwrap();
+}
+
+fn json_merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                json_merge(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+fn assert_round_trip(dir: &Path, probes: &[&str]) {
+    let reference = tokenizers::Tokenizer::from_file(dir.join("tokenizer.json")).unwrap();
+    let encoder = HtfEncoder::from_htf(&build_htf(dir).unwrap()).unwrap();
+    for probe in probes {
+        let expected = reference.encode(*probe, false).unwrap().get_ids().to_vec();
+        assert_eq!(encoder.encode(probe), expected, "probe {:?}", probe);
+    }
+}
+
+#[test]
+fn test_byte_level_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let merges = [
+        ("Ġ", "t"), ("h", "e"), ("Ġt", "he"), ("l", "l"), ("o", "r"), ("Ġ", "w"),
+        ("Ġw", "or"), ("Ġwor", "l"), ("Ġworl", "d"), ("H", "e"), ("He", "ll"),
+        ("Hell", "o"), ("Ġ", "Ġ"), ("Ċ", "Ċ"), ("1", "2"), ("'", "s"),
+    ];
+    write_tokenizer(
+        dir.path(),
+        byte_chars().iter().map(|c| c.to_string()).collect(),
+        &merges,
+        &["<|endoftext|>"],
+        json!({
+            "pre_tokenizer": {"type": "ByteLevel", "add_prefix_space": false, "trim_offsets": true, "use_regex": true},
+            "decoder": {"type": "ByteLevel", "add_prefix_space": true, "trim_offsets": true, "use_regex": true},
+        }),
+    );
+    
+    assert_round_trip(dir.path(), &[
+        "Hello world",
+        "the world's",
+        "  two  spaces  ",
+        "line\n\nbreak\n",
+        "Hello<|endoftext|> world",
+        "12345 café 東京 🚀",
+    ]);
+}
+
+#[test]
+fn test_metaspace_byte_fallback_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut base: Vec<String> = vec!["<unk>".into()];
+    base.extend((0..=255u8).map(|b| format!("<0x{:02X}>", b)));
+    base.push("▁".into());
+    base.extend(('a'..='z').map(String::from));
+    let merges = [
+        ("▁", "h"), ("e", "l"), ("▁h", "el"), ("l", "o"), ("▁hel", "lo"),
+        ("▁", "w"), ("o", "r"), ("▁w", "or"), ("▁wor", "l"), ("▁worl", "d"),
+    ];
+    write_tokenizer(
+        dir.path(),
+        base,
+        &merges,
+        &["<s>", "</s>"],
+        json!({
+            "pre_tokenizer": {"type": "Metaspace", "replacement": "▁", "prepend_scheme": "always", "split": true},
+            "decoder": {"type": "Metaspace", "replacement": "▁", "prepend_scheme": "always", "split": true},
+            "model": {"unk_token": "<unk>", "byte_fallback": true},
+        }),
+    );
+    
+    assert_round_trip(dir.path(), &[
+        "hello world",
+        "hello  world",
+        "<s>hello world</s>",
+        "Hello World",
+        "ñandú 東",
+    ]);
+}